@@ -16,8 +16,6 @@ if [ $# -lt 1 ]; then
     exit 1
 fi
 
-export RUST_MIN_STACK=16777216
-
 END_BLOCK_ARG=$1
 NUM_BLOCKS=${2:-1}
 
@@ -12,12 +12,16 @@ pub struct Tx {
     pub input: Hex,
     pub to: Option<Address>,
     pub value: Word,
+    #[serde(default)]
+    pub nonce: Word,
     #[serde(flatten)]
     pub gas_info: TxGas,
     #[serde(rename = "blobVersionedHashes", default)]
     pub blob_versioned_hashes: Option<Vec<Word>>,
     #[serde(rename = "accessList", default)]
     pub access_list: Vec<AccessListItem>,
+    #[serde(rename = "authorizationList", default)]
+    pub authorization_list: Vec<Authorization>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -27,6 +31,19 @@ pub struct AccessListItem {
     pub storage_keys: Vec<Word>,
 }
 
+/// EIP-7702: signed authorization to set delegation code of the signer (authority)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Authorization {
+    #[serde(rename = "chainId")]
+    pub chain_id: Word,
+    pub address: Address,
+    pub nonce: Word,
+    #[serde(rename = "yParity")]
+    pub y_parity: Word,
+    pub r: Word,
+    pub s: Word,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TxGas {
     #[serde(rename = "gasPrice", default)]
@@ -73,15 +90,18 @@ impl Header {
     /// Calculate blob gas price from excess_blob_gas per EIP-4844
     /// blob_gas_price = fake_exponential(excess_blob_gas, BLOB_BASE_FEE_UPDATE_FRACTION)
     pub fn blob_gas_price(&self) -> Word {
+        self.blob_gas_price_with_fraction(3338477) // BLOB_GASPRICE_UPDATE_FRACTION (Cancun)
+    }
+
+    /// Same as `blob_gas_price` but with the fork-specific update fraction
+    /// (EIP-7691 raised it in Prague)
+    pub fn blob_gas_price_with_fraction(&self, update_fraction: u128) -> Word {
         if self.excess_blob_gas.is_zero() {
             return Word::from(1);
         }
         // Simplified calculation: blob_gas_price = MIN_BLOB_GASPRICE * e^(excess_blob_gas / BLOB_GASPRICE_UPDATE_FRACTION)
         // For approximation, we use the fake_exponential function from EIP-4844
-        fake_exponential(
-            self.excess_blob_gas.as_u64() as u128,
-            3338477, // BLOB_GASPRICE_UPDATE_FRACTION
-        )
+        fake_exponential(self.excess_blob_gas.as_u64() as u128, update_fraction)
     }
 }
 
@@ -119,6 +139,9 @@ pub struct Header {
     pub state_root: Word,
     #[serde(rename = "mixHash")]
     pub mix_hash: Word,
+    /// Zero since the Merge (EIP-3675)
+    #[serde(default)]
+    pub difficulty: Word,
     #[serde(rename = "parentHash")]
     pub parent_hash: Word,
     #[serde(rename = "gasLimit")]
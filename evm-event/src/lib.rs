@@ -42,11 +42,6 @@ pub enum AccountEvent {
     //     address: Address,
     //     val: Word,
     // },
-    // Destroy {
-    //     address: Address,
-    //     beneficiary: Address,
-    //     balance: Word,
-    // },
     SetNonce {
         address: Address,
         val: u64,
@@ -57,6 +52,11 @@ pub enum AccountEvent {
         val: Word,
         new: Word,
     },
+    Destroy {
+        address: Address,
+        beneficiary: Address,
+        balance: Word,
+    },
 }
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -65,6 +65,7 @@ pub enum CallType {
     Call,
     Create,
     Create2,
+    EofCreate,
     Static,
     Delegate,
     Callcode,
@@ -86,10 +87,15 @@ fn is_zero_i64(x: &i64) -> bool {
     x == &0
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum HaltReason {
     InvalidOpcode,
     OutOfGas,
+    StackUnderflow,
+    StackOverflow,
+    StepLimit,
+    Cancelled,
+    Timeout,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -31,7 +31,8 @@ async fn main() -> Result<()> {
     };
     eprintln!("📦 Fetched block number: {} [{} txs]", block.header.number, txs.len());
 
-    let mut f = runner(block.header, provider);
+    let chain_id = provider.get_chain_id().await?;
+    let mut f = runner(block.header, provider, chain_id);
     for tx in txs {
         let idx = tx.transaction_index.unwrap_or_default();
         let now = Instant::now();
@@ -68,6 +68,7 @@ impl From<ExecResultAndState<ExecutionResult>> for TxResult {
 pub fn runner(
     header: Header,
     client: impl Provider + 'static,
+    chain_id: u64,
 ) -> impl FnMut(Tx) -> Result<(TxResult, Vec<Event>)> {
     let prev_id: BlockId = (header.number - 1).into();
     let state_db = WrapDatabaseAsync::new(AlloyDB::new(client, prev_id))
@@ -87,7 +88,7 @@ pub fn runner(
         })
         .modify_cfg_chained(|c| {
             c.spec = SpecId::OSAKA;
-            c.chain_id = 1;
+            c.chain_id = chain_id;
             c.disable_nonce_check = true;
             c.disable_balance_check = true;
         })
@@ -104,7 +105,7 @@ pub fn runner(
             .gas_limit(tx.gas_limit())
             .value(tx.value())
             .data(tx.input().to_owned())
-            .chain_id(Some(1))
+            .chain_id(Some(chain_id))
             .nonce(tx.nonce())
             .gas_price(tx.gas_price().unwrap_or(tx.inner.max_fee_per_gas()))
             .gas_priority_fee(tx.max_priority_fee_per_gas())
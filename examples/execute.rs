@@ -9,6 +9,7 @@ async fn main() -> eyre::Result<()> {
         eth::EthClient,
         executor::{Evm, Executor},
         ext::Ext,
+        spec::Spec,
         tracer::{EventTracer, LoggingTracer},
     };
 
@@ -56,7 +57,7 @@ async fn main() -> eyre::Result<()> {
     let gas = word(&args[6]);
     let block = word(&args[7]);
 
-    let code = Decoder::decode(bytecode);
+    let code = Decoder::decode(bytecode, Spec::default());
     dump(&code);
 
     let call = Call {
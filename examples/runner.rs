@@ -1,7 +1,6 @@
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::collections::{BTreeMap, HashSet};
 use std::{future::Future, pin::Pin, sync::Arc};
 
-use evm_common::address::Address;
 use evm_event::{Event, EventData};
 use evm_tracer::alloy_eips::BlockNumberOrTag;
 use evm_tracer::alloy_provider::{Provider, ProviderBuilder};
@@ -15,7 +14,9 @@ use solenoid::{
     eth,
     ext::Ext,
     ext::TxContext,
-    solenoid::{Builder as _, CallResult, Solenoid},
+    parallel, prestate,
+    solenoid::{Builder as _, CallResult, Runner, Solenoid},
+    spec::{Chain, Spec},
     tracer::{EventTracer as _, LoggingTracer},
 };
 
@@ -45,32 +46,10 @@ async fn as_state(
     result: &CallResult<LoggingTracer>,
     ext: &mut Ext,
 ) -> eyre::Result<BTreeMap<String, Value>> {
-    let mut kv: BTreeMap<Address, BTreeSet<Word>> = BTreeMap::new();
-    let touched = result
-        .evm
-        .touches
-        .iter()
-        .filter_map(|touch| match touch {
-            solenoid::executor::AccountTouch::GetState(address, key, _, _) => {
-                kv.entry(*address).or_default().insert(*key);
-                Some(*address)
-            }
-            solenoid::executor::AccountTouch::GetCode(address, _, _) => Some(*address),
-            // touches that modify the state:
-            solenoid::executor::AccountTouch::FeePay(address, _, _) => Some(*address),
-            solenoid::executor::AccountTouch::SetState(address, key, _, _, _) => {
-                kv.entry(*address).or_default().insert(*key);
-                Some(*address)
-            }
-            solenoid::executor::AccountTouch::SetNonce(address, _, _) => Some(*address),
-            solenoid::executor::AccountTouch::SetValue(address, _, _) => Some(*address),
-            solenoid::executor::AccountTouch::Create(address, _, _, _, _) => Some(*address),
-            _ => None,
-        })
-        .collect::<BTreeSet<_>>();
+    let touched = prestate::touched(&result.evm.touches);
 
     let mut ret: BTreeMap<String, serde_json::Value> = BTreeMap::new();
-    for address in touched.into_iter() {
+    for (address, keys) in touched {
         let account = ext.pull(&address).await?;
         let mut json = serde_json::json!({
             "balance": account.value,
@@ -79,7 +58,7 @@ async fn as_state(
         });
 
         let mut state = BTreeMap::new();
-        for key in kv.get(&address).cloned().unwrap_or_default() {
+        for key in keys {
             let val = ext.get(&address, &key).await?;
             state.insert(key, val);
         }
@@ -93,88 +72,113 @@ async fn as_state(
 
 pub type FutureResult = dyn Future<Output = eyre::Result<(TxResult, Vec<Event>)>>;
 
-pub fn runner(header: Header, ext: Ext) -> impl FnMut(Tx) -> Pin<Box<FutureResult>> {
-    let ext = Arc::new(Mutex::new(ext));
-    let base_fee = header.base_fee;
-    move |tx| {
-        let effective_gas_price = tx.effective_gas_price(base_fee);
-        let calldata = tx.input.as_ref().to_vec();
-
-        let call_cost = 21000i64;
-        let data_cost = {
-            let total_calldata_len = calldata.len();
-            let nonzero_bytes_count = calldata.iter().filter(|byte| *byte != &0).count();
-            nonzero_bytes_count * 16 + (total_calldata_len - nonzero_bytes_count) * 4
-        } as i64;
-        let create_cost = 32000i64;
-        let init_code_cost = 2 * calldata.len().div_ceil(32) as i64;
-
-        // EIP-7623: Increase calldata cost
-        let calldata_tokens = {
-            let zero_bytes = calldata.iter().filter(|b| **b == 0).count() as i64;
-            let nonzero_bytes = calldata.len() as i64 - zero_bytes;
-            zero_bytes + nonzero_bytes * 4
-        };
-        let gas_floor = 21000 + 10 * calldata_tokens;
+// Intrinsic gas of the transaction, charged outside of the execution
+struct Intrinsic {
+    costs: i64,
+    create: bool,
+    // EIP-7623: Increase calldata cost
+    floor: i64,
+}
 
-        let header = header.clone();
-        let ext = ext.clone();
+impl Intrinsic {
+    fn gas_costs(&self, ret: &[u8]) -> i64 {
+        if self.create {
+            let deployed_code_cost = 200 * ret.len() as i64;
+            self.costs + deployed_code_cost
+        } else {
+            self.costs
+        }
+    }
+}
 
-        let tx_ctx = TxContext {
-            gas_price: effective_gas_price,
-            gas_max_fee: tx.gas_info.max_fee.unwrap_or_default(),
-            gas_max_priority_fee: tx.gas_info.max_priority_fee.unwrap_or_default(),
-            blob_max_fee: tx.gas_info.max_fee_per_blob.unwrap_or_default(),
-            blob_gas_used: (tx.blob_count() * 131072) as u64,
-            access_list: tx.access_list.clone(),
-        };
+fn prepare(chain: &Chain, header: &Header, tx: &Tx) -> (TxContext, Intrinsic, Runner) {
+    let spec = chain.spec_for(header);
+    let effective_gas_price = tx.effective_gas_price(header.base_fee);
+    let calldata = tx.input.as_ref();
+
+    let call_cost = 21000i64;
+    let data_cost = {
+        let total_calldata_len = calldata.len();
+        let nonzero_bytes_count = calldata.iter().filter(|byte| *byte != &0).count();
+        nonzero_bytes_count * 16 + (total_calldata_len - nonzero_bytes_count) * 4
+    } as i64;
+    let create_cost = 32000i64;
+    let init_code_cost = 2 * calldata.len().div_ceil(32) as i64;
+
+    let tx_ctx = TxContext {
+        gas_price: effective_gas_price,
+        gas_max_fee: tx.gas_info.max_fee.unwrap_or_default(),
+        gas_max_priority_fee: tx.gas_info.max_priority_fee.unwrap_or_default(),
+        blob_max_fee: tx.gas_info.max_fee_per_blob.unwrap_or_default(),
+        blob_gas_used: (tx.blob_count() * 131072) as u64,
+        blob_hashes: tx.blob_versioned_hashes.clone().unwrap_or_default(),
+        access_list: tx.access_list.clone(),
+        authorization_list: tx.authorization_list.clone(),
+        nonce: Some(tx.nonce),
+    };
 
-        let access_list_cost = tx_ctx.access_list_cost();
+    let authorization_list_cost = if spec.is_enabled(Spec::Prague) {
+        tx_ctx.authorization_list_cost()
+    } else {
+        0
+    };
+    let access_list_cost = tx_ctx.access_list_cost() + authorization_list_cost;
+    let intrinsic = Intrinsic {
+        costs: if tx.to.is_some() {
+            call_cost + data_cost + access_list_cost
+        } else {
+            call_cost + data_cost + create_cost + init_code_cost + access_list_cost
+        },
+        create: tx.to.is_none(),
+        floor: spec.gas_floor(calldata),
+    };
+
+    let runner = Solenoid::new()
+        .execute(tx.to.unwrap_or_default(), "", tx.input.as_ref())
+        .with_header(header.clone())
+        .with_chain(chain.clone())
+        .with_sender(tx.from)
+        .with_gas(tx.gas)
+        .with_value(tx.value)
+        .ready();
+    (tx_ctx, intrinsic, runner)
+}
+
+fn opcode_traces(result: &mut CallResult<LoggingTracer>) -> Vec<Event> {
+    result
+        .tracer
+        .take()
+        .into_iter()
+        .filter(|event| matches!(event.data, EventData::OpCode(_)))
+        .map(|mut event| {
+            if let EventData::OpCode(opcode) = &mut event.data {
+                opcode.stack.reverse();
+            }
+            event
+        })
+        .collect()
+}
+
+pub fn runner(chain: Chain, header: Header, ext: Ext) -> impl FnMut(Tx) -> Pin<Box<FutureResult>> {
+    let ext = Arc::new(Mutex::new(ext));
+    move |tx| {
+        let (tx_ctx, intrinsic, solenoid) = prepare(&chain, &header, &tx);
+        let ext = ext.clone();
 
         Box::pin(async move {
             let (tx_result, traces) = tokio::spawn(async move {
                 let mut guard = ext.lock().await;
                 guard.reset(tx_ctx);
 
-                let mut result = Solenoid::new()
-                    .execute(tx.to.unwrap_or_default(), "", tx.input.as_ref())
-                    .with_header(header.clone())
-                    .with_sender(tx.from)
-                    .with_gas(tx.gas)
-                    .with_value(tx.value)
-                    .ready()
-                    .apply(&mut guard)
-                    .await?;
+                let mut result = solenoid.apply(&mut guard).await?;
 
                 // let coinbase_balance = guard.balance(&header.miner).await?;
                 // println!("[SOLE] COINBASE BALANCE: {coinbase_balance:#x}");
 
-                let gas_costs = if tx.to.is_some() {
-                    call_cost + data_cost + access_list_cost
-                } else {
-                    let deployed_code_cost = 200 * result.ret.len() as i64;
-                    call_cost
-                        + data_cost
-                        + create_cost
-                        + init_code_cost
-                        + deployed_code_cost
-                        + access_list_cost
-                };
-
-                let traces = result
-                    .tracer
-                    .take()
-                    .into_iter()
-                    .filter(|event| matches!(event.data, EventData::OpCode(_)))
-                    .map(|mut event| {
-                        if let EventData::OpCode(opcode) = &mut event.data {
-                            opcode.stack.reverse();
-                        }
-                        event
-                    })
-                    .collect();
-
-                let tx_result = as_tx_result(gas_costs, gas_floor, &result, &mut guard).await?;
+                let gas_costs = intrinsic.gas_costs(&result.ret);
+                let traces = opcode_traces(&mut result);
+                let tx_result =
+                    as_tx_result(gas_costs, intrinsic.floor, &result, &mut guard).await?;
 
                 Ok::<_, eyre::Report>((tx_result, traces))
             })
@@ -185,6 +189,46 @@ pub fn runner(header: Header, ext: Ext) -> impl FnMut(Tx) -> Pin<Box<FutureResul
     }
 }
 
+/// Execute all transactions of the block at once, see `solenoid::parallel`
+pub async fn parallel_runner(
+    chain: Chain,
+    header: Header,
+    mut ext: Ext,
+    transactions: Vec<Tx>,
+) -> eyre::Result<Vec<(TxResult, Vec<Event>)>> {
+    let coinbase = header.miner;
+    let count = transactions.len();
+    // Transactions that conflict are executed again: prepare them once
+    let (runs, intrinsics): (Vec<_>, Vec<_>) = transactions
+        .iter()
+        .map(|tx| {
+            let (tx_ctx, intrinsic, solenoid) = prepare(&chain, &header, tx);
+            ((tx_ctx, solenoid), intrinsic)
+        })
+        .unzip();
+    let runs = Arc::new(runs);
+    let executed = parallel::execute(ext.snapshot(), coinbase, count, move |index, mut ext| {
+        let (tx_ctx, solenoid) = runs[index].clone();
+        async move {
+            ext.reset(tx_ctx);
+            let result = solenoid.apply(&mut ext).await?;
+            Ok((result, ext))
+        }
+    })
+    .await?;
+
+    let mut results = Vec::with_capacity(count);
+    for (intrinsic, mut executed) in intrinsics.into_iter().zip(executed) {
+        let mut state = executed.state.ext();
+        let gas_costs = intrinsic.gas_costs(&executed.result.ret);
+        let traces = opcode_traces(&mut executed.result);
+        let tx_result =
+            as_tx_result(gas_costs, intrinsic.floor, &executed.result, &mut state).await?;
+        results.push((tx_result, traces));
+    }
+    Ok(results)
+}
+
 use solenoid::allocator::LoggingAllocator;
 use std::alloc::System;
 
@@ -201,9 +245,9 @@ async fn main() -> eyre::Result<()> {
 
     // Fail fast if the RPC URL is invalid or unresponsive
     let chain_id = eth.chain_id().await?;
-    if chain_id != 1 {
+    let Some(chain) = Chain::by_id(chain_id) else {
         eyre::bail!("Unexpected chain ID: {chain_id}");
-    }
+    };
 
     let block_number = std::env::args().nth(1);
     let block_number = if block_number.as_deref() == Some("latest") {
@@ -218,6 +262,7 @@ async fn main() -> eyre::Result<()> {
     let args = std::env::args().skip(1).collect::<HashSet<String>>();
     let progress = args.contains("--progress");
     let memory = args.contains("--memory");
+    let parallel = args.contains("--parallel");
 
     let Block {
         header,
@@ -231,7 +276,17 @@ async fn main() -> eyre::Result<()> {
 
     let ext = Ext::at_number(Word::from(block_number - 1), eth).await?;
 
-    let mut f = runner(header, ext);
+    let (mut f, mut executed) = if parallel {
+        let started = std::time::Instant::now();
+        let results = parallel_runner(chain, header, ext, transactions.clone()).await?;
+        println!(
+            "⚡ Executed in parallel in {} ms",
+            started.elapsed().as_millis()
+        );
+        (None, Some(results.into_iter()))
+    } else {
+        (Some(runner(chain, header, ext)), None)
+    };
 
     let provider = ProviderBuilder::new().connect_http(url.parse()?);
     let block = match provider
@@ -253,7 +308,7 @@ async fn main() -> eyre::Result<()> {
         txs.len()
     );
 
-    let mut g = evm_tracer::run::runner(block.header, provider);
+    let mut g = evm_tracer::run::runner(block.header, provider, chain_id);
 
     let len = transactions.len();
     assert_eq!(txs.len(), len);
@@ -270,7 +325,11 @@ async fn main() -> eyre::Result<()> {
         let (revm_result, revm_traces) = g(tx)?;
 
         let tx = transactions[idx].clone();
-        let result = f(tx).await;
+        let result = match (f.as_mut(), executed.as_mut()) {
+            (Some(f), _) => f(tx).await,
+            (None, Some(results)) => Ok(results.next().expect("one result per transaction")),
+            (None, None) => unreachable!(),
+        };
         match result {
             Ok((sole_result, sole_traces)) => {
                 let rev_ok = revm_result.rev == sole_result.rev;
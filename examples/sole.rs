@@ -4,8 +4,9 @@ use eyre::{Context, eyre};
 use solenoid::{
     eth,
     ext::{Ext, TxContext},
+    prestate::{self, PrestateConfig},
     solenoid::{Builder, Solenoid},
-    tracer::EventTracer,
+    tracer::{EventTracer, LoggingTracer},
 };
 
 #[allow(dead_code)]
@@ -53,17 +54,21 @@ async fn main() -> eyre::Result<()> {
             gas_max_priority_fee: tx.gas_info.max_priority_fee.unwrap_or_default(),
             blob_max_fee: tx.gas_info.max_fee_per_blob.unwrap_or_default(),
             blob_gas_used: (tx.blob_count() * 131072) as u64,
+            blob_hashes: tx.blob_versioned_hashes.clone().unwrap_or_default(),
             access_list: tx.access_list.clone(),
+            authorization_list: tx.authorization_list.clone(),
+            nonce: Some(tx.nonce),
         };
         ext.reset(tx_ctx);
-        let mut result = Solenoid::new()
+        let config = PrestateConfig { diff_mode: true };
+        let (mut result, prestate) = Solenoid::new()
             .execute(tx.to.unwrap_or_default(), "", tx.input.as_ref())
             .with_header(block.header.clone())
             .with_sender(tx.from)
             .with_gas(tx.gas)
             .with_value(tx.value)
             .ready()
-            .apply(&mut ext)
+            .apply_with_prestate(LoggingTracer::default(), config, &mut ext)
             .await
             .map_err(|_| eyre!("panic-caught"))
             .with_context(|| format!("TX:{idx}:{}", tx.hash))?;
@@ -158,41 +163,19 @@ async fn main() -> eyre::Result<()> {
         drop(events);
         println!("TRACES: {len} in {path}");
 
-        use std::collections::{BTreeMap, BTreeSet};
-        let mut kv: BTreeMap<Address, BTreeSet<Word>> = BTreeMap::new();
-        let mut touched = result
-            .evm
-            .touches
-            .iter()
-            .filter_map(|touch| match touch {
-                solenoid::executor::AccountTouch::GetState(address, key, _, _) => {
-                    kv.entry(*address).or_default().insert(*key);
-                    Some(*address)
-                }
-                solenoid::executor::AccountTouch::GetCode(address, _, _) => Some(*address),
-                // touches that modify the state:
-                solenoid::executor::AccountTouch::FeePay(address, _, _) => Some(*address),
-                solenoid::executor::AccountTouch::SetState(address, key, _, _, _) => {
-                    kv.entry(*address).or_default().insert(*key);
-                    Some(*address)
-                }
-                solenoid::executor::AccountTouch::SetNonce(address, _, _) => Some(*address),
-                solenoid::executor::AccountTouch::SetValue(address, _, _) => Some(*address),
-                solenoid::executor::AccountTouch::Create(address, _, _, _, _) => Some(*address),
-                _ => None,
-            })
-            .collect::<BTreeSet<_>>();
+        use std::collections::BTreeMap;
+        let mut touched = prestate::touched(&result.evm.touches);
 
         // Include addresses from access list (REVM includes these in state diff)
         for item in &ext.tx_ctx.access_list {
-            touched.insert(item.address);
-            for key in &item.storage_keys {
-                kv.entry(item.address).or_default().insert(*key);
-            }
+            touched
+                .entry(item.address)
+                .or_default()
+                .extend(item.storage_keys.iter().copied());
         }
 
         let mut ret: BTreeMap<Address, serde_json::Value> = BTreeMap::new();
-        for address in touched.into_iter() {
+        for (address, keys) in touched {
             let account = ext.pull(&address).await?;
             let mut json = serde_json::json!({
                 "balance": account.value,
@@ -201,7 +184,7 @@ async fn main() -> eyre::Result<()> {
             });
 
             let mut state = BTreeMap::new();
-            for key in kv.get(&address).cloned().unwrap_or_default() {
+            for key in keys {
                 let val = ext.get(&address, &key).await?;
                 state.insert(key, val);
             }
@@ -213,6 +196,11 @@ async fn main() -> eyre::Result<()> {
         let path = format!("sole.{block_number}.{skip}.state.json");
         evm_tracer::aux::dump(&path, &[ret])?;
         println!("STATE: {path}");
+
+        // Same as geth `debug_traceTransaction` with `prestateTracer` in diff mode
+        let path = format!("sole.{block_number}.{skip}.prestate.json");
+        evm_tracer::aux::dump(&path, &[prestate])?;
+        println!("PRESTATE: {path}");
     }
 
     // Explicitly drop ext to close HTTP client connections
@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use serde::Serialize;
+
+use evm_common::{address::Address, block::AccessListItem, word::Word};
+
+use crate::{ext::Ext, solenoid::Runner, tracer::NoopTracer};
+
+// EIP-2930: intrinsic cost of the entries of the list
+const ADDRESS_COST: i64 = 2400;
+const STORAGE_KEY_COST: i64 = 1900;
+// EIP-2929: saved by warm accesses
+const COLD_ACCOUNT_SAVING: i64 = 2600 - 100;
+const COLD_SLOAD_SAVING: i64 = 2100 - 100;
+
+// Touching more state with the list (it leaves more gas) can change the list
+const MAX_ROUNDS: usize = 8;
+
+/// Access list generated for a transaction, see [`Runner::create_access_list`]
+#[derive(Clone, Debug, Serialize)]
+pub struct AccessListResult {
+    #[serde(rename = "accessList")]
+    pub access_list: Vec<AccessListItem>,
+    /// Gas used with the access list
+    #[serde(rename = "gasUsed")]
+    pub gas_used: i64,
+    /// Gas used without any access list
+    #[serde(rename = "gasUsedWithout")]
+    pub gas_used_without: i64,
+}
+
+type Touched = BTreeMap<Address, BTreeSet<Word>>;
+
+/// Execute the transaction with the list of the previous execution until the
+/// list is stable. Every execution runs on a fork of the state.
+pub(crate) async fn create(
+    runner: Runner,
+    excluded: HashSet<Address>,
+    ext: &mut Ext,
+) -> eyre::Result<AccessListResult> {
+    let mut list = touched(&ext.tx_ctx.access_list);
+    let mut rounds = 0;
+    let gas_used = loop {
+        let mut fork = ext.fork();
+        fork.tx_ctx.access_list = items(&list);
+        fork.track_accesses();
+        let result = runner
+            .clone()
+            .apply_with_tracer(NoopTracer, &mut fork)
+            .await?;
+        let accesses = fork.take_accesses().unwrap_or_default();
+
+        // Warm state that the execution used: the list warms up all of it
+        let mut next = Touched::new();
+        for addr in &fork.accessed_addresses {
+            if accesses.reads.accounts.contains(addr) || accesses.writes.accounts.contains(addr) {
+                next.entry(*addr).or_default();
+            }
+        }
+        for slot in &fork.accessed_storage {
+            if accesses.reads.storage.contains(slot) || accesses.writes.storage.contains(slot) {
+                next.entry(slot.0).or_default().insert(slot.1);
+            }
+        }
+        // Created accounts are warm
+        let mut excluded = excluded.clone();
+        excluded.extend(fork.created_accounts.iter().copied());
+        let next = optimal(next, &excluded);
+
+        rounds += 1;
+        if next == list || rounds == MAX_ROUNDS {
+            break result.gas.gas_use;
+        }
+        list = next;
+    };
+
+    let mut fork = ext.fork();
+    fork.tx_ctx.access_list.clear();
+    let result = runner.apply_with_tracer(NoopTracer, &mut fork).await?;
+    Ok(AccessListResult {
+        access_list: items(&list),
+        gas_used,
+        gas_used_without: result.gas.gas_use,
+    })
+}
+
+// Entries that save more gas than they cost. Excluded addresses are warm
+// anyway: only their storage keys can pay for the entry.
+fn optimal(touched: Touched, excluded: &HashSet<Address>) -> Touched {
+    touched
+        .into_iter()
+        .filter(|(addr, keys)| {
+            let saving = if excluded.contains(addr) {
+                0
+            } else {
+                COLD_ACCOUNT_SAVING
+            };
+            let keys = keys.len() as i64;
+            saving + keys * COLD_SLOAD_SAVING > ADDRESS_COST + keys * STORAGE_KEY_COST
+        })
+        .collect()
+}
+
+fn touched(items: &[AccessListItem]) -> Touched {
+    let mut touched = Touched::new();
+    for item in items {
+        touched
+            .entry(item.address)
+            .or_default()
+            .extend(item.storage_keys.iter().copied());
+    }
+    touched
+}
+
+fn items(touched: &Touched) -> Vec<AccessListItem> {
+    touched
+        .iter()
+        .map(|(address, keys)| AccessListItem {
+            address: *address,
+            storage_keys: keys.iter().copied().collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_optimal() {
+        let warm = Address::from([1u8; 20]);
+        let cold = Address::from([2u8; 20]);
+        let keys = |n: u64| (0..n).map(Word::from).collect::<BTreeSet<_>>();
+        let excluded = HashSet::from([warm]);
+
+        let list = optimal(
+            Touched::from([(warm, keys(24)), (cold, keys(0))]),
+            &excluded,
+        );
+        assert_eq!(list, Touched::from([(cold, keys(0))]));
+
+        // 25 warm accesses pay for the entry of a warm address
+        let list = optimal(Touched::from([(warm, keys(25))]), &excluded);
+        assert_eq!(list, Touched::from([(warm, keys(25))]));
+    }
+}
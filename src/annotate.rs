@@ -0,0 +1,424 @@
+use std::{cell::Cell, collections::HashMap, fmt, sync::Arc};
+
+use serde::Serialize;
+
+use evm_common::{address::Address, hash::keccak256, word::Word};
+use evm_event::{AccountEvent, Event, EventData};
+
+use crate::{
+    artifact::{Artifacts, Source, SourceMapEntry},
+    tracer::{EventTracer, Trace},
+};
+
+/// Source position of an instruction
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct SourceLocation {
+    pub file: String,
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in bytes
+    pub column: usize,
+    /// Enclosing function qualified by its contract: `Contract.function`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.function {
+            Some(function) => write!(
+                f,
+                "{function} ({}:{}:{})",
+                self.file, self.line, self.column
+            ),
+            None => write!(f, "{}:{}:{}", self.file, self.line, self.column),
+        }
+    }
+}
+
+/// Maps instructions of known bytecode (by codehash) to source positions.
+/// Bytecode with immutables differs from the compiled one once deployed and
+/// is not recognized.
+#[derive(Default)]
+pub struct Annotator {
+    codes: HashMap<Word, HashMap<usize, SourceMapEntry>>,
+    sources: Vec<(Source, Vec<Scope>)>,
+}
+
+impl Annotator {
+    pub fn new(artifacts: &Artifacts) -> Self {
+        let codes = artifacts
+            .contracts
+            .values()
+            .map(|contract| {
+                let codehash = Word::from_bytes(&keccak256(&contract.bytecode));
+                let entries = contract
+                    .instructions()
+                    .map(|(pc, _, entry)| (pc, entry))
+                    .collect();
+                (codehash, entries)
+            })
+            .collect();
+        let sources = artifacts
+            .sources
+            .iter()
+            .map(|source| (source.clone(), scopes(source)))
+            .collect();
+        Self { codes, sources }
+    }
+
+    /// Source position of the instruction at the pc of the code
+    pub fn locate(&self, codehash: &Word, pc: usize) -> Option<SourceLocation> {
+        let entry = self.codes.get(codehash)?.get(&pc)?;
+        let (source, scopes) = self.sources.get(entry.file?)?;
+        let (line, column) = source.position(entry.offset)?;
+        // Scopes are sorted by start: the last enclosing one is the innermost
+        let enclosing = |kind| {
+            scopes
+                .iter()
+                .rfind(|scope| {
+                    scope.kind == kind && scope.start <= entry.offset && entry.offset < scope.end
+                })
+                .map(|scope| scope.name.as_str())
+        };
+        let function = match (
+            enclosing(ScopeKind::Contract),
+            enclosing(ScopeKind::Function),
+        ) {
+            (Some(contract), Some(function)) => Some(format!("{contract}.{function}")),
+            (None, Some(function)) => Some(function.to_string()),
+            (Some(contract), None) => Some(contract.to_string()),
+            (None, None) => None,
+        };
+        Some(SourceLocation {
+            file: source.path.clone(),
+            line,
+            column,
+            function,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ScopeKind {
+    /// Contract, library or interface
+    Contract,
+    /// Function, modifier, constructor, fallback or receive
+    Function,
+}
+
+/// Declaration in a source file with its byte range, from the keyword to
+/// the end of the body
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Scope {
+    kind: ScopeKind,
+    name: String,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Open,
+    Close,
+    Semicolon,
+}
+
+// Identifiers, braces and semicolons outside of comments and string literals
+fn tokens(content: &str) -> impl Iterator<Item = (usize, Token<'_>)> {
+    let bytes = content.as_bytes();
+    let mut at = 0;
+    std::iter::from_fn(move || {
+        while at < bytes.len() {
+            let start = at;
+            at += 1;
+            match bytes[start] {
+                b'{' => return Some((start, Token::Open)),
+                b'}' => return Some((start, Token::Close)),
+                b';' => return Some((start, Token::Semicolon)),
+                b'/' if bytes.get(at) == Some(&b'/') => {
+                    while at < bytes.len() && bytes[at] != b'\n' {
+                        at += 1;
+                    }
+                }
+                b'/' if bytes.get(at) == Some(&b'*') => {
+                    at += 1;
+                    while at < bytes.len() && !bytes[at - 1..].starts_with(b"*/") {
+                        at += 1;
+                    }
+                    at += 1;
+                }
+                quote @ (b'"' | b'\'') => {
+                    while at < bytes.len() && bytes[at] != quote {
+                        at += if bytes[at] == b'\\' { 2 } else { 1 };
+                    }
+                    at += 1;
+                }
+                b if b.is_ascii_alphabetic() || b == b'_' || b == b'$' => {
+                    while at < bytes.len()
+                        && (bytes[at].is_ascii_alphanumeric()
+                            || bytes[at] == b'_'
+                            || bytes[at] == b'$')
+                    {
+                        at += 1;
+                    }
+                    return Some((start, Token::Ident(&content[start..at])));
+                }
+                _ => (),
+            }
+        }
+        None
+    })
+}
+
+// Contracts and functions declared in the source, by byte range.
+// Comments and string literals are skipped, the rest is matched by
+// keywords and braces: good enough to name the enclosing function.
+fn scopes(source: &Source) -> Vec<Scope> {
+    let mut scopes = Vec::new();
+    // Declaration waiting for its opening brace, from its keyword
+    let mut pending: Option<(ScopeKind, String, usize)> = None;
+    // Opening braces with the declarations they end
+    let mut stack: Vec<Option<(ScopeKind, String, usize)>> = Vec::new();
+    let mut tokens = tokens(&source.content).peekable();
+    while let Some((offset, token)) = tokens.next() {
+        match token {
+            Token::Ident(keyword) => {
+                let kind = match keyword {
+                    "contract" | "library" | "interface" => ScopeKind::Contract,
+                    "function" | "modifier" => ScopeKind::Function,
+                    "constructor" | "fallback" | "receive" => {
+                        pending = Some((ScopeKind::Function, keyword.to_string(), offset));
+                        continue;
+                    }
+                    _ => continue,
+                };
+                // Function types (`function (uint) f`) have no name
+                if let Some((_, Token::Ident(name))) = tokens.peek() {
+                    pending = Some((kind, name.to_string(), offset));
+                    tokens.next();
+                }
+            }
+            Token::Open => stack.push(pending.take()),
+            Token::Close => {
+                if let Some(Some((kind, name, start))) = stack.pop() {
+                    scopes.push(Scope {
+                        kind,
+                        name,
+                        start,
+                        end: offset + 1,
+                    });
+                }
+            }
+            Token::Semicolon => pending = None,
+        }
+    }
+    scopes.sort_by_key(|scope| scope.start);
+    scopes
+}
+
+/// Frame of a [`StackTrace`]: the reverting instruction of the innermost
+/// frame, the call site in the others
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StackFrame {
+    pub address: Address,
+    pub location: Option<SourceLocation>,
+}
+
+/// Solidity-style stack trace of a reverted call, innermost frame first
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StackTrace {
+    /// Decoded `Error(string)` revert reason
+    pub reason: Option<String>,
+    /// Revert data
+    pub data: Vec<u8>,
+    pub frames: Vec<StackFrame>,
+}
+
+impl fmt::Display for StackTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            Some(reason) => writeln!(f, "Error: {reason}")?,
+            None if self.data.is_empty() => writeln!(f, "Error: reverted")?,
+            None => writeln!(f, "Error: reverted with 0x{}", hex::encode(&self.data))?,
+        }
+        for frame in &self.frames {
+            match &frame.location {
+                Some(location) => writeln!(f, "    at {location}")?,
+                None => writeln!(f, "    at {}", frame.address)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Adds the source position to the `debug` info of `OpCode` events, as
+/// `{"source": {"file", "line", "column", "function"}}`, and builds the stack
+/// trace of a reverted call. Events are passed on to the inner tracer.
+///
+/// Like [`CoverageTracer`](crate::coverage::CoverageTracer), the code of a
+/// nested call is known from the `GetCode` event of the caller, the code of
+/// the outermost frame is given to [`AnnotatingTracer::new`]. Code run by
+/// CREATE is not annotated.
+#[derive(Default)]
+pub struct AnnotatingTracer<T> {
+    inner: T,
+    annotator: Arc<Annotator>,
+    // Codehash of the frame, if known
+    code: Option<Word>,
+    // Code announced for the next child frame
+    next: Cell<Option<Word>>,
+    // Depth and address of the frame, from its Call event
+    frame: Option<(usize, Address)>,
+    // Position of the last executed instruction
+    last: Option<SourceLocation>,
+    // Revert of the frame
+    revert: Option<StackTrace>,
+    // Revert of the last child frame with the position of its call site
+    callee: Option<(StackTrace, Option<SourceLocation>)>,
+}
+
+impl<T: EventTracer> AnnotatingTracer<T> {
+    /// Tracer for the execution of the bytecode
+    pub fn new(inner: T, annotator: Arc<Annotator>, bytecode: &[u8]) -> Self {
+        let codehash = Some(Word::from_bytes(&keccak256(bytecode)));
+        // The outermost frame runs either in this tracer or in its first fork
+        Self {
+            inner,
+            annotator,
+            code: codehash,
+            next: Cell::new(codehash),
+            ..Self::default()
+        }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Stack trace of the reverted call
+    pub fn revert(&self) -> Option<&StackTrace> {
+        self.revert
+            .as_ref()
+            .or(self.callee.as_ref().map(|(trace, _)| trace))
+    }
+}
+
+impl<T: EventTracer> EventTracer for AnnotatingTracer<T> {
+    const TRACE: Trace = Trace {
+        events: true,
+        opcodes: true,
+        ..T::TRACE
+    };
+
+    fn push(&mut self, mut event: Event) {
+        match &mut event.data {
+            EventData::Account(AccountEvent::GetCode { bytecode, .. }) => {
+                let codehash = Word::from_bytes(&keccak256(bytecode.as_ref()));
+                self.next.set(Some(codehash));
+            }
+            EventData::Call { to, .. } if self.frame.is_none() => {
+                self.frame = Some((event.depth, *to));
+            }
+            EventData::OpCode(op) => {
+                // Only a fork right after GetCode runs the announced code
+                self.next.set(None);
+                self.last = self
+                    .code
+                    .and_then(|codehash| self.annotator.locate(&codehash, op.pc));
+                if let Some(location) = &self.last {
+                    op.debug["source"] = serde_json::json!(location);
+                }
+            }
+            EventData::Return {
+                ok: false,
+                data,
+                error,
+                ..
+            } => {
+                // Return events of precompiles are pushed by the caller
+                if let Some((depth, address)) = self.frame
+                    && depth == event.depth
+                    && self.revert.is_none()
+                {
+                    let frame = StackFrame {
+                        address,
+                        location: self.last.clone(),
+                    };
+                    // The revert data of the callee is bubbled up
+                    let trace = match self.callee.take() {
+                        Some((mut trace, site)) if trace.data == data.as_ref() => {
+                            trace.frames.push(StackFrame {
+                                address,
+                                location: site,
+                            });
+                            trace
+                        }
+                        _ => StackTrace {
+                            reason: error.clone(),
+                            data: data.as_ref().to_vec(),
+                            frames: vec![frame],
+                        },
+                    };
+                    self.revert = Some(trace);
+                }
+            }
+            _ => (),
+        }
+        self.inner.push(event);
+    }
+
+    fn peek(&self) -> &[Event] {
+        self.inner.peek()
+    }
+
+    fn take(&mut self) -> Vec<Event> {
+        self.inner.take()
+    }
+
+    fn fork(&self) -> Self {
+        Self {
+            inner: self.inner.fork(),
+            annotator: self.annotator.clone(),
+            code: self.next.take(),
+            ..Self::default()
+        }
+    }
+
+    fn join(&mut self, other: Self, reverted: bool) {
+        // The CALL instruction is executed before the callee is joined
+        self.callee = other.revert.map(|trace| (trace, self.last.clone()));
+        self.inner.join(other.inner, reverted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scopes() {
+        let source = Source {
+            path: "Fail.sol".to_string(),
+            content: include_str!("../etc/fail/Fail.sol").to_string(),
+        };
+        let names: Vec<_> = scopes(&source)
+            .into_iter()
+            .map(|scope| (scope.kind, scope.name))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (ScopeKind::Contract, "Fail".to_string()),
+                (ScopeKind::Function, "constructor".to_string()),
+                (ScopeKind::Function, "even_only".to_string()),
+                (ScopeKind::Function, "is_owner".to_string()),
+            ]
+        );
+    }
+}
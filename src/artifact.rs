@@ -0,0 +1,340 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum ArtifactError {
+    #[error("Invalid source map entry {index}: '{entry}'")]
+    InvalidSourceMap { index: usize, entry: String },
+    #[error("Invalid JSON: {0}")]
+    InvalidJson(String),
+    #[error("Missing field '{0}'")]
+    MissingField(String),
+    #[error("Invalid bytecode of contract {0}")]
+    InvalidBytecode(String),
+    #[error("Failed to read source {path}: {error}")]
+    Read { path: String, error: String },
+}
+
+/// Source file of the compiled contracts
+#[derive(Clone, Debug, Default)]
+pub struct Source {
+    pub path: String,
+    pub content: String,
+}
+
+impl Source {
+    /// Line and column (both 1-based) of the byte offset
+    pub fn position(&self, offset: usize) -> Option<(usize, usize)> {
+        let prefix = self.content.as_bytes().get(..offset)?;
+        let line = prefix.iter().filter(|b| **b == b'\n').count() + 1;
+        let column = match prefix.iter().rposition(|b| *b == b'\n') {
+            Some(newline) => offset - newline,
+            None => offset + 1,
+        };
+        Some((line, column))
+    }
+}
+
+/// Entry of a solc source map: source range of one instruction
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SourceMapEntry {
+    /// Byte offset of the range in the source file
+    pub offset: usize,
+    pub length: usize,
+    /// Source id, `None` for compiler generated code
+    pub file: Option<usize>,
+    /// Jump type: 'i' into a function, 'o' out of a function, '-' regular
+    pub jump: char,
+}
+
+/// solc source map (`evm.deployedBytecode.sourceMap`): one entry per
+/// instruction, in the compressed `s:l:f:j:m;...` form
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SourceMap(pub Vec<SourceMapEntry>);
+
+impl SourceMap {
+    pub fn parse(map: &str) -> Result<Self, ArtifactError> {
+        let mut entries = Vec::new();
+        let mut last = SourceMapEntry {
+            jump: '-',
+            ..SourceMapEntry::default()
+        };
+        for (index, entry) in map.trim().split(';').enumerate() {
+            let invalid = || ArtifactError::InvalidSourceMap {
+                index,
+                entry: entry.to_string(),
+            };
+            // Empty fields are the same as in the previous entry
+            for (field, value) in entry.split(':').enumerate() {
+                if value.is_empty() {
+                    continue;
+                }
+                match field {
+                    0 => last.offset = value.parse().map_err(|_| invalid())?,
+                    1 => last.length = value.parse().map_err(|_| invalid())?,
+                    2 => {
+                        let file: i64 = value.parse().map_err(|_| invalid())?;
+                        last.file = usize::try_from(file).ok();
+                    }
+                    3 => last.jump = value.chars().next().ok_or_else(invalid)?,
+                    // Modifier depth
+                    4 => (),
+                    _ => return Err(invalid()),
+                }
+            }
+            entries.push(last);
+        }
+        Ok(Self(entries))
+    }
+}
+
+/// Runtime bytecode of a compiled contract with its source map
+#[derive(Clone, Debug)]
+pub struct Contract {
+    pub bytecode: Vec<u8>,
+    pub source_map: SourceMap,
+}
+
+impl Contract {
+    fn new(name: &str, bytecode: &str, source_map: &str) -> Result<Self, ArtifactError> {
+        let bytecode = hex::decode(bytecode.trim_start_matches("0x"))
+            .map_err(|_| ArtifactError::InvalidBytecode(name.to_string()))?;
+        Ok(Self {
+            bytecode,
+            source_map: SourceMap::parse(source_map)?,
+        })
+    }
+
+    /// Instructions covered by the source map: pc, opcode and source range
+    pub fn instructions(&self) -> impl Iterator<Item = (usize, u8, SourceMapEntry)> + '_ {
+        let mut pc = 0;
+        self.source_map.0.iter().map_while(move |entry| {
+            let op = *self.bytecode.get(pc)?;
+            let at = pc;
+            // PUSH1..PUSH32 are followed by their immediate
+            pc += 1 + if (0x60..=0x7f).contains(&op) {
+                (op - 0x5f) as usize
+            } else {
+                0
+            };
+            Some((at, op, *entry))
+        })
+    }
+}
+
+/// Compiled contracts, named `path:Name`, with their sources indexed by
+/// the solc source id
+#[derive(Clone, Debug, Default)]
+pub struct Artifacts {
+    pub contracts: BTreeMap<String, Contract>,
+    pub sources: Vec<Source>,
+}
+
+impl Artifacts {
+    /// Output of `solc --combined-json bin-runtime,srcmap-runtime`, the
+    /// sources are read from the `sourceList` paths relative to `root`
+    pub fn from_combined_json(json: &str, root: &Path) -> Result<Self, ArtifactError> {
+        let json = parse(json)?;
+        let mut artifacts = Self::default();
+        for path in field(&json, &["sourceList"])?
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            let content =
+                std::fs::read_to_string(root.join(path)).map_err(|e| ArtifactError::Read {
+                    path: path.to_string(),
+                    error: e.to_string(),
+                })?;
+            artifacts.sources.push(Source {
+                path: path.to_string(),
+                content,
+            });
+        }
+        for (name, contract) in field(&json, &["contracts"])?
+            .as_object()
+            .into_iter()
+            .flatten()
+        {
+            let bytecode = str_field(contract, &["bin-runtime"])?;
+            let source_map = str_field(contract, &["srcmap-runtime"])?;
+            // Interfaces and abstract contracts have no code
+            if !bytecode.is_empty() {
+                let contract = Contract::new(name, bytecode, source_map)?;
+                artifacts.contracts.insert(name.clone(), contract);
+            }
+        }
+        Ok(artifacts)
+    }
+
+    /// Standard JSON output of solc with the input it was compiled from:
+    /// the input provides the source contents, the output the source ids
+    pub fn from_standard_json(input: &str, output: &str) -> Result<Self, ArtifactError> {
+        Self::from_values(&parse(input)?, &parse(output)?)
+    }
+
+    /// Build info of Hardhat (`artifacts/build-info`) or Foundry
+    /// (`out/build-info`, with `--build-info`): the standard JSON input and
+    /// output in one file
+    pub fn from_build_info(json: &str) -> Result<Self, ArtifactError> {
+        let json = parse(json)?;
+        Self::from_values(field(&json, &["input"])?, field(&json, &["output"])?)
+    }
+
+    fn from_values(input: &Value, output: &Value) -> Result<Self, ArtifactError> {
+        let mut artifacts = Self::default();
+        for (path, source) in field(output, &["sources"])?
+            .as_object()
+            .into_iter()
+            .flatten()
+        {
+            let id = field(source, &["id"])?
+                .as_u64()
+                .ok_or_else(|| ArtifactError::MissingField(format!("sources.{path}.id")))?
+                as usize;
+            let content = input["sources"][path]["content"]
+                .as_str()
+                .unwrap_or_default();
+            if artifacts.sources.len() <= id {
+                artifacts.sources.resize(id + 1, Source::default());
+            }
+            artifacts.sources[id] = Source {
+                path: path.clone(),
+                content: content.to_string(),
+            };
+        }
+        for (path, contracts) in field(output, &["contracts"])?
+            .as_object()
+            .into_iter()
+            .flatten()
+        {
+            for (name, contract) in contracts.as_object().into_iter().flatten() {
+                let name = format!("{path}:{name}");
+                let bytecode = str_field(contract, &["evm", "deployedBytecode", "object"])?;
+                let source_map = str_field(contract, &["evm", "deployedBytecode", "sourceMap"])?;
+                if !bytecode.is_empty() {
+                    let contract = Contract::new(&name, bytecode, source_map)?;
+                    artifacts.contracts.insert(name, contract);
+                }
+            }
+        }
+        Ok(artifacts)
+    }
+}
+
+fn parse(json: &str) -> Result<Value, ArtifactError> {
+    serde_json::from_str(json).map_err(|e| ArtifactError::InvalidJson(e.to_string()))
+}
+
+fn field<'a>(json: &'a Value, path: &[&str]) -> Result<&'a Value, ArtifactError> {
+    path.iter()
+        .try_fold(json, |json, key| json.get(key))
+        .ok_or_else(|| ArtifactError::MissingField(path.join(".")))
+}
+
+fn str_field<'a>(json: &'a Value, path: &[&str]) -> Result<&'a str, ArtifactError> {
+    field(json, path)?
+        .as_str()
+        .ok_or_else(|| ArtifactError::MissingField(path.join(".")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_map() {
+        let map = SourceMap::parse("0:10:0:-:0;;12:3;:5:-1:i;1:2:1:o").unwrap();
+        assert_eq!(
+            map.0,
+            vec![
+                SourceMapEntry {
+                    offset: 0,
+                    length: 10,
+                    file: Some(0),
+                    jump: '-'
+                },
+                SourceMapEntry {
+                    offset: 0,
+                    length: 10,
+                    file: Some(0),
+                    jump: '-'
+                },
+                SourceMapEntry {
+                    offset: 12,
+                    length: 3,
+                    file: Some(0),
+                    jump: '-'
+                },
+                SourceMapEntry {
+                    offset: 12,
+                    length: 5,
+                    file: None,
+                    jump: 'i'
+                },
+                SourceMapEntry {
+                    offset: 1,
+                    length: 2,
+                    file: Some(1),
+                    jump: 'o'
+                },
+            ]
+        );
+        assert_eq!(
+            SourceMap::parse("0:1:0;x:1"),
+            Err(ArtifactError::InvalidSourceMap {
+                index: 1,
+                entry: "x:1".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_instructions() {
+        // PUSH2 0x0102; PUSH0; SSTORE; followed by metadata not in the source map
+        let contract = Contract {
+            bytecode: hex::decode("6101025f55a264").unwrap(),
+            source_map: SourceMap::parse("0:1:0;;").unwrap(),
+        };
+        let instructions: Vec<_> = contract
+            .instructions()
+            .map(|(pc, op, _)| (pc, op))
+            .collect();
+        assert_eq!(instructions, vec![(0, 0x61), (3, 0x5f), (4, 0x55)]);
+    }
+
+    #[test]
+    fn test_position() {
+        let source = Source {
+            path: "Fail.sol".to_string(),
+            content: include_str!("../etc/fail/Fail.sol").to_string(),
+        };
+        let offset = source.content.find("return true").unwrap();
+        assert_eq!(source.position(offset), Some((20, 9)));
+        assert_eq!(source.position(0), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_standard_json() {
+        let input = r#"{"sources": {"A.sol": {"content": "contract A {}"}}}"#;
+        let output = r#"{
+            "sources": {"A.sol": {"id": 0}},
+            "contracts": {"A.sol": {"A": {"evm": {"deployedBytecode": {
+                "object": "6080", "sourceMap": "0:13:0:-:0;"
+            }}}}}
+        }"#;
+        let artifacts = Artifacts::from_standard_json(input, output).unwrap();
+        assert_eq!(artifacts.sources[0].content, "contract A {}");
+        assert_eq!(artifacts.contracts["A.sol:A"].bytecode, vec![0x60, 0x80]);
+        assert_eq!(artifacts.contracts["A.sol:A"].source_map.0.len(), 2);
+
+        let build_info = format!(r#"{{"input": {input}, "output": {{}}}}"#);
+        assert_eq!(
+            Artifacts::from_build_info(&build_info).unwrap_err(),
+            ArtifactError::MissingField("sources".to_string())
+        );
+    }
+}
@@ -0,0 +1,813 @@
+//! Debug Adapter Protocol server over stdio: replays a transaction of a block
+//! (forking the state at the parent block) or runs local code step by step
+//! under control of a DAP client (e.g. VS Code).
+//!
+//! Launch arguments:
+//! - fork: `{"block": 23027350, "tx": 0, "rpc": "https://..."}` (`rpc` defaults to `URL` env var)
+//! - local: `{"code": "0x...", "data": "0x...", "value": "0x0", "gas": 1000000}`
+//! - both: `"stopOnEntry": true`
+//!
+//! Every contract code is presented as a disassembly source (one instruction
+//! per line), so line breakpoints are pc breakpoints in that contract.
+//! Function breakpoints take an opcode (`SSTORE`) or a contract address
+//! (break when a call enters it). Instruction breakpoints match a pc in any
+//! contract.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{BufRead, Write},
+};
+
+use evm_common::{
+    address::Address,
+    block::{Header, Tx},
+    hash::keccak256,
+    word::Word,
+};
+use eyre::{OptionExt, eyre};
+use serde_json::{Value, json};
+
+use solenoid::{
+    decoder::{Bytecode, Instruction},
+    eth::EthClient,
+    ext::{Account, Ext, TxContext},
+    opcodes::get_opcode,
+    session::{FrameView, Session},
+    solenoid::{Builder, CallResult, Prepared, Runner, Solenoid},
+    spec::{Chain, Spec},
+    tracer::NoopTracer,
+};
+
+fn read_message(reader: &mut impl BufRead) -> eyre::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>()?);
+        }
+    }
+    let length = length.ok_or_eyre("missing Content-Length header")?;
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+fn write_message(writer: &mut impl Write, message: &Value) -> eyre::Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn parse_bytes(value: Option<&Value>) -> eyre::Result<Vec<u8>> {
+    let hex = value.and_then(Value::as_str).unwrap_or_default();
+    Ok(hex::decode(hex.trim_start_matches("0x"))?)
+}
+
+fn parse_word(value: Option<&Value>) -> eyre::Result<Word> {
+    match value {
+        Some(Value::Number(number)) => Ok(Word::from(number.as_u64().ok_or_eyre("not a u64")?)),
+        Some(Value::String(hex)) => Word::from_hex(hex),
+        _ => Ok(Word::zero()),
+    }
+}
+
+fn parse_offset(reference: &str) -> Option<usize> {
+    match reference.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => reference.parse().ok(),
+    }
+}
+
+fn is_opcode(name: &str) -> bool {
+    (0..=255u8).any(|code| {
+        let opcode = get_opcode(code);
+        opcode.code == code && opcode.name() == name
+    })
+}
+
+fn tx_context(tx: &Tx, base_fee: Word) -> TxContext {
+    TxContext {
+        gas_price: tx.effective_gas_price(base_fee),
+        gas_max_fee: tx.gas_info.max_fee.unwrap_or_default(),
+        gas_max_priority_fee: tx.gas_info.max_priority_fee.unwrap_or_default(),
+        blob_max_fee: tx.gas_info.max_fee_per_blob.unwrap_or_default(),
+        blob_gas_used: (tx.blob_count() * 131072) as u64,
+        blob_hashes: tx.blob_versioned_hashes.clone().unwrap_or_default(),
+        access_list: tx.access_list.clone(),
+        authorization_list: tx.authorization_list.clone(),
+        nonce: Some(tx.nonce),
+    }
+}
+
+// Runner of a transaction of the block
+fn runner(tx: &Tx, header: &Header, chain: &Chain) -> Runner {
+    match tx.to {
+        Some(to) => ready(
+            Solenoid::new().execute(to, "", tx.input.as_ref()),
+            tx,
+            header,
+            chain,
+        ),
+        None => ready(
+            Solenoid::new().create(tx.input.as_ref().to_vec()),
+            tx,
+            header,
+            chain,
+        ),
+    }
+}
+
+fn ready(builder: impl Builder, tx: &Tx, header: &Header, chain: &Chain) -> Runner {
+    builder
+        .with_header(header.clone())
+        .with_chain(chain.clone())
+        .with_sender(tx.from)
+        .with_gas(tx.gas)
+        .with_value(tx.value)
+        .ready()
+}
+
+#[derive(Default)]
+struct Breakpoints {
+    // Instruction indices (disassembly lines) per code hash
+    lines: HashMap<Word, HashSet<usize>>,
+    // Byte offsets (pc) in any contract
+    offsets: HashSet<usize>,
+    opcodes: HashSet<String>,
+    // Break when a call frame enters the contract
+    contracts: HashSet<Address>,
+}
+
+impl Breakpoints {
+    fn hit(&self, frame: &FrameView, instruction: &Instruction) -> bool {
+        let pc = frame.evm.pc;
+        self.lines
+            .get(&code_hash(frame.code))
+            .is_some_and(|lines| lines.contains(&pc))
+            || self.offsets.contains(&instruction.offset)
+            || self.opcodes.contains(&instruction.opcode.name())
+            || (pc == 0 && self.contracts.contains(&frame.call.to))
+    }
+}
+
+fn code_hash(code: &Bytecode) -> Word {
+    Word::from_bytes(&keccak256(&code.bytecode))
+}
+
+/// Disassembly of the contract code: line N is the instruction N-1
+fn disassemble(code: &Bytecode) -> String {
+    code.instructions
+        .iter()
+        .map(|instruction| {
+            let argument = instruction
+                .argument
+                .as_ref()
+                .map(|arg| format!(" 0x{}", hex::encode(arg)))
+                .unwrap_or_default();
+            format!(
+                "{:#06x}  {}{argument}\n",
+                instruction.offset,
+                instruction.opcode.name()
+            )
+        })
+        .collect()
+}
+
+struct Source {
+    name: String,
+    hash: Word,
+    content: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Resume {
+    Continue,
+    Over,
+    In,
+    Out,
+}
+
+#[derive(Clone, Copy)]
+enum Scope {
+    Stack,
+    Memory,
+    Storage,
+    Transient,
+}
+
+const SCOPES: [Scope; 4] = [
+    Scope::Stack,
+    Scope::Memory,
+    Scope::Storage,
+    Scope::Transient,
+];
+
+struct Debugger<W: Write> {
+    writer: W,
+    seq: i64,
+    ext: Ext,
+    // Transaction being debugged, its top-level frame runs in the session
+    tx: Option<Prepared<NoopTracer>>,
+    breakpoints: Breakpoints,
+    // Index + 1 is the DAP source reference
+    sources: Vec<Source>,
+    stop_on_entry: bool,
+}
+
+impl<W: Write> Debugger<W> {
+    fn new(writer: W) -> Self {
+        Self {
+            writer,
+            seq: 0,
+            ext: Ext::local(),
+            tx: None,
+            breakpoints: Breakpoints::default(),
+            sources: Vec::new(),
+            stop_on_entry: false,
+        }
+    }
+
+    fn send(&mut self, mut message: Value) -> eyre::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.writer, &message)
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) -> eyre::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    fn fail(&mut self, request: &Value, message: String) -> eyre::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> eyre::Result<()> {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }))
+    }
+
+    /// Handle the request, returns false once the client disconnects
+    async fn handle(&mut self, request: &Value) -> eyre::Result<bool> {
+        let args = &request["arguments"];
+        let result = match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                self.respond(
+                    request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsFunctionBreakpoints": true,
+                        "supportsInstructionBreakpoints": true,
+                        "supportsTerminateRequest": true,
+                    }),
+                )?;
+                self.event("initialized", json!({}))
+            }
+            "launch" => match self.launch(args).await {
+                Ok(()) => self.respond(request, json!({})),
+                Err(e) => self.fail(request, format!("{e:#}")),
+            },
+            "setBreakpoints" => {
+                let body = self.set_breakpoints(args);
+                self.respond(request, body)
+            }
+            "setFunctionBreakpoints" => {
+                let body = self.set_function_breakpoints(args);
+                self.respond(request, body)
+            }
+            "setInstructionBreakpoints" => {
+                let body = self.set_instruction_breakpoints(args);
+                self.respond(request, body)
+            }
+            "setExceptionBreakpoints" => self.respond(request, json!({"breakpoints": []})),
+            "configurationDone" => {
+                self.respond(request, json!({}))?;
+                if self.stop_on_entry {
+                    self.stopped("entry")
+                } else {
+                    self.resume(Resume::Continue).await
+                }
+            }
+            "threads" => self.respond(request, json!({"threads": [{"id": 1, "name": "main"}]})),
+            "stackTrace" => {
+                let body = self.stack_trace();
+                self.respond(request, body)
+            }
+            "scopes" => {
+                let frame = args["frameId"].as_u64().unwrap_or_default() as usize;
+                let scopes = SCOPES
+                    .iter()
+                    .enumerate()
+                    .map(|(kind, scope)| {
+                        let name = match scope {
+                            Scope::Stack => "Stack",
+                            Scope::Memory => "Memory",
+                            Scope::Storage => "Storage",
+                            Scope::Transient => "Transient Storage",
+                        };
+                        json!({
+                            "name": name,
+                            "variablesReference": frame * SCOPES.len() + kind + 1,
+                            "expensive": false,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                self.respond(request, json!({"scopes": scopes}))
+            }
+            "variables" => {
+                let reference = args["variablesReference"].as_u64().unwrap_or_default() as usize;
+                let variables = self.variables(reference);
+                self.respond(request, json!({"variables": variables}))
+            }
+            "source" => {
+                let reference = args["sourceReference"].as_u64().unwrap_or_default() as usize;
+                match reference.checked_sub(1).and_then(|i| self.sources.get(i)) {
+                    Some(source) => {
+                        let content = source.content.clone();
+                        self.respond(request, json!({"content": content}))
+                    }
+                    None => self.fail(request, format!("unknown source {reference}")),
+                }
+            }
+            command @ ("continue" | "next" | "stepIn" | "stepOut") => {
+                let mode = match command {
+                    "continue" => Resume::Continue,
+                    "next" => Resume::Over,
+                    "stepIn" => Resume::In,
+                    _ => Resume::Out,
+                };
+                self.respond(request, json!({"allThreadsContinued": true}))?;
+                self.resume(mode).await
+            }
+            // Execution only runs while handling a request, so it is always paused
+            "pause" => self.respond(request, json!({})),
+            "disconnect" | "terminate" => {
+                self.respond(request, json!({}))?;
+                return Ok(false);
+            }
+            command => self.fail(request, format!("unsupported request '{command}'")),
+        };
+        result.map(|_| true)
+    }
+
+    async fn launch(&mut self, args: &Value) -> eyre::Result<()> {
+        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or_default();
+        let tx = if let Some(block) = args["block"].as_u64() {
+            let index = args["tx"].as_u64().unwrap_or_default() as usize;
+            let url = match args["rpc"].as_str() {
+                Some(url) => url.to_string(),
+                None => std::env::var("URL")?,
+            };
+            self.fork(&url, block, index).await?
+        } else {
+            self.local(args).await?
+        };
+        self.tx = Some(tx);
+        Ok(())
+    }
+
+    /// Run local code against an empty state
+    async fn local(&mut self, args: &Value) -> eyre::Result<Prepared<NoopTracer>> {
+        let from = Address::try_from("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266")?;
+        let to = Address::try_from("0x5fbdb2315678afecb367f032d93f642f64180aa3")?;
+        let code = parse_bytes(args.get("code"))?;
+        let gas = args["gas"].as_u64().unwrap_or(1_000_000);
+
+        self.ext = Ext::local();
+        self.ext.state.insert(from, Account::default());
+        self.ext.state.insert(
+            to,
+            Account {
+                code: (code.clone(), Word::from_bytes(&keccak256(&code))),
+                ..Account::default()
+            },
+        );
+        Solenoid::new()
+            .execute(to, "", &parse_bytes(args.get("data"))?)
+            .with_spec(Spec::default())
+            .with_sender(from)
+            .with_value(parse_word(args.get("value"))?)
+            .with_gas(Word::from(gas))
+            .ready()
+            .prepare(NoopTracer, &mut self.ext)
+            .await
+    }
+
+    /// Replay the block up to the transaction, then apply the prelude of the
+    /// transaction the way `Runner::apply` does
+    async fn fork(
+        &mut self,
+        url: &str,
+        number: u64,
+        index: usize,
+    ) -> eyre::Result<Prepared<NoopTracer>> {
+        let eth = EthClient::new(url);
+        let chain_id = eth.chain_id().await?;
+        let chain = Chain::by_id(chain_id).ok_or_else(|| eyre!("unknown chain id {chain_id}"))?;
+        let block = eth.get_full_block(Word::from(number)).await?;
+        let header = block.header;
+        self.ext = Ext::at_number(Word::from(number - 1), eth).await?;
+
+        let tx = block
+            .transactions
+            .get(index)
+            .ok_or_else(|| eyre!("no transaction {index} in block {number}"))?;
+        for prev in &block.transactions[..index] {
+            self.ext.reset(tx_context(prev, header.base_fee));
+            runner(prev, &header, &chain).apply(&mut self.ext).await?;
+        }
+        self.ext.reset(tx_context(tx, header.base_fee));
+        runner(tx, &header, &chain)
+            .prepare(NoopTracer, &mut self.ext)
+            .await
+    }
+
+    /// Source reference of the disassembly of the code
+    fn source(&mut self, code: &Bytecode, address: &Address) -> usize {
+        let hash = code_hash(code);
+        if let Some(index) = self.sources.iter().position(|source| source.hash == hash) {
+            return index + 1;
+        }
+        self.sources.push(Source {
+            name: format!("{address}.evm"),
+            hash,
+            content: disassemble(code),
+        });
+        self.sources.len()
+    }
+
+    fn set_breakpoints(&mut self, args: &Value) -> Value {
+        let source = &args["source"];
+        let index = source["sourceReference"]
+            .as_u64()
+            .and_then(|reference| (reference as usize).checked_sub(1))
+            .or_else(|| {
+                let name = source["name"].as_str().or(source["path"].as_str())?;
+                self.sources.iter().position(|source| source.name == name)
+            });
+        let lines = args["breakpoints"]
+            .as_array()
+            .map(|breakpoints| {
+                breakpoints
+                    .iter()
+                    .filter_map(|bp| bp["line"].as_u64())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let Some(source) = index.and_then(|index| self.sources.get(index)) else {
+            let breakpoints = lines
+                .iter()
+                .map(|line| json!({"verified": false, "line": line, "message": "unknown source"}))
+                .collect::<Vec<_>>();
+            return json!({"breakpoints": breakpoints});
+        };
+        let count = source.content.lines().count() as u64;
+        let valid = lines
+            .iter()
+            .filter(|line| (1..=count).contains(*line))
+            .map(|line| *line as usize - 1)
+            .collect();
+        self.breakpoints.lines.insert(source.hash, valid);
+        let breakpoints = lines
+            .iter()
+            .map(|line| json!({"verified": (1..=count).contains(line), "line": line}))
+            .collect::<Vec<_>>();
+        json!({"breakpoints": breakpoints})
+    }
+
+    fn set_function_breakpoints(&mut self, args: &Value) -> Value {
+        self.breakpoints.opcodes.clear();
+        self.breakpoints.contracts.clear();
+        let names = args["breakpoints"]
+            .as_array()
+            .map(|breakpoints| {
+                breakpoints
+                    .iter()
+                    .filter_map(|bp| bp["name"].as_str())
+                    .map(|name| name.trim().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let breakpoints = names
+            .into_iter()
+            .map(|name| {
+                let verified = if let Ok(address) = Address::try_from(name.as_str()) {
+                    self.breakpoints.contracts.insert(address)
+                } else if is_opcode(&name.to_uppercase()) {
+                    self.breakpoints.opcodes.insert(name.to_uppercase())
+                } else {
+                    false
+                };
+                json!({"verified": verified})
+            })
+            .collect::<Vec<_>>();
+        json!({"breakpoints": breakpoints})
+    }
+
+    fn set_instruction_breakpoints(&mut self, args: &Value) -> Value {
+        self.breakpoints.offsets.clear();
+        let breakpoints = args["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .iter()
+            .map(|bp| {
+                let offset = bp["instructionReference"]
+                    .as_str()
+                    .and_then(parse_offset)
+                    .and_then(|pc| pc.checked_add_signed(bp["offset"].as_i64()? as isize));
+                if let Some(pc) = offset {
+                    self.breakpoints.offsets.insert(pc);
+                }
+                json!({"verified": offset.is_some()})
+            })
+            .collect::<Vec<_>>();
+        json!({"breakpoints": breakpoints})
+    }
+
+    async fn resume(&mut self, mode: Resume) -> eyre::Result<()> {
+        let Self {
+            tx: Some(Prepared { session, .. }),
+            ext,
+            breakpoints,
+            ..
+        } = self
+        else {
+            return self.terminated().await;
+        };
+        let depth = session.depth();
+        // Leave the current instruction first, so its breakpoint does not hit again
+        session.step(ext).await;
+        let hit = |session: &Session<NoopTracer>| {
+            let Some(instruction) = session.instruction() else {
+                return false;
+            };
+            let frame = session.frames().last().expect("current frame");
+            breakpoints.hit(&frame, instruction)
+        };
+        let mut reason = "step";
+        let paused = match mode {
+            Resume::In => !session.is_done(),
+            Resume::Over => {
+                session
+                    .run_until(ext, |s| s.depth() <= depth || hit(s))
+                    .await
+            }
+            Resume::Out => {
+                session
+                    .run_until(ext, |s| s.depth() < depth || hit(s))
+                    .await
+            }
+            Resume::Continue => {
+                reason = "breakpoint";
+                session.run_until(ext, hit).await
+            }
+        };
+        if paused && mode != Resume::Continue && hit(session) {
+            reason = "breakpoint";
+        }
+        if paused {
+            self.stopped(reason)
+        } else {
+            self.terminated().await
+        }
+    }
+
+    fn stopped(&mut self, reason: &str) -> eyre::Result<()> {
+        self.event(
+            "stopped",
+            json!({"reason": reason, "threadId": 1, "allThreadsStopped": true}),
+        )
+    }
+
+    async fn terminated(&mut self) -> eyre::Result<()> {
+        if let Some(tx) = self.tx.take() {
+            let CallResult { evm, ret, gas, .. } = tx.finish(&mut self.ext).await?;
+            let status = if evm.reverted { "REVERT" } else { "OK" };
+            self.event(
+                "output",
+                json!({
+                    "category": "console",
+                    "output": format!(
+                        "{status}: gas used {}, return data 0x{}\n",
+                        gas.gas_use,
+                        hex::encode(ret)
+                    ),
+                }),
+            )?;
+            self.event("exited", json!({"exitCode": evm.reverted as u8}))?;
+        }
+        self.event("terminated", json!({}))
+    }
+
+    fn stack_trace(&mut self) -> Value {
+        let Some(tx) = self.tx.take() else {
+            return json!({"stackFrames": [], "totalFrames": 0});
+        };
+        let mut frames = tx
+            .session
+            .frames()
+            .enumerate()
+            .map(|(id, frame)| {
+                let reference = self.source(frame.code, &frame.call.to);
+                let source = &self.sources[reference - 1];
+                let offset = frame
+                    .code
+                    .instructions
+                    .get(frame.evm.pc)
+                    .map(|instruction| instruction.offset)
+                    .unwrap_or(frame.code.bytecode.len());
+                json!({
+                    "id": id,
+                    "name": format!("{:?} {}", frame.ctx.call_type, frame.call.to),
+                    "source": {"name": source.name, "sourceReference": reference},
+                    "line": frame.evm.pc + 1,
+                    "column": 1,
+                    "instructionPointerReference": format!("{offset:#x}"),
+                })
+            })
+            .collect::<Vec<_>>();
+        self.tx = Some(tx);
+        frames.reverse();
+        json!({"totalFrames": frames.len(), "stackFrames": frames})
+    }
+
+    fn variables(&self, reference: usize) -> Vec<Value> {
+        let Some(Prepared { session, .. }) = self.tx.as_ref() else {
+            return vec![];
+        };
+        let Some(index) = reference.checked_sub(1) else {
+            return vec![];
+        };
+        let Some(frame) = session.frames().nth(index / SCOPES.len()) else {
+            return vec![];
+        };
+        let variable = |name: String, value: String| json!({"name": name, "value": value, "variablesReference": 0});
+        match SCOPES[index % SCOPES.len()] {
+            // Top of the stack first
+            Scope::Stack => frame
+                .evm
+                .stack
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(i, value)| variable(i.to_string(), format!("{value:#x}")))
+                .collect(),
+            Scope::Memory => frame
+                .evm
+                .memory
+                .chunks(32)
+                .enumerate()
+                .map(|(i, chunk)| variable(format!("{:#06x}", i * 32), hex::encode(chunk)))
+                .collect(),
+            // Only the slots loaded so far
+            Scope::Storage => self
+                .ext
+                .state
+                .get(&frame.address())
+                .map(|account| {
+                    account
+                        .state
+                        .iter()
+                        .collect::<BTreeMap<_, _>>()
+                        .into_iter()
+                        .map(|(key, val)| variable(format!("{key:#x}"), format!("{val:#x}")))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Scope::Transient => self
+                .ext
+                .transient
+                .iter()
+                .filter(|((address, _), _)| address == &frame.address())
+                .map(|((_, key), val)| (key, val))
+                .collect::<BTreeMap<_, _>>()
+                .into_iter()
+                .map(|(key, val)| variable(format!("{key:#x}"), format!("{val:#x}")))
+                .collect(),
+        }
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> eyre::Result<()> {
+    dotenv::dotenv().ok();
+
+    let mut stdin = std::io::stdin().lock();
+    let mut debugger = Debugger::new(std::io::stdout());
+    while let Some(request) = read_message(&mut stdin)? {
+        if !debugger.handle(&request).await? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(mut bytes: &[u8]) -> Vec<Value> {
+        let mut messages = vec![];
+        while let Some(message) = read_message(&mut bytes).unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    async fn request(debugger: &mut Debugger<Vec<u8>>, command: &str, arguments: Value) {
+        let request =
+            json!({"seq": 1, "type": "request", "command": command, "arguments": arguments});
+        assert!(debugger.handle(&request).await.unwrap());
+    }
+
+    #[test]
+    fn test_framing() {
+        let mut buffer = vec![];
+        write_message(&mut buffer, &json!({"seq": 1})).unwrap();
+        write_message(&mut buffer, &json!({"seq": 2})).unwrap();
+        assert!(buffer.starts_with(b"Content-Length: 9\r\n\r\n{\"seq\":1}"));
+        assert_eq!(
+            messages(&buffer),
+            vec![json!({"seq": 1}), json!({"seq": 2})]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_opcode_breakpoint() {
+        let mut debugger = Debugger::new(vec![]);
+        // ADD(1, 2); SSTORE(0, ...); STOP
+        let code = "6001600201600055";
+        request(
+            &mut debugger,
+            "launch",
+            json!({"code": code, "stopOnEntry": true}),
+        )
+        .await;
+        request(
+            &mut debugger,
+            "setFunctionBreakpoints",
+            json!({"breakpoints": [{"name": "sstore"}]}),
+        )
+        .await;
+        request(&mut debugger, "configurationDone", json!({})).await;
+        request(&mut debugger, "continue", json!({"threadId": 1})).await;
+
+        let session = &debugger.tx.as_ref().unwrap().session;
+        assert_eq!(session.instruction().unwrap().opcode.name(), "SSTORE");
+        assert_eq!(debugger.variables(1).len(), 2);
+        assert_eq!(debugger.variables(1)[1]["value"], json!("0x3"));
+
+        request(&mut debugger, "continue", json!({"threadId": 1})).await;
+        assert!(debugger.tx.is_none());
+        let events = messages(&debugger.writer)
+            .into_iter()
+            .filter(|message| message["type"] == "event")
+            .map(|message| message["event"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec!["stopped", "stopped", "output", "exited", "terminated"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revert() {
+        let mut debugger = Debugger::new(vec![]);
+        // SSTORE(0, 1); REVERT(0, 0)
+        let code = "60016000555f5ffd";
+        request(&mut debugger, "launch", json!({"code": code})).await;
+        request(&mut debugger, "configurationDone", json!({})).await;
+        assert!(debugger.tx.is_none());
+
+        // Settled like any transaction: the write is undone, not the nonce
+        let from = Address::try_from("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266").unwrap();
+        let to = Address::try_from("0x5fbdb2315678afecb367f032d93f642f64180aa3").unwrap();
+        let ext = &mut debugger.ext;
+        assert!(ext.get(&to, &Word::zero()).await.unwrap().is_zero());
+        assert_eq!(ext.nonce(&from).await.unwrap(), Word::one());
+    }
+}
@@ -0,0 +1,202 @@
+use std::{cell::Cell, collections::BTreeMap, fmt::Write as _};
+
+use evm_common::{hash::keccak256, word::Word};
+use evm_event::{AccountEvent, Event, EventData};
+
+use crate::{
+    artifact::{Contract, Source},
+    tracer::{EventTracer, Trace},
+};
+
+const JUMPI: u8 = 0x57;
+
+// Line of a JUMPI and its outcomes, if it was executed
+type Branch = (usize, Option<(u64, u64)>);
+
+/// Executed instructions and branch outcomes of one bytecode, by pc
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CodeCoverage {
+    /// Number of executions of the instruction at the pc
+    pub hits: BTreeMap<usize, u64>,
+    /// JUMPI at the pc: number of times the jump was taken and not taken
+    pub branches: BTreeMap<usize, (u64, u64)>,
+}
+
+impl CodeCoverage {
+    fn merge(&mut self, other: CodeCoverage) {
+        for (pc, hits) in other.hits {
+            *self.hits.entry(pc).or_default() += hits;
+        }
+        for (pc, (taken, not_taken)) in other.branches {
+            let branch = self.branches.entry(pc).or_default();
+            branch.0 += taken;
+            branch.1 += not_taken;
+        }
+    }
+}
+
+/// Coverage accumulated across executions, by codehash
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Coverage {
+    pub codes: BTreeMap<Word, CodeCoverage>,
+}
+
+impl Coverage {
+    pub fn merge(&mut self, other: Coverage) {
+        for (codehash, code) in other.codes {
+            self.codes.entry(codehash).or_default().merge(code);
+        }
+    }
+
+    pub fn get(&self, bytecode: &[u8]) -> Option<&CodeCoverage> {
+        self.codes.get(&Word::from_bytes(&keccak256(bytecode)))
+    }
+
+    /// LCOV report of the contracts: line and branch (JUMPI) coverage of
+    /// the source files. Files are indexed by their solc source id.
+    pub fn lcov(&self, contracts: &[Contract], sources: &[Source]) -> String {
+        // Hits by line and JUMPI outcomes by line, per source id
+        let mut lines: BTreeMap<usize, BTreeMap<usize, u64>> = BTreeMap::new();
+        let mut branches: BTreeMap<usize, Vec<Branch>> = BTreeMap::new();
+        let empty = CodeCoverage::default();
+        for contract in contracts {
+            let coverage = self.get(&contract.bytecode).unwrap_or(&empty);
+            for (pc, op, entry) in contract.instructions() {
+                let Some((file, line)) = entry
+                    .file
+                    .and_then(|file| Some((file, sources.get(file)?.position(entry.offset)?.0)))
+                else {
+                    continue;
+                };
+                let hits = coverage.hits.get(&pc).copied().unwrap_or_default();
+                let line_hits = lines.entry(file).or_default().entry(line).or_default();
+                *line_hits = (*line_hits).max(hits);
+                if op == JUMPI {
+                    let outcome = coverage.branches.get(&pc).copied();
+                    branches.entry(file).or_default().push((line, outcome));
+                }
+            }
+        }
+
+        let mut out = String::new();
+        for (file, lines) in lines {
+            let _ = writeln!(out, "TN:");
+            let _ = writeln!(out, "SF:{}", sources[file].path);
+            let branches = branches.remove(&file).unwrap_or_default();
+            let mut hit = 0;
+            for (block, (line, outcome)) in branches.iter().enumerate() {
+                match outcome {
+                    Some((taken, not_taken)) => {
+                        hit += (*taken > 0) as usize + (*not_taken > 0) as usize;
+                        let _ = writeln!(out, "BRDA:{line},{block},0,{taken}");
+                        let _ = writeln!(out, "BRDA:{line},{block},1,{not_taken}");
+                    }
+                    None => {
+                        let _ = writeln!(out, "BRDA:{line},{block},0,-");
+                        let _ = writeln!(out, "BRDA:{line},{block},1,-");
+                    }
+                }
+            }
+            let _ = writeln!(out, "BRF:{}", branches.len() * 2);
+            let _ = writeln!(out, "BRH:{hit}");
+            for (line, hits) in &lines {
+                let _ = writeln!(out, "DA:{line},{hits}");
+            }
+            let _ = writeln!(out, "LF:{}", lines.len());
+            let _ = writeln!(
+                out,
+                "LH:{}",
+                lines.values().filter(|hits| **hits > 0).count()
+            );
+            let _ = writeln!(out, "end_of_record");
+        }
+        out
+    }
+}
+
+/// Records executed instructions and JUMPI outcomes per codehash. The code
+/// of a nested call is known from the `GetCode` event of the caller, the
+/// code of the outermost frame is given to [`CoverageTracer::new`]. Code run
+/// by CREATE is not covered.
+#[derive(Default)]
+pub struct CoverageTracer {
+    coverage: Coverage,
+    // Codehash of the frame, if known
+    code: Option<Word>,
+    // Code announced for the next child frame
+    next: Cell<Option<Word>>,
+    // Pc of the last executed instruction if it was a JUMPI
+    jumpi: Option<usize>,
+}
+
+impl CoverageTracer {
+    /// Tracer for the execution of the bytecode
+    pub fn new(bytecode: &[u8]) -> Self {
+        let codehash = Some(Word::from_bytes(&keccak256(bytecode)));
+        // The outermost frame runs either in this tracer or in its first fork
+        Self {
+            code: codehash,
+            next: Cell::new(codehash),
+            ..Self::default()
+        }
+    }
+
+    pub fn coverage(&self) -> &Coverage {
+        &self.coverage
+    }
+
+    pub fn into_coverage(self) -> Coverage {
+        self.coverage
+    }
+}
+
+impl EventTracer for CoverageTracer {
+    const TRACE: Trace = Trace {
+        events: true,
+        opcodes: true,
+        ..Trace::NONE
+    };
+
+    fn push(&mut self, event: Event) {
+        match event.data {
+            EventData::Account(AccountEvent::GetCode { bytecode, .. }) => {
+                let codehash = Word::from_bytes(&keccak256(bytecode.as_ref()));
+                self.next.set(Some(codehash));
+            }
+            EventData::OpCode(op) => {
+                // Only a fork right after GetCode runs the announced code
+                self.next.set(None);
+                let Some(codehash) = self.code else {
+                    return;
+                };
+                let code = self.coverage.codes.entry(codehash).or_default();
+                *code.hits.entry(op.pc).or_default() += 1;
+                // The jump is not taken if the next instruction follows the JUMPI
+                if let Some(pc) = self.jumpi.take() {
+                    let branch = code.branches.entry(pc).or_default();
+                    if op.pc == pc + 1 {
+                        branch.1 += 1;
+                    } else {
+                        branch.0 += 1;
+                    }
+                }
+                if op.op == JUMPI {
+                    self.jumpi = Some(op.pc);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn fork(&self) -> Self {
+        Self {
+            code: self.next.take(),
+            ..Self::default()
+        }
+    }
+
+    fn join(&mut self, other: Self, _reverted: bool) {
+        // Reverted executions are covered too
+        self.coverage.merge(other.coverage);
+    }
+}
@@ -1,26 +1,88 @@
-use crate::opcodes::{Opcode, get_opcode};
+use crate::{
+    eof::{self, Container},
+    opcodes::{Opcode, get_legacy_opcode, get_opcode},
+    spec::Spec,
+};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Instruction {
     pub opcode: Opcode,
     pub offset: usize,
     pub argument: Option<Vec<u8>>,
+    // Set on the first instruction of every basic block
+    pub block: Option<Block>,
+}
+
+/// Basic block: straight-line run of instructions that is only entered at the
+/// first one. Static gas and stack requirements are validated once on entry,
+/// or per instruction if the block as a whole fails them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Block {
+    /// Sum of static gas costs of the instructions (see `Opcode::static_gas`)
+    pub gas: i64,
+    /// Number of stack items required on entry
+    pub stack_min: usize,
+    /// Maximal growth of the stack within the block
+    pub stack_max: usize,
+}
+
+impl Block {
+    pub(crate) fn of(instructions: &[Instruction]) -> Self {
+        let mut block = Self::default();
+        let mut height = 0i64;
+        for instruction in instructions {
+            let (take, put) = instruction.stack_io();
+            block.gas += instruction.opcode.static_gas();
+            height -= take as i64;
+            if height < 0 {
+                block.stack_min = block.stack_min.max(-height as usize);
+            }
+            height += put as i64;
+            if height > 0 {
+                block.stack_max = block.stack_max.max(height as usize);
+            }
+        }
+        block
+    }
 }
 
 impl Instruction {
     pub(crate) fn is_call(&self) -> bool {
         matches!(
             self.opcode.name,
-            "CALL" | "DELEGATECALL" | "STATICCALL" | "CALLCODE" | "CREATE" | "CREATE2"
+            "CALL"
+                | "DELEGATECALL"
+                | "STATICCALL"
+                | "CALLCODE"
+                | "CREATE"
+                | "CREATE2"
+                | "EXTCALL"
+                | "EXTDELEGATECALL"
+                | "EXTSTATICCALL"
+                | "EOFCREATE"
         )
     }
+
+    fn stack_io(&self) -> (usize, usize) {
+        let immediate = self.argument.as_deref().unwrap_or_default();
+        eof::stack_io(&self.opcode, immediate)
+    }
+}
+
+/// EOF code: the container and the first instruction of every code section
+#[derive(Clone, Debug)]
+pub struct EofCode {
+    pub container: Container,
+    pub sections: Vec<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Bytecode {
     pub bytecode: Vec<u8>,
     pub instructions: Vec<Instruction>,
+    /// Legacy code: offsets of JUMPDESTs, EOF code: offsets of all instructions
     pub jumptable: Vec<(usize, usize)>,
+    pub eof: Option<EofCode>,
 }
 
 impl Bytecode {
@@ -39,17 +101,31 @@ impl Bytecode {
 pub struct Decoder;
 
 impl Decoder {
-    pub fn decode(bytecode: Vec<u8>) -> Bytecode {
+    /// Decode the code of an account: EOF containers are deployed only after
+    /// validation, anything else is legacy code.
+    pub fn decode(bytecode: Vec<u8>, spec: Spec) -> Bytecode {
+        if spec.is_enabled(Spec::Eof)
+            && eof::is_eof(&bytecode)
+            && let Ok(container) = Container::parse(&bytecode)
+        {
+            return Self::decode_eof(container);
+        }
+        Self::decode_legacy(bytecode)
+    }
+
+    /// Decode the code as legacy bytecode, where EOF-only opcodes are undefined
+    pub fn decode_legacy(bytecode: Vec<u8>) -> Bytecode {
         let mut instructions = Vec::new();
         let mut jumptable = Vec::new();
 
         let mut pos = 0;
         while pos < bytecode.len() {
-            let opcode = get_opcode(bytecode[pos]);
+            let opcode = get_legacy_opcode(bytecode[pos]);
             let mut instruction = Instruction {
                 opcode,
                 offset: pos,
                 argument: None,
+                block: None,
             };
 
             // JUMPDEST
@@ -74,10 +150,170 @@ impl Decoder {
             instructions.push(instruction);
         }
 
+        // A block ends before JUMPDEST
+        let starts = instructions
+            .iter()
+            .map(|instruction| instruction.opcode.code == 0x5b)
+            .collect::<Vec<_>>();
+        split_blocks(&mut instructions, &starts);
+
         Bytecode {
             bytecode,
             instructions,
             jumptable,
+            eof: None,
+        }
+    }
+
+    /// Decode the code sections of the (validated) EOF container. Offsets of
+    /// instructions are offsets in the container.
+    pub fn decode_eof(container: Container) -> Bytecode {
+        let mut instructions = Vec::new();
+        let mut jumptable = Vec::new();
+        let mut sections = Vec::with_capacity(container.code_sections());
+        let mut targets = Vec::new();
+
+        for index in 0..container.code_sections() {
+            let code = container.code(index);
+            let base = container.code_offset(index);
+            sections.push(instructions.len());
+            targets.push(base);
+
+            let mut pos = 0;
+            while pos < code.len() {
+                let opcode = get_opcode(code[pos]);
+                let len = immediate_len(code, pos);
+                let argument = (len > 0).then(|| code[pos + 1..pos + 1 + len].to_vec());
+                if let Some(immediate) = &argument {
+                    let jumps = eof::jump_targets(opcode.code, pos, immediate);
+                    targets.extend(jumps.into_iter().map(|target| base + target as usize));
+                }
+                jumptable.push((base + pos, instructions.len()));
+                instructions.push(Instruction {
+                    opcode,
+                    offset: base + pos,
+                    argument,
+                    block: None,
+                });
+                pos += 1 + len;
+            }
+        }
+
+        // A block ends before the start of a code section and before every
+        // target of a relative jump
+        let mut starts = vec![false; instructions.len()];
+        for target in targets {
+            if let Ok(index) = jumptable.binary_search_by_key(&target, |(key, _)| *key) {
+                starts[jumptable[index].1] = true;
+            }
+        }
+        split_blocks(&mut instructions, &starts);
+
+        Bytecode {
+            bytecode: container.bytes.clone(),
+            instructions,
+            jumptable,
+            eof: Some(EofCode {
+                container,
+                sections,
+            }),
         }
     }
 }
+
+fn immediate_len(code: &[u8], pos: usize) -> usize {
+    // Immediates of validated code are never truncated
+    eof::immediate_len(code, pos).min(code.len() - pos - 1)
+}
+
+/// Split instructions into basic blocks: a block ends before a block start
+/// or after an instruction that ends it (see `Opcode::is_block_end`)
+fn split_blocks(instructions: &mut [Instruction], starts: &[bool]) {
+    let mut start = 0;
+    for index in 0..instructions.len() {
+        let is_last = starts.get(index + 1).is_none_or(|is_start| *is_start);
+        if is_last || instructions[index].opcode.is_block_end() {
+            instructions[start].block = Some(Block::of(&instructions[start..=index]));
+            start = index + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        // PUSH1 4; JUMP; INVALID; JUMPDEST; ADD; PUSH0; SSTORE; GAS; POP; STOP
+        let code = Decoder::decode(
+            hex::decode("600456fe5b015f555a5000").unwrap(),
+            Spec::default(),
+        );
+        let blocks = code
+            .instructions
+            .iter()
+            .map(|instruction| instruction.block.map(|block| (instruction.offset, block)))
+            .collect::<Vec<_>>();
+        let block = |offset, gas, stack_min, stack_max| {
+            Some((
+                offset,
+                Block {
+                    gas,
+                    stack_min,
+                    stack_max,
+                },
+            ))
+        };
+        assert_eq!(
+            blocks,
+            vec![
+                block(0, 11, 0, 1),
+                None,
+                block(3, 0, 0, 0),
+                block(4, 6, 2, 0),
+                None,
+                None,
+                None,
+                block(8, 0, 0, 1),
+                block(9, 2, 1, 0),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_eof() {
+        // Code section 0: PUSH0; RJUMPI +1; INVALID; STOP
+        // Data section: 0xaa
+        let code = hex::decode(concat!(
+            "ef00010100040200010006ff00010000800001",
+            "5fe10001fe00aa"
+        ))
+        .unwrap();
+        let legacy = Decoder::decode(code.clone(), Spec::Osaka);
+        let code = Decoder::decode(code, Spec::Eof);
+        let eof = code.eof.as_ref().expect("EOF code");
+        assert_eq!(eof.sections, vec![0]);
+        assert_eq!(eof.container.data(), &[0xaa]);
+        let offsets = code
+            .instructions
+            .iter()
+            .map(|instruction| (instruction.offset, instruction.block.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            offsets,
+            vec![(19, true), (20, false), (23, true), (24, true)]
+        );
+        assert_eq!(code.resolve_jump(24), Some(3));
+
+        // Legacy code before EOF is enabled
+        assert!(legacy.eof.is_none());
+        assert_eq!(legacy.instructions[0].opcode.code, 0xfe);
+
+        // EOF-only opcodes are undefined in legacy code
+        let code = Decoder::decode(hex::decode("e00000").unwrap(), Spec::Eof);
+        assert!(code.eof.is_none());
+        assert_eq!(code.instructions[0].opcode.code, 0xfe);
+    }
+}
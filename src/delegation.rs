@@ -0,0 +1,165 @@
+use evm_common::{
+    address::Address,
+    block::Authorization,
+    hash::keccak256,
+    word::{Word, word},
+};
+
+use crate::precompiles;
+
+/// EIP-7702: delegation designator, CODE = <0xef0100> + <20 bytes address>
+pub const DESIGNATOR: [u8; 3] = [0xef, 0x01, 0x00];
+
+/// EIP-7702: intrinsic cost of every authorization tuple
+pub const PER_EMPTY_ACCOUNT_COST: i64 = 25000;
+
+/// EIP-7702: cost of an authorization to an existing authority, which is
+/// refunded `PER_EMPTY_ACCOUNT_COST - PER_AUTH_BASE_COST`
+pub const PER_AUTH_BASE_COST: i64 = 12500;
+
+/// EIP-7702: prefix of the signed authorization message
+const MAGIC: u8 = 0x05;
+
+/// Address the code delegates to, if the code is a delegation designator
+pub fn target(code: &[u8]) -> Option<Address> {
+    if code.len() == 23 && code.starts_with(&DESIGNATOR) {
+        Address::try_from(&code[3..]).ok()
+    } else {
+        None
+    }
+}
+
+/// Delegation designator pointing to the given address
+pub fn designator(address: &Address) -> Vec<u8> {
+    let mut code = DESIGNATOR.to_vec();
+    code.extend_from_slice(&address.0);
+    code
+}
+
+/// Message signed by the authority: keccak256(MAGIC || rlp([chain_id, address, nonce]))
+pub fn signing_hash(auth: &Authorization) -> [u8; 32] {
+    let mut payload = Vec::with_capacity(64);
+    payload.extend(rlp_bytes(&trim(&auth.chain_id.into_bytes())));
+    payload.extend(rlp_bytes(&auth.address.0));
+    payload.extend(rlp_bytes(&trim(&auth.nonce.into_bytes())));
+
+    let mut message = vec![MAGIC];
+    message.extend(rlp_list(&payload));
+    keccak256(&message)
+}
+
+/// Recover the authority (signer) of the authorization tuple.
+/// Returns None for malformed signatures: y_parity must be 0 or 1 and
+/// s must be in the lower half of the curve order (EIP-2).
+pub fn authority(auth: &Authorization) -> Option<Address> {
+    let secp256k1_n_half = word("7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0");
+    if auth.y_parity > Word::one() || auth.s > secp256k1_n_half {
+        return None;
+    }
+
+    let mut input = [0u8; 128];
+    input[0..32].copy_from_slice(&signing_hash(auth));
+    input[63] = 27 + auth.y_parity.as_u64() as u8;
+    input[64..96].copy_from_slice(&auth.r.into_bytes());
+    input[96..128].copy_from_slice(&auth.s.into_bytes());
+
+    let recovered = precompiles::ecrecover(&input).ok()?;
+    let address = Address::try_from(&recovered[12..]).ok()?;
+    (!address.is_zero()).then_some(address)
+}
+
+fn trim(bytes: &[u8]) -> Vec<u8> {
+    let skip = bytes.iter().take_while(|b| **b == 0).count();
+    bytes[skip..].to_vec()
+}
+
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut out = rlp_prefix(0x80, bytes.len());
+    out.extend_from_slice(bytes);
+    out
+}
+
+fn rlp_list(payload: &[u8]) -> Vec<u8> {
+    let mut out = rlp_prefix(0xc0, payload.len());
+    out.extend_from_slice(payload);
+    out
+}
+
+fn rlp_prefix(offset: u8, len: usize) -> Vec<u8> {
+    if len < 56 {
+        vec![offset + len as u8]
+    } else {
+        let len = trim(&len.to_be_bytes());
+        let mut out = vec![offset + 55 + len.len() as u8];
+        out.extend(len);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use k256::ecdsa::SigningKey;
+
+    use super::*;
+
+    fn sign(key: &SigningKey, chain_id: Word, address: Address, nonce: u64) -> Authorization {
+        let mut auth = Authorization {
+            chain_id,
+            address,
+            nonce: Word::from(nonce),
+            y_parity: Word::zero(),
+            r: Word::zero(),
+            s: Word::zero(),
+        };
+        let hash = signing_hash(&auth);
+        let (signature, recovery_id) = key.sign_prehash_recoverable(&hash).unwrap();
+        let bytes = signature.to_bytes();
+        auth.r = Word::from_bytes(&bytes[..32]);
+        auth.s = Word::from_bytes(&bytes[32..]);
+        auth.y_parity = Word::from(recovery_id.to_byte() as u64);
+        auth
+    }
+
+    fn address_of(key: &SigningKey) -> Address {
+        let pubkey = key.verifying_key().to_encoded_point(false);
+        let hash = keccak256(&pubkey.as_bytes()[1..]);
+        Address::try_from(&hash[12..]).unwrap()
+    }
+
+    #[test]
+    fn test_designator() {
+        let address = Address::from([0x42u8; 20]);
+        let code = designator(&address);
+        assert_eq!(code.len(), 23);
+        assert_eq!(target(&code), Some(address));
+        assert_eq!(target(&code[..22]), None);
+        assert_eq!(target(&[0x60, 0x00]), None);
+    }
+
+    #[test]
+    fn test_authority() {
+        let key = SigningKey::from_slice(&[0x11u8; 32]).unwrap();
+        let address = Address::from([0x42u8; 20]);
+
+        let auth = sign(&key, Word::one(), address, 0);
+        assert_eq!(authority(&auth), Some(address_of(&key)));
+
+        // Large values use the long form of the RLP list length
+        let auth = sign(&key, Word::max(), address, u64::MAX - 1);
+        assert_eq!(authority(&auth), Some(address_of(&key)));
+
+        let mut invalid = auth.clone();
+        invalid.y_parity = Word::from(27);
+        assert_eq!(authority(&invalid), None);
+
+        // High-s signatures are rejected, unlike in the ecrecover precompile
+        let secp256k1_n = word("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141");
+        let mut invalid = auth.clone();
+        invalid.s = secp256k1_n - auth.s;
+        invalid.y_parity = Word::one() - auth.y_parity;
+        assert_eq!(authority(&invalid), None);
+    }
+}
@@ -0,0 +1,814 @@
+use std::{collections::HashSet, ops::Range};
+
+use thiserror::Error;
+
+use evm_common::{address::Address, hash::keccak256, word::Word};
+
+use crate::opcodes::{Opcode, get_opcode};
+
+/// EIP-3540: EOF container prefix, followed by the version byte
+pub const MAGIC: [u8; 2] = [0xef, 0x00];
+
+const VERSION: u8 = 0x01;
+
+const KIND_TYPES: u8 = 0x01;
+const KIND_CODE: u8 = 0x02;
+const KIND_CONTAINER: u8 = 0x03;
+const KIND_DATA: u8 = 0xff;
+const TERMINATOR: u8 = 0x00;
+
+const MAX_CODE_SECTIONS: usize = 1024;
+const MAX_CONTAINERS: usize = 256;
+const STACK_LIMIT: usize = 1024;
+
+/// EIP-4750: `outputs` of a code section that never returns (RETF is not allowed)
+pub const NON_RETURNING: u8 = 0x80;
+
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum EofError {
+    #[error("Invalid magic or version")]
+    InvalidMagic,
+    #[error("Invalid header")]
+    InvalidHeader,
+    #[error("Container size does not match the header")]
+    InvalidSize,
+    #[error("Invalid type of code section {0}")]
+    InvalidType(usize),
+    #[error("Undefined opcode {op:#04x} at {offset}")]
+    UndefinedOpcode { offset: usize, op: u8 },
+    #[error("Truncated immediate at {0}")]
+    TruncatedImmediate(usize),
+    #[error("Invalid relative jump at {0}")]
+    InvalidJump(usize),
+    #[error("Invalid code section reference at {0}")]
+    InvalidSection(usize),
+    #[error("Invalid container reference at {0}")]
+    InvalidContainer(usize),
+    #[error("Data offset out of bounds at {0}")]
+    InvalidDataOffset(usize),
+    #[error("Code section {0} ends without a terminating instruction")]
+    MissingTerminator(usize),
+    #[error("Invalid stack height at {0}")]
+    InvalidStack(usize),
+    #[error("Unreachable instruction at {0}")]
+    UnreachableCode(usize),
+    #[error("Unreachable code section {0}")]
+    UnreachableSection(usize),
+    #[error("Opcode {op:#04x} at {offset} is not allowed in {kind:?} container")]
+    InvalidKind {
+        offset: usize,
+        op: u8,
+        kind: ContainerKind,
+    },
+    #[error("Subcontainer {0} is not referenced exactly once by kind")]
+    InvalidSubcontainer(usize),
+    #[error("Truncated data section")]
+    TruncatedData,
+}
+
+/// Code running in the container: initcode returns the deployed container
+/// with RETURNCONTRACT, runtime code returns data with RETURN
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContainerKind {
+    Initcode,
+    Runtime,
+}
+
+/// EIP-4750: signature of a code section
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CodeType {
+    pub inputs: u8,
+    pub outputs: u8,
+    pub max_stack_increase: u16,
+}
+
+impl CodeType {
+    pub fn is_returning(&self) -> bool {
+        self.outputs != NON_RETURNING
+    }
+}
+
+/// Parsed EOF v1 container. Sections are kept as ranges of the raw bytes.
+#[derive(Clone, Debug)]
+pub struct Container {
+    pub bytes: Vec<u8>,
+    pub types: Vec<CodeType>,
+    code: Vec<Range<usize>>,
+    containers: Vec<Range<usize>>,
+    data: Range<usize>,
+    /// Data size declared in the header: more than the actual data only in
+    /// containers deployed by RETURNCONTRACT, which appends the rest
+    pub data_size: usize,
+    // Position of the data size in the header
+    data_size_at: usize,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn u8(&mut self) -> Result<u8, EofError> {
+        let byte = *self.bytes.get(self.pos).ok_or(EofError::InvalidHeader)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn u16(&mut self) -> Result<usize, EofError> {
+        Ok(((self.u8()? as usize) << 8) | self.u8()? as usize)
+    }
+
+    fn u32(&mut self) -> Result<usize, EofError> {
+        Ok((self.u16()? << 16) | self.u16()?)
+    }
+
+    fn expect(&mut self, kind: u8) -> Result<(), EofError> {
+        if self.u8()? != kind {
+            return Err(EofError::InvalidHeader);
+        }
+        Ok(())
+    }
+
+    /// Section count followed by the section sizes
+    fn sizes(&mut self, max: usize, wide: bool) -> Result<Vec<usize>, EofError> {
+        let count = self.u16()?;
+        if count == 0 || count > max {
+            return Err(EofError::InvalidHeader);
+        }
+        let mut sizes = Vec::with_capacity(count);
+        for _ in 0..count {
+            let size = if wide { self.u32()? } else { self.u16()? };
+            if size == 0 {
+                return Err(EofError::InvalidHeader);
+            }
+            sizes.push(size);
+        }
+        Ok(sizes)
+    }
+}
+
+pub fn is_eof(code: &[u8]) -> bool {
+    code.starts_with(&MAGIC)
+}
+
+/// EIP-7620: address of the contract created by EOFCREATE
+pub fn create_address(sender: &Address, salt: &Word) -> Address {
+    let mut buffer = Vec::with_capacity(1 + 32 + 32);
+    buffer.push(0xffu8);
+    buffer.extend_from_slice(&[0u8; 12]);
+    buffer.extend_from_slice(&sender.0);
+    buffer.extend_from_slice(&salt.into_bytes());
+    let mut hash = keccak256(&buffer);
+    hash[0..12].copy_from_slice(&[0u8; 12]);
+    Address::from(&Word::from_bytes(&hash))
+}
+
+/// Number of immediate bytes following the opcode at `pos`
+pub fn immediate_len(code: &[u8], pos: usize) -> usize {
+    match code[pos] {
+        op @ 0x60..=0x7f => (op - 0x5f) as usize,
+        0xd1 | 0xe0 | 0xe1 | 0xe3 | 0xe5 => 2,
+        0xe6..=0xe8 | 0xec | 0xee => 1,
+        // RJUMPV: max_index, then (max_index + 1) relative offsets
+        0xe2 => code
+            .get(pos + 1)
+            .map_or(1, |max| 1 + 2 * (*max as usize + 1)),
+        _ => 0,
+    }
+}
+
+/// Targets of RJUMP, RJUMPI and RJUMPV as offsets in the code section
+pub fn jump_targets(op: u8, offset: usize, immediate: &[u8]) -> Vec<isize> {
+    let next = (offset + 1 + immediate.len()) as isize;
+    let relative = |bytes: &[u8]| i16::from_be_bytes([bytes[0], bytes[1]]) as isize;
+    match op {
+        0xe0 | 0xe1 => vec![next + relative(immediate)],
+        0xe2 => immediate[1..]
+            .chunks(2)
+            .map(|chunk| next + relative(chunk))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// EIP-663: EXCHANGE swaps the stack items at these depths (1 is the top)
+pub fn exchange_operands(immediate: u8) -> (usize, usize) {
+    let n = (immediate >> 4) as usize + 1;
+    let m = (immediate & 0x0f) as usize + 1;
+    (n + 1, n + m + 1)
+}
+
+/// Stack inputs and outputs of the instruction, including the EIP-663
+/// instructions that take the stack depth from the immediate
+pub fn stack_io(opcode: &Opcode, immediate: &[u8]) -> (usize, usize) {
+    match (opcode.code, immediate) {
+        (0xe6, [n]) => (*n as usize + 1, *n as usize + 2),
+        (0xe7, [n]) => (*n as usize + 2, *n as usize + 2),
+        (0xe8, [imm]) => {
+            let (_, m) = exchange_operands(*imm);
+            (m, m)
+        }
+        _ => opcode.stack_io(),
+    }
+}
+
+fn is_terminating(op: u8) -> bool {
+    matches!(op, 0x00 | 0xe4 | 0xe5 | 0xee | 0xf3 | 0xfd | 0xfe)
+}
+
+/// Legacy opcodes that are not allowed in EOF code: code introspection,
+/// gas observability, dynamic jumps and legacy calls and creates
+fn is_banned(op: u8) -> bool {
+    matches!(
+        op,
+        0x38 | 0x39 | 0x3b | 0x3c | 0x3f | 0x56 | 0x57 | 0x58 | 0x5a | 0xf0
+            ..=0xf2 | 0xf4 | 0xf5 | 0xfa | 0xff
+    )
+}
+
+impl Container {
+    /// Parse the container header and split the body into sections
+    pub fn parse(bytes: &[u8]) -> Result<Self, EofError> {
+        let (container, size) = Self::parse_header(bytes)?;
+        // Only the data section can be shorter than declared
+        if bytes.len() > size || container.data.start > bytes.len() {
+            return Err(EofError::InvalidSize);
+        }
+        Ok(container)
+    }
+
+    /// Full size of the container declared by the header (EIP-7698: creation
+    /// transactions append the calldata to the initcode container)
+    pub fn declared_size(bytes: &[u8]) -> Result<usize, EofError> {
+        Self::parse_header(bytes).map(|(_, size)| size)
+    }
+
+    fn parse_header(bytes: &[u8]) -> Result<(Self, usize), EofError> {
+        if !is_eof(bytes) || bytes.get(2) != Some(&VERSION) {
+            return Err(EofError::InvalidMagic);
+        }
+        let mut reader = Reader { bytes, pos: 3 };
+        reader.expect(KIND_TYPES)?;
+        let types_size = reader.u16()?;
+        reader.expect(KIND_CODE)?;
+        let code_sizes = reader.sizes(MAX_CODE_SECTIONS, false)?;
+        if types_size != code_sizes.len() * 4 {
+            return Err(EofError::InvalidHeader);
+        }
+        let container_sizes = if bytes.get(reader.pos) == Some(&KIND_CONTAINER) {
+            reader.pos += 1;
+            reader.sizes(MAX_CONTAINERS, true)?
+        } else {
+            vec![]
+        };
+        reader.expect(KIND_DATA)?;
+        let data_size_at = reader.pos;
+        let data_size = reader.u16()?;
+        reader.expect(TERMINATOR)?;
+
+        let mut pos = reader.pos;
+        let types_at = pos;
+        pos += types_size;
+        let mut sections = |sizes: &[usize]| {
+            sizes
+                .iter()
+                .map(|size| {
+                    let range = pos..pos + size;
+                    pos += size;
+                    range
+                })
+                .collect::<Vec<_>>()
+        };
+        let code = sections(&code_sizes);
+        let containers = sections(&container_sizes);
+        let size = pos + data_size;
+        let data = pos..bytes.len().clamp(pos, size);
+        if bytes.len() < pos {
+            return Err(EofError::InvalidSize);
+        }
+
+        let types = bytes[types_at..types_at + types_size]
+            .chunks(4)
+            .map(|chunk| CodeType {
+                inputs: chunk[0],
+                outputs: chunk[1],
+                max_stack_increase: u16::from_be_bytes([chunk[2], chunk[3]]),
+            })
+            .collect();
+        let container = Self {
+            bytes: bytes[..data.end].to_vec(),
+            types,
+            code,
+            containers,
+            data,
+            data_size,
+            data_size_at,
+        };
+        Ok((container, size))
+    }
+
+    /// EIP-7698: split the data of a creation transaction into the initcode
+    /// container and the calldata that follows it
+    pub fn split_initcode(bytes: &[u8]) -> Result<(Self, Vec<u8>), EofError> {
+        let size = Self::declared_size(bytes)?;
+        if bytes.len() < size {
+            return Err(EofError::InvalidSize);
+        }
+        let container = Self::parse(&bytes[..size])?;
+        container.validate(ContainerKind::Initcode)?;
+        Ok((container, bytes[size..].to_vec()))
+    }
+
+    pub fn code(&self, index: usize) -> &[u8] {
+        &self.bytes[self.code[index].clone()]
+    }
+
+    /// Offset of the code section in the container
+    pub fn code_offset(&self, index: usize) -> usize {
+        self.code[index].start
+    }
+
+    pub fn code_sections(&self) -> usize {
+        self.code.len()
+    }
+
+    pub fn container(&self, index: usize) -> Option<&[u8]> {
+        self.containers
+            .get(index)
+            .map(|range| &self.bytes[range.clone()])
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.bytes[self.data.clone()]
+    }
+
+    /// EIP-7620: deployed container with the auxiliary data appended to the
+    /// data section. Fails if the data section is still incomplete.
+    pub fn with_aux_data(&self, aux: &[u8]) -> Result<Vec<u8>, EofError> {
+        let size = self.data().len() + aux.len();
+        if size < self.data_size || size > u16::MAX as usize {
+            return Err(EofError::TruncatedData);
+        }
+        let mut bytes = self.bytes.clone();
+        bytes.extend_from_slice(aux);
+        bytes[self.data_size_at..self.data_size_at + 2]
+            .copy_from_slice(&(size as u16).to_be_bytes());
+        Ok(bytes)
+    }
+
+    /// Deploy-time validation of the container and all its subcontainers
+    pub fn validate(&self, kind: ContainerKind) -> Result<(), EofError> {
+        if self.data().len() < self.data_size {
+            return Err(EofError::TruncatedData);
+        }
+        self.validate_with(kind)
+    }
+
+    fn validate_with(&self, kind: ContainerKind) -> Result<(), EofError> {
+        let first = self.types[0];
+        if first.inputs != 0 || first.is_returning() {
+            return Err(EofError::InvalidType(0));
+        }
+        for (index, ty) in self.types.iter().enumerate() {
+            if ty.inputs > 127
+                || (ty.outputs > 127 && ty.is_returning())
+                || ty.max_stack_increase as usize > STACK_LIMIT - 1
+            {
+                return Err(EofError::InvalidType(index));
+            }
+        }
+
+        let mut subcontainers = vec![None; self.containers.len()];
+        let mut reachable = HashSet::from([0]);
+        let mut queue = vec![0];
+        while let Some(index) = queue.pop() {
+            for section in self.validate_code(index, kind, &mut subcontainers)? {
+                if reachable.insert(section) {
+                    queue.push(section);
+                }
+            }
+        }
+        if let Some(index) = (0..self.code.len()).find(|index| !reachable.contains(index)) {
+            return Err(EofError::UnreachableSection(index));
+        }
+
+        for (index, kind) in subcontainers.into_iter().enumerate() {
+            let (Some(kind), Some(bytes)) = (kind, self.container(index)) else {
+                return Err(EofError::InvalidSubcontainer(index));
+            };
+            let container = Container::parse(bytes)?;
+            // Data of deployed containers is completed by RETURNCONTRACT
+            match kind {
+                ContainerKind::Runtime => container.validate_with(kind)?,
+                ContainerKind::Initcode => container.validate(kind)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate instructions, jumps and stack heights of the code section.
+    /// Returns the code sections it refers to with CALLF and JUMPF.
+    fn validate_code(
+        &self,
+        index: usize,
+        kind: ContainerKind,
+        subcontainers: &mut [Option<ContainerKind>],
+    ) -> Result<Vec<usize>, EofError> {
+        let code = self.code(index);
+        let base = self.code_offset(index);
+        let ty = self.types[index];
+        let mut sections = Vec::new();
+
+        // Instruction boundaries
+        let mut starts = vec![false; code.len()];
+        let mut pos = 0;
+        while pos < code.len() {
+            let op = code[pos];
+            let offset = base + pos;
+            if get_opcode(op).code != op || is_banned(op) {
+                return Err(EofError::UndefinedOpcode { offset, op });
+            }
+            let forbidden = match kind {
+                ContainerKind::Initcode => matches!(op, 0x00 | 0xf3),
+                ContainerKind::Runtime => op == 0xee,
+            };
+            if forbidden {
+                return Err(EofError::InvalidKind { offset, op, kind });
+            }
+            let len = immediate_len(code, pos);
+            if pos + 1 + len > code.len() {
+                return Err(EofError::TruncatedImmediate(offset));
+            }
+            starts[pos] = true;
+            pos += 1 + len;
+        }
+
+        // Instruction operands
+        let mut pos = 0;
+        while pos < code.len() {
+            let op = code[pos];
+            let offset = base + pos;
+            let immediate = &code[pos + 1..pos + 1 + immediate_len(code, pos)];
+            for target in jump_targets(op, pos, immediate) {
+                if target < 0 || target as usize >= code.len() || !starts[target as usize] {
+                    return Err(EofError::InvalidJump(offset));
+                }
+            }
+            let arg = || u16::from_be_bytes([immediate[0], immediate[1]]) as usize;
+            match op {
+                0xe3 | 0xe5 => {
+                    let target = arg();
+                    let returning = self.types.get(target).map(CodeType::is_returning);
+                    // CALLF only calls returning sections, JUMPF can only
+                    // jump to a returning section from a returning one
+                    let valid = match (op, returning) {
+                        (_, None) => false,
+                        (0xe3, Some(returning)) => returning,
+                        (_, Some(returning)) => !returning || ty.is_returning(),
+                    };
+                    if !valid {
+                        return Err(EofError::InvalidSection(offset));
+                    }
+                    sections.push(target);
+                }
+                0xe4 if !ty.is_returning() => {
+                    return Err(EofError::InvalidSection(offset));
+                }
+                0xd1 if arg() + 32 > self.data_size => {
+                    return Err(EofError::InvalidDataOffset(offset));
+                }
+                0xec | 0xee => {
+                    let target = immediate[0] as usize;
+                    let kind = if op == 0xec {
+                        ContainerKind::Initcode
+                    } else {
+                        ContainerKind::Runtime
+                    };
+                    match subcontainers.get_mut(target) {
+                        Some(slot @ None) => *slot = Some(kind),
+                        Some(Some(used)) if *used == kind => {}
+                        Some(_) => return Err(EofError::InvalidSubcontainer(target)),
+                        None => return Err(EofError::InvalidContainer(offset)),
+                    }
+                }
+                _ => {}
+            }
+            pos += 1 + immediate.len();
+        }
+
+        self.validate_stack(index, &starts)?;
+        Ok(sections)
+    }
+
+    /// EIP-5450: the stack height range at every instruction is known
+    /// statically, so underflows and overflows are rejected at deploy time
+    fn validate_stack(&self, index: usize, starts: &[bool]) -> Result<(), EofError> {
+        let code = self.code(index);
+        let base = self.code_offset(index);
+        let ty = self.types[index];
+        let inputs = ty.inputs as usize;
+
+        let mut heights: Vec<Option<(usize, usize)>> = vec![None; code.len()];
+        heights[0] = Some((inputs, inputs));
+        let mut max_height = inputs;
+
+        let mut pos = 0;
+        while pos < code.len() {
+            let op = code[pos];
+            let offset = base + pos;
+            let len = immediate_len(code, pos);
+            let immediate = &code[pos + 1..pos + 1 + len];
+            // Instructions are only reachable by forward jumps or fallthrough
+            let Some((min, max)) = heights[pos] else {
+                return Err(EofError::UnreachableCode(offset));
+            };
+
+            let (take, put) = match op {
+                0xe3 | 0xe5 => {
+                    let target =
+                        self.types[u16::from_be_bytes([immediate[0], immediate[1]]) as usize];
+                    if max + target.max_stack_increase as usize > STACK_LIMIT {
+                        return Err(EofError::InvalidStack(offset));
+                    }
+                    if op == 0xe5 && target.is_returning() {
+                        // JUMPF to a returning section: the stack must match
+                        // the outputs of the current one after the jump
+                        let exact = ty.outputs as usize + target.inputs as usize;
+                        if min != max
+                            || exact < target.outputs as usize
+                            || min != exact - target.outputs as usize
+                        {
+                            return Err(EofError::InvalidStack(offset));
+                        }
+                    }
+                    let put = if target.is_returning() {
+                        target.outputs
+                    } else {
+                        0
+                    };
+                    (target.inputs as usize, put as usize)
+                }
+                0xe4 => {
+                    if min != max || min != ty.outputs as usize {
+                        return Err(EofError::InvalidStack(offset));
+                    }
+                    (0, 0)
+                }
+                _ => stack_io(&get_opcode(op), immediate),
+            };
+            if min < take {
+                return Err(EofError::InvalidStack(offset));
+            }
+            let next = (min - take + put, max - take + put);
+            max_height = max_height.max(next.1);
+            if max_height > STACK_LIMIT {
+                return Err(EofError::InvalidStack(offset));
+            }
+
+            let mut successors = jump_targets(op, pos, immediate)
+                .into_iter()
+                .map(|target| target as usize)
+                .collect::<Vec<_>>();
+            if !is_terminating(op) && op != 0xe0 {
+                let fallthrough = pos + 1 + len;
+                if fallthrough >= code.len() {
+                    return Err(EofError::MissingTerminator(index));
+                }
+                successors.push(fallthrough);
+            }
+            for target in successors {
+                debug_assert!(starts[target]);
+                heights[target] = match heights[target] {
+                    None if target > pos => Some(next),
+                    Some((lo, hi)) if target > pos => Some((lo.min(next.0), hi.max(next.1))),
+                    // Backward jumps must not change the stack height
+                    Some(height) if height == next => Some(height),
+                    _ => return Err(EofError::InvalidStack(offset)),
+                };
+            }
+            pos += 1 + len;
+        }
+
+        if max_height - inputs != ty.max_stack_increase as usize {
+            return Err(EofError::InvalidType(index));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Section<'a> = ((u8, u8, u16), &'a str);
+
+    fn container(sections: &[Section], containers: &[Vec<u8>], data: &str) -> Vec<u8> {
+        let code = sections
+            .iter()
+            .map(|(_, code)| hex::decode(code).unwrap())
+            .collect::<Vec<_>>();
+        let data = hex::decode(data).unwrap();
+        let mut bytes = vec![0xef, 0x00, VERSION, KIND_TYPES];
+        bytes.extend_from_slice(&(4 * sections.len() as u16).to_be_bytes());
+        bytes.push(KIND_CODE);
+        bytes.extend_from_slice(&(code.len() as u16).to_be_bytes());
+        for code in &code {
+            bytes.extend_from_slice(&(code.len() as u16).to_be_bytes());
+        }
+        if !containers.is_empty() {
+            bytes.push(KIND_CONTAINER);
+            bytes.extend_from_slice(&(containers.len() as u16).to_be_bytes());
+            for container in containers {
+                bytes.extend_from_slice(&(container.len() as u32).to_be_bytes());
+            }
+        }
+        bytes.push(KIND_DATA);
+        bytes.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        bytes.push(TERMINATOR);
+        for ((inputs, outputs, max), _) in sections {
+            bytes.extend_from_slice(&[*inputs, *outputs]);
+            bytes.extend_from_slice(&max.to_be_bytes());
+        }
+        code.iter().for_each(|code| bytes.extend_from_slice(code));
+        containers
+            .iter()
+            .for_each(|container| bytes.extend_from_slice(container));
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+
+    fn validate(sections: &[Section]) -> Result<(), EofError> {
+        let bytes = container(sections, &[], "");
+        Container::parse(&bytes)?.validate(ContainerKind::Runtime)
+    }
+
+    #[test]
+    fn test_parse() {
+        let bytes = container(
+            &[((0, 0x80, 1), "5f5f5500"), ((1, 1, 0), "e4")],
+            &[],
+            "aabb",
+        );
+        let container = Container::parse(&bytes).unwrap();
+        assert_eq!(container.code_sections(), 2);
+        assert_eq!(container.code(0), &[0x5f, 0x5f, 0x55, 0x00]);
+        assert_eq!(container.code(1), &[0xe4]);
+        assert_eq!(container.data(), &[0xaa, 0xbb]);
+        assert!(container.types[1].is_returning());
+        assert!(!container.types[0].is_returning());
+
+        assert_eq!(
+            Container::parse(&bytes[..bytes.len() - 3]).err(),
+            Some(EofError::InvalidSize)
+        );
+        assert_eq!(
+            Container::parse(&[0xef, 0x00, 0x02]).err(),
+            Some(EofError::InvalidMagic)
+        );
+        assert_eq!(
+            Container::parse(&bytes[..10]).err(),
+            Some(EofError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        // PUSH0; PUSH0; SSTORE; STOP
+        assert_eq!(validate(&[((0, 0x80, 2), "5f5f5500")]), Ok(()));
+        // CALLF 1; POP; STOP + PUSH0; RETF
+        assert_eq!(
+            validate(&[((0, 0x80, 1), "e300015000"), ((0, 1, 1), "5fe4")]),
+            Ok(())
+        );
+        // PUSH0; RJUMPI +1; INVALID; STOP
+        assert_eq!(validate(&[((0, 0x80, 1), "5fe10001fe00")]), Ok(()));
+        // RJUMP -3: loop with a constant stack height
+        assert_eq!(validate(&[((0, 0x80, 0), "e0fffd")]), Ok(()));
+
+        // JUMP is not allowed in EOF code
+        assert_eq!(
+            validate(&[((0, 0x80, 1), "5f5600")]),
+            Err(EofError::UndefinedOpcode {
+                offset: 20,
+                op: 0x56
+            })
+        );
+        assert_eq!(
+            validate(&[((0, 0x80, 1), "61aa")]),
+            Err(EofError::TruncatedImmediate(19))
+        );
+        // RJUMP into its own immediate
+        assert_eq!(
+            validate(&[((0, 0x80, 0), "e0ffff")]),
+            Err(EofError::InvalidJump(19))
+        );
+        assert_eq!(
+            validate(&[((0, 0x80, 0), "01")]),
+            Err(EofError::InvalidStack(19))
+        );
+        assert_eq!(
+            validate(&[((0, 0x80, 0), "005f00")]),
+            Err(EofError::UnreachableCode(20))
+        );
+        assert_eq!(
+            validate(&[((0, 0x80, 1), "5f")]),
+            Err(EofError::MissingTerminator(0))
+        );
+        assert_eq!(
+            validate(&[((0, 0x80, 2), "5f5000")]),
+            Err(EofError::InvalidType(0))
+        );
+        // Non-returning section called with CALLF
+        assert_eq!(
+            validate(&[((0, 0x80, 0), "e3000100"), ((0, 0x80, 0), "00")]),
+            Err(EofError::InvalidSection(25))
+        );
+        assert_eq!(
+            validate(&[((0, 0x80, 0), "00"), ((0, 0x80, 0), "00")]),
+            Err(EofError::UnreachableSection(1))
+        );
+        // RETURNCONTRACT is only allowed in initcode
+        assert_eq!(
+            validate(&[((0, 0x80, 2), "5f5fee00")]),
+            Err(EofError::InvalidKind {
+                offset: 21,
+                op: 0xee,
+                kind: ContainerKind::Runtime
+            })
+        );
+    }
+
+    #[test]
+    fn test_subcontainers() {
+        let runtime = container(&[((0, 0x80, 0), "00")], &[], "");
+        // PUSH0; PUSH0; RETURNCONTRACT 0
+        let initcode = container(
+            &[((0, 0x80, 2), "5f5fee00")],
+            std::slice::from_ref(&runtime),
+            "",
+        );
+        let parsed = Container::parse(&initcode).unwrap();
+        assert_eq!(parsed.validate(ContainerKind::Initcode), Ok(()));
+        assert_eq!(parsed.container(0), Some(runtime.as_slice()));
+
+        // Unreferenced subcontainer
+        let bytes = container(&[((0, 0x80, 0), "00")], &[runtime], "");
+        assert_eq!(
+            Container::parse(&bytes)
+                .unwrap()
+                .validate(ContainerKind::Runtime),
+            Err(EofError::InvalidSubcontainer(0))
+        );
+    }
+
+    #[test]
+    fn test_aux_data() {
+        // Declared data size is 4, one byte is present
+        let mut bytes = container(&[((0, 0x80, 0), "00")], &[], "aa");
+        let at = bytes.len() - 1 - 4 - 1 - 1 - 2;
+        bytes[at..at + 2].copy_from_slice(&4u16.to_be_bytes());
+        let container = Container::parse(&bytes).unwrap();
+        assert_eq!(container.data_size, 4);
+        assert_eq!(
+            container.validate(ContainerKind::Runtime),
+            Err(EofError::TruncatedData)
+        );
+        assert_eq!(
+            container.with_aux_data(&[0xbb]),
+            Err(EofError::TruncatedData)
+        );
+
+        let deployed = container.with_aux_data(&[0xbb, 0xcc, 0xdd]).unwrap();
+        let deployed = Container::parse(&deployed).unwrap();
+        assert_eq!(deployed.data(), &[0xaa, 0xbb, 0xcc, 0xdd]);
+        assert_eq!(deployed.validate(ContainerKind::Runtime), Ok(()));
+    }
+
+    #[test]
+    fn test_split_initcode() {
+        let runtime = container(&[((0, 0x80, 0), "00")], &[], "");
+        let mut bytes = container(&[((0, 0x80, 2), "5f5fee00")], &[runtime], "");
+        let size = bytes.len();
+        bytes.extend_from_slice(&[1, 2, 3]);
+        let (container, data) = Container::split_initcode(&bytes).unwrap();
+        assert_eq!(container.bytes.len(), size);
+        assert_eq!(data, vec![1, 2, 3]);
+
+        assert_eq!(
+            Container::split_initcode(&bytes[..size - 1]).err(),
+            Some(EofError::InvalidSize)
+        );
+    }
+
+    #[test]
+    fn test_create_address() {
+        let sender = Address::from([0x11; 20]);
+        let salt = Word::from(7u64);
+        assert_ne!(create_address(&sender, &salt), sender.create2(&salt, &[]));
+        assert_ne!(
+            create_address(&sender, &salt),
+            create_address(&sender, &Word::zero())
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use std::future::Future;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use evm_common::word::decode_error_string;
+use evm_event::HaltReason;
+
+use crate::{ext::Ext, solenoid::CallResult, tracer::NoopTracer};
+
+/// Reasons for a transaction to fail at any gas limit
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum EstimateError {
+    #[error("Execution reverted: {}", .reason.as_deref().unwrap_or("no reason"))]
+    Reverted {
+        /// Decoded `Error(string)` revert reason
+        reason: Option<String>,
+        data: Vec<u8>,
+    },
+    #[error("Execution halted: {0:?}")]
+    Halted(HaltReason),
+    #[error("Gas required exceeds the limit of {0}")]
+    GasLimitExceeded(i64),
+}
+
+/// Gas estimate of a transaction, see [`Runner::estimate_gas`]
+///
+/// [`Runner::estimate_gas`]: crate::solenoid::Runner::estimate_gas
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct GasEstimate {
+    /// Minimal gas limit at which the transaction succeeds
+    #[serde(rename = "gasLimit")]
+    pub gas_limit: i64,
+    /// Gas used with that limit, after refunds
+    #[serde(rename = "gasUsed")]
+    pub gas_used: i64,
+}
+
+/// Binary search of the minimal gas limit between `min` (intrinsic gas and
+/// EIP-7623 floor, not enough to fail on their own) and `max`. Every attempt
+/// runs on a fork of the state. The search is on success rather than on the
+/// gas used: the 63/64 rule and refunds make the limit needed higher.
+pub(crate) async fn estimate<F, Fut>(
+    min: i64,
+    max: i64,
+    ext: &mut Ext,
+    run: F,
+) -> eyre::Result<GasEstimate>
+where
+    F: Fn(i64, Ext) -> Fut,
+    Fut: Future<Output = eyre::Result<CallResult<NoopTracer>>>,
+{
+    let attempt = async |gas: i64, ext: &mut Ext| -> eyre::Result<CallResult<NoopTracer>> {
+        let result = run(gas, ext.fork()).await?;
+        // Not a matter of gas: give up
+        if let Some(reason @ (HaltReason::Cancelled | HaltReason::Timeout)) = result.evm.halt {
+            return Err(EstimateError::Halted(reason).into());
+        }
+        Ok(result)
+    };
+
+    if max < min {
+        return Err(EstimateError::GasLimitExceeded(max).into());
+    }
+    let result = attempt(max, ext).await?;
+    if result.evm.reverted {
+        let error = match result.evm.halt {
+            Some(HaltReason::OutOfGas) => EstimateError::GasLimitExceeded(max),
+            Some(reason) => EstimateError::Halted(reason),
+            None => EstimateError::Reverted {
+                reason: decode_error_string(&result.ret),
+                data: result.ret,
+            },
+        };
+        return Err(error.into());
+    }
+    let mut hi = max;
+    let mut gas_used = result.gas.gas_use;
+
+    // Gas used before refunds, with the call stipend and the 63/64 withheld
+    // by nested calls: usually enough, which saves most of the search
+    let guess = (gas_used + result.evm.gas.refund + 2300) * 64 / 63;
+    let mut lo = (min - 1).max(0);
+    if lo < guess && guess < hi {
+        let result = attempt(guess, ext).await?;
+        if result.evm.reverted {
+            lo = guess;
+        } else {
+            hi = guess;
+            gas_used = result.gas.gas_use;
+        }
+    }
+
+    while lo + 1 < hi {
+        let mid = lo + (hi - lo) / 2;
+        let result = attempt(mid, ext).await?;
+        if result.evm.reverted {
+            lo = mid;
+        } else {
+            hi = mid;
+            gas_used = result.gas.gas_use;
+        }
+    }
+    Ok(GasEstimate {
+        gas_limit: hi,
+        gas_used,
+    })
+}
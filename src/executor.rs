@@ -1,3 +1,5 @@
+use std::{borrow::Cow, slice, sync::Arc, time::Instant};
+
 use evm_event::{
     AccountEvent, CallType, Event, EventData, HaltReason, HashAlg, OpCode, StateEvent,
 };
@@ -15,13 +17,20 @@ use evm_common::{
 };
 
 use crate::{
-    decoder::{Bytecode, Decoder, Instruction},
-    ext::Ext,
-    precompiles,
+    decoder::{Block, Bytecode, Decoder, EofCode, Instruction},
+    delegation,
+    eof::{self, Container},
+    ext::{Checkpoint, Ext, Log},
+    fees::{Charge, FeeMarket, Fees},
+    limits::{CancellationToken, ExecutionLimits},
+    precompiles::Precompiles,
+    session::Session,
+    spec::{Chain, Spec},
     tracer::EventTracer,
+    validation::{self, InvalidTx},
 };
 
-#[derive(Error, Debug)]
+#[derive(Clone, Error, Debug)]
 pub enum ExecutorError {
     #[error("Stack overflow")]
     StackOverflow,
@@ -49,15 +58,44 @@ pub enum ExecutorError {
     StaticCallViolation(u8),
     #[error("Invalid allocation: {0}")]
     InvalidAllocation(usize),
+    #[error("Invalid address: {0:?}")]
+    InvalidAddress(Word),
+    #[error("Initcode size {size} exceeds the limit {limit}")]
+    InitcodeSizeExceeded { size: usize, limit: usize },
+    #[error("Step limit exceeded")]
+    StepLimitExceeded,
+    #[error("Execution cancelled")]
+    Cancelled,
+    #[error("Execution deadline exceeded")]
+    DeadlineExceeded,
 }
 
-const STACK_LIMIT: usize = 1024;
+impl ExecutorError {
+    /// Reason reported for the exceptional halt caused by the error
+    pub fn halt_reason(&self) -> HaltReason {
+        match self {
+            ExecutorError::StackUnderflow => HaltReason::StackUnderflow,
+            ExecutorError::StackOverflow => HaltReason::StackOverflow,
+            ExecutorError::InvalidOpcode(_) | ExecutorError::UnknownOpcode(_) => {
+                HaltReason::InvalidOpcode
+            }
+            ExecutorError::StepLimitExceeded => HaltReason::StepLimit,
+            ExecutorError::Cancelled => HaltReason::Cancelled,
+            ExecutorError::DeadlineExceeded => HaltReason::Timeout,
+            _ => HaltReason::OutOfGas,
+        }
+    }
+}
 
-const CALL_DEPTH_LIMIT: usize = 1024;
+// EIP-4750: maximal number of nested CALLF
+const RETURN_STACK_LIMIT: usize = 1024;
 
-// 2 MB: opinionated allocation sanity check limit
-const ALLOCATION_SANITY_LIMIT: usize = 2 * 1024 * 1024;
+// Number of steps between checks of the execution deadline
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
 
+/// Record of the state read and written by the execution, e.g. to build
+/// state diffs. Reverts are applied by the journal of `Ext`, the record is
+/// not replayed.
 #[derive(Debug, Default, Eq, PartialEq)]
 pub enum AccountTouch {
     #[default]
@@ -77,6 +115,12 @@ pub enum AccountTouch {
     SetTransientState(Address, Word, Word, Word),
 
     Create(Address, Word, Word, Vec<u8>, Word),
+
+    // EIP-7702: (code, codehash) before and after the delegation is set
+    SetCode(Address, (Vec<u8>, Word), (Vec<u8>, Word)),
+
+    // SELFDESTRUCT: account is deleted at the end of the transaction
+    Destroy(Address),
 }
 
 impl AccountTouch {
@@ -95,7 +139,7 @@ impl AccountTouch {
     pub fn is_create(&self) -> bool {
         matches!(self, AccountTouch::Create(_, _, _, _, _))
     }
-    /// Returns true if this touch should be preserved even when the call reverts.
+    /// Returns true if this touch is kept in the record even when the call reverts.
     /// This includes read-only touches, fee-pay touches, and Create touches
     /// (because REVM includes created-then-reverted accounts in its state diff).
     pub fn survives_revert(&self) -> bool {
@@ -103,9 +147,6 @@ impl AccountTouch {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Log(pub Address, pub Vec<Word>, pub Vec<u8>);
-
 #[derive(Debug, Default)]
 pub struct Evm {
     pub memory: Vec<u8>,
@@ -115,11 +156,21 @@ pub struct Evm {
     pub stopped: bool,
     pub reverted: bool,
 
-    pub logs: Vec<Log>,
     pub touches: Vec<AccountTouch>,
 
     pub mem_cost: i64,
     pub refund: i64,
+    // Static gas of the rest of the current basic block, charged on entry
+    pub prepaid: i64,
+    // The rest of the current basic block is checked instruction by
+    // instruction: its entry failed, or the frame was changed from outside
+    pub stepwise: bool,
+    // Settled by the outermost frame of the transaction
+    pub fees: Fees,
+    // EIP-4750: instructions to return to from the code sections entered by CALLF
+    pub returns: Vec<usize>,
+    // Exceptional halt, as opposed to REVERT (distinguished by EXT*CALL)
+    pub halt: Option<HaltReason>,
 }
 
 impl Evm {
@@ -135,9 +186,14 @@ impl Evm {
         exp_cost
     }
 
-    pub(crate) fn address_access_cost(&mut self, address: &Address, ext: &mut Ext) -> i64 {
+    pub(crate) fn address_access_cost(
+        &mut self,
+        address: &Address,
+        ext: &mut Ext,
+        is_precompile: bool,
+    ) -> i64 {
         // EIP-2929: Check if address has been accessed during this transaction
-        if precompiles::is_precompile(address) {
+        if is_precompile {
             return 100;
         }
         let is_warm = ext.is_address_warm(address);
@@ -160,8 +216,9 @@ impl Evm {
         Err(e)
     }
 
-    pub fn push(&mut self, value: Word) -> eyre::Result<()> {
-        if self.stack.len() >= STACK_LIMIT {
+    /// Push a word, overflowing the stack once it holds `limit` words
+    pub fn push(&mut self, value: Word, limit: usize) -> eyre::Result<()> {
+        if self.stack.len() >= limit {
             self.error(ExecutorError::StackOverflow.into())?;
         }
         self.stack.push(value);
@@ -191,6 +248,34 @@ impl Evm {
         Ok(ret)
     }
 
+    /// Validate the stack and charge static gas on entry to the basic block
+    pub(crate) fn enter_block(
+        &mut self,
+        block: &Block,
+        stack_limit: usize,
+    ) -> Result<(), ExecutorError> {
+        if self.stack.len() < block.stack_min {
+            return Err(ExecutorError::StackUnderflow);
+        }
+        if self.stack.len() + block.stack_max > stack_limit {
+            return Err(ExecutorError::StackOverflow);
+        }
+        if self.gas.remaining() < block.gas {
+            return Err(ExecutorError::OutOfGas());
+        }
+        self.gas.used += block.gas;
+        self.prepaid = block.gas;
+        Ok(())
+    }
+
+    /// Give back the static gas prepaid for the rest of the basic block and
+    /// check the remaining instructions one by one
+    pub(crate) fn leave_block(&mut self) {
+        self.gas.used -= self.prepaid;
+        self.prepaid = 0;
+        self.stepwise = true;
+    }
+
     pub fn gas(&mut self, cost: i64) -> eyre::Result<()> {
         match self.gas.sub(cost) {
             Ok(_) => Ok(()),
@@ -218,47 +303,16 @@ impl Evm {
         }
     }
 
-    pub async fn revert(&mut self, ext: &mut Ext) -> eyre::Result<()> {
-        for t in self.touches.iter().rev() {
-            match t {
-                AccountTouch::SetState(address, key, val, _, is_warm) => {
-                    // Always restore the storage value, regardless of warm/cold status
-                    // Directly set storage without calling get() to avoid polluting ext.original
-                    ext.state
-                        .entry(*address)
-                        .or_default()
-                        .state
-                        .insert(*key, *val);
-                    if !*is_warm {
-                        ext.accessed_storage.remove(&(*address, *key));
-                    }
-                }
-                AccountTouch::SetTransientState(address, key, val, _) => {
-                    ext.transient.insert((*address, *key), *val);
-                }
-                AccountTouch::GetState(address, key, _, is_warm) => {
-                    if !*is_warm {
-                        ext.accessed_storage.remove(&(*address, *key));
-                    }
-                }
-                AccountTouch::SetNonce(addr, val, _new) => {
-                    ext.account_mut(addr).nonce = (*val).into();
-                }
-                AccountTouch::SetValue(addr, val, _new) => {
-                    ext.account_mut(addr).value = *val;
-                }
-                AccountTouch::Create(addr, _value, _nonce, _code, _hash) => {
-                    *ext.code_mut(addr) = (vec![], Word::from_bytes(&empty()));
-                    ext.account_mut(addr).nonce = Word::zero();
-                    ext.account_mut(addr).value = Word::zero();
-                }
-                AccountTouch::WarmUp(addr) => {
-                    ext.accessed_addresses.remove(addr);
-                }
-                _ => (),
-            }
+    /// Keep the touches of a completed child frame. Reverted changes are
+    /// undone by the journal of `Ext`: the touches of a reverted frame are
+    /// only kept if they survive the revert.
+    pub(crate) fn join(&mut self, touches: Vec<AccountTouch>, reverted: bool) {
+        if reverted {
+            self.touches
+                .extend(touches.into_iter().filter(|t| t.survives_revert()));
+        } else {
+            self.touches.extend(touches);
         }
-        Ok(())
     }
 }
 
@@ -327,15 +381,158 @@ pub struct Context {
 pub enum StepResult {
     Ok(i64),
     Halt(i64),
+    // CALL* or CREATE* instruction entered a child frame
+    Suspend,
+}
+
+/// Outcome of executing a single instruction of a call frame
+pub(crate) enum Step {
+    Next,
+    // Child frame was entered, see `Executor::pending`
+    Suspend,
+    // Frame is complete with the return data
+    Return(Vec<u8>),
+}
+
+/// State of a transaction between [`Executor::prepare`] and
+/// [`Executor::settle`]
+pub(crate) struct Prelude {
+    pub ctx: Context,
+    market: FeeMarket,
+    charge: Charge,
+    checkpoint: Checkpoint,
+    intrinsic: i64,
+    authorization_refund: i64,
+}
+
+/// Call frame on the explicit frame stack (see `Session`)
+pub(crate) struct Frame<'a, T: EventTracer> {
+    pub(crate) executor: Executor<T>,
+    pub(crate) code: Cow<'a, Bytecode>,
+    pub(crate) call: Cow<'a, Call>,
+    pub(crate) evm: Evm,
+    pub(crate) ctx: Context,
+}
+
+/// State of the CALL* or CREATE* instruction suspended until its child frame returns
+pub(crate) enum Resume {
+    Call(CallResume),
+    Create(CreateResume),
+}
+
+pub(crate) struct CallResume {
+    ctx: Context,
+    gas: i64,
+    call_gas: Word,
+    value: Word,
+    args_offset: usize,
+    args_size: usize,
+    ret_offset: usize,
+    ret_size: usize,
+    gas_stipend_adjustment: i64,
+    total_gas_cost_for_tracing: i64,
+    is_delegated: bool,
+    access_cost: i64,
+    is_empty: bool,
+    // EIP-7069: push the status code of EXT*CALL instead of the success flag
+    is_eof: bool,
+    // Taken before the value transfer, reverted with the child frame
+    checkpoint: Checkpoint,
+}
+
+pub(crate) struct CreateResume {
+    ctx: Context,
+    this: Address,
+    value: Word,
+    nonce: Word,
+    created: Address,
+    memory_expansion_cost: i64,
+    create_cost: i64,
+    init_code_cost: i64,
+    gas_to_forward: i64,
+    checkpoint: Checkpoint,
+}
+
+impl<T: EventTracer> Frame<'_, T> {
+    pub(crate) fn into_parts(self) -> (T, Evm) {
+        (self.executor.tracer, self.evm)
+    }
+
+    pub(crate) fn enter(&mut self, ext: &mut Ext) -> Option<Vec<u8>> {
+        self.executor
+            .enter(&self.call, &mut self.evm, ext, self.ctx)
+    }
+
+    pub(crate) fn halt(&mut self, e: ExecutorError) -> Vec<u8> {
+        self.executor.halt(e, &mut self.evm, self.ctx)
+    }
+
+    pub(crate) async fn step(&mut self, ext: &mut Ext) -> Step {
+        self.executor
+            .step(&self.code, &self.call, &mut self.evm, ext, self.ctx)
+            .await
+    }
+
+    /// Complete the suspended instruction with the outcome of the child frame.
+    /// Returns the return data if this frame is complete as well.
+    pub(crate) async fn resume(
+        &mut self,
+        resume: Resume,
+        child: Frame<'_, T>,
+        ret: Vec<u8>,
+        ext: &mut Ext,
+    ) -> Option<Vec<u8>> {
+        let instruction = &self.code.instructions[self.evm.pc];
+        let result = match resume {
+            Resume::Call(resume) => {
+                let gas = resume.gas;
+                match self
+                    .executor
+                    .call_return(instruction, resume, child, ret, &mut self.evm, ext)
+                    .await
+                {
+                    Ok(()) => Ok(StepResult::Ok(gas)),
+                    Err(_) => Ok(StepResult::Halt(self.evm.gas.remaining())),
+                }
+            }
+            Resume::Create(resume) => self
+                .executor
+                .create_return(instruction, resume, child, ret, &mut self.evm, ext)
+                .await
+                .map(|_| StepResult::Ok(0)),
+        };
+        if matches!(result, Ok(StepResult::Ok(_))) {
+            self.evm.pc += 1;
+        }
+        if let Some(ret) = self
+            .executor
+            .complete(instruction, result, &mut self.evm, self.ctx)
+        {
+            return Some(ret);
+        }
+        if self.evm.stopped || self.evm.pc >= self.code.instructions.len() {
+            return Some(self.executor.finish(&self.code, &self.evm, self.ctx));
+        }
+        None
+    }
 }
 
 #[derive(Default)]
 pub struct Executor<T: EventTracer> {
     header: Header,
+    chain: Chain,
+    spec: Spec,
     tracer: T,
     ret: Vec<u8>,
     log: bool,
+    validate: bool,
+    limits: ExecutionLimits,
+    cancellation: Option<CancellationToken>,
+    deadline: Option<Instant>,
+    precompiles: Arc<Precompiles>,
     debug: serde_json::Value,
+    // Child frame entered by the last executed instruction
+    pub(crate) pending: Option<Box<(Frame<'static, T>, Resume)>>,
 }
 
 impl<T: EventTracer> Executor<T> {
@@ -347,10 +544,92 @@ impl<T: EventTracer> Executor<T> {
         Self { header, ..self }
     }
 
+    pub fn with_chain(self, chain: Chain) -> Self {
+        Self { chain, ..self }
+    }
+
+    pub fn with_spec(self, spec: Spec) -> Self {
+        Self { spec, ..self }
+    }
+
     pub fn with_log(self) -> Self {
         Self { log: true, ..self }
     }
 
+    /// Reject invalid transactions (see [`validation::validate`]) before execution
+    pub fn with_validation(self) -> Self {
+        Self {
+            validate: true,
+            ..self
+        }
+    }
+
+    pub fn with_limits(self, limits: ExecutionLimits) -> Self {
+        Self { limits, ..self }
+    }
+
+    pub fn limits(&self) -> &ExecutionLimits {
+        &self.limits
+    }
+
+    /// Halt the execution once the token is cancelled
+    pub fn with_cancellation(self, token: CancellationToken) -> Self {
+        Self {
+            cancellation: Some(token),
+            ..self
+        }
+    }
+
+    /// Halt the execution once the deadline has passed. Not checked on
+    /// wasm32, that has no clock (`Instant::now` panics).
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    /// Check the step budget, the cancellation token and the deadline before
+    /// the given step of the transaction
+    pub(crate) fn interrupt(&self, steps: u64) -> Option<ExecutorError> {
+        if self.limits.steps.is_some_and(|limit| steps > limit) {
+            return Some(ExecutorError::StepLimitExceeded);
+        }
+        if self
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            return Some(ExecutorError::Cancelled);
+        }
+        // Reading the clock on every step would slow down the execution
+        #[cfg(not(target_arch = "wasm32"))]
+        if steps % DEADLINE_CHECK_INTERVAL == 1
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Some(ExecutorError::DeadlineExceeded);
+        }
+        None
+    }
+
+    /// Replace the standard precompiles, e.g. to add chain-specific ones
+    pub fn with_precompiles(self, precompiles: Precompiles) -> Self {
+        Self {
+            precompiles: Arc::new(precompiles),
+            ..self
+        }
+    }
+
+    pub fn precompiles(&self) -> &Precompiles {
+        &self.precompiles
+    }
+
+    fn is_precompile(&self, address: &Address) -> bool {
+        self.precompiles.contains(address, self.spec)
+    }
+
     pub fn set_log(&mut self, log: bool) {
         self.log = log;
     }
@@ -362,6 +641,94 @@ impl<T: EventTracer> Executor<T> {
         }
     }
 
+    /// Executor for a nested frame: same block context and rules, forked tracer
+    pub(crate) fn inner(&self) -> Executor<T> {
+        let mut executor = Executor::<T>::with_tracer(self.tracer.fork())
+            .with_header(self.header.clone())
+            .with_chain(self.chain.clone())
+            .with_spec(self.spec)
+            .with_limits(self.limits.clone());
+        executor.cancellation = self.cancellation.clone();
+        executor.deadline = self.deadline;
+        executor.precompiles = self.precompiles.clone();
+        executor.set_log(self.log);
+        executor
+    }
+
+    /// EIP-7702: Apply the authorization list of the transaction, skipping
+    /// invalid tuples. Returns the authorities that got their code updated and
+    /// the refund for the authorities that already existed.
+    async fn authorize(
+        &mut self,
+        evm: &mut Evm,
+        ext: &mut Ext,
+    ) -> eyre::Result<(Vec<Address>, i64)> {
+        let chain_id = Word::from(self.chain.id);
+        let mut authorities = Vec::new();
+        let mut refund = 0;
+        for auth in ext.tx_ctx.authorization_list.clone() {
+            if !auth.chain_id.is_zero() && auth.chain_id != chain_id {
+                continue;
+            }
+            if auth.nonce >= Word::from(u64::MAX) {
+                continue;
+            }
+            let Some(authority) = delegation::authority(&auth) else {
+                continue;
+            };
+            if ext.warm_address(&authority) {
+                evm.touches.push(AccountTouch::WarmUp(authority));
+            }
+
+            let (code, codehash) = ext.code(&authority).await?;
+            evm.touches
+                .push(AccountTouch::GetCode(authority, codehash, code.clone()));
+            if !code.is_empty() && delegation::target(&code).is_none() {
+                continue;
+            }
+            let nonce = ext.nonce(&authority).await?;
+            if nonce != auth.nonce {
+                continue;
+            }
+            if !ext.is_empty(&authority).await? {
+                refund += delegation::PER_EMPTY_ACCOUNT_COST - delegation::PER_AUTH_BASE_COST;
+            }
+
+            // Delegation to the zero address clears the code
+            let delegated = if auth.address.is_zero() {
+                (vec![], Word::from_bytes(&empty()))
+            } else {
+                let code = delegation::designator(&auth.address);
+                let hash = Word::from_bytes(&keccak256(&code));
+                (code, hash)
+            };
+            ext.set_code(&authority, delegated.clone());
+            evm.touches.push(AccountTouch::SetCode(
+                authority,
+                (code, codehash),
+                delegated,
+            ));
+
+            ext.set_nonce(&authority, nonce + Word::one());
+            evm.touches.push(AccountTouch::SetNonce(
+                authority,
+                nonce.as_u64(),
+                nonce.as_u64() + 1,
+            ));
+            self.emit(|_| Event {
+                data: EventData::Account(AccountEvent::SetNonce {
+                    address: authority,
+                    val: nonce.as_u64(),
+                    new: nonce.as_u64() + 1,
+                }),
+                depth: 1,
+                reverted: false,
+            });
+            authorities.push(authority);
+        }
+        Ok((authorities, refund))
+    }
+
     pub async fn execute(
         mut self,
         code: &Bytecode,
@@ -369,6 +736,43 @@ impl<T: EventTracer> Executor<T> {
         evm: &mut Evm,
         ext: &mut Ext,
     ) -> eyre::Result<(T, Vec<u8>)> {
+        let (prelude, reloaded) = self.prepare(code, call, evm, ext).await?;
+        let executor = self.inner();
+        let (tracer, ret) = executor
+            .execute_with_context(
+                reloaded.as_ref().unwrap_or(code),
+                call,
+                evm,
+                ext,
+                prelude.ctx,
+            )
+            .await;
+        let tracer = self.settle(prelude, call, tracer, &ret, evm, ext).await?;
+        Ok((tracer, ret))
+    }
+
+    /// Prelude of the transaction: upfront fees, intrinsic gas, nonce,
+    /// delegations (EIP-7702) and value transfer. Leaves a checkpoint open
+    /// for [`Executor::settle`] once the frame is complete. Also returns the
+    /// code of the target if its delegation was just updated.
+    pub(crate) async fn prepare(
+        &mut self,
+        code: &Bytecode,
+        call: &Call,
+        evm: &mut Evm,
+        ext: &mut Ext,
+    ) -> eyre::Result<(Prelude, Option<Bytecode>)> {
+        if self.validate {
+            validation::validate(call, &self.header, ext).await?;
+        }
+        if call.to.is_zero() && call.data.len() > self.limits.initcode_size {
+            return Err(InvalidTx::InitcodeSizeExceeded {
+                size: call.data.len(),
+                limit: self.limits.initcode_size,
+            }
+            .into());
+        }
+
         // EIP-2929: Pre-warm sender and target addresses at transaction start
         if ext.warm_address(&call.from) {
             evm.touches.push(AccountTouch::WarmUp(call.from));
@@ -378,16 +782,9 @@ impl<T: EventTracer> Executor<T> {
                 evm.touches.push(AccountTouch::WarmUp(call.to));
             }
 
-            // EIP-7702: If call.to is delegated, also pre-warm the target address
             let (code, codehash) = ext.code(&call.to).await?;
             evm.touches
                 .push(AccountTouch::GetCode(call.to, codehash, code.clone()));
-            if code.len() == 23 && code.starts_with(&[0xef, 0x01, 0x00]) {
-                let target = Address::try_from(&code[3..]).expect("must succeed");
-                if ext.warm_address(&target) {
-                    evm.touches.push(AccountTouch::WarmUp(target));
-                }
-            }
         }
 
         // EIP-3651 (Shanghai): Pre-warm coinbase address
@@ -396,23 +793,23 @@ impl<T: EventTracer> Executor<T> {
             evm.touches.push(AccountTouch::WarmUp(coinbase));
         }
 
-        // Deduct upfront gas payment before execution
-        let gas_prepayment = call.gas * ext.tx_ctx.gas_price;
-        let sender_balance = ext.balance(&call.from).await?;
-        if !gas_prepayment.is_zero() {
-            let updated_balance = sender_balance.saturating_sub(gas_prepayment);
-            ext.account_mut(&call.from).value = updated_balance;
-        }
-        // Note: Gas prepayment is deducted from sender but NOT transferred to coinbase yet.
-        // The base fee will be burned (removed from circulation), and only the priority fee
-        // will be transferred to the coinbase after execution completes (see below).
+        // Buy the gas upfront: the unused part is refunded after execution,
+        // the base fee is burned and the priority fee goes to the coinbase
+        let market = FeeMarket::new(&ext.tx_ctx, &self.header, self.spec);
+        let charge = market.charge(&call.from, call.gas.as_i64(), ext).await?;
 
         let access_list_cost = ext.tx_ctx.access_list_cost();
+        let authorization_list_cost = if self.spec.is_enabled(Spec::Prague) {
+            ext.tx_ctx.authorization_list_cost()
+        } else {
+            0
+        };
 
         let mut gas = call.gas.as_i64();
         let call_cost = 21000;
         gas -= call_cost;
         gas -= access_list_cost;
+        gas -= authorization_list_cost;
         ext.apply_access_list();
 
         let data_cost = {
@@ -424,6 +821,59 @@ impl<T: EventTracer> Executor<T> {
 
         evm.gas = Gas::new(gas);
 
+        let nonce = ext.nonce(&call.from).await?;
+        ext.set_nonce(&call.from, nonce + Word::one());
+        evm.touches.push(AccountTouch::SetNonce(
+            call.from,
+            nonce.as_u64(),
+            nonce.as_u64() + 1,
+        ));
+        self.emit(|_| Event {
+            data: EventData::Account(AccountEvent::SetNonce {
+                address: call.from,
+                val: nonce.as_u64(),
+                new: nonce.as_u64() + 1,
+            }),
+            depth: 1,
+            reverted: false,
+        });
+
+        // EIP-7702: Delegations are set before execution and are not undone on revert
+        let (authorities, authorization_refund) = if self.spec.is_enabled(Spec::Prague) {
+            self.authorize(evm, ext).await?
+        } else {
+            (vec![], 0)
+        };
+        // The code of call.to was resolved by the caller, reload it if it was just (un)delegated
+        let reloaded = if authorities.contains(&call.to) {
+            let (code, codehash) = ext.code(&call.to).await?;
+            evm.touches
+                .push(AccountTouch::GetCode(call.to, codehash, code.clone()));
+            let code = if let Some(target) = delegation::target(&code) {
+                let (code, codehash) = ext.code(&target).await?;
+                evm.touches
+                    .push(AccountTouch::GetCode(target, codehash, code.clone()));
+                code
+            } else {
+                code
+            };
+            Some(Decoder::decode(code, self.spec))
+        } else {
+            None
+        };
+        let code = reloaded.as_ref().unwrap_or(code);
+
+        // EIP-7702: If call.to is delegated, also pre-warm the target address
+        if !call.to.is_zero()
+            && let Some(target) = delegation::target(&ext.code(&call.to).await?.0)
+            && ext.warm_address(&target)
+        {
+            evm.touches.push(AccountTouch::WarmUp(target));
+        }
+
+        // Nonce bump and delegations survive the revert, everything after does not
+        let checkpoint = ext.checkpoint();
+
         // TODO: sort out value transfer!
         let src = ext.balance(&call.from).await?;
         let dst = ext.balance(&call.to).await?;
@@ -431,10 +881,10 @@ impl<T: EventTracer> Executor<T> {
             let value = ext.account_mut(&call.from).value;
             // Avoid strict balance checks for now
             let updated = value.saturating_sub(call.value);
-            ext.account_mut(&call.from).value = updated;
+            ext.set_balance(&call.from, updated);
             evm.touches
                 .push(AccountTouch::SetValue(call.from, src, updated));
-            self.tracer.push(Event {
+            self.emit(|_| Event {
                 data: EventData::Account(AccountEvent::SetValue {
                     address: call.from,
                     val: src,
@@ -444,10 +894,11 @@ impl<T: EventTracer> Executor<T> {
                 reverted: false,
             });
 
-            ext.account_mut(&call.to).value += call.value;
+            let received = ext.account_mut(&call.to).value + call.value;
+            ext.set_balance(&call.to, received);
             evm.touches
                 .push(AccountTouch::SetValue(call.to, dst, dst + call.value));
-            self.tracer.push(Event {
+            self.emit(|_| Event {
                 data: EventData::Account(AccountEvent::SetValue {
                     address: call.to,
                     val: src,
@@ -458,23 +909,6 @@ impl<T: EventTracer> Executor<T> {
             });
         }
 
-        let nonce = ext.nonce(&call.from).await?;
-        ext.account_mut(&call.from).nonce = nonce + Word::one();
-        evm.touches.push(AccountTouch::SetNonce(
-            call.from,
-            nonce.as_u64(),
-            nonce.as_u64() + 1,
-        ));
-        self.tracer.push(Event {
-            data: EventData::Account(AccountEvent::SetNonce {
-                address: call.from,
-                val: nonce.as_u64(),
-                new: nonce.as_u64() + 1,
-            }),
-            depth: 1,
-            reverted: false,
-        });
-
         let is_transfer_only =
             code.bytecode.is_empty() && call.data.is_empty() && !call.to.is_zero();
         if is_transfer_only {
@@ -491,20 +925,41 @@ impl<T: EventTracer> Executor<T> {
             ..Context::default()
         };
 
-        // Store base_fee and header info for later use (before moving self.header)
-        let base_fee = self.header.base_fee;
-        let header_clone = self.header.clone();
+        let prelude = Prelude {
+            ctx,
+            market,
+            charge,
+            checkpoint,
+            intrinsic: call_cost + data_cost + access_list_cost + authorization_list_cost,
+            authorization_refund,
+        };
+        Ok((prelude, reloaded))
+    }
 
-        let tracer = self.tracer.fork();
-        let mut executor = Executor::<T>::with_tracer(tracer).with_header(self.header);
-        executor.set_log(self.log);
-        let (tracer, ret) = executor
-            .execute_with_context(code, call, evm, ext, ctx)
-            .await;
+    /// Join the tracer of the complete frame, close the checkpoint of the
+    /// prelude, charge the gas used and settle the fees
+    pub(crate) async fn settle(
+        mut self,
+        prelude: Prelude,
+        call: &Call,
+        tracer: T,
+        ret: &[u8],
+        evm: &mut Evm,
+        ext: &mut Ext,
+    ) -> eyre::Result<T> {
         self.tracer.join(tracer, evm.reverted);
-
+        let Prelude {
+            market,
+            charge,
+            checkpoint,
+            intrinsic,
+            authorization_refund,
+            ..
+        } = prelude;
         if evm.reverted {
-            evm.revert(ext).await?;
+            ext.revert_to(checkpoint);
+        } else {
+            ext.commit(checkpoint);
         }
 
         // Calculate gas costs and transaction fee
@@ -513,153 +968,78 @@ impl<T: EventTracer> Executor<T> {
         let init_code_cost = 2 * call.data.len().div_ceil(32) as i64;
 
         // EIP-7623: Increase calldata cost
-        let calldata_tokens = {
-            let zero_bytes = call.data.iter().filter(|b| **b == 0).count() as i64;
-            let nonzero_bytes = call.data.len() as i64 - zero_bytes;
-            zero_bytes + nonzero_bytes * 4
-        };
-        let gas_floor = call_cost + 10 * calldata_tokens;
+        let gas_floor = self.spec.gas_floor(&call.data);
 
         let gas_costs = if !call.to.is_zero() {
-            call_cost + data_cost + access_list_cost
+            intrinsic
         } else {
             let deployed_code_cost = 200 * ret.len() as i64;
-            call_cost
-                + data_cost
-                + create_cost
-                + init_code_cost
-                + deployed_code_cost
-                + access_list_cost
+            intrinsic + create_cost + init_code_cost + deployed_code_cost
         };
 
-        let gas_final = evm.gas.finalized(gas_costs, evm.reverted).max(gas_floor);
-
-        // Calculate effective gas price for fee calculation
-        // For EIP-1559: min(max_fee_per_gas, base_fee + max_priority_fee_per_gas)
-        // For legacy: gas_price
-        let effective_gas_price = if ext.tx_ctx.gas_max_priority_fee.is_zero() {
-            // Legacy transaction
-            ext.tx_ctx.gas_price
+        // EIP-7702: Authorization refund is granted even if the transaction reverts
+        let gas_final = if evm.reverted {
+            let used = evm.gas.finalized(gas_costs, true);
+            used - authorization_refund.min(used / 5)
         } else {
-            // EIP-1559 transaction
-            let base_plus_priority = base_fee + ext.tx_ctx.gas_max_priority_fee;
-            Word::min(ext.tx_ctx.gas_max_fee, base_plus_priority)
-        };
-
-        let gas_used_fee = Word::from(gas_final) * effective_gas_price;
-        let gas_refund = gas_prepayment - gas_used_fee;
-
-        // Refund unused gas to sender
-        if !gas_refund.is_zero() {
-            let src = ext.balance(&call.from).await?;
-            let new_balance = src + gas_refund;
-            ext.account_mut(&call.from).value = new_balance;
-        }
-
-        let balance_after = ext.balance(&call.from).await?;
-        evm.touches.push(AccountTouch::FeePay(
-            call.from,
-            sender_balance,
-            balance_after,
-        ));
-
-        // Transfer priority fee to coinbase (base fee is burned per EIP-1559)
-        // Match revm's calculation: recalculate effective_gas_price from max_fee and max_priority
-        // then coinbase_gas_price = effective_gas_price - basefee
-        // Note: EIP-4844 blob transactions pay BOTH regular gas fees AND blob fees to coinbase
-        // Note: Gas fees are charged regardless of whether the transaction succeeds or reverts
-        if !coinbase.is_zero() {
-            // Effective priority to coinbase = min(max_priority, max_fee - base_fee) = min(10.01, 30.03 - 0.665752928) ≈ 10.01 Gwei
-            let coinbase_gas_price = if ext.tx_ctx.gas_max_priority_fee.is_zero() {
-                // Legacy transaction (type 0): gas_price - base_fee goes to miner (base fee is burned)
-                // COINBASE BALANCE fix 1/1: legacy tx effective gas price
-                ext.tx_ctx.gas_price.saturating_sub(base_fee)
-            } else {
-                // EIP-1559 transaction (type 2): recalculate effective_gas_price like revm does
-                // effective_gas_price = min(max_fee_per_gas, base_fee + max_priority_fee_per_gas)
-                let effective_gas_price = {
-                    let base_plus_priority = base_fee + ext.tx_ctx.gas_max_priority_fee;
-                    Word::min(ext.tx_ctx.gas_max_fee, base_plus_priority)
-                };
-                // coinbase_gas_price = effective_gas_price - basefee
-                effective_gas_price.saturating_sub(base_fee)
-            };
-
-            let priority_fee_total = Word::from(gas_final) * coinbase_gas_price;
-
-            // Ensure coinbase is in state (it may not be if no code accessed it during execution)
-            let current_coinbase_balance = ext.balance(&coinbase).await?;
-
-            if !priority_fee_total.is_zero() {
-                // TODO: charge sender with `priority_fee_total`
-
-                let new_coinbase_balance = current_coinbase_balance + priority_fee_total;
-                ext.account_mut(&coinbase).value = new_coinbase_balance;
-                // println!("[SOLE] COINBASE (GAS)  : {new_coinbase_balance:#x} *{priority_fee_total:#x}");
-                // DO NOT add to evm.touches - fee additions are final and should never be reverted
-                // COINBASE BALANCE fix 1/2: do not revert unreversible fee charge
-                self.tracer.push(Event {
-                    data: EventData::Account(AccountEvent::SetValue {
-                        address: coinbase,
-                        val: current_coinbase_balance,
-                        new: new_coinbase_balance,
-                    }),
-                    depth: 1,
-                    reverted: false,
-                });
-                evm.touches.push(AccountTouch::FeePay(
-                    coinbase,
-                    current_coinbase_balance,
-                    new_coinbase_balance,
-                ));
-            } else {
-                // Even if no priority fees are paid, COINBASE should be marked as touched
-                // because we accessed it (read its balance) during fee calculation
-                evm.touches.push(AccountTouch::FeePay(
-                    coinbase,
-                    current_coinbase_balance,
-                    current_coinbase_balance,
-                ));
-            }
-        }
-
-        // Add blob gas fees (EIP-4844) if this transaction used blob gas
-        if ext.tx_ctx.blob_gas_used > 0 && !coinbase.is_zero() {
-            let blob_gas_price = header_clone.blob_gas_price().min(ext.tx_ctx.blob_max_fee);
-            let blob_fee = Word::from(ext.tx_ctx.blob_gas_used) * blob_gas_price;
-
-            if !blob_fee.is_zero() {
-                // TODO: charge sender with `blob_fee`
-
-                // let current_coinbase_balance = ext.balance(&coinbase).await?;
-                // println!("[SOLE] COINBASE (BLOB) : {current_coinbase_balance:#x} !{blob_fee:#x}");
-                // DO NOT add to evm.touches - blob fees are final and should never be reverted
-            }
+            evm.gas.refund += authorization_refund;
+            evm.gas.finalized(gas_costs, false)
         }
+        .max(gas_floor);
+
+        let fees = market
+            .settle(
+                &call.from,
+                &self.header.miner,
+                gas_final,
+                charge,
+                evm,
+                ext,
+                &mut self.tracer,
+            )
+            .await?;
 
         // Emit fee event showing gas consumed
-        self.tracer.push(Event {
+        self.emit(|_| Event {
             data: EventData::Fee {
                 gas: Word::from(gas_final),
                 price: ext.tx_ctx.gas_price,
-                total: gas_used_fee,
+                total: fees.total,
             },
             depth: 1,
             reverted: false,
         });
-
-        Ok((self.tracer, ret))
+        evm.fees = fees;
+        Ok(self.tracer)
     }
 
+    /// Run the call frame to completion. Nested calls do not recurse: frames
+    /// are kept on an explicit stack (see `Session`).
     pub async fn execute_with_context(
-        mut self,
+        self,
         code: &Bytecode,
         call: &Call,
         evm: &mut Evm,
         ext: &mut Ext,
         ctx: Context,
     ) -> (T, Vec<u8>) {
-        self.tracer.push(Event {
+        let mut session = Session::new(self, code, call, std::mem::take(evm), ext, ctx);
+        session.run(ext).await;
+        let (tracer, ret, inner) = session.finish().expect("session is complete");
+        *evm = inner;
+        (tracer, ret)
+    }
+
+    /// Emit the call event and check the depth limit. Returns the return data
+    /// if the frame is already complete.
+    pub(crate) fn enter(
+        &mut self,
+        call: &Call,
+        evm: &mut Evm,
+        ext: &mut Ext,
+        ctx: Context,
+    ) -> Option<Vec<u8>> {
+        self.emit(|_| Event {
             data: EventData::Call {
                 r#type: ctx.call_type,
                 data: call.data.clone().into(),
@@ -677,96 +1057,304 @@ impl<T: EventTracer> Executor<T> {
             evm.touches.push(AccountTouch::WarmUp(call.from));
         }
 
-        if ctx.depth > CALL_DEPTH_LIMIT {
+        if ctx.depth > self.limits.call_depth {
             evm.stopped = true;
             evm.reverted = true;
-            return (self.tracer, vec![]);
+            return Some(vec![]);
         }
+        None
+    }
 
-        while !evm.stopped && evm.pc < code.instructions.len() {
-            let pc = evm.pc;
-            let instruction = &code.instructions[pc];
-            match self
-                .execute_instruction(code, call, evm, ext, ctx, instruction)
-                .await
-            {
-                Ok(result) => {
-                    let (cost, halt) = match result {
-                        StepResult::Ok(cost) => (cost, false),
-                        StepResult::Halt(cost) => {
-                            // let cost = evm.gas.remaining();
-                            // evm.gas(cost).ok();
-                            evm.stopped = true;
-                            evm.reverted = true;
-                            (cost, true)
-                        }
-                    };
-
-                    let is_sstore = halt && instruction.opcode.name == "SSTORE";
-                    if !instruction.is_call() && !is_sstore {
-                        // HERE: TODO: remove this label
-                        let charged_cost = cost.min(evm.gas.remaining());
-                        let refund = evm.gas.refund - evm.refund;
-                        evm.refund = evm.gas.refund;
-
-                        self.tracer.push(Event {
-                            depth: ctx.depth,
-                            reverted: false,
-                            data: EventData::OpCode(OpCode {
-                                pc: instruction.offset,
-                                op: instruction.opcode.code,
-                                name: instruction.opcode.name(),
-                                data: instruction.argument.clone().map(Into::into),
-                                gas_cost: charged_cost,
-                                gas_used: evm.gas.used + charged_cost,
-                                gas_back: refund,
-                                gas_left: evm.gas.remaining() - charged_cost,
-                                stack: evm.stack.clone(),
-                                memory: evm.memory.chunks(32).map(Word::from_bytes).collect(),
-                                debug: self.debug.take(),
-                            }),
-                        });
-                    }
-                    if halt || instruction.opcode.code == 0xfe {
-                        // INVALID opcode
-                        // eprintln!("OPCODE INVALID: depth={} evm.pc={} op={}", ctx.depth, evm.pc, instruction.opcode.name());
-                        evm.gas.sub(evm.gas.remaining()).expect("must succeed");
+    /// Execute the next instruction of the frame
+    pub(crate) async fn step(
+        &mut self,
+        code: &Bytecode,
+        call: &Call,
+        evm: &mut Evm,
+        ext: &mut Ext,
+        ctx: Context,
+    ) -> Step {
+        if evm.stopped || evm.pc >= code.instructions.len() {
+            return Step::Return(self.finish(code, evm, ctx));
+        }
+        let instruction = &code.instructions[evm.pc];
+        // A block that cannot be entered as a whole runs up to the instruction
+        // that fails, which is where the halt is reported
+        if let Some(block) = &instruction.block {
+            evm.stepwise = evm.enter_block(block, self.limits.stack).is_err();
+        }
+        if evm.stepwise
+            && let Err(e) =
+                evm.enter_block(&Block::of(slice::from_ref(instruction)), self.limits.stack)
+        {
+            return Step::Return(self.halt(e, evm, ctx));
+        }
+        let result = self
+            .execute_instruction(code, call, evm, ext, ctx, instruction)
+            .await;
+        if matches!(result, Ok(StepResult::Suspend)) {
+            return Step::Suspend;
+        }
+        if let Some(ret) = self.complete(instruction, result, evm, ctx) {
+            return Step::Return(ret);
+        }
+        if evm.stopped || evm.pc >= code.instructions.len() {
+            return Step::Return(self.finish(code, evm, ctx));
+        }
+        Step::Next
+    }
+
+    /// Stack snapshot for the OpCode event, if the tracer needs it
+    fn stack(evm: &Evm) -> Vec<Word> {
+        if T::TRACE.stack {
+            evm.stack.clone()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Memory snapshot for the OpCode event, if the tracer needs it
+    fn memory(evm: &Evm) -> Vec<Word> {
+        if T::TRACE.memory {
+            evm.memory.chunks(32).map(Word::from_bytes).collect()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Debug info for the OpCode event, if the tracer needs it
+    fn debug(info: impl FnOnce() -> serde_json::Value) -> serde_json::Value {
+        if T::TRACE.debug {
+            info()
+        } else {
+            serde_json::Value::Null
+        }
+    }
+
+    /// EIP-7761: code of an account as seen by EXTCODESIZE and EXTCODECOPY,
+    /// which do not expose the contents of EOF containers
+    fn legacy_view(&self, code: Vec<u8>) -> Vec<u8> {
+        if self.spec.is_enabled(Spec::Eof) && eof::is_eof(&code) {
+            eof::MAGIC.to_vec()
+        } else {
+            code
+        }
+    }
+
+    /// EOF container of the code, EOF-only opcodes are not decoded otherwise
+    fn eof(code: &Bytecode) -> Result<&EofCode, ExecutorError> {
+        code.eof.as_ref().ok_or(ExecutorError::MissingData)
+    }
+
+    /// Immediate argument of the instruction as a big-endian number
+    fn immediate(instruction: &Instruction) -> Result<usize, ExecutorError> {
+        let argument = instruction
+            .argument
+            .as_ref()
+            .ok_or(ExecutorError::MissingData)?;
+        Ok(argument
+            .iter()
+            .fold(0usize, |acc, byte| (acc << 8) | *byte as usize))
+    }
+
+    /// Push the event built by `event`, if the tracer takes events
+    #[inline]
+    fn emit(&mut self, event: impl FnOnce(&Self) -> Event) {
+        if T::TRACE.events {
+            let event = event(self);
+            self.tracer.push(event);
+        }
+    }
+
+    /// OpCode event of the EOF call instruction that did not enter a child frame
+    fn trace_call(&mut self, instruction: &Instruction, evm: &Evm, ctx: Context, gas_cost: i64) {
+        if T::TRACE.opcodes {
+            self.tracer.push(Event {
+                depth: ctx.depth,
+                reverted: false,
+                data: EventData::OpCode(OpCode {
+                    pc: instruction.offset,
+                    op: instruction.opcode.code,
+                    name: instruction.opcode.name(),
+                    data: instruction.argument.clone().map(Into::into),
+                    gas_cost,
+                    gas_used: evm.gas.used + gas_cost,
+                    gas_back: 0,
+                    gas_left: evm.gas.remaining() - gas_cost,
+                    stack: Self::stack(evm),
+                    memory: Self::memory(evm),
+                    debug: self.debug.take(),
+                }),
+            });
+        }
+    }
+
+    /// Call and Return events of a precompile call, which does not enter a
+    /// child frame
+    fn trace_precompile(&mut self, call: Call, ok: bool, gas_used: i64, ctx: Context) {
+        self.emit(|_| Event {
+            data: EventData::Call {
+                r#type: CallType::Precompile(call.to),
+                data: call.data.into(),
+                value: call.value,
+                from: call.from,
+                to: call.to,
+                gas: call.gas,
+            },
+            depth: ctx.depth + 1,
+            reverted: !ok,
+        });
+        self.emit(|this| Event {
+            data: EventData::Return {
+                ok,
+                data: if ok { this.ret.clone() } else { vec![] }.into(),
+                gas_used,
+                error: None,
+            },
+            depth: ctx.depth + 1,
+            reverted: !ok,
+        });
+    }
+
+    /// Exceptional halt before the next instruction: on entry to a basic
+    /// block or once the step budget is exhausted
+    pub(crate) fn halt(&mut self, e: ExecutorError, evm: &mut Evm, ctx: Context) -> Vec<u8> {
+        let reason = e.halt_reason();
+        evm.gas.used = evm.gas.limit;
+        evm.stopped = true;
+        evm.reverted = true;
+        evm.halt = Some(reason);
+        self.emit(|_| Event {
+            depth: ctx.depth,
+            reverted: true,
+            data: EventData::Halt(reason),
+        });
+        vec![]
+    }
+
+    /// Frame ran out of instructions or was stopped: returns the return data
+    pub(crate) fn finish(&mut self, code: &Bytecode, evm: &Evm, ctx: Context) -> Vec<u8> {
+        if T::TRACE.opcodes && !evm.stopped && !code.instructions.is_empty() {
+            self.tracer.push(Event {
+                depth: ctx.depth,
+                reverted: false,
+                data: EventData::OpCode(OpCode {
+                    pc: evm.pc,
+                    op: 0x00,
+                    name: "STOP".to_string(),
+                    data: None,
+                    gas_cost: 0,
+                    gas_used: evm.gas.used,
+                    gas_back: 0,
+                    gas_left: evm.gas.remaining(),
+                    stack: Self::stack(evm),
+                    memory: Self::memory(evm),
+                    debug: self.debug.take(),
+                }),
+            });
+        }
+
+        std::mem::take(&mut self.ret)
+    }
+
+    /// Charge and trace the executed instruction. Returns the return data
+    /// if the instruction terminated the frame.
+    fn complete(
+        &mut self,
+        instruction: &Instruction,
+        result: eyre::Result<StepResult>,
+        evm: &mut Evm,
+        ctx: Context,
+    ) -> Option<Vec<u8>> {
+        match result {
+            Ok(result) => {
+                let (cost, halt) = match result {
+                    StepResult::Ok(cost) => (cost, false),
+                    StepResult::Suspend => unreachable!("suspended instruction"),
+                    StepResult::Halt(cost) => {
+                        // let cost = evm.gas.remaining();
+                        // evm.gas(cost).ok();
                         evm.stopped = true;
                         evm.reverted = true;
-                        self.tracer.push(Event {
-                            depth: ctx.depth,
-                            reverted: true,
-                            data: if instruction.opcode.code == 0xfe {
-                                EventData::Halt(HaltReason::InvalidOpcode)
-                            } else {
-                                EventData::Halt(HaltReason::OutOfGas)
-                            },
-                        });
-                        return (self.tracer, vec![]);
+                        (cost, true)
                     }
-                    if evm.gas(cost).is_err() {
-                        // out of gas
-                        // eprintln!("OUT OF GAS: depth={} evm.pc={} op={}", ctx.depth, evm.pc, instruction.opcode.name());
-                        evm.stopped = true;
-                        evm.reverted = true;
+                };
+
+                // Static gas was charged on entry to the basic block
+                let static_gas = instruction.opcode.static_gas();
+                evm.prepaid -= static_gas;
+
+                let is_sstore = halt && instruction.opcode.name == "SSTORE";
+                if !instruction.is_call() && !is_sstore {
+                    let charged_cost = cost.min(evm.gas.remaining() + evm.prepaid);
+                    let refund = evm.gas.refund - evm.refund;
+                    evm.refund = evm.gas.refund;
+
+                    if T::TRACE.opcodes {
                         self.tracer.push(Event {
                             depth: ctx.depth,
-                            reverted: true,
-                            data: EventData::Halt(HaltReason::OutOfGas),
+                            reverted: false,
+                            data: EventData::OpCode(OpCode {
+                                pc: instruction.offset,
+                                op: instruction.opcode.code,
+                                name: instruction.opcode.name(),
+                                data: instruction.argument.clone().map(Into::into),
+                                gas_cost: static_gas + charged_cost,
+                                gas_used: evm.gas.used - evm.prepaid + charged_cost,
+                                gas_back: refund,
+                                gas_left: evm.gas.remaining() + evm.prepaid - charged_cost,
+                                stack: Self::stack(evm),
+                                memory: Self::memory(evm),
+                                debug: self.debug.take(),
+                            }),
                         });
-                        return (self.tracer, vec![]);
-                    }
-                    if instruction.opcode.code == 0xff {
-                        // SELFDESTRUCT opcode
-                        return (self.tracer, vec![]);
                     }
                 }
-                Err(_) => {
-                    // opcode failed
-                    // eprintln!("OPCODE FAILED: depth={} evm.pc={} op={}", ctx.depth, evm.pc, instruction.opcode.name());
+                if halt || instruction.opcode.code == 0xfe {
+                    // INVALID opcode
+                    evm.gas.sub(evm.gas.remaining()).expect("must succeed");
+                    evm.stopped = true;
+                    evm.reverted = true;
+                    let reason = if instruction.opcode.code == 0xfe {
+                        HaltReason::InvalidOpcode
+                    } else {
+                        HaltReason::OutOfGas
+                    };
+                    evm.halt = Some(reason);
+                    self.emit(|_| Event {
+                        depth: ctx.depth,
+                        reverted: true,
+                        data: EventData::Halt(reason),
+                    });
+                    return Some(vec![]);
+                }
+                if evm.gas(cost).is_err() {
+                    // out of gas
                     evm.stopped = true;
                     evm.reverted = true;
+                    evm.halt = Some(HaltReason::OutOfGas);
+                    self.emit(|_| Event {
+                        depth: ctx.depth,
+                        reverted: true,
+                        data: EventData::Halt(HaltReason::OutOfGas),
+                    });
+                    return Some(vec![]);
+                }
+                if instruction.opcode.code == 0xff {
+                    // SELFDESTRUCT opcode
+                    return Some(vec![]);
+                }
+            }
+            Err(e) => {
+                // opcode failed
+                evm.stopped = true;
+                evm.reverted = true;
+                evm.halt = Some(
+                    e.downcast_ref::<ExecutorError>()
+                        .map_or(HaltReason::OutOfGas, ExecutorError::halt_reason),
+                );
 
+                if T::TRACE.opcodes {
                     self.tracer.push(Event {
                         depth: ctx.depth,
                         reverted: true,
@@ -779,65 +1367,44 @@ impl<T: EventTracer> Executor<T> {
                             gas_used: evm.gas.used,
                             gas_back: evm.gas.refund - evm.refund,
                             gas_left: evm.gas.remaining(),
-                            stack: evm.stack.clone(),
-                            memory: evm.memory.chunks(32).map(Word::from_bytes).collect(),
+                            stack: Self::stack(evm),
+                            memory: Self::memory(evm),
                             debug: self.debug.take(),
                         }),
                     });
-
-                    evm.gas(evm.gas.remaining()).expect("must succeed");
-                    return (self.tracer, vec![]);
                 }
-            }
 
-            if self.log {
-                let data = instruction
-                    .argument
-                    .as_ref()
-                    .map(|data| format!("0x{}", hex::encode(data)));
-                println!(
-                    "{:#06x}: {} {}",
-                    evm.pc,
-                    instruction.opcode.name(),
-                    data.unwrap_or_default()
-                );
-                println!("MEMORY:{}", if evm.memory.is_empty() { " []" } else { "" });
-                evm.memory.chunks(32).enumerate().for_each(|(index, word)| {
-                    let offset = index << 5;
-                    let word = hex::encode(word);
-                    println!("{offset:#04x}: {word}");
-                });
-                println!("STACK:{}", if evm.stack.is_empty() { " []" } else { "" });
-                evm.stack
-                    .iter()
-                    .rev()
-                    .enumerate()
-                    .for_each(|(i, word)| println!("{:>4}: {word:#02x}", i + 1));
-                println!();
+                evm.gas(evm.gas.remaining()).expect("must succeed");
+                return Some(vec![]);
             }
         }
 
-        if !evm.stopped && !code.instructions.is_empty() {
-            self.tracer.push(Event {
-                depth: ctx.depth,
-                reverted: false,
-                data: EventData::OpCode(OpCode {
-                    pc: evm.pc,
-                    op: 0x00,
-                    name: "STOP".to_string(),
-                    data: None,
-                    gas_cost: 0,
-                    gas_used: evm.gas.used,
-                    gas_back: 0,
-                    gas_left: evm.gas.remaining(),
-                    stack: evm.stack.clone(),
-                    memory: evm.memory.chunks(32).map(Word::from_bytes).collect(),
-                    debug: self.debug.take(),
-                }),
+        if self.log {
+            let data = instruction
+                .argument
+                .as_ref()
+                .map(|data| format!("0x{}", hex::encode(data)));
+            println!(
+                "{:#06x}: {} {}",
+                evm.pc,
+                instruction.opcode.name(),
+                data.unwrap_or_default()
+            );
+            println!("MEMORY:{}", if evm.memory.is_empty() { " []" } else { "" });
+            evm.memory.chunks(32).enumerate().for_each(|(index, word)| {
+                let offset = index << 5;
+                let word = hex::encode(word);
+                println!("{offset:#04x}: {word}");
             });
+            println!("STACK:{}", if evm.stack.is_empty() { " []" } else { "" });
+            evm.stack
+                .iter()
+                .rev()
+                .enumerate()
+                .for_each(|(i, word)| println!("{:>4}: {word:#02x}", i + 1));
+            println!();
         }
-
-        (self.tracer, self.ret)
+        None
     }
 
     async fn execute_instruction(
@@ -849,7 +1416,9 @@ impl<T: EventTracer> Executor<T> {
         ctx: Context,
         instruction: &Instruction,
     ) -> eyre::Result<StepResult> {
-        self.debug = json!({});
+        if T::TRACE.debug {
+            self.debug = json!({});
+        }
         let mut gas = 0i64;
         let mut pc_increment = true;
 
@@ -860,6 +1429,13 @@ impl<T: EventTracer> Executor<T> {
         };
 
         let opcode = instruction.opcode.code;
+        if !self.spec.has_opcode(opcode) {
+            return Err(ExecutorError::UnknownOpcode(opcode).into());
+        }
+        // Before EOF is enabled, the 0xEF prefix is an undefined opcode
+        if code.eof.is_some() && !self.spec.is_enabled(Spec::Eof) {
+            return Err(ExecutorError::UnknownOpcode(eof::MAGIC[0]).into());
+        }
         match opcode {
             // STOP
             0x00 => {
@@ -868,7 +1444,7 @@ impl<T: EventTracer> Executor<T> {
                 evm.reverted = false;
                 self.ret.clear();
 
-                self.tracer.push(Event {
+                self.emit(|_| Event {
                     data: EventData::Return {
                         ok: true,
                         data: vec![].into(),
@@ -883,57 +1459,37 @@ impl<T: EventTracer> Executor<T> {
             // 0x01..0x0b: Arithmetic Operations
             0x01 => {
                 // ADD
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
                 let (res, _) = a.overflowing_add(b);
-                evm.push(res)?;
+                evm.push(res, self.limits.stack)?;
             }
             0x02 => {
                 // MUL
-                gas = 5;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
                 let (res, _) = a.overflowing_mul(b);
-                evm.push(res)?;
+                evm.push(res, self.limits.stack)?;
             }
             0x03 => {
                 // SUB
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
                 let (res, _) = a.overflowing_sub(b);
-                evm.push(res)?;
+                evm.push(res, self.limits.stack)?;
             }
             0x04 => {
                 // DIV
-                gas = 5;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
                 if b.is_zero() || a.is_zero() {
-                    evm.push(Word::zero())?;
+                    evm.push(Word::zero(), self.limits.stack)?;
                 } else {
-                    evm.push(a / b)?;
+                    evm.push(a / b, self.limits.stack)?;
                 }
             }
             0x05 => {
                 // SDIV
-                gas = 5;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
                 let a_signed = I256::from_be_bytes(a.into_bytes());
@@ -945,28 +1501,20 @@ impl<T: EventTracer> Executor<T> {
                 } else {
                     a_signed / b_signed
                 };
-                evm.push(Word::from_bytes(&res.to_be_bytes()))?;
+                evm.push(Word::from_bytes(&res.to_be_bytes()), self.limits.stack)?;
             }
             0x06 => {
                 // MOD
-                gas = 5;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
                 if b.is_zero() {
-                    evm.push(Word::zero())?;
+                    evm.push(Word::zero(), self.limits.stack)?;
                 } else {
-                    evm.push(a % b)?;
+                    evm.push(a % b, self.limits.stack)?;
                 }
             }
             0x07 => {
                 // SMOD
-                gas = 5;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
                 let a_signed = I256::from_be_bytes(a.into_bytes());
@@ -976,14 +1524,10 @@ impl<T: EventTracer> Executor<T> {
                 } else {
                     a_signed % b_signed
                 };
-                evm.push(Word::from_bytes(&res.to_be_bytes()))?;
+                evm.push(Word::from_bytes(&res.to_be_bytes()), self.limits.stack)?;
             }
             0x08 => {
                 // ADDMOD
-                gas = 8;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
                 let m = evm.pop()?;
@@ -992,25 +1536,23 @@ impl<T: EventTracer> Executor<T> {
                 } else {
                     a.add_modulo(&b, &m)
                 };
-                self.debug["ADDMOD"] = json!({
-                    "a": a,
-                    "b": b,
-                    "m": m,
-                    "r": r,
-                });
-                evm.push(r)?;
+                if T::TRACE.debug {
+                    self.debug["ADDMOD"] = json!({
+                        "a": a,
+                        "b": b,
+                        "m": m,
+                        "r": r,
+                    });
+                }
+                evm.push(r, self.limits.stack)?;
             }
             0x09 => {
                 // MULMOD
-                gas = 8;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
                 let m = evm.pop()?;
                 let res = a.mul_modulo(&b, &m);
-                evm.push(res)?;
+                evm.push(res, self.limits.stack)?;
             }
             0x0a => {
                 // EXP
@@ -1025,14 +1567,10 @@ impl<T: EventTracer> Executor<T> {
                 if evm.gas.remaining() < gas {
                     return Ok(StepResult::Halt(gas));
                 }
-                evm.push(base.pow(exponent))?;
+                evm.push(base.pow(exponent), self.limits.stack)?;
             }
             0x0b => {
                 // SIGNEXTEND
-                gas = 5;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let x = evm.pop()?.as_usize();
                 let b = evm.pop()?;
 
@@ -1041,180 +1579,138 @@ impl<T: EventTracer> Executor<T> {
 
                 let mask = Word::max() << (bit + 1);
                 let y = if neg { b | mask } else { b & !mask };
-                evm.push(y)?;
+                evm.push(y, self.limits.stack)?;
             }
 
             // 0x10s: Comparison & Bitwise Logic
             0x10 => {
                 // LT
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
-                evm.push(if a < b { Word::one() } else { Word::zero() })?;
+                evm.push(
+                    if a < b { Word::one() } else { Word::zero() },
+                    self.limits.stack,
+                )?;
             }
             0x11 => {
                 // GT
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
-                evm.push(if a > b { Word::one() } else { Word::zero() })?;
+                evm.push(
+                    if a > b { Word::one() } else { Word::zero() },
+                    self.limits.stack,
+                )?;
             }
             0x12 => {
                 // SLT
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
                 let a_signed = I256::from_be_bytes(a.into_bytes());
                 let b_signed = I256::from_be_bytes(b.into_bytes());
-                evm.push(if a_signed < b_signed {
-                    Word::one()
-                } else {
-                    Word::zero()
-                })?;
+                evm.push(
+                    if a_signed < b_signed {
+                        Word::one()
+                    } else {
+                        Word::zero()
+                    },
+                    self.limits.stack,
+                )?;
             }
             0x13 => {
                 // SGT
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
                 let a_signed = I256::from_be_bytes(a.into_bytes());
                 let b_signed = I256::from_be_bytes(b.into_bytes());
-                evm.push(if a_signed > b_signed {
-                    Word::one()
-                } else {
-                    Word::zero()
-                })?;
+                evm.push(
+                    if a_signed > b_signed {
+                        Word::one()
+                    } else {
+                        Word::zero()
+                    },
+                    self.limits.stack,
+                )?;
             }
             0x14 => {
                 // EQ
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
-                evm.push(if a == b { Word::one() } else { Word::zero() })?;
+                evm.push(
+                    if a == b { Word::one() } else { Word::zero() },
+                    self.limits.stack,
+                )?;
             }
             0x15 => {
                 // ISZERO
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
-                evm.push(if a.is_zero() {
-                    Word::one()
-                } else {
-                    Word::zero()
-                })?;
+                evm.push(
+                    if a.is_zero() {
+                        Word::one()
+                    } else {
+                        Word::zero()
+                    },
+                    self.limits.stack,
+                )?;
             }
             0x16 => {
                 // AND
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
-                evm.push(a & b)?;
+                evm.push(a & b, self.limits.stack)?;
             }
             0x17 => {
                 // OR
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
-                evm.push(a | b)?;
+                evm.push(a | b, self.limits.stack)?;
             }
             0x18 => {
                 // XOR
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
                 let b = evm.pop()?;
-                evm.push(a ^ b)?;
+                evm.push(a ^ b, self.limits.stack)?;
             }
             0x19 => {
                 // NOT
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let a = evm.pop()?;
-                evm.push(!a)?;
+                evm.push(!a, self.limits.stack)?;
             }
             0x1a => {
                 // BYTE
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let index = evm.pop()?.as_usize();
                 let value: Word = evm.pop()?;
                 if index < 32 {
-                    evm.push(Word::from(value.into_bytes()[index]))?;
+                    evm.push(Word::from(value.into_bytes()[index]), self.limits.stack)?;
                 } else {
-                    evm.push(Word::zero())?;
+                    evm.push(Word::zero(), self.limits.stack)?;
                 }
             }
             0x1b => {
                 // SHL
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let shift = evm.pop()?.as_usize();
                 let value = evm.pop()?;
                 let ret = value << shift;
-                evm.push(ret)?;
+                evm.push(ret, self.limits.stack)?;
             }
             0x1c => {
                 // SHR
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let shift = evm.pop()?.as_usize();
                 let value = evm.pop()?;
                 let ret = value >> shift;
-                evm.push(ret)?;
+                evm.push(ret, self.limits.stack)?;
             }
             0x1d => {
                 // SAR
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let shift = evm.pop()?.as_usize();
                 let value = evm.pop()?;
                 let value = I256::from_be_bytes(value.into_bytes());
                 let ret = value >> shift;
                 let ret = Word::from_bytes(&ret.to_be_bytes());
-                evm.push(ret)?;
+                evm.push(ret, self.limits.stack)?;
             }
             0x1e => {
                 // CLZ (EIP-7939): Count Leading Zeros on a 256-bit word.
                 // Returns 256 for input zero, otherwise the number of leading zero bits.
-                gas = 5;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let value = evm.pop()?;
                 let clz = if value.is_zero() {
                     256u64
@@ -1231,13 +1727,13 @@ impl<T: EventTracer> Executor<T> {
                     }
                     count
                 };
-                evm.push(Word::from(clz))?;
+                evm.push(Word::from(clz), self.limits.stack)?;
             }
             0x20 => {
                 // SHA3 (KECCAK256)
                 let offset = evm.pop()?.as_usize();
                 let size = evm.pop()?.as_usize();
-                if size > ALLOCATION_SANITY_LIMIT {
+                if size > self.limits.memory {
                     return Err(ExecutorError::InvalidAllocation(offset + size).into());
                 }
                 let mut data = vec![0u8; size];
@@ -1247,7 +1743,7 @@ impl<T: EventTracer> Executor<T> {
                 };
                 let sha3 = keccak256(&data);
                 let hash = Word::from_bytes(&sha3);
-                self.tracer.push(Event {
+                self.emit(|_| Event {
                     data: EventData::Hash {
                         data: data.into(),
                         hash: sha3.into(),
@@ -1260,87 +1756,65 @@ impl<T: EventTracer> Executor<T> {
                 if evm.gas.remaining() < gas {
                     return Ok(StepResult::Halt(gas));
                 }
-                evm.push(hash)?;
+                evm.push(hash, self.limits.stack)?;
             }
 
             // 30-3f
             0x30 => {
                 // ADDRESS
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push((&this).into())?;
+                evm.push((&this).into(), self.limits.stack)?;
             }
             0x31 => {
                 // BALANCE
                 let addr = (&evm.pop()?).into();
                 // EIP-2929: Use proper address access tracking
-                gas = evm.address_access_cost(&addr, ext);
+                gas = evm.address_access_cost(&addr, ext, self.is_precompile(&addr));
                 if evm.gas.remaining() < gas {
                     return Ok(StepResult::Halt(gas));
                 }
                 let value = ext.balance(&addr).await?;
                 evm.touches.push(AccountTouch::GetValue(addr, value));
-                self.debug["BALANCE"] = json!({
-                    "address": addr,
-                    "balance": value,
-                    "is_coinbase": addr == self.header.miner,
-                });
-                evm.push(value)?;
+                if T::TRACE.debug {
+                    self.debug["BALANCE"] = json!({
+                        "address": addr,
+                        "balance": value,
+                        "is_coinbase": addr == self.header.miner,
+                    });
+                }
+                evm.push(value, self.limits.stack)?;
             }
             0x32 => {
                 // ORIGIN
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push((&ctx.origin).into())?;
+                evm.push((&ctx.origin).into(), self.limits.stack)?;
             }
             0x33 => {
                 // CALLER
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push((&call.from).into())?;
+                evm.push((&call.from).into(), self.limits.stack)?;
             }
             0x34 => {
                 // CALLVALUE
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let value = if matches!(ctx.call_type, CallType::Static) {
                     Word::zero()
                 } else {
                     call.value
                 };
-                evm.push(value)?;
+                evm.push(value, self.limits.stack)?;
             }
             0x35 => {
                 // CALLDATALOAD
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let offset = evm.pop()?.as_usize();
                 if offset > call.data.len() {
-                    evm.push(Word::zero())?;
+                    evm.push(Word::zero(), self.limits.stack)?;
                 } else {
                     let mut data = [0u8; 32];
                     let copy = call.data.len().min(offset + 32) - offset;
                     data[0..copy].copy_from_slice(&call.data[offset..offset + copy]);
-                    evm.push(Word::from_bytes(&data))?;
+                    evm.push(Word::from_bytes(&data), self.limits.stack)?;
                 }
             }
             0x36 => {
                 // CALLDATASIZE
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(Word::from(call.data.len()))?;
+                evm.push(Word::from(call.data.len()), self.limits.stack)?;
             }
             0x37 => {
                 // CALLDATACOPY
@@ -1348,7 +1822,7 @@ impl<T: EventTracer> Executor<T> {
                 let offset = evm.pop()?.as_usize();
                 let size = evm.pop()?.min((usize::MAX >> 1).into()).as_usize();
                 if size > 0 && dest_offset + size > evm.memory.len() {
-                    if dest_offset + size > ALLOCATION_SANITY_LIMIT {
+                    if dest_offset + size > self.limits.memory {
                         return Err(ExecutorError::InvalidAllocation(dest_offset + size).into());
                     }
                     let padding = 32 - (dest_offset + size) % 32;
@@ -1356,7 +1830,7 @@ impl<T: EventTracer> Executor<T> {
                 }
                 let mut buffer = call.data.clone();
                 if size > 0 && offset + size > buffer.len() {
-                    if offset + size > ALLOCATION_SANITY_LIMIT {
+                    if offset + size > self.limits.memory {
                         return Err(ExecutorError::InvalidAllocation(offset + size).into());
                     }
                     let padding = 32 - (offset + size) % 32;
@@ -1371,12 +1845,8 @@ impl<T: EventTracer> Executor<T> {
             }
             0x38 => {
                 // CODESIZE
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let len = code.bytecode.len();
-                evm.push(len.into())?;
+                evm.push(len.into(), self.limits.stack)?;
             }
             0x39 => {
                 // CODECOPY
@@ -1384,7 +1854,7 @@ impl<T: EventTracer> Executor<T> {
                 let offset = evm.pop()?.as_usize();
                 let size = evm.pop()?.as_usize();
                 if evm.memory.len() < dest_offset + size {
-                    if dest_offset + size > ALLOCATION_SANITY_LIMIT {
+                    if dest_offset + size > self.limits.memory {
                         return Err(ExecutorError::InvalidAllocation(dest_offset + size).into());
                     }
                     let padding = 32 - (dest_offset + size) % 32;
@@ -1392,7 +1862,7 @@ impl<T: EventTracer> Executor<T> {
                 }
                 let mut code = code.bytecode.clone();
                 if code.len() < offset + size {
-                    if offset + size > ALLOCATION_SANITY_LIMIT {
+                    if offset + size > self.limits.memory {
                         return Err(ExecutorError::InvalidAllocation(offset + size).into());
                     }
                     code.resize(offset + size, 0);
@@ -1404,23 +1874,20 @@ impl<T: EventTracer> Executor<T> {
             }
             0x3a => {
                 // GASPRICE
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(ext.tx_ctx.gas_price)?;
+                evm.push(ext.tx_ctx.gas_price, self.limits.stack)?;
             }
             0x3b => {
                 // EXTCODESIZE
                 let address: Address = (&evm.pop()?).into();
-                gas = evm.address_access_cost(&address, ext);
+                gas = evm.address_access_cost(&address, ext, self.is_precompile(&address));
                 if evm.gas.remaining() < gas {
                     return Ok(StepResult::Halt(gas));
                 }
                 let (code, codehash) = ext.code(&address).await?;
                 evm.touches
                     .push(AccountTouch::GetCode(address, codehash, code.clone()));
-                evm.push(Word::from(code.len()))?;
+                let code = self.legacy_view(code);
+                evm.push(Word::from(code.len()), self.limits.stack)?;
             }
             0x3c => {
                 // EXTCODECOPY
@@ -1429,18 +1896,19 @@ impl<T: EventTracer> Executor<T> {
                 let offset = evm.pop()?.as_usize();
                 let size = evm.pop()?.as_usize();
 
-                let (mut code, codehash) = ext.code(&address).await?;
+                let (code, codehash) = ext.code(&address).await?;
                 evm.touches
                     .push(AccountTouch::GetCode(address, codehash, code.clone()));
+                let mut code = self.legacy_view(code);
                 if evm.memory.len() < dest_offset + size {
-                    if dest_offset + size > ALLOCATION_SANITY_LIMIT {
+                    if dest_offset + size > self.limits.memory {
                         return Err(ExecutorError::InvalidAllocation(dest_offset + size).into());
                     }
                     let padding = 32 - (dest_offset + size) % 32;
                     evm.memory.resize(dest_offset + size + padding % 32, 0);
                 }
                 if code.len() < offset + size {
-                    if offset + size > ALLOCATION_SANITY_LIMIT {
+                    if offset + size > self.limits.memory {
                         return Err(ExecutorError::InvalidAllocation(offset + size).into());
                     }
                     code.resize(offset + size, 0);
@@ -1449,15 +1917,11 @@ impl<T: EventTracer> Executor<T> {
                     .copy_from_slice(&code[offset..offset + size]);
                 gas = 3 * size.div_ceil(32) as i64;
                 gas += evm.memory_expansion_cost();
-                gas += evm.address_access_cost(&address, ext);
+                gas += evm.address_access_cost(&address, ext, self.is_precompile(&address));
             }
             0x3d => {
                 // RETURNDATASIZE
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(self.ret.len().into())?;
+                evm.push(self.ret.len().into(), self.limits.stack)?;
             }
             0x3e => {
                 // RETURNDATACOPY
@@ -1465,7 +1929,7 @@ impl<T: EventTracer> Executor<T> {
                 let offset = evm.pop()?.as_usize();
                 let size = evm.pop()?.as_usize();
                 if evm.memory.len() < dest_offset + size {
-                    if dest_offset + size > ALLOCATION_SANITY_LIMIT {
+                    if dest_offset + size > self.limits.memory {
                         return Err(ExecutorError::InvalidAllocation(dest_offset + size).into());
                     }
                     let padding = 32 - (dest_offset + size) % 32;
@@ -1490,142 +1954,113 @@ impl<T: EventTracer> Executor<T> {
             0x3f => {
                 // EXTCODEHASH
                 let address: Address = (&evm.pop()?).into();
-                gas = evm.address_access_cost(&address, ext);
+                gas = evm.address_access_cost(&address, ext, self.is_precompile(&address));
                 if evm.gas.remaining() < gas {
                     return Ok(StepResult::Halt(gas));
                 }
                 let (code, hash) = ext.code(&address).await?;
-                evm.touches.push(AccountTouch::GetCode(address, hash, code));
+                evm.touches
+                    .push(AccountTouch::GetCode(address, hash, code.clone()));
                 let is_empty = ext.is_empty(&address).await?;
                 if is_empty {
-                    evm.push(Word::zero())?;
+                    evm.push(Word::zero(), self.limits.stack)?;
+                } else if self.spec.is_enabled(Spec::Eof) && eof::is_eof(&code) {
+                    // EIP-7761: legacy code sees EOF code as the 0xEF00 prefix
+                    evm.push(Word::from_bytes(&keccak256(&eof::MAGIC)), self.limits.stack)?;
                 } else {
-                    evm.push(hash)?;
+                    evm.push(hash, self.limits.stack)?;
                 }
             }
 
             // 40-4a
             0x40 => {
                 // BLOCKHASH
-                gas = 20;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let block_number = evm.pop()?;
 
                 if block_number >= self.header.number
                     || self.header.number - block_number > Word::from(256)
                 {
-                    evm.push(Word::zero())?;
+                    evm.push(Word::zero(), self.limits.stack)?;
                 } else {
                     let block_hash = ext.get_block_hash(block_number).await?;
-                    evm.push(block_hash)?;
+                    evm.push(block_hash, self.limits.stack)?;
                 }
             }
             0x41 => {
                 // COINBASE
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push((&self.header.miner).into())?;
+                evm.push((&self.header.miner).into(), self.limits.stack)?;
             }
             0x42 => {
                 // TIMESTAMP
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(self.header.timestamp)?;
+                evm.push(self.header.timestamp, self.limits.stack)?;
             }
             0x43 => {
                 // NUMBER
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(self.header.number)?;
+                evm.push(self.header.number, self.limits.stack)?;
             }
             0x44 => {
                 // PREVRANDAO
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(Word::zero())?;
+                // EIP-4399: mixHash field of the header carries the beacon chain
+                // randomness, DIFFICULTY of proof-of-work blocks is non-zero
+                let value = if self.header.difficulty.is_zero() {
+                    self.header.mix_hash
+                } else {
+                    self.header.difficulty
+                };
+                evm.push(value, self.limits.stack)?;
             }
             0x45 => {
                 // GASLIMIT
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(self.header.gas_limit)?;
+                evm.push(self.header.gas_limit, self.limits.stack)?;
             }
             0x46 => {
                 // CHAINID
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(Word::one())?; // TODO: From TX
+                evm.push(Word::from(self.chain.id), self.limits.stack)?;
             }
             0x47 => {
                 // SELFBALANCE
-                gas = 5;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let balance = ext.balance(&this).await?;
                 evm.touches.push(AccountTouch::GetValue(this, balance));
 
-                self.debug["SELFBALANCE"] = json!({
-                    "address": this,
-                    "balance": balance,
-                });
+                if T::TRACE.debug {
+                    self.debug["SELFBALANCE"] = json!({
+                        "address": this,
+                        "balance": balance,
+                    });
+                }
 
-                evm.push(balance)?;
+                evm.push(balance, self.limits.stack)?;
             }
             0x48 => {
                 // BASEFEE
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(self.header.base_fee)?;
+                evm.push(self.header.base_fee, self.limits.stack)?;
             }
             0x49 => {
                 // BLOBHASH
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                let _index = evm.pop()?;
-                // evm.push(self.header.extra_data)?;
-                evm.push(Word::zero())?;
-                // TODO: make it work properly?
+                let index = evm.pop()?;
                 // > tx.blob_versioned_hashes[index] if index < len(tx.blob_versioned_hashes),
                 // > and otherwise with a zeroed bytes32 value."
                 // (See: https://www.evm.codes/?fork=prague#49)
+                let hashes = &ext.tx_ctx.blob_hashes;
+                let hash = if index < Word::from(hashes.len() as u64) {
+                    hashes[index.as_usize()]
+                } else {
+                    Word::zero()
+                };
+                evm.push(hash, self.limits.stack)?;
             }
             0x4a => {
                 // BLOBBASEFEE
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 // https://eips.ethereum.org/EIPS/eip-4844#gas-accounting
-                let word = self.header.blob_gas_price();
-                evm.push(word)?;
+                let word = self
+                    .header
+                    .blob_gas_price_with_fraction(self.spec.blob_base_fee_update_fraction());
+                evm.push(word, self.limits.stack)?;
             }
 
             // 0x50s: Stack, Memory, Storage and Flow Operations
             0x50 => {
                 // POP
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 evm.pop()?;
             }
             0x51 => {
@@ -1633,7 +2068,7 @@ impl<T: EventTracer> Executor<T> {
                 let offset = evm.pop()?.as_usize();
                 let end = offset + 32;
                 if end > evm.memory.len() {
-                    if end > ALLOCATION_SANITY_LIMIT {
+                    if end > self.limits.memory {
                         return Err(ExecutorError::InvalidAllocation(end).into());
                     }
                     let padding = 32 - end % 32;
@@ -1645,7 +2080,7 @@ impl<T: EventTracer> Executor<T> {
                     return Ok(StepResult::Halt(gas));
                 }
                 let value = Word::from_bytes(&evm.memory[offset..end]);
-                evm.push(value)?;
+                evm.push(value, self.limits.stack)?;
             }
             0x52 => {
                 // MSTORE
@@ -1653,7 +2088,7 @@ impl<T: EventTracer> Executor<T> {
                 let value = evm.pop()?;
                 let end = offset + 32;
                 if end > evm.memory.len() {
-                    if end > ALLOCATION_SANITY_LIMIT {
+                    if end > self.limits.memory {
                         return Err(ExecutorError::InvalidAllocation(end).into());
                     }
                     let padding = 32 - end % 32;
@@ -1672,7 +2107,7 @@ impl<T: EventTracer> Executor<T> {
                 let offset = evm.pop()?.as_usize();
                 let value = evm.pop()?;
                 if offset >= evm.memory.len() {
-                    if offset + 1 > ALLOCATION_SANITY_LIMIT {
+                    if offset + 1 > self.limits.memory {
                         return Err(ExecutorError::InvalidAllocation(offset + 1).into());
                     }
                     let padding = 32 - (offset + 1) % 32;
@@ -1709,10 +2144,10 @@ impl<T: EventTracer> Executor<T> {
 
                 evm.pop()?;
                 let val = evm.get(ext, &this, &key).await?;
-                evm.push(val)?;
+                evm.push(val, self.limits.stack)?;
                 evm.touches
                     .push(AccountTouch::GetState(this, key, val, is_warm));
-                self.tracer.push(Event {
+                self.emit(|_| Event {
                     data: EventData::State(StateEvent::Get {
                         address: this,
                         key,
@@ -1722,12 +2157,14 @@ impl<T: EventTracer> Executor<T> {
                     reverted: false,
                 });
 
-                self.debug["SLOAD"] = json!({
-                    "address": this,
-                    "key": key,
-                    "val": val,
-                    "is_warm": is_warm,
-                });
+                if T::TRACE.debug {
+                    self.debug["SLOAD"] = json!({
+                        "address": this,
+                        "key": key,
+                        "val": val,
+                        "is_warm": is_warm,
+                    });
+                }
             }
             0x55 => {
                 // SSTORE
@@ -1827,79 +2264,89 @@ impl<T: EventTracer> Executor<T> {
                     }
                 }
 
-                self.debug["SSTORE"] = json!({
-                    "is_warm": is_warm,
-                    "original": original,
-                    "address": this,
-                    "key": key,
-                    "val": val,
-                    "new": new,
-                    "gas_cost": gas_cost,
-                    "gas_back": gas_refund,
-                    "gas_left": evm.gas.remaining(),
-                    "refund": refund_traces
-                        .into_iter()
-                        .map(ToOwned::to_owned)
-                        .map(serde_json::Value::from)
-                        .collect::<Vec<_>>()
-                });
+                if T::TRACE.debug {
+                    self.debug["SSTORE"] = json!({
+                        "is_warm": is_warm,
+                        "original": original,
+                        "address": this,
+                        "key": key,
+                        "val": val,
+                        "new": new,
+                        "gas_cost": gas_cost,
+                        "gas_back": gas_refund,
+                        "gas_left": evm.gas.remaining(),
+                        "refund": refund_traces
+                            .into_iter()
+                            .map(ToOwned::to_owned)
+                            .map(serde_json::Value::from)
+                            .collect::<Vec<_>>()
+                    });
+                }
 
                 if evm.gas.remaining() + gas_refund < 0 {
                     let gas_left = evm.gas.remaining();
 
-                    self.debug["SSTORE"]["is_oog_1"] = json!(true);
-                    self.debug["SSTORE"]["note"] = json!("edge case with negative gas refund");
-                    self.tracer.push(Event {
-                        depth: ctx.depth,
-                        reverted: true,
-                        data: EventData::OpCode(OpCode {
-                            pc: instruction.offset,
-                            op: instruction.opcode.code,
-                            name: instruction.opcode.name(),
-                            data: instruction.argument.clone().map(Into::into),
-                            gas_cost: 0,
-                            gas_used: evm.gas.used,
-                            gas_back: 0,
-                            gas_left,
-                            stack: evm.stack.clone(),
-                            memory: evm.memory.chunks(32).map(Word::from_bytes).collect(),
-                            debug: self.debug.take(),
-                        }),
-                    });
+                    if T::TRACE.debug {
+                        self.debug["SSTORE"]["is_oog_1"] = json!(true);
+                        self.debug["SSTORE"]["note"] = json!("edge case with negative gas refund");
+                    }
+                    if T::TRACE.opcodes {
+                        self.tracer.push(Event {
+                            depth: ctx.depth,
+                            reverted: true,
+                            data: EventData::OpCode(OpCode {
+                                pc: instruction.offset,
+                                op: instruction.opcode.code,
+                                name: instruction.opcode.name(),
+                                data: instruction.argument.clone().map(Into::into),
+                                gas_cost: 0,
+                                gas_used: evm.gas.used,
+                                gas_back: 0,
+                                gas_left,
+                                stack: Self::stack(evm),
+                                memory: Self::memory(evm),
+                                debug: self.debug.take(),
+                            }),
+                        });
+                    }
                     return Ok(StepResult::Halt(0));
                 }
 
                 if evm.gas.remaining() < gas_cost {
                     let gas_left = evm.gas.remaining();
 
-                    self.debug["SSTORE"]["is_oog_2"] = json!(true);
-                    self.tracer.push(Event {
-                        depth: ctx.depth,
-                        reverted: true,
-                        data: EventData::OpCode(OpCode {
-                            pc: instruction.offset,
-                            op: instruction.opcode.code,
-                            name: instruction.opcode.name(),
-                            data: instruction.argument.clone().map(Into::into),
+                    if T::TRACE.debug {
+                        self.debug["SSTORE"]["is_oog_2"] = json!(true);
+                    }
+                    if T::TRACE.opcodes {
+                        self.tracer.push(Event {
+                            depth: ctx.depth,
+                            reverted: true,
+                            data: EventData::OpCode(OpCode {
+                                pc: instruction.offset,
+                                op: instruction.opcode.code,
+                                name: instruction.opcode.name(),
+                                data: instruction.argument.clone().map(Into::into),
 
-                            // TODO: sort out mutually exclusive cases of OOG handling
-
-                            /* block 23891503: ok, but 23891512: fails (tx 252) */
-                            gas_cost: gas_left,
-                            gas_used: evm.gas.used + gas_left,
-                            gas_back: 0,
-                            gas_left: 0,
-
-                            /* block 23891503: fails (txs 21, 29) but 23891512: ok */
-                            // gas_cost: 0,
-                            // gas_used: evm.gas.used,
-                            // gas_back: 0,
-                            // gas_left,
-                            stack: evm.stack.clone(),
-                            memory: evm.memory.chunks(32).map(Word::from_bytes).collect(),
-                            debug: self.debug.take(),
-                        }),
-                    });
+                                // TODO: sort out mutually exclusive cases of OOG handling
+
+                                /* block 23891503: ok, but 23891512: fails (tx 252) */
+                                gas_cost: gas_left,
+                                gas_used: evm.gas.used + gas_left,
+                                gas_back: 0,
+                                gas_left: 0,
+
+                                /* block 23891503: fails (txs 21, 29) but 23891512: ok */
+                                // gas_cost: 0,
+                                // gas_used: evm.gas.used,
+                                // gas_back: 0,
+                                // gas_left,
+                                stack: Self::stack(evm),
+                                memory: Self::memory(evm),
+                                debug: self.debug.take(),
+                            }),
+                        });
+                    }
                     return Ok(StepResult::Halt(0));
                 }
 
@@ -1907,7 +2354,7 @@ impl<T: EventTracer> Executor<T> {
 
                 evm.gas.refund(gas_refund);
                 gas = gas_cost;
-                self.tracer.push(Event {
+                self.emit(|_| Event {
                     data: EventData::State(StateEvent::Put {
                         address: this,
                         key,
@@ -1923,10 +2370,6 @@ impl<T: EventTracer> Executor<T> {
             }
             0x56 => {
                 // JUMP
-                gas = 8;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let dest = evm.pop()?.as_usize();
                 let Some(dest) = code.resolve_jump(dest) else {
                     return Ok(StepResult::Halt(gas));
@@ -1942,10 +2385,6 @@ impl<T: EventTracer> Executor<T> {
             }
             0x57 => {
                 // JUMPI
-                gas = 10;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let dest = evm.pop()?.as_usize();
                 let cond = evm.pop()?;
                 if !cond.is_zero() {
@@ -1964,19 +2403,11 @@ impl<T: EventTracer> Executor<T> {
             }
             0x58 => {
                 // PC
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(Word::from(instruction.offset))?;
+                evm.push(Word::from(instruction.offset), self.limits.stack)?;
             }
             0x59 => {
                 // MSIZE
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(Word::from(evm.memory.len()))?;
+                evm.push(Word::from(evm.memory.len()), self.limits.stack)?;
             }
             0x5a => {
                 // GAS
@@ -1985,47 +2416,39 @@ impl<T: EventTracer> Executor<T> {
                     return Ok(StepResult::Halt(gas));
                 }
                 let val = (evm.gas.remaining() - 2) as u64;
-                evm.push(val.into())?;
+                evm.push(val.into(), self.limits.stack)?;
             }
             0x5b => {
                 // JUMPDEST: noop, a valid destination for JUMP/JUMPI
-                gas = 1;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
             }
             0x5c => {
                 // TLOAD
-                gas = 100;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let key = evm.pop()?;
-                let val = ext.transient.get(&(this, key)).copied().unwrap_or_default();
-                evm.push(val)?;
-                self.debug["TLOAD"] = json!({
-                    "address": this,
-                    "key": key,
-                    "val": val,
-                });
+                let val = ext.get_transient(&this, &key);
+                evm.push(val, self.limits.stack)?;
+                if T::TRACE.debug {
+                    self.debug["TLOAD"] = json!({
+                        "address": this,
+                        "key": key,
+                        "val": val,
+                    });
+                }
             }
             0x5d => {
                 // TSTORE
-                gas = 100;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let key = evm.pop()?;
                 let val = evm.pop()?;
-                let old = ext.transient.insert((this, key), val).unwrap_or_default();
+                let old = ext.put_transient(&this, key, val);
                 evm.touches
                     .push(AccountTouch::SetTransientState(this, key, old, val));
-                self.debug["TSTORE"] = json!({
-                    "address": this,
-                    "key": key,
-                    "val": val,
-                    "old": old,
-                });
+                if T::TRACE.debug {
+                    self.debug["TSTORE"] = json!({
+                        "address": this,
+                        "key": key,
+                        "val": val,
+                        "old": old,
+                    });
+                }
             }
             0x5e => {
                 // MCOPY
@@ -2042,7 +2465,7 @@ impl<T: EventTracer> Executor<T> {
                 if size > 0 {
                     let len = (dest_offset + size).max(offset + size);
                     if len > evm.memory.len() {
-                        if dest_offset + size > ALLOCATION_SANITY_LIMIT {
+                        if dest_offset + size > self.limits.memory {
                             return Err(ExecutorError::InvalidAllocation(dest_offset + size).into());
                         }
                         let padding = 32 - len % 32;
@@ -2064,55 +2487,41 @@ impl<T: EventTracer> Executor<T> {
                     evm.memory[dest_offset..dest_offset + size].copy_from_slice(&buffer);
                 }
 
-                self.debug["MCOPY"] = json!({
-                    "dest_offset": dest_offset,
-                    "offset": offset,
-                    "size": size,
-                    "mem.len": evm.memory.len(),
-                });
+                if T::TRACE.debug {
+                    self.debug["MCOPY"] = json!({
+                        "dest_offset": dest_offset,
+                        "offset": offset,
+                        "size": size,
+                        "mem.len": evm.memory.len(),
+                    });
+                }
             }
             0x5f => {
                 // PUSH0
-                gas = 2;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
-                evm.push(Word::zero())?;
+                evm.push(Word::zero(), self.limits.stack)?;
             }
 
             0x60..=0x7f => {
                 // PUSH1..PUSH32
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let arg = instruction
                     .argument
                     .as_ref()
                     .ok_or(ExecutorError::MissingData)?;
-                evm.push(Word::from_bytes(arg))?;
+                evm.push(Word::from_bytes(arg), self.limits.stack)?;
             }
 
             0x80..=0x8f => {
                 // DUP1..DUP16
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let n = instruction.opcode.n as usize;
                 if evm.stack.len() < n {
                     evm.error(ExecutorError::StackUnderflow.into())?;
                 }
                 let val = evm.stack[evm.stack.len() - n];
-                evm.push(val)?;
+                evm.push(val, self.limits.stack)?;
             }
 
             0x90..=0x9f => {
                 // SWAP1..SWAP16
-                gas = 3;
-                if evm.gas.remaining() < gas {
-                    return Ok(StepResult::Halt(gas));
-                }
                 let n = instruction.opcode.n as usize;
                 if evm.stack.len() <= n {
                     evm.error(ExecutorError::StackUnderflow.into())?;
@@ -2149,7 +2558,7 @@ impl<T: EventTracer> Executor<T> {
                 topics.reverse();
 
                 let data = if offset + size > evm.memory.len() {
-                    if offset + size > ALLOCATION_SANITY_LIMIT {
+                    if offset + size > self.limits.memory {
                         return Err(ExecutorError::InvalidAllocation(offset + size).into());
                     }
                     let mut data = evm.memory.clone();
@@ -2158,39 +2567,212 @@ impl<T: EventTracer> Executor<T> {
                 } else {
                     evm.memory[offset..offset + size].to_vec()
                 };
-                evm.logs.push(Log(this, topics, data));
+                ext.log(Log(this, topics, data));
             }
 
-            0xf0 => {
-                // CREATE
-                if matches!(ctx.call_type, CallType::Static) {
-                    return Err(ExecutorError::StaticCallViolation(opcode).into());
-                }
-                self.create(instruction, this, &mut gas, evm, ext, ctx)
-                    .await?;
+            0xd0 => {
+                // DATALOAD
+                let offset = evm.pop()?;
+                let data = Self::eof(code)?.container.data();
+                evm.push(load_word(data, offset), self.limits.stack)?;
             }
-            0xf1 => {
-                // CALL
-                if matches!(ctx.call_type, CallType::Static) {
-                    let value = evm
-                        .stack
-                        .iter()
-                        .rev()
-                        .nth(2)
-                        .ok_or(ExecutorError::StackUnderflow)?;
-                    if !value.is_zero() {
-                        return Err(ExecutorError::StaticCallViolation(opcode).into());
+            0xd1 => {
+                // DATALOADN
+                let offset = Self::immediate(instruction)?;
+                let data = Self::eof(code)?.container.data();
+                evm.push(load_word(data, Word::from(offset)), self.limits.stack)?;
+            }
+            0xd2 => {
+                // DATASIZE
+                let data = Self::eof(code)?.container.data();
+                evm.push(Word::from(data.len()), self.limits.stack)?;
+            }
+            0xd3 => {
+                // DATACOPY
+                let dest_offset = evm.pop()?.as_usize();
+                let offset = evm.pop()?;
+                let size = evm.pop()?.as_usize();
+                if size > 0 && dest_offset + size > evm.memory.len() {
+                    if dest_offset + size > self.limits.memory {
+                        return Err(ExecutorError::InvalidAllocation(dest_offset + size).into());
                     }
+                    let padding = 32 - (dest_offset + size) % 32;
+                    evm.memory.resize(dest_offset + size + padding % 32, 0);
                 }
-                let ctx = Context {
-                    call_type: CallType::Call,
-                    ..ctx
-                };
-                match self
-                    .call(instruction, this, call, &mut gas, evm, ext, ctx)
-                    .await
-                    .with_context(|| "opcode: CALL")
+                // Copy only the available data and zero-fill the rest
+                let data = Self::eof(code)?.container.data();
+                let offset = offset.min(Word::from(data.len())).as_usize();
+                let to_copy = size.min(data.len() - offset);
+                if size > 0 {
+                    evm.memory[dest_offset..dest_offset + to_copy]
+                        .copy_from_slice(&data[offset..offset + to_copy]);
+                    evm.memory[dest_offset + to_copy..dest_offset + size].fill(0);
+                }
+                gas = 3 + 3 * size.div_ceil(32) as i64;
+                gas += evm.memory_expansion_cost();
+            }
+            0xe0..=0xe2 => {
+                // RJUMP, RJUMPI, RJUMPV
+                let immediate = instruction
+                    .argument
+                    .as_ref()
+                    .ok_or(ExecutorError::MissingData)?;
+                let targets = eof::jump_targets(opcode, instruction.offset, immediate);
+                let target = match opcode {
+                    0xe0 => targets.first().copied(),
+                    0xe1 => {
+                        let cond = evm.pop()?;
+                        targets.first().copied().filter(|_| !cond.is_zero())
+                    }
+                    _ => {
+                        // Out of bounds index falls through
+                        let index = evm.pop()?;
+                        targets
+                            .get(index.min(Word::from(targets.len())).as_usize())
+                            .copied()
+                    }
+                };
+                if let Some(target) = target {
+                    let target = target as usize;
+                    evm.pc = code
+                        .resolve_jump(target)
+                        .ok_or(ExecutorError::InvalidJump(target))?;
+                    pc_increment = false;
+                }
+            }
+            0xe3 | 0xe5 => {
+                // CALLF, JUMPF
+                let eof = Self::eof(code)?;
+                let section = Self::immediate(instruction)?;
+                let ty = eof.container.types[section];
+                if evm.stack.len() + ty.max_stack_increase as usize > self.limits.stack {
+                    return Ok(StepResult::Halt(gas));
+                }
+                if opcode == 0xe3 {
+                    if evm.returns.len() >= RETURN_STACK_LIMIT {
+                        return Ok(StepResult::Halt(gas));
+                    }
+                    evm.returns.push(evm.pc + 1);
+                }
+                evm.pc = eof.sections[section];
+                pc_increment = false;
+            }
+            0xe4 => {
+                // RETF
+                evm.pc = evm.returns.pop().ok_or(ExecutorError::MissingData)?;
+                pc_increment = false;
+            }
+            0xe6 => {
+                // DUPN
+                let n = Self::immediate(instruction)? + 1;
+                if evm.stack.len() < n {
+                    evm.error(ExecutorError::StackUnderflow.into())?;
+                }
+                let val = evm.stack[evm.stack.len() - n];
+                evm.push(val, self.limits.stack)?;
+            }
+            0xe7 | 0xe8 => {
+                // SWAPN, EXCHANGE
+                let immediate = Self::immediate(instruction)?;
+                let (a, b) = if opcode == 0xe7 {
+                    (1, immediate + 2)
+                } else {
+                    eof::exchange_operands(immediate as u8)
+                };
+                let stack_len = evm.stack.len();
+                if stack_len < b {
+                    evm.error(ExecutorError::StackUnderflow.into())?;
+                }
+                evm.stack.swap(stack_len - a, stack_len - b);
+            }
+            0xec => {
+                // EOFCREATE
+                if matches!(ctx.call_type, CallType::Static) {
+                    return Err(ExecutorError::StaticCallViolation(opcode).into());
+                }
+                self.eof_create(code, instruction, this, &mut gas, evm, ext, ctx)
+                    .await?;
+                if self.pending.is_some() {
+                    return Ok(StepResult::Suspend);
+                }
+            }
+            0xee => {
+                // RETURNCONTRACT
+                let index = Self::immediate(instruction)?;
+                let offset = evm.pop()?.as_usize();
+                let size = evm.pop()?.as_usize();
+                if size > 0 && offset + size > evm.memory.len() {
+                    if offset + size > self.limits.memory {
+                        return Err(ExecutorError::InvalidAllocation(offset + size).into());
+                    }
+                    let padding = 32 - (offset + size) % 32;
+                    evm.memory.resize(offset + size + padding % 32, 0);
+                }
+                gas = evm.memory_expansion_cost();
+                let aux = if size > 0 {
+                    &evm.memory[offset..offset + size]
+                } else {
+                    &[]
+                };
+                // Deployed container must have its data section complete
+                let deployed = Self::eof(code)?
+                    .container
+                    .container(index)
+                    .ok_or(ExecutorError::MissingData)
+                    .map(Container::parse)?
+                    .and_then(|container| container.with_aux_data(aux));
+                let Ok(deployed) = deployed else {
+                    return Ok(StepResult::Halt(gas));
+                };
+                evm.stopped = true;
+                evm.reverted = false;
+                self.ret = deployed;
+
+                self.emit(|this| Event {
+                    data: EventData::Return {
+                        ok: true,
+                        data: this.ret.clone().into(),
+                        error: None,
+                        gas_used: evm.gas.used,
+                    },
+                    depth: ctx.depth,
+                    reverted: false,
+                });
+            }
+
+            0xf0 => {
+                // CREATE
+                if matches!(ctx.call_type, CallType::Static) {
+                    return Err(ExecutorError::StaticCallViolation(opcode).into());
+                }
+                self.create(this, evm, ext, ctx).await?;
+                if self.pending.is_some() {
+                    return Ok(StepResult::Suspend);
+                }
+            }
+            0xf1 => {
+                // CALL
+                if matches!(ctx.call_type, CallType::Static) {
+                    let value = evm
+                        .stack
+                        .iter()
+                        .rev()
+                        .nth(2)
+                        .ok_or(ExecutorError::StackUnderflow)?;
+                    if !value.is_zero() {
+                        return Err(ExecutorError::StaticCallViolation(opcode).into());
+                    }
+                }
+                let ctx = Context {
+                    call_type: CallType::Call,
+                    ..ctx
+                };
+                match self
+                    .call(instruction, this, call, &mut gas, evm, ext, ctx)
+                    .await
+                    .with_context(|| "opcode: CALL")
                 {
+                    Ok(()) if self.pending.is_some() => return Ok(StepResult::Suspend),
                     Ok(()) => {} // ignore
                     Err(_) => {
                         return Ok(StepResult::Halt(evm.gas.remaining()));
@@ -2210,6 +2792,7 @@ impl<T: EventTracer> Executor<T> {
                     .call(instruction, this, call, &mut gas, evm, ext, ctx)
                     .await
                 {
+                    Ok(()) if self.pending.is_some() => return Ok(StepResult::Suspend),
                     Ok(()) => {} // ignore
                     Err(_) => {
                         return Ok(StepResult::Halt(evm.gas.remaining()));
@@ -2227,7 +2810,7 @@ impl<T: EventTracer> Executor<T> {
 
                 if size > 0 {
                     if offset + size > evm.memory.len() {
-                        if offset + size > ALLOCATION_SANITY_LIMIT {
+                        if offset + size > self.limits.memory {
                             return Err(ExecutorError::InvalidAllocation(offset + size).into());
                         }
                         let padding = 32 - (offset + size) % 32;
@@ -2239,12 +2822,12 @@ impl<T: EventTracer> Executor<T> {
                 }
                 gas = evm.memory_expansion_cost();
 
-                self.tracer.push(Event {
+                self.emit(|this| Event {
                     data: EventData::Return {
                         ok: !evm.reverted,
-                        data: self.ret.clone().into(),
+                        data: this.ret.clone().into(),
                         error: if evm.reverted {
-                            decode_error_string(&self.ret)
+                            decode_error_string(&this.ret)
                         } else {
                             None
                         },
@@ -2266,6 +2849,7 @@ impl<T: EventTracer> Executor<T> {
                     .call(instruction, this, call, &mut gas, evm, ext, ctx)
                     .await
                 {
+                    Ok(()) if self.pending.is_some() => return Ok(StepResult::Suspend),
                     Ok(()) => {} // ignore
                     Err(_) => {
                         return Ok(StepResult::Halt(evm.gas.remaining()));
@@ -2281,8 +2865,38 @@ impl<T: EventTracer> Executor<T> {
                     call_type: CallType::Create2,
                     ..ctx
                 };
-                self.create(instruction, this, &mut gas, evm, ext, ctx)
+                self.create(this, evm, ext, ctx).await?;
+                if self.pending.is_some() {
+                    return Ok(StepResult::Suspend);
+                }
+            }
+            0xf7 => {
+                // RETURNDATALOAD
+                let offset = evm.pop()?;
+                evm.push(load_word(&self.ret, offset), self.limits.stack)?;
+            }
+            0xf8 | 0xf9 | 0xfb => {
+                // EXTCALL, EXTDELEGATECALL, EXTSTATICCALL
+                let ctx = match opcode {
+                    0xf8 => Context {
+                        call_type: CallType::Call,
+                        ..ctx
+                    },
+                    0xf9 => Context {
+                        call_type: CallType::Delegate,
+                        ..ctx
+                    },
+                    _ => Context {
+                        call_type: CallType::Static,
+                        is_static_call: true,
+                        ..ctx
+                    },
+                };
+                self.ext_call(instruction, this, call, &mut gas, evm, ext, ctx)
                     .await?;
+                if self.pending.is_some() {
+                    return Ok(StepResult::Suspend);
+                }
             }
             0xfa => {
                 // STATICCALL
@@ -2295,6 +2909,7 @@ impl<T: EventTracer> Executor<T> {
                     .call(instruction, this, call, &mut gas, evm, ext, ctx)
                     .await
                 {
+                    Ok(()) if self.pending.is_some() => return Ok(StepResult::Suspend),
                     Ok(()) => {} // ignore
                     Err(_) => {
                         return Ok(StepResult::Halt(evm.gas.remaining()));
@@ -2310,29 +2925,88 @@ impl<T: EventTracer> Executor<T> {
                     return Err(ExecutorError::StaticCallViolation(opcode).into());
                 }
 
-                let address: Address = (&evm.pop()?).into();
+                let beneficiary: Address = (&evm.pop()?).into();
+                let balance = ext.balance(&this).await?;
 
                 let opcode_cost = 5000;
-                let access_cost = if ext.is_address_warm(&address) {
-                    0
-                } else {
-                    2600
-                };
-                let create_cost = if ext.is_empty(&address).await? {
+                // EIP-2929: only cold access is charged (warm access is free)
+                let access_cost =
+                    evm.address_access_cost(&beneficiary, ext, self.is_precompile(&beneficiary))
+                        - 100;
+                // EIP-161: account creation is charged only if value is sent to an empty account
+                let create_cost = if !balance.is_zero() && ext.is_empty(&beneficiary).await? {
                     25000 // account creation cost
                 } else {
                     0
                 };
+                gas = opcode_cost + access_cost + create_cost;
+                if evm.gas.remaining() < gas {
+                    return Ok(StepResult::Halt(gas));
+                }
 
-                let total_gas_cost = opcode_cost + access_cost + create_cost;
-                gas = total_gas_cost.into();
+                // EIP-6780 (Cancun): account is deleted only if created in the same transaction
+                let destroy =
+                    !self.spec.is_enabled(Spec::Cancun) || ext.created_accounts.contains(&this);
+
+                // Sending the balance to itself is a no-op, unless the account is deleted
+                // (then the balance is burned)
+                if !balance.is_zero() && (beneficiary != this || destroy) {
+                    ext.set_balance(&this, Word::zero());
+                    evm.touches
+                        .push(AccountTouch::SetValue(this, balance, Word::zero()));
+                    self.emit(|_| Event {
+                        data: EventData::Account(AccountEvent::SetValue {
+                            address: this,
+                            val: balance,
+                            new: Word::zero(),
+                        }),
+                        depth: ctx.depth,
+                        reverted: false,
+                    });
 
-                let balance = ext.balance(&this).await?;
-                ext.account_mut(&this).value = Word::zero();
-                ext.account_mut(&address).value += balance;
-                ext.destroyed_accounts.push(this);
+                    if beneficiary != this {
+                        let value = ext.balance(&beneficiary).await?;
+                        ext.set_balance(&beneficiary, value + balance);
+                        evm.touches.push(AccountTouch::SetValue(
+                            beneficiary,
+                            value,
+                            value + balance,
+                        ));
+                        self.emit(|_| Event {
+                            data: EventData::Account(AccountEvent::SetValue {
+                                address: beneficiary,
+                                val: value,
+                                new: value + balance,
+                            }),
+                            depth: ctx.depth,
+                            reverted: false,
+                        });
+                    }
+                }
 
-                // TODO: add traces and account/state events
+                if destroy {
+                    ext.destroy(&this);
+                    evm.touches.push(AccountTouch::Destroy(this));
+                    self.emit(|_| Event {
+                        data: EventData::Account(AccountEvent::Destroy {
+                            address: this,
+                            beneficiary,
+                            balance,
+                        }),
+                        depth: ctx.depth,
+                        reverted: false,
+                    });
+                }
+
+                self.emit(|_| Event {
+                    data: EventData::SelfDestruct {
+                        address: this,
+                        beneficiary,
+                        balance,
+                    },
+                    depth: ctx.depth,
+                    reverted: false,
+                });
             }
             _ => {
                 return Err(ExecutorError::UnknownOpcode(opcode).into());
@@ -2368,250 +3042,731 @@ impl<T: EventTracer> Executor<T> {
         };
         let args_offset = evm.pop()?.as_usize();
         let args_size = evm.pop()?.as_usize();
-        let ret_offset = evm.pop()?.as_usize();
-        let ret_size = evm.pop()?.as_usize();
-
-        // Handle memory expansion for arguments and return data
-        let args_max = if args_size > 0 {
-            args_offset + args_size
-        } else {
-            0
-        };
-        let ret_max = if ret_size > 0 {
-            ret_offset + ret_size
-        } else {
-            0
+        let ret_offset = evm.pop()?.as_usize();
+        let ret_size = evm.pop()?.as_usize();
+
+        // Handle memory expansion for arguments and return data
+        let args_max = if args_size > 0 {
+            args_offset + args_size
+        } else {
+            0
+        };
+        let ret_max = if ret_size > 0 {
+            ret_offset + ret_size
+        } else {
+            0
+        };
+        let size = args_max.max(ret_max);
+        if size > evm.memory.len() {
+            if size > self.limits.memory {
+                return Err(ExecutorError::InvalidAllocation(size).into());
+            }
+            let size = size.div_ceil(32) * 32;
+            evm.memory.resize(size, 0);
+        }
+        let memory_expansion_cost = evm.memory_expansion_cost();
+
+        let mut create_cost = 0;
+        let is_empty = !self.is_precompile(&address) && ext.is_empty(&address).await?;
+        if !value.is_zero() && is_empty {
+            create_cost = 25000; // account creation cost
+        }
+
+        // Calculate address access cost (EIP-2929)
+        let (code, codehash) = ext.code(&address).await?;
+        evm.touches
+            .push(AccountTouch::GetCode(address, codehash, code.clone()));
+        let mut access_cost = evm.address_access_cost(&address, ext, self.is_precompile(&address));
+
+        // Check and resolve delegation: CODE = <0xef0100> + <20 bytes address>
+        let delegated = delegation::target(&code);
+        let is_delegated = delegated.is_some();
+        let code = if let Some(target) = delegated {
+            access_cost += 100;
+            let (code, codehash) = ext.code(&target).await?;
+            evm.touches
+                .push(AccountTouch::GetCode(target, codehash, code.clone()));
+            let target_cost = evm.address_access_cost(&target, ext, self.is_precompile(&target));
+            access_cost += target_cost - 100;
+            code
+        } else {
+            code
+        };
+
+        // Calculate base gas cost
+        let mut base_gas_cost = access_cost + memory_expansion_cost + create_cost;
+
+        let mut gas_stipend_adjustment = 0;
+
+        // Add value transfer cost if applicable (not for DELEGATECALL/STATICCALL)
+        if !matches!(ctx.call_type, CallType::Static | CallType::Delegate) && !value.is_zero() {
+            base_gas_cost += 9000; // value transfer cost
+            gas_stipend_adjustment = 2300;
+        }
+
+        // Check if there is enough gas for the base cost (out-of-gas condition)
+        if base_gas_cost > evm.gas.remaining() {
+            let gas_cost = evm.gas.remaining();
+            *gas = gas_cost;
+
+            if T::TRACE.opcodes {
+                self.tracer.push(Event {
+                    depth: ctx.depth,
+                    reverted: false,
+                    data: EventData::OpCode(OpCode {
+                        pc: instruction.offset,
+                        op: instruction.opcode.code,
+                        name: instruction.opcode.name(),
+                        data: instruction.argument.clone().map(Into::into),
+                        gas_cost,
+                        gas_used: evm.gas.used + gas_cost,
+                        gas_left: 0,
+                        stack: Self::stack(evm),
+                        memory: Self::memory(evm),
+                        gas_back: 0,
+                        debug: Self::debug(|| json!({
+                            "is_call": true,
+                            "evm.gas.used": evm.gas.used,
+                            "evm.gas.refund": evm.gas.refund,
+                            "call.address": address,
+                            "call.input": hex::encode(&evm.memory[args_offset.min(evm.memory.len())..(args_offset + args_size).min(evm.memory.len())]),
+                            "call.result": "OOG",
+                            "call.gas": call_gas.as_u64(),
+                            "access_cost": access_cost,
+                            "memory_expansion_cost": memory_expansion_cost,
+                        })),
+                    }),
+                });
+            }
+
+            // Don't add refunds from reverted calls
+            evm.refund = evm.gas.refund;
+            evm.push(Word::zero(), self.limits.stack)?;
+            return Err(ExecutorError::OutOfGas().into());
+        }
+
+        // Calculate available gas for forwarding using "all but one 64th" rule
+        let remaining_gas = evm.gas.remaining() - base_gas_cost;
+        let all_but_one_64th = remaining_gas - remaining_gas / 64;
+        let gas_to_forward = call_gas.as_i64().min(all_but_one_64th) + gas_stipend_adjustment;
+
+        // For EVM accounting: only charge the outer EVM for base cost
+        // (forwarded gas was already "spent" by allocating it to inner call)
+        *gas = base_gas_cost;
+
+        // For tracing: report the total cost including forwarded gas (to match REVM)
+        let total_gas_cost_for_tracing = base_gas_cost + gas_to_forward - gas_stipend_adjustment;
+
+        let data = if args_size > 0 {
+            &evm.memory[args_offset..args_offset + args_size]
+        } else {
+            &[]
+        };
+
+        // Handle precompile call
+        if let Some(precompile) = self.precompiles.get(&address, self.spec) {
+            let data = data.to_vec();
+            let gas_cost = precompile.gas_cost(&data, self.spec);
+            // TODO: check if there is enough gas
+            let checkpoint = ext.checkpoint();
+            let result = match precompile.execute(&data, self.spec, ext).await {
+                Ok(ret) => {
+                    ext.commit(checkpoint);
+                    self.ret = ret;
+                    Word::one()
+                }
+                Err(_) => {
+                    ext.revert_to(checkpoint);
+                    Word::zero()
+                }
+            };
+
+            if T::TRACE.opcodes {
+                self.tracer.push(Event {
+                    depth: ctx.depth,
+                    reverted: false,
+                    data: EventData::OpCode(OpCode {
+                        pc: instruction.offset,
+                        op: instruction.opcode.code,
+                        name: instruction.opcode.name(),
+                        data: instruction.argument.clone().map(Into::into),
+                        gas_cost: total_gas_cost_for_tracing,
+                        gas_used: evm.gas.used + total_gas_cost_for_tracing,
+                        gas_left: evm.gas.remaining() - total_gas_cost_for_tracing,
+                        stack: Self::stack(evm),
+                        memory: Self::memory(evm),
+                        gas_back: 0,
+                        debug: Self::debug(|| json!({
+                            "is_call": true,
+                            "is_precompile": true,
+                            "gas_left": evm.gas.remaining() - base_gas_cost,
+                            "gas_cost": total_gas_cost_for_tracing,
+                            "evm.gas.used": evm.gas.used,
+                            "evm.gas.refund": evm.gas.refund,
+                            "call.address": address,
+                            "call.input": hex::encode(&evm.memory[args_offset..args_offset + args_size]),
+                            "call.result": result,
+                            "call.gas": call_gas.as_u64(),
+                            "access_cost": access_cost,
+                            "precompile_gas_cost": gas_cost,
+                            "memory_expansion_cost": memory_expansion_cost,
+                            "ret": hex::encode(&self.ret),
+                        })),
+                    }),
+                });
+            }
+
+            let precompile_call = Call {
+                data,
+                value,
+                from: this,
+                to: address,
+                gas: (gas_to_forward as u64).into(),
+            };
+            self.trace_precompile(precompile_call, !result.is_zero(), gas_cost, ctx);
+
+            let copy_len = self.ret.len().min(ret_size);
+            evm.memory[ret_offset..ret_offset + copy_len].copy_from_slice(&self.ret[..copy_len]);
+
+            evm.push(result, self.limits.stack)?;
+            evm.gas(gas_cost)?;
+            return Ok(());
+        }
+
+        let inner_call = Call {
+            data: data.to_vec(),
+            value,
+            from: if matches!(ctx.call_type, CallType::Delegate | CallType::Callcode) {
+                call.from
+            } else {
+                this
+            },
+            to: if matches!(ctx.call_type, CallType::Delegate | CallType::Callcode) {
+                this
+            } else {
+                address
+            },
+            gas: (gas_to_forward as u64).into(),
+        };
+        let inner_evm = Evm {
+            gas: Gas::new(gas_to_forward),
+            ..Default::default()
+        };
+
+        // Apply value transfer BEFORE call execution
+        let sender_balance = ext.balance(&this).await?;
+        let receiver_balance = ext.balance(&address).await?;
+        let checkpoint = ext.checkpoint();
+        if !value.is_zero() && !matches!(ctx.call_type, CallType::Static | CallType::Delegate) {
+            if sender_balance >= value {
+                // For self-calls (where sender == receiver), no net balance change
+                if this != address {
+                    let new_sender_balance = sender_balance - value;
+                    ext.set_balance(&this, new_sender_balance);
+                    evm.touches.push(AccountTouch::SetValue(
+                        this,
+                        sender_balance,
+                        new_sender_balance,
+                    ));
+                    self.emit(|_| Event {
+                        data: EventData::Account(AccountEvent::SetValue {
+                            address: this,
+                            val: sender_balance,
+                            new: new_sender_balance,
+                        }),
+                        depth: ctx.depth,
+                        reverted: false,
+                    });
+                    let new_receiver_balance = receiver_balance + value;
+                    ext.set_balance(&address, new_receiver_balance);
+                    evm.touches.push(AccountTouch::SetValue(
+                        address,
+                        receiver_balance,
+                        new_receiver_balance,
+                    ));
+                    self.emit(|_| Event {
+                        data: EventData::Account(AccountEvent::SetValue {
+                            address,
+                            val: receiver_balance,
+                            new: new_receiver_balance,
+                        }),
+                        depth: ctx.depth,
+                        reverted: false,
+                    });
+                }
+            } else {
+                // TODO: insufficient funds to transfer
+            }
+        }
+
+        let inner_ctx = Context {
+            depth: ctx.depth + 1,
+            ..ctx
+        };
+
+        let code = Decoder::decode(code, self.spec);
+        self.emit(|_| Event {
+            data: EventData::Account(AccountEvent::GetCode {
+                address,
+                codehash,
+                bytecode: code.bytecode.clone().into(),
+            }),
+            depth: ctx.depth,
+            reverted: false,
+        });
+
+        let child = Frame {
+            executor: self.inner(),
+            code: Cow::Owned(code),
+            call: Cow::Owned(inner_call),
+            evm: inner_evm,
+            ctx: inner_ctx,
+        };
+        let resume = CallResume {
+            ctx,
+            gas: base_gas_cost,
+            call_gas,
+            value,
+            args_offset,
+            args_size,
+            ret_offset,
+            ret_size,
+            gas_stipend_adjustment,
+            total_gas_cost_for_tracing,
+            is_delegated,
+            access_cost,
+            is_empty,
+            is_eof: false,
+            checkpoint,
+        };
+        self.pending = Some(Box::new((child, Resume::Call(resume))));
+        Ok(())
+    }
+
+    /// Complete the CALL* instruction once its child frame returns
+    async fn call_return(
+        &mut self,
+        instruction: &Instruction,
+        resume: CallResume,
+        child: Frame<'_, T>,
+        ret: Vec<u8>,
+        evm: &mut Evm,
+        ext: &mut Ext,
+    ) -> eyre::Result<()> {
+        let CallResume {
+            ctx,
+            call_gas,
+            value,
+            args_offset,
+            args_size,
+            ret_offset,
+            ret_size,
+            gas_stipend_adjustment,
+            total_gas_cost_for_tracing,
+            is_delegated,
+            access_cost,
+            is_empty,
+            is_eof,
+            checkpoint,
+            ..
+        } = resume;
+        let Frame {
+            executor,
+            code,
+            call: inner_call,
+            evm: mut inner_evm,
+            ..
+        } = child;
+        let tracer = executor.tracer;
+
+        // The changes of the child frame are kept only if it succeeded
+        if inner_evm.reverted {
+            ext.revert_to(checkpoint);
+        } else {
+            ext.commit(checkpoint);
+        }
+        evm.join(std::mem::take(&mut inner_evm.touches), inner_evm.reverted);
+
+        if T::TRACE.opcodes {
+            self.tracer.push(Event {
+                depth: ctx.depth,
+                reverted: false,
+                data: EventData::OpCode(OpCode {
+                    pc: instruction.offset,
+                    op: instruction.opcode.code,
+                    name: instruction.opcode.name(),
+                    data: instruction.argument.clone().map(Into::into),
+                    stack: Self::stack(evm),
+                    memory: Self::memory(evm),
+                    gas_back: 0,
+                    gas_cost: total_gas_cost_for_tracing,
+                    gas_used: evm.gas.used + total_gas_cost_for_tracing,
+                    gas_left: evm.gas.remaining() - total_gas_cost_for_tracing,
+                    debug: Self::debug(|| {
+                        json!({
+                            "is_call": true,
+                            "gas_left": evm.gas.remaining() - total_gas_cost_for_tracing,
+                            "gas_cost": total_gas_cost_for_tracing,
+                            "evm.gas.used": evm.gas.used,
+                            "evm.gas.refund": evm.gas.refund,
+                            "args_offset": args_offset,
+                            "args_size": args_size,
+                            "ret_offset": ret_offset,
+                            "ret_size": ret_size,
+                            "memory.len": evm.memory.len(),
+                            "call.from": inner_call.from,
+                            "call.to": inner_call.to,
+                            "call.input": hex::encode(&inner_call.data),
+                            "call.value": value,
+                            "call.gas": call_gas.as_u64(),
+                            "call.is_delegated": is_delegated,
+                            "access_cost": access_cost,
+                            "inner_evm.reverted": inner_evm.reverted,
+                            "is_empty": is_empty,
+                            "code.len": code.bytecode.len(),
+                            "ret": hex::encode(&self.ret),
+                        })
+                    }),
+                }),
+            });
+        }
+        self.tracer.join(tracer, inner_evm.reverted);
+
+        let copy_len = ret.len().min(ret_size);
+        if copy_len > 0 {
+            evm.memory[ret_offset..ret_offset + copy_len].copy_from_slice(&ret[..copy_len]);
+        }
+
+        // TODO: check if there is enough gas
+
+        evm.gas.used += inner_evm.gas.used;
+        evm.gas.used -= gas_stipend_adjustment;
+
+        if inner_evm.reverted {
+            // Don't add refunds from reverted calls
+            evm.refund = evm.gas.refund;
+            self.ret = ret;
+            if !is_eof {
+                evm.push(Word::zero(), self.limits.stack)?;
+            } else if inner_evm.halt.is_some() {
+                evm.push(Word::from(2u64), self.limits.stack)?;
+            } else {
+                evm.push(Word::one(), self.limits.stack)?;
+            }
+            return Ok(());
+        }
+
+        // Only add refunds if call succeeded
+        evm.gas.refund += inner_evm.gas.refund;
+        evm.refund = evm.gas.refund;
+
+        // Preserve the actual return data as-is for RETURNDATA* opcodes
+        self.ret = ret;
+        evm.push(
+            if is_eof { Word::zero() } else { Word::one() },
+            self.limits.stack,
+        )?;
+
+        Ok(())
+    }
+
+    /// EIP-7620: create a contract from the initcode subcontainer
+    #[allow(clippy::too_many_arguments)]
+    async fn eof_create(
+        &mut self,
+        code: &Bytecode,
+        instruction: &Instruction,
+        this: Address,
+        gas: &mut i64,
+        evm: &mut Evm,
+        ext: &mut Ext,
+        ctx: Context,
+    ) -> eyre::Result<()> {
+        let index = Self::immediate(instruction)?;
+        let value = evm.pop()?;
+        let salt = evm.pop()?;
+        let offset = evm.pop()?.as_usize();
+        let size = evm.pop()?.as_usize();
+
+        if size > 0 && offset + size > evm.memory.len() {
+            if offset + size > self.limits.memory {
+                return Err(ExecutorError::InvalidAllocation(offset + size).into());
+            }
+            let padding = 32 - (offset + size) % 32;
+            evm.memory.resize(offset + size + padding % 32, 0);
+        }
+        let memory_expansion_cost = evm.memory_expansion_cost();
+        let create_cost = 32000;
+        let base_gas_cost = memory_expansion_cost + create_cost;
+        if base_gas_cost > evm.gas.remaining() {
+            *gas = base_gas_cost;
+            return Err(ExecutorError::OutOfGas().into());
+        }
+
+        // Light failure: the creation is not attempted and only the base cost is charged
+        let nonce = ext.nonce(&this).await?;
+        if ctx.depth >= self.limits.call_depth
+            || value > ext.balance(&this).await?
+            || nonce >= Word::from(u64::MAX)
+        {
+            self.trace_call(instruction, evm, ctx, base_gas_cost);
+            *gas = base_gas_cost;
+            self.ret.clear();
+            evm.push(Word::zero(), self.limits.stack)?;
+            return Ok(());
+        }
+
+        let initcode = Self::eof(code)?
+            .container
+            .container(index)
+            .ok_or(ExecutorError::MissingData)?;
+        // Subcontainers were validated together with the container
+        let initcode = Decoder::decode_eof(Container::parse(initcode)?);
+        let data = if size > 0 {
+            evm.memory[offset..offset + size].to_vec()
+        } else {
+            vec![]
+        };
+
+        ext.set_nonce(&this, nonce + Word::one());
+        evm.touches.push(AccountTouch::SetNonce(
+            this,
+            nonce.as_u64(),
+            nonce.as_u64() + 1,
+        ));
+        self.emit(|_| Event {
+            data: EventData::Account(AccountEvent::SetNonce {
+                address: this,
+                val: nonce.as_u64(),
+                new: nonce.as_u64() + 1,
+            }),
+            depth: ctx.depth,
+            reverted: false,
+        });
+
+        let created = eof::create_address(&this, &salt);
+        if ext.warm_address(&created) {
+            evm.touches.push(AccountTouch::WarmUp(created));
+        }
+        ext.state.entry(created).or_default();
+        // Nonce bump and warm up survive the failure of the creation
+        let checkpoint = ext.checkpoint();
+        ext.create_account(&created);
+
+        let remaining_gas = evm.gas.remaining() - base_gas_cost;
+        let gas_to_forward = remaining_gas - remaining_gas / 64;
+
+        let inner_call = Call {
+            data,
+            value,
+            from: this,
+            to: Address::zero(),
+            gas: gas_to_forward.unsigned_abs().into(),
+        };
+        let inner_evm = Evm {
+            gas: Gas::new(gas_to_forward),
+            ..Default::default()
+        };
+        let ctx = Context {
+            call_type: CallType::EofCreate,
+            ..ctx
+        };
+        let inner_ctx = Context {
+            created,
+            depth: ctx.depth + 1,
+            ..ctx
+        };
+        let child = Frame {
+            executor: self.inner(),
+            code: Cow::Owned(initcode),
+            call: Cow::Owned(inner_call),
+            evm: inner_evm,
+            ctx: inner_ctx,
+        };
+        let resume = CreateResume {
+            ctx,
+            this,
+            value,
+            nonce,
+            created,
+            memory_expansion_cost,
+            create_cost,
+            init_code_cost: 0,
+            gas_to_forward,
+            checkpoint,
+        };
+        self.pending = Some(Box::new((child, Resume::Create(resume))));
+        Ok(())
+    }
+
+    /// EIP-7069: EXTCALL, EXTDELEGATECALL and EXTSTATICCALL. The callee gets
+    /// all but 1/64 of the remaining gas, the status pushed on the stack is
+    /// 0 on success, 1 on revert and 2 on failure.
+    #[allow(clippy::too_many_arguments)]
+    async fn ext_call(
+        &mut self,
+        instruction: &Instruction,
+        this: Address,
+        call: &Call,
+        gas: &mut i64,
+        evm: &mut Evm,
+        ext: &mut Ext,
+        ctx: Context,
+    ) -> eyre::Result<()> {
+        let target = evm.pop()?;
+        let args_offset = evm.pop()?.as_usize();
+        let args_size = evm.pop()?.as_usize();
+        let value = match ctx.call_type {
+            CallType::Call => evm.pop()?,
+            CallType::Delegate => call.value,
+            _ => Word::zero(),
         };
-        let size = args_max.max(ret_max);
-        if size > evm.memory.len() {
-            if size > ALLOCATION_SANITY_LIMIT {
-                return Err(ExecutorError::InvalidAllocation(size).into());
+        if target.into_bytes()[..12].iter().any(|byte| *byte != 0) {
+            return Err(ExecutorError::InvalidAddress(target).into());
+        }
+        let address: Address = (&target).into();
+        if ctx.is_static_call && !value.is_zero() {
+            return Err(ExecutorError::StaticCallViolation(instruction.opcode.code).into());
+        }
+
+        if args_size > 0 && args_offset + args_size > evm.memory.len() {
+            if args_offset + args_size > self.limits.memory {
+                return Err(ExecutorError::InvalidAllocation(args_offset + args_size).into());
             }
-            let size = size.div_ceil(32) * 32;
+            let size = (args_offset + args_size).div_ceil(32) * 32;
             evm.memory.resize(size, 0);
         }
         let memory_expansion_cost = evm.memory_expansion_cost();
 
-        let mut create_cost = 0;
-        let is_empty = !precompiles::is_precompile(&address) && ext.is_empty(&address).await?;
-        if !value.is_zero() && is_empty {
-            create_cost = 25000; // account creation cost
-        }
-
-        // Calculate address access cost (EIP-2929)
         let (code, codehash) = ext.code(&address).await?;
         evm.touches
             .push(AccountTouch::GetCode(address, codehash, code.clone()));
-        let mut access_cost = evm.address_access_cost(&address, ext);
-
-        // Check and resolve delegation: CODE = <0xef0100> + <20 bytes address>
-        let is_delegated = code.len() == 23 && code.starts_with(&[0xef, 0x01, 0x00]);
-        let code = if is_delegated {
-            access_cost += 100;
-            let target = Address::try_from(&code[3..]).expect("must succeed");
+        let access_cost = evm.address_access_cost(&address, ext, self.is_precompile(&address));
+        let code = if let Some(target) = delegation::target(&code) {
             let (code, codehash) = ext.code(&target).await?;
             evm.touches
                 .push(AccountTouch::GetCode(target, codehash, code.clone()));
-            let target_cost = evm.address_access_cost(&target, ext);
-            access_cost += target_cost - 100;
             code
         } else {
             code
         };
 
-        // Calculate base gas cost
-        let mut base_gas_cost = access_cost + memory_expansion_cost + create_cost;
-
-        let mut gas_stipend_adjustment = 0;
-
-        // Add value transfer cost if applicable (not for DELEGATECALL/STATICCALL)
-        if !matches!(ctx.call_type, CallType::Static | CallType::Delegate) && !value.is_zero() {
-            base_gas_cost += 9000; // value transfer cost
-            gas_stipend_adjustment = 2300;
+        let precompile = self.precompiles.get(&address, self.spec);
+        let mut base_gas_cost = access_cost + memory_expansion_cost;
+        if !value.is_zero() {
+            base_gas_cost += 9000;
+            if precompile.is_none() && ext.is_empty(&address).await? {
+                base_gas_cost += 25000;
+            }
         }
-
-        // Check if there is enough gas for the base cost (out-of-gas condition)
         if base_gas_cost > evm.gas.remaining() {
-            let gas_cost = evm.gas.remaining();
-            *gas = gas_cost;
-
-            self.tracer.push(Event {
-                depth: ctx.depth,
-                reverted: false,
-                data: EventData::OpCode(OpCode {
-                    pc: instruction.offset,
-                    op: instruction.opcode.code,
-                    name: instruction.opcode.name(),
-                    data: instruction.argument.clone().map(Into::into),
-                    gas_cost,
-                    gas_used: evm.gas.used + gas_cost,
-                    gas_left: 0,
-                    stack: evm.stack.clone(),
-                    memory: evm.memory.chunks(32).map(Word::from_bytes).collect(),
-                    gas_back: 0,
-                    debug: json!({
-                        "is_call": true,
-                        "evm.gas.used": evm.gas.used,
-                        "evm.gas.refund": evm.gas.refund,
-                        "call.address": address,
-                        "call.input": hex::encode(&evm.memory[args_offset.min(evm.memory.len())..(args_offset + args_size).min(evm.memory.len())]),
-                        "call.result": "OOG",
-                        "call.gas": call_gas.as_u64(),
-                        "access_cost": access_cost,
-                        "memory_expansion_cost": memory_expansion_cost,
-                    }),
-                }),
-            });
-
-            // Don't add refunds from reverted calls
-            evm.refund = evm.gas.refund;
-            evm.push(Word::zero())?;
+            *gas = base_gas_cost;
             return Err(ExecutorError::OutOfGas().into());
         }
-
-        // Calculate available gas for forwarding using "all but one 64th" rule
-        let remaining_gas = evm.gas.remaining() - base_gas_cost;
-        let all_but_one_64th = remaining_gas - remaining_gas / 64;
-        let gas_to_forward = call_gas.as_i64().min(all_but_one_64th) + gas_stipend_adjustment;
-
-        // For EVM accounting: only charge the outer EVM for base cost
-        // (forwarded gas was already "spent" by allocating it to inner call)
         *gas = base_gas_cost;
 
-        // For tracing: report the total cost including forwarded gas (to match REVM)
-        let total_gas_cost_for_tracing = base_gas_cost + gas_to_forward - gas_stipend_adjustment;
+        let remaining_gas = evm.gas.remaining() - base_gas_cost;
+        let gas_to_forward = remaining_gas - (remaining_gas / 64).max(5000);
 
         let data = if args_size > 0 {
-            &evm.memory[args_offset..args_offset + args_size]
+            evm.memory[args_offset..args_offset + args_size].to_vec()
         } else {
-            &[]
+            vec![]
         };
 
-        // Handle precompile call
-        if precompiles::is_precompile(&address) {
-            let gas_cost = precompiles::gas_cost(&address, data);
-            // TODO: check if there is enough gas
-            let result = match precompiles::execute(&address, data) {
-                Ok(ret) => {
+        // Light failure: the call is not attempted and only the base cost is charged
+        if gas_to_forward < 2300
+            || ctx.depth >= self.limits.call_depth
+            || value > ext.balance(&this).await?
+            || (matches!(ctx.call_type, CallType::Delegate) && !eof::is_eof(&code))
+        {
+            self.trace_call(instruction, evm, ctx, base_gas_cost);
+            self.ret.clear();
+            evm.push(Word::one(), self.limits.stack)?;
+            return Ok(());
+        }
+
+        if let Some(precompile) = precompile {
+            let gas_cost = precompile.gas_cost(&data, self.spec);
+            let checkpoint = ext.checkpoint();
+            let result = match precompile.execute(&data, self.spec, ext).await {
+                Ok(ret) if gas_cost <= gas_to_forward => {
+                    ext.commit(checkpoint);
                     self.ret = ret;
-                    Word::one()
+                    *gas += gas_cost;
+                    Word::zero()
+                }
+                _ => {
+                    // Failed precompile consumes all the gas passed to it
+                    ext.revert_to(checkpoint);
+                    self.ret.clear();
+                    *gas += gas_to_forward;
+                    Word::from(2u64)
                 }
-                Err(_) => Word::zero(),
             };
-
-            self.tracer.push(Event {
-                depth: ctx.depth,
-                reverted: false,
-                data: EventData::OpCode(OpCode {
-                    pc: instruction.offset,
-                    op: instruction.opcode.code,
-                    name: instruction.opcode.name(),
-                    data: instruction.argument.clone().map(Into::into),
-                    gas_cost: total_gas_cost_for_tracing,
-                    gas_used: evm.gas.used + total_gas_cost_for_tracing,
-                    gas_left: evm.gas.remaining() - total_gas_cost_for_tracing,
-                    stack: evm.stack.clone(),
-                    memory: evm.memory.chunks(32).map(Word::from_bytes).collect(),
-                    gas_back: 0,
-                    debug: json!({
-                        "is_call": true,
-                        "is_precompile": true,
-                        "gas_left": evm.gas.remaining() - base_gas_cost,
-                        "gas_cost": total_gas_cost_for_tracing,
-                        "evm.gas.used": evm.gas.used,
-                        "evm.gas.refund": evm.gas.refund,
-                        "call.address": address,
-                        "call.input": hex::encode(&evm.memory[args_offset..args_offset + args_size]),
-                        "call.result": result,
-                        "call.gas": call_gas.as_u64(),
-                        "access_cost": access_cost,
-                        "precompile_gas_cost": gas_cost,
-                        "memory_expansion_cost": memory_expansion_cost,
-                        "ret": hex::encode(&self.ret),
-                    }),
-                }),
-            });
-
-            let copy_len = self.ret.len().min(ret_size);
-            evm.memory[ret_offset..ret_offset + copy_len].copy_from_slice(&self.ret[..copy_len]);
-
-            evm.push(result)?;
-            evm.gas(gas_cost)?;
+            self.trace_call(instruction, evm, ctx, *gas);
+            let precompile_call = Call {
+                data,
+                value,
+                from: this,
+                to: address,
+                gas: (gas_to_forward as u64).into(),
+            };
+            self.trace_precompile(precompile_call, result.is_zero(), *gas - base_gas_cost, ctx);
+            evm.push(result, self.limits.stack)?;
             return Ok(());
         }
 
         let inner_call = Call {
-            data: data.to_vec(),
+            data,
             value,
-            from: if matches!(ctx.call_type, CallType::Delegate | CallType::Callcode) {
+            from: if matches!(ctx.call_type, CallType::Delegate) {
                 call.from
             } else {
                 this
             },
-            to: if matches!(ctx.call_type, CallType::Delegate | CallType::Callcode) {
+            to: if matches!(ctx.call_type, CallType::Delegate) {
                 this
             } else {
                 address
             },
             gas: (gas_to_forward as u64).into(),
         };
-        let mut inner_evm = Evm {
+        let inner_evm = Evm {
             gas: Gas::new(gas_to_forward),
             ..Default::default()
         };
 
-        // Apply value transfer BEFORE call execution
-        let sender_balance = ext.balance(&this).await?;
-        let receiver_balance = ext.balance(&address).await?;
-        if !value.is_zero() && !matches!(ctx.call_type, CallType::Static | CallType::Delegate) {
-            if sender_balance >= value {
-                // For self-calls (where sender == receiver), no net balance change
-                if this != address {
-                    let new_sender_balance = sender_balance - value;
-                    ext.account_mut(&this).value = new_sender_balance;
-                    evm.touches.push(AccountTouch::SetValue(
-                        this,
-                        sender_balance,
-                        new_sender_balance,
-                    ));
-                    self.tracer.push(Event {
-                        data: EventData::Account(AccountEvent::SetValue {
-                            address: this,
-                            val: sender_balance,
-                            new: new_sender_balance,
-                        }),
-                        depth: ctx.depth,
-                        reverted: false,
-                    });
-                    let new_receiver_balance = receiver_balance + value;
-                    ext.account_mut(&address).value = new_receiver_balance;
-                    evm.touches.push(AccountTouch::SetValue(
-                        address,
-                        receiver_balance,
-                        new_receiver_balance,
-                    ));
-                    self.tracer.push(Event {
-                        data: EventData::Account(AccountEvent::SetValue {
-                            address,
-                            val: receiver_balance,
-                            new: new_receiver_balance,
-                        }),
-                        depth: ctx.depth,
-                        reverted: false,
-                    });
-                }
-            } else {
-                // TODO: insufficient funds to transfer
+        let checkpoint = ext.checkpoint();
+        if !value.is_zero() && this != address {
+            let sender_balance = ext.balance(&this).await?;
+            let receiver_balance = ext.balance(&address).await?;
+            ext.set_balance(&this, sender_balance - value);
+            evm.touches.push(AccountTouch::SetValue(
+                this,
+                sender_balance,
+                sender_balance - value,
+            ));
+            ext.set_balance(&address, receiver_balance + value);
+            evm.touches.push(AccountTouch::SetValue(
+                address,
+                receiver_balance,
+                receiver_balance + value,
+            ));
+            for (address, val, new) in [
+                (this, sender_balance, sender_balance - value),
+                (address, receiver_balance, receiver_balance + value),
+            ] {
+                self.emit(|_| Event {
+                    data: EventData::Account(AccountEvent::SetValue { address, val, new }),
+                    depth: ctx.depth,
+                    reverted: false,
+                });
             }
         }
 
-        let inner_ctx = Context {
-            depth: ctx.depth + 1,
-            ..ctx
-        };
-
-        let code = Decoder::decode(code);
-        self.tracer.push(Event {
+        let code = Decoder::decode(code, self.spec);
+        self.emit(|_| Event {
             data: EventData::Account(AccountEvent::GetCode {
                 address,
                 codehash,
@@ -2621,99 +3776,41 @@ impl<T: EventTracer> Executor<T> {
             reverted: false,
         });
 
-        let mut executor =
-            Executor::<T>::with_tracer(self.tracer.fork()).with_header(self.header.clone());
-        executor.set_log(self.log);
-        let future =
-            executor.execute_with_context(&code, &inner_call, &mut inner_evm, ext, inner_ctx);
-        let (tracer, ret) = Box::pin(future).await;
-
-        // HERE: TODO: remove this label
-        self.tracer.push(Event {
-            depth: ctx.depth,
-            reverted: false,
-            data: EventData::OpCode(OpCode {
-                pc: instruction.offset,
-                op: instruction.opcode.code,
-                name: instruction.opcode.name(),
-                data: instruction.argument.clone().map(Into::into),
-                stack: evm.stack.clone(),
-                memory: evm.memory.chunks(32).map(Word::from_bytes).collect(),
-                gas_back: 0,
-                gas_cost: total_gas_cost_for_tracing,
-                gas_used: evm.gas.used + total_gas_cost_for_tracing,
-                gas_left: evm.gas.remaining() - total_gas_cost_for_tracing,
-                debug: json!({
-                    "is_call": true,
-                    "gas_left": evm.gas.remaining() - total_gas_cost_for_tracing,
-                    "gas_cost": total_gas_cost_for_tracing,
-                    "evm.gas.used": evm.gas.used,
-                    "evm.gas.refund": evm.gas.refund,
-                    "args_offset": args_offset,
-                    "args_size": args_size,
-                    "ret_offset": ret_offset,
-                    "ret_size": ret_size,
-                    "memory.len": evm.memory.len(),
-                    "call.from": inner_call.from,
-                    "call.to": inner_call.to,
-                    "call.input": hex::encode(data),
-                    "call.value": value,
-                    "call.gas": call_gas.as_u64(),
-                    "call.is_delegated": is_delegated,
-                    "access_cost": access_cost,
-                    "inner_evm.reverted": inner_evm.reverted,
-                    "is_empty": is_empty,
-                    "code.len": code.bytecode.len(),
-                    "ret": hex::encode(&self.ret),
-                }),
-            }),
-        });
-        self.tracer.join(tracer, inner_evm.reverted);
-
-        let copy_len = ret.len().min(ret_size);
-        if copy_len > 0 {
-            evm.memory[ret_offset..ret_offset + copy_len].copy_from_slice(&ret[..copy_len]);
-        }
-
-        // TODO: check if there is enough gas
-
-        evm.gas.used += inner_evm.gas.used;
-        evm.gas.used -= gas_stipend_adjustment;
-
-        if inner_evm.reverted {
-            // Don't add refunds from reverted calls
-            evm.refund = evm.gas.refund;
-            self.ret = ret;
-            evm.push(Word::zero())?;
-            inner_evm.revert(ext).await?;
-            evm.touches.extend(
-                inner_evm
-                    .touches
-                    .into_iter()
-                    .filter(|t| t.survives_revert()),
-            );
-            return Ok(());
-        }
-
-        // Only add refunds if call succeeded
-        evm.gas.refund += inner_evm.gas.refund;
-        evm.refund = evm.gas.refund;
-
-        evm.touches.extend(inner_evm.touches.into_iter());
-
-        // Preserve the actual return data as-is for RETURNDATA* opcodes
-        self.ret = ret;
-        evm.push(Word::one())?;
-
+        let inner_ctx = Context {
+            depth: ctx.depth + 1,
+            ..ctx
+        };
+        let child = Frame {
+            executor: self.inner(),
+            code: Cow::Owned(code),
+            call: Cow::Owned(inner_call),
+            evm: inner_evm,
+            ctx: inner_ctx,
+        };
+        let resume = CallResume {
+            ctx,
+            gas: base_gas_cost,
+            call_gas: Word::from(gas_to_forward as u64),
+            value,
+            args_offset,
+            args_size,
+            ret_offset: 0,
+            ret_size: 0,
+            gas_stipend_adjustment: 0,
+            total_gas_cost_for_tracing: base_gas_cost + gas_to_forward,
+            is_delegated: false,
+            access_cost,
+            is_empty: false,
+            is_eof: true,
+            checkpoint,
+        };
+        self.pending = Some(Box::new((child, Resume::Call(resume))));
         Ok(())
     }
 
-    #[allow(clippy::too_many_arguments)]
     async fn create(
         &mut self,
-        instruction: &Instruction,
         this: Address,
-        gas: &mut i64,
         evm: &mut Evm,
         ext: &mut Ext,
         ctx: Context,
@@ -2728,7 +3825,7 @@ impl<T: EventTracer> Executor<T> {
         };
 
         if size > 0 && offset + size > evm.memory.len() {
-            if offset + size > ALLOCATION_SANITY_LIMIT {
+            if offset + size > self.limits.memory {
                 return Err(ExecutorError::InvalidAllocation(offset + size).into());
             }
             let padding = 32 - (offset + size) % 32;
@@ -2737,6 +3834,15 @@ impl<T: EventTracer> Executor<T> {
 
         let memory_expansion_cost = evm.memory_expansion_cost();
 
+        // EIP-3860: creation with too large initcode fails like out of gas
+        if size > self.limits.initcode_size {
+            return Err(ExecutorError::InitcodeSizeExceeded {
+                size,
+                limit: self.limits.initcode_size,
+            }
+            .into());
+        }
+
         let bytecode = evm.memory[offset..offset + size].to_vec();
         let word_size = bytecode.len().div_ceil(32) as i64;
         let init_code_cost = 2 * word_size
@@ -2745,17 +3851,18 @@ impl<T: EventTracer> Executor<T> {
             } else {
                 0
             };
-        let code = Decoder::decode(bytecode);
+        // Initcode of CREATE and CREATE2 is always legacy code
+        let code = Decoder::decode_legacy(bytecode);
 
         // EVM pre-increments creator's nonce before computing CREATE address
         let nonce = ext.nonce(&this).await?;
-        ext.account_mut(&this).nonce = nonce + Word::one();
+        ext.set_nonce(&this, nonce + Word::one());
         evm.touches.push(AccountTouch::SetNonce(
             this,
             nonce.as_u64(),
             nonce.as_u64() + 1,
         ));
-        self.tracer.push(Event {
+        self.emit(|_| Event {
             data: EventData::Account(AccountEvent::SetNonce {
                 address: this,
                 val: nonce.as_u64(),
@@ -2771,7 +3878,6 @@ impl<T: EventTracer> Executor<T> {
             this.create(nonce)
         };
 
-        ext.created_accounts.push(created);
         if ext.warm_address(&created) {
             evm.touches.push(AccountTouch::WarmUp(created));
         }
@@ -2788,6 +3894,9 @@ impl<T: EventTracer> Executor<T> {
 
         let all_but_one_64th = remaining_gas - remaining_gas / 64;
         let gas_to_forward = all_but_one_64th;
+        // Nonce bump and warm up survive the failure of the creation
+        let checkpoint = ext.checkpoint();
+        ext.create_account(&created);
 
         let inner_call = Call {
             data: vec![],
@@ -2796,7 +3905,7 @@ impl<T: EventTracer> Executor<T> {
             to: Address::zero(),
             gas: gas_to_forward.unsigned_abs().into(),
         };
-        let mut inner_evm = Evm {
+        let inner_evm = Evm {
             gas: Gas::new(gas_to_forward),
             ..Default::default()
         };
@@ -2805,12 +3914,58 @@ impl<T: EventTracer> Executor<T> {
             depth: ctx.depth + 1,
             ..ctx
         };
-        let mut executor =
-            Executor::<T>::with_tracer(self.tracer.fork()).with_header(self.header.clone());
-        executor.set_log(self.log);
-        let future =
-            executor.execute_with_context(&code, &inner_call, &mut inner_evm, ext, inner_ctx);
-        let (tracer, code) = Box::pin(future).await;
+        let child = Frame {
+            executor: self.inner(),
+            code: Cow::Owned(code),
+            call: Cow::Owned(inner_call),
+            evm: inner_evm,
+            ctx: inner_ctx,
+        };
+        let resume = CreateResume {
+            ctx,
+            this,
+            value,
+            nonce,
+            created,
+            memory_expansion_cost,
+            create_cost,
+            init_code_cost,
+            gas_to_forward,
+            checkpoint,
+        };
+        self.pending = Some(Box::new((child, Resume::Create(resume))));
+        Ok(())
+    }
+
+    /// Complete the CREATE* instruction once its child frame returns
+    async fn create_return(
+        &mut self,
+        instruction: &Instruction,
+        resume: CreateResume,
+        child: Frame<'_, T>,
+        code: Vec<u8>,
+        evm: &mut Evm,
+        ext: &mut Ext,
+    ) -> eyre::Result<()> {
+        let CreateResume {
+            ctx,
+            this,
+            value,
+            nonce,
+            created,
+            memory_expansion_cost,
+            create_cost,
+            init_code_cost,
+            gas_to_forward,
+            checkpoint,
+        } = resume;
+        let Frame {
+            executor,
+            call: inner_call,
+            evm: inner_evm,
+            ..
+        } = child;
+        let tracer = executor.tracer;
 
         let deployed_code_cost = if !inner_evm.reverted {
             200 * code.len() as i64
@@ -2819,92 +3974,86 @@ impl<T: EventTracer> Executor<T> {
         };
         let base_cost_without_deploy = memory_expansion_cost + create_cost + init_code_cost;
 
-        // HERE: TODO: remove this label
         let total_gas_cost_for_tracing =
             memory_expansion_cost + create_cost + init_code_cost + gas_to_forward;
-        self.tracer.push(Event {
-            depth: ctx.depth,
-            reverted: false,
-            data: EventData::OpCode(OpCode {
-                pc: instruction.offset,
-                op: instruction.opcode.code,
-                name: instruction.opcode.name(),
-                data: instruction.argument.clone().map(Into::into),
-                stack: evm.stack.clone(),
-                memory: evm.memory.chunks(32).map(Word::from_bytes).collect(),
-                gas_back: 0,
-                gas_cost: total_gas_cost_for_tracing,
-                gas_used: evm.gas.used + total_gas_cost_for_tracing,
-                gas_left: evm.gas.remaining() - total_gas_cost_for_tracing,
-                debug: json!({
-                    "is_call": true,
-                    "evm.gas.used": evm.gas.used,
-                    "evm.gas.refund": evm.gas.refund,
-                    "created": {
-                        "opcode": ctx.call_type,
-                        "address": created,
-                        "creator": this,
-                        "nonce": nonce,
-                    },
-                    "inner_evm.reverted": inner_evm.reverted,
-                    "inner_call": inner_call,
+        if T::TRACE.opcodes {
+            self.tracer.push(Event {
+                depth: ctx.depth,
+                reverted: false,
+                data: EventData::OpCode(OpCode {
+                    pc: instruction.offset,
+                    op: instruction.opcode.code,
+                    name: instruction.opcode.name(),
+                    data: instruction.argument.clone().map(Into::into),
+                    stack: Self::stack(evm),
+                    memory: Self::memory(evm),
+                    gas_back: 0,
+                    gas_cost: total_gas_cost_for_tracing,
+                    gas_used: evm.gas.used + total_gas_cost_for_tracing,
+                    gas_left: evm.gas.remaining() - total_gas_cost_for_tracing,
+                    debug: Self::debug(|| {
+                        json!({
+                            "is_call": true,
+                            "evm.gas.used": evm.gas.used,
+                            "evm.gas.refund": evm.gas.refund,
+                            "created": {
+                                "opcode": ctx.call_type,
+                                "address": created,
+                                "creator": this,
+                                "nonce": nonce,
+                            },
+                            "inner_evm.reverted": inner_evm.reverted,
+                            "inner_call": *inner_call,
+                        })
+                    }),
                 }),
-            }),
-        });
+            });
+        }
 
         self.tracer.join(tracer, inner_evm.reverted);
 
         // Check if there's enough gas left to pay for deployed code
         // gas_to_forward is what was given to inner call
         // After inner execution, we need: inner_evm.gas.used + deployed_code_cost <= gas_to_forward
-        if gas_to_forward < inner_evm.gas.used + deployed_code_cost {
+        // EIP-170: deploying too large code fails the same way
+        let is_too_large = !inner_evm.reverted && code.len() > self.limits.code_size;
+        if gas_to_forward < inner_evm.gas.used + deployed_code_cost || is_too_large {
             // Not enough gas to deploy the code - creation fails
             evm.gas.used += base_cost_without_deploy + gas_to_forward;
-            evm.push(Word::zero())?;
-            inner_evm.revert(ext).await?;
-            evm.touches.extend(
-                inner_evm
-                    .touches
-                    .into_iter()
-                    .filter(|t| t.survives_revert()),
-            );
+            ext.revert_to(checkpoint);
+            evm.join(inner_evm.touches, true);
+            evm.push(Word::zero(), self.limits.stack)?;
             return Ok(());
         }
 
         evm.gas.used += base_cost_without_deploy + deployed_code_cost + inner_evm.gas.used;
-        *gas = 0;
 
         if inner_evm.reverted {
-            evm.push(Word::zero())?;
-            inner_evm.revert(ext).await?;
-            evm.touches.extend(
-                inner_evm
-                    .touches
-                    .into_iter()
-                    .filter(|t| t.survives_revert()),
-            );
+            ext.revert_to(checkpoint);
+            evm.join(inner_evm.touches, true);
+            evm.push(Word::zero(), self.limits.stack)?;
             return Ok(());
         }
 
         let hash = keccak256(&code);
         let _empty = ext.code(&created).await?;
-        *ext.code_mut(&created) = (code.clone(), Word::from_bytes(&hash));
+        ext.set_code(&created, (code.clone(), Word::from_bytes(&hash)));
 
         // EIP-161: Contract accounts start with nonce=1
-        ext.account_mut(&created).nonce = Word::one();
+        ext.set_nonce(&created, Word::one());
 
         let sender_balance = ext.balance(&this).await?;
         let receiver_balance = ext.balance(&created).await?;
         if !value.is_zero() && !matches!(ctx.call_type, CallType::Static | CallType::Delegate) {
             if sender_balance >= value {
                 let new_sender_balance = sender_balance - value;
-                ext.account_mut(&this).value = new_sender_balance;
+                ext.set_balance(&this, new_sender_balance);
                 evm.touches.push(AccountTouch::SetValue(
                     this,
                     sender_balance,
                     new_sender_balance,
                 ));
-                self.tracer.push(Event {
+                self.emit(|_| Event {
                     data: EventData::Account(AccountEvent::SetValue {
                         address: this,
                         val: sender_balance,
@@ -2914,13 +4063,13 @@ impl<T: EventTracer> Executor<T> {
                     reverted: false,
                 });
                 let new_receiver_balance = receiver_balance + value;
-                ext.account_mut(&created).value = new_receiver_balance;
+                ext.set_balance(&created, new_receiver_balance);
                 evm.touches.push(AccountTouch::SetValue(
                     created,
                     receiver_balance,
                     new_receiver_balance,
                 ));
-                self.tracer.push(Event {
+                self.emit(|_| Event {
                     data: EventData::Account(AccountEvent::SetValue {
                         address: created,
                         val: receiver_balance,
@@ -2941,7 +4090,7 @@ impl<T: EventTracer> Executor<T> {
             code.clone(),
             Word::from_bytes(&hash),
         ));
-        self.tracer.push(Event {
+        self.emit(|_| Event {
             data: EventData::Account(AccountEvent::Create {
                 address: created,
                 creator: this,
@@ -2958,8 +4107,18 @@ impl<T: EventTracer> Executor<T> {
         evm.gas.refund += inner_evm.gas.refund;
         evm.refund = evm.gas.refund;
 
-        evm.touches.extend(inner_evm.touches.into_iter());
-        evm.push((&created).into())?;
+        ext.commit(checkpoint);
+        evm.join(inner_evm.touches, false);
+        evm.push((&created).into(), self.limits.stack)?;
         Ok(())
     }
 }
+
+/// Word at the offset in the data, zero-padded past the end
+fn load_word(data: &[u8], offset: Word) -> Word {
+    let offset = offset.min(Word::from(data.len())).as_usize();
+    let len = data.len().min(offset + 32) - offset;
+    let mut word = [0u8; 32];
+    word[..len].copy_from_slice(&data[offset..offset + len]);
+    Word::from_bytes(&word)
+}
@@ -1,21 +1,46 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 #[cfg(all(feature = "tracing", not(target_arch = "wasm32")))]
 use std::time::Instant;
 
-use evm_common::{address::Address, block::AccessListItem, hash::keccak256, word::Word};
+use evm_common::{
+    address::Address,
+    block::{AccessListItem, Authorization},
+    hash::keccak256,
+    word::Word,
+};
 
-use crate::eth::EthClient;
+use crate::{delegation, eth::EthClient};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
+pub struct Log(pub Address, pub Vec<Word>, pub Vec<u8>);
+
+#[derive(Clone, Debug, Default)]
 pub struct Account {
     pub value: Word,
     pub nonce: Word,
     pub root: Word,
     pub code: (Vec<u8>, Word),
     pub state: HashMap<Word, Word>,
+    /// Storage is known in full, missing slots are zero and not fetched:
+    /// the account was deleted
+    pub cleared: bool,
+}
+
+impl Account {
+    /// Account deleted by SELFDESTRUCT
+    pub fn deleted() -> Self {
+        Self {
+            cleared: true,
+            ..Self::default()
+        }
+    }
 }
 
+#[derive(Clone)]
 struct Remote {
     eth: EthClient,
     block_hash: String,
@@ -24,6 +49,9 @@ struct Remote {
 #[derive(Default)]
 pub struct Ext {
     remote: Option<Remote>,
+    // State shared with the other forks, read through on a miss in `state`
+    base: Option<Arc<Layer>>,
+    /// Accounts cached or changed by this fork, copied from the base on write
     pub state: HashMap<Address, Account>,
     pub original: HashMap<(Address, Word), Word>,
     pub transient: HashMap<(Address, Word), Word>,
@@ -34,7 +62,116 @@ pub struct Ext {
     pub created_accounts: Vec<Address>,
     pub destroyed_accounts: Vec<Address>,
 
+    pub logs: Vec<Log>,
+
     pub tx_ctx: TxContext,
+
+    // Undo records of the changes made since the outermost open checkpoint
+    journal: Vec<Change>,
+    // Journal length at each open checkpoint, innermost last
+    checkpoints: Vec<usize>,
+
+    // Shared state accessed, if recorded
+    accesses: Option<Accesses>,
+    // Accounts and slots written, adopted by `merge`
+    dirty: Locations,
+}
+
+/// Shared state accessed through an [`Ext`], recorded to validate
+/// speculative execution
+#[derive(Clone, Debug, Default)]
+pub struct Accesses {
+    pub reads: Locations,
+    pub writes: Locations,
+    /// Balance increments made without reading the balance, by account
+    pub credits: HashMap<Address, Word>,
+}
+
+/// Nonce, balance and code are tracked per account
+#[derive(Clone, Debug, Default)]
+pub struct Locations {
+    pub accounts: HashSet<Address>,
+    pub storage: HashSet<(Address, Word)>,
+}
+
+/// Open checkpoint of `Ext`, by nesting depth: the changes made after it are
+/// undone by [`Ext::revert_to`]. Taken at every call and create boundary.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[must_use]
+pub struct Checkpoint(usize);
+
+// Value before the change, `None` if it was not cached yet
+#[derive(Debug)]
+enum Change {
+    Balance(Address, Word),
+    Nonce(Address, Word),
+    Code(Address, (Vec<u8>, Word)),
+    Storage(Address, Word, Option<Word>),
+    Transient(Address, Word, Option<Word>),
+    WarmAddress(Address),
+    WarmStorage(Address, Word),
+    Log,
+    Create(Address),
+    Destroy(Address),
+}
+
+// Layers a lookup can walk through before the chain is flattened
+const MAX_LAYERS: usize = 16;
+
+// Frozen state of a fork, layered over the state it was forked from
+struct Layer {
+    state: HashMap<Address, Account>,
+    parent: Option<Arc<Layer>>,
+    // Number of layers below this one
+    depth: usize,
+}
+
+impl Layer {
+    // Copy the accounts of the layers below into this one, the latest
+    // version of each
+    fn flatten(&mut self) {
+        let mut parent = self.parent.take();
+        while let Some(layer) = parent {
+            for (addr, account) in &layer.state {
+                self.state.entry(*addr).or_insert_with(|| account.clone());
+            }
+            parent = layer.parent.clone();
+        }
+        self.depth = 0;
+    }
+
+    fn get(&self, addr: &Address) -> Option<&Account> {
+        let mut layer = self;
+        loop {
+            if let Some(account) = layer.state.get(addr) {
+                return Some(account);
+            }
+            layer = layer.parent.as_deref()?;
+        }
+    }
+}
+
+/// Immutable state of an [`Ext`], cheap to clone and to share between
+/// threads. Every `Ext` made from it reads through the snapshot and fetches
+/// what is missing from the same block.
+#[derive(Clone)]
+pub struct Snapshot {
+    base: Option<Arc<Layer>>,
+    remote: Option<Remote>,
+    // SELFDESTRUCTs of the last transaction, applied by `Ext::reset`
+    destroyed_accounts: Vec<Address>,
+}
+
+impl Snapshot {
+    /// New overlay over the snapshot, with no transaction state
+    pub fn ext(&self) -> Ext {
+        Ext {
+            remote: self.remote.clone(),
+            base: self.base.clone(),
+            destroyed_accounts: self.destroyed_accounts.clone(),
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Clone, Default)]
@@ -44,7 +181,11 @@ pub struct TxContext {
     pub blob_max_fee: Word,
     pub gas_max_priority_fee: Word,
     pub blob_gas_used: u64,
+    pub blob_hashes: Vec<Word>,
     pub access_list: Vec<AccessListItem>,
+    pub authorization_list: Vec<Authorization>,
+    /// Transaction nonce, checked against the sender by strict validation
+    pub nonce: Option<Word>,
 }
 
 impl TxContext {
@@ -56,6 +197,11 @@ impl TxContext {
         }
         cost
     }
+
+    /// EIP-7702: intrinsic cost of the authorization list
+    pub fn authorization_list_cost(&self) -> i64 {
+        self.authorization_list.len() as i64 * delegation::PER_EMPTY_ACCOUNT_COST
+    }
 }
 
 impl Ext {
@@ -80,6 +226,146 @@ impl Ext {
         Ok(Self::at_hash(block_hash, eth))
     }
 
+    /// Freeze the state: it is moved to a shared immutable layer that this
+    /// `Ext` keeps reading through. Meant to be taken between transactions.
+    pub fn snapshot(&mut self) -> Snapshot {
+        if !self.state.is_empty() {
+            let parent = self.base.take();
+            let mut layer = Layer {
+                state: std::mem::take(&mut self.state),
+                depth: parent.as_ref().map_or(0, |parent| parent.depth + 1),
+                parent,
+            };
+            if layer.depth >= MAX_LAYERS {
+                layer.flatten();
+            }
+            self.base = Some(Arc::new(layer));
+        }
+        Snapshot {
+            base: self.base.clone(),
+            remote: self.remote.clone(),
+            destroyed_accounts: self.destroyed_accounts.clone(),
+        }
+    }
+
+    /// Copy of the `Ext` that shares its current state: changes made to
+    /// either of them afterwards are not seen by the other. Open checkpoints
+    /// are not carried over.
+    pub fn fork(&mut self) -> Self {
+        let mut ext = self.snapshot().ext();
+        ext.original = self.original.clone();
+        ext.transient = self.transient.clone();
+        ext.accessed_addresses = self.accessed_addresses.clone();
+        ext.accessed_storage = self.accessed_storage.clone();
+        ext.created_accounts = self.created_accounts.clone();
+        ext.logs = self.logs.clone();
+        ext.tx_ctx = self.tx_ctx.clone();
+        ext
+    }
+
+    /// Adopt the changes of a fork of this `Ext` and its pending
+    /// SELFDESTRUCTs: the account fields and storage slots the fork wrote,
+    /// where they differ from the state it was forked from. Changes made by
+    /// this `Ext` since the fork are kept otherwise, as is its transaction
+    /// state (access tracking, transient storage, logs).
+    pub fn merge(&mut self, mut other: Ext) {
+        let Locations { accounts, storage } = std::mem::take(&mut other.dirty);
+        for addr in accounts {
+            let Some(account) = other.state.get(&addr) else {
+                continue;
+            };
+            let base = other.base(&addr);
+            self.dirty.accounts.insert(addr);
+            // Fetched by the fork: unknown to this `Ext` as well
+            if base.is_none() && self.cached(&addr).is_none() {
+                self.state.insert(addr, account.clone());
+                continue;
+            }
+            let target = self.owned(&addr);
+            if base.is_none_or(|base| base.value != account.value) {
+                target.value = account.value;
+            }
+            if base.is_none_or(|base| base.nonce != account.nonce) {
+                target.nonce = account.nonce;
+            }
+            if base.is_none_or(|base| base.code.1 != account.code.1) {
+                target.code = account.code.clone();
+            }
+            // Deleted by the fork: so is the storage this `Ext` has
+            if account.cleared && base.is_none_or(|base| !base.cleared) {
+                target.cleared = true;
+                target.state = account.state.clone();
+            }
+        }
+        for (addr, key) in storage {
+            let Some(val) = other
+                .state
+                .get(&addr)
+                .and_then(|account| account.state.get(&key))
+            else {
+                continue;
+            };
+            let base = other
+                .base(&addr)
+                .and_then(|account| account.state.get(&key));
+            if base != Some(val) {
+                self.dirty.storage.insert((addr, key));
+                self.owned(&addr).state.insert(key, *val);
+            }
+        }
+        for addr in other.destroyed_accounts {
+            if !self.destroyed_accounts.contains(&addr) {
+                self.destroyed_accounts.push(addr);
+            }
+        }
+    }
+
+    /// Record the shared state accessed from now on
+    pub fn track_accesses(&mut self) {
+        self.accesses = Some(Accesses::default());
+    }
+
+    pub fn take_accesses(&mut self) -> Option<Accesses> {
+        self.accesses.take()
+    }
+
+    fn read_account(&mut self, addr: &Address) {
+        if let Some(accesses) = self.accesses.as_mut() {
+            accesses.reads.accounts.insert(*addr);
+        }
+    }
+
+    fn write_account(&mut self, addr: &Address) {
+        self.dirty.accounts.insert(*addr);
+        if let Some(accesses) = self.accesses.as_mut() {
+            accesses.writes.accounts.insert(*addr);
+        }
+    }
+
+    fn write_storage(&mut self, addr: &Address, key: &Word) {
+        self.dirty.storage.insert((*addr, *key));
+        if let Some(accesses) = self.accesses.as_mut() {
+            accesses.writes.storage.insert((*addr, *key));
+        }
+    }
+
+    /// Add to the balance without recording a read of it. Returns the
+    /// balance before the credit.
+    pub async fn credit(&mut self, addr: &Address, value: Word) -> eyre::Result<Word> {
+        let accesses = self.accesses.take();
+        let current = self.balance(addr).await;
+        self.accesses = accesses;
+        let current = current?;
+        if let Some(accesses) = self.accesses.as_mut() {
+            *accesses.credits.entry(*addr).or_default() += value;
+        }
+        if !value.is_zero() {
+            self.dirty.accounts.insert(*addr);
+            self.owned(addr).value = current + value;
+        }
+        Ok(current)
+    }
+
     pub fn apply_access_list(&mut self) {
         for item in self.tx_ctx.access_list.clone() {
             self.warm_address(&item.address);
@@ -101,10 +387,16 @@ impl Ext {
         self.accessed_storage.clear();
         self.created_accounts.clear();
 
-        // Apply SELFDESTRUCT
+        self.logs.clear();
+        self.journal.clear();
+        self.checkpoints.clear();
+
+        // Apply SELFDESTRUCT: keep the deleted account as empty, otherwise
+        // it and its storage are pulled again
         let destroyed = self.destroyed_accounts.drain(..).collect::<Vec<_>>();
         for addr in destroyed {
-            self.state.remove(&addr);
+            self.write_account(&addr);
+            self.state.insert(addr, Account::deleted());
         }
     }
 
@@ -115,7 +407,11 @@ impl Ext {
 
     /// Mark an address as accessed in the current transaction (EIP-2929)
     pub fn warm_address(&mut self, addr: &Address) -> bool {
-        self.accessed_addresses.insert(*addr)
+        let inserted = self.accessed_addresses.insert(*addr);
+        if inserted {
+            self.record(Change::WarmAddress(*addr));
+        }
+        inserted
     }
 
     /// Check if a storage slot has been accessed in the current transaction (EIP-2929)
@@ -125,20 +421,167 @@ impl Ext {
 
     /// Mark a storage slot as accessed in the current transaction (EIP-2929)
     pub fn warm_storage(&mut self, addr: &Address, key: &Word) -> bool {
-        self.accessed_storage.insert((*addr, *key))
+        let inserted = self.accessed_storage.insert((*addr, *key));
+        if inserted {
+            self.record(Change::WarmStorage(*addr, *key));
+        }
+        inserted
+    }
+
+    /// Open a checkpoint: changes made from now on can be reverted
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.checkpoints.push(self.journal.len());
+        Checkpoint(self.checkpoints.len() - 1)
+    }
+
+    /// Keep the changes made since the checkpoint. They are still undone
+    /// if an enclosing checkpoint is reverted.
+    pub fn commit(&mut self, checkpoint: Checkpoint) {
+        self.close(checkpoint);
+        // Nothing left to revert to
+        if self.checkpoints.is_empty() {
+            self.journal.clear();
+        }
+    }
+
+    /// Undo the changes made since the checkpoint, latest first
+    pub fn revert_to(&mut self, checkpoint: Checkpoint) {
+        let offset = self.close(checkpoint);
+        while self.journal.len() > offset {
+            let Some(change) = self.journal.pop() else {
+                break;
+            };
+            match change {
+                Change::Balance(addr, val) => self.owned(&addr).value = val,
+                Change::Nonce(addr, val) => self.owned(&addr).nonce = val,
+                Change::Code(addr, code) => self.owned(&addr).code = code,
+                Change::Storage(addr, key, val) => {
+                    // Restore the value directly, without polluting `original`
+                    let state = &mut self.owned(&addr).state;
+                    match val {
+                        Some(val) => state.insert(key, val),
+                        None => state.remove(&key),
+                    };
+                }
+                Change::Transient(addr, key, val) => {
+                    match val {
+                        Some(val) => self.transient.insert((addr, key), val),
+                        None => self.transient.remove(&(addr, key)),
+                    };
+                }
+                Change::WarmAddress(addr) => {
+                    self.accessed_addresses.remove(&addr);
+                }
+                Change::WarmStorage(addr, key) => {
+                    self.accessed_storage.remove(&(addr, key));
+                }
+                Change::Log => {
+                    self.logs.pop();
+                }
+                Change::Create(addr) => {
+                    if let Some(index) = self.created_accounts.iter().rposition(|a| a == &addr) {
+                        self.created_accounts.remove(index);
+                    }
+                }
+                Change::Destroy(addr) => {
+                    if let Some(index) = self.destroyed_accounts.iter().rposition(|a| a == &addr) {
+                        self.destroyed_accounts.remove(index);
+                    }
+                }
+            }
+        }
+    }
+
+    // Checkpoints still open inside the closed one, left behind by a failed
+    // frame, are closed with it. Returns the journal length at the checkpoint.
+    fn close(&mut self, checkpoint: Checkpoint) -> usize {
+        assert!(
+            checkpoint.0 < self.checkpoints.len(),
+            "checkpoint {} closed twice",
+            checkpoint.0
+        );
+        let offset = self.checkpoints[checkpoint.0];
+        self.checkpoints.truncate(checkpoint.0);
+        offset
+    }
+
+    // Changes outside of any checkpoint are final
+    fn record(&mut self, change: Change) {
+        if !self.checkpoints.is_empty() {
+            self.journal.push(change);
+        }
+    }
+
+    pub fn set_balance(&mut self, addr: &Address, value: Word) {
+        self.write_account(addr);
+        let account = self.owned(addr);
+        let old = std::mem::replace(&mut account.value, value);
+        self.record(Change::Balance(*addr, old));
+    }
+
+    pub fn set_nonce(&mut self, addr: &Address, nonce: Word) {
+        self.write_account(addr);
+        let account = self.owned(addr);
+        let old = std::mem::replace(&mut account.nonce, nonce);
+        self.record(Change::Nonce(*addr, old));
+    }
+
+    pub fn set_code(&mut self, addr: &Address, code: (Vec<u8>, Word)) {
+        self.write_account(addr);
+        let account = self.owned(addr);
+        let old = std::mem::replace(&mut account.code, code);
+        self.record(Change::Code(*addr, old));
+    }
+
+    /// EIP-1153: transient storage, zero if not set
+    pub fn get_transient(&self, addr: &Address, key: &Word) -> Word {
+        self.transient
+            .get(&(*addr, *key))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// EIP-1153: set transient storage, returns the previous value
+    pub fn put_transient(&mut self, addr: &Address, key: Word, val: Word) -> Word {
+        let old = self.transient.insert((*addr, key), val);
+        self.record(Change::Transient(*addr, key, old));
+        old.unwrap_or_default()
+    }
+
+    pub fn log(&mut self, log: Log) {
+        self.logs.push(log);
+        self.record(Change::Log);
+    }
+
+    /// The account is created by the transaction, unless reverted
+    pub fn create_account(&mut self, addr: &Address) {
+        self.created_accounts.push(*addr);
+        self.record(Change::Create(*addr));
+    }
+
+    /// SELFDESTRUCT: the account is deleted at the end of the transaction
+    pub fn destroy(&mut self, addr: &Address) {
+        self.destroyed_accounts.push(*addr);
+        self.record(Change::Destroy(*addr));
     }
 
     pub async fn get(&mut self, addr: &Address, key: &Word) -> eyre::Result<Word> {
-        if !self.state.contains_key(addr) {
+        if let Some(accesses) = self.accesses.as_mut() {
+            accesses.reads.storage.insert((*addr, *key));
+        }
+        if self.cached(addr).is_none() {
             self.pull(addr).await?;
         }
-        if let Some(val) = self.state.get(addr).and_then(|s| s.state.get(key)).copied() {
+        let cleared = self.cached(addr).is_some_and(|account| account.cleared);
+        if let Some(val) = self.cached(addr).and_then(|s| s.state.get(key)).copied() {
             #[cfg(feature = "tracing")]
             tracing::debug!("GET: {addr}[{key:#x}]={val:#064x} [cached]");
 
             self.original.entry((*addr, *key)).or_insert(val);
             Ok(val)
-        } else if let Some(Remote { eth, block_hash }) = self.remote.as_ref() {
+        } else if let Some(Remote { eth, block_hash }) = self.remote.as_ref()
+            && !cleared
+        {
             #[cfg(all(feature = "tracing", not(target_arch = "wasm32")))]
             let now = Instant::now();
 
@@ -149,7 +592,7 @@ impl Ext {
             #[cfg(all(feature = "tracing", not(target_arch = "wasm32")))]
             let ms = now.elapsed().as_millis();
 
-            self.state.entry(*addr).or_default().state.insert(*key, val);
+            self.owned(addr).state.insert(*key, val);
             self.original.entry((*addr, *key)).or_insert(val);
 
             #[cfg(all(feature = "tracing", not(target_arch = "wasm32")))]
@@ -162,7 +605,9 @@ impl Ext {
     }
 
     pub async fn put(&mut self, addr: &Address, key: Word, val: Word) -> eyre::Result<()> {
-        self.state.entry(*addr).or_default().state.insert(key, val);
+        self.write_storage(addr, &key);
+        let old = self.owned(addr).state.insert(key, val);
+        self.record(Change::Storage(*addr, key, old));
 
         #[cfg(all(feature = "tracing", not(target_arch = "wasm32")))]
         {
@@ -183,7 +628,8 @@ impl Ext {
     }
 
     pub async fn code(&mut self, addr: &Address) -> eyre::Result<(Vec<u8>, Word)> {
-        if let Some(code) = self.state.get(addr).map(|s| s.code.clone()) {
+        self.read_account(addr);
+        if let Some(code) = self.cached(addr).map(|s| s.code.clone()) {
             Ok(code)
         } else {
             Ok(self.pull(addr).await?.code.clone())
@@ -191,7 +637,8 @@ impl Ext {
     }
 
     pub async fn balance(&mut self, addr: &Address) -> eyre::Result<Word> {
-        if let Some(value) = self.state.get(addr).map(|s| s.value) {
+        self.read_account(addr);
+        if let Some(value) = self.cached(addr).map(|s| s.value) {
             Ok(value)
         } else {
             Ok(self.pull(addr).await?.value)
@@ -199,7 +646,8 @@ impl Ext {
     }
 
     pub async fn nonce(&mut self, addr: &Address) -> eyre::Result<Word> {
-        if let Some(nonce) = self.state.get(addr).map(|s| s.nonce) {
+        self.read_account(addr);
+        if let Some(nonce) = self.cached(addr).map(|s| s.nonce) {
             // EIP-161 nonce=1 is now set explicitly in executor.rs when CREATE succeeds
             Ok(nonce)
         } else {
@@ -217,8 +665,9 @@ impl Ext {
     }
 
     pub async fn pull(&mut self, addr: &Address) -> eyre::Result<&Account> {
-        if self.state.contains_key(addr) {
-            return Ok(self.state.get(addr).expect("must be present"));
+        self.read_account(addr);
+        if self.cached(addr).is_some() {
+            return Ok(self.cached(addr).expect("must be present"));
         }
         if let Some(Remote { eth, block_hash }) = self.remote.as_ref() {
             let address = format!("0x{}", hex::encode(addr.0));
@@ -232,6 +681,7 @@ impl Ext {
                 code: (code, hash),
                 root: Word::zero(),
                 state: Default::default(),
+                cleared: false,
             };
             self.state.insert(*addr, account);
             Ok(self.state.get(addr).expect("must always be present"))
@@ -240,13 +690,48 @@ impl Ext {
         }
     }
 
+    /// Account if it exists: only a remote `Ext` fetches a missing one
+    pub async fn try_pull(&mut self, addr: &Address) -> eyre::Result<Option<&Account>> {
+        if self.remote.is_none() && self.cached(addr).is_none() {
+            return Ok(None);
+        }
+        self.pull(addr).await.map(Some)
+    }
+
     pub fn account_mut(&mut self, addr: &Address) -> &mut Account {
-        if let Some(account) = self.state.get_mut(addr) {
-            return account;
+        self.read_account(addr);
+        self.write_account(addr);
+        if self.cached(addr).is_some() {
+            return self.owned(addr);
         }
         panic!("missing account {addr}")
     }
 
+    /// Account of the snapshot this `Ext` reads through
+    pub fn base(&self, addr: &Address) -> Option<&Account> {
+        self.base.as_ref()?.get(addr)
+    }
+
+    fn cached(&self, addr: &Address) -> Option<&Account> {
+        self.state
+            .get(addr)
+            .or_else(|| self.base.as_ref()?.get(addr))
+    }
+
+    // Account in this fork, copied from the base on first write
+    pub(crate) fn owned(&mut self, addr: &Address) -> &mut Account {
+        if !self.state.contains_key(addr) {
+            let account = self
+                .base
+                .as_ref()
+                .and_then(|base| base.get(addr))
+                .cloned()
+                .unwrap_or_default();
+            self.state.insert(*addr, account);
+        }
+        self.state.get_mut(addr).expect("must be present")
+    }
+
     pub fn state_mut(&mut self, addr: &Address) -> &mut HashMap<Word, Word> {
         &mut self.account_mut(addr).state
     }
@@ -255,3 +740,191 @@ impl Ext {
         &mut self.account_mut(addr).code
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use evm_common::address::addr;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_journal() -> eyre::Result<()> {
+        let a = addr("00000000000000000000000000000000000000aa");
+        let b = addr("00000000000000000000000000000000000000bb");
+        let key = Word::one();
+        let mut ext = Ext::local();
+        ext.state.insert(a, Account::default());
+        ext.set_balance(&a, Word::from(100u64));
+
+        let outer = ext.checkpoint();
+        ext.set_balance(&a, Word::from(90u64));
+        ext.put(&a, key, Word::from(7u64)).await?;
+        ext.warm_address(&b);
+
+        let inner = ext.checkpoint();
+        ext.set_balance(&a, Word::from(80u64));
+        ext.set_nonce(&a, Word::one());
+        ext.put(&a, key, Word::from(8u64)).await?;
+        ext.put_transient(&a, key, Word::from(9u64));
+        ext.warm_storage(&a, &key);
+        ext.log(Log(a, vec![], vec![]));
+        ext.destroy(&a);
+        ext.revert_to(inner);
+
+        assert_eq!(ext.balance(&a).await?, Word::from(90u64));
+        assert_eq!(ext.nonce(&a).await?, Word::zero());
+        assert_eq!(ext.get(&a, &key).await?, Word::from(7u64));
+        assert_eq!(ext.get_transient(&a, &key), Word::zero());
+        assert!(!ext.is_storage_warm(&a, &key));
+        assert!(ext.is_address_warm(&b));
+        assert!(ext.logs.is_empty());
+        assert!(ext.destroyed_accounts.is_empty());
+
+        // Committed changes are still undone by the enclosing checkpoint
+        let inner = ext.checkpoint();
+        ext.set_code(&a, (vec![0x00], Word::one()));
+        ext.log(Log(a, vec![], vec![]));
+        ext.commit(inner);
+        assert_eq!(ext.logs.len(), 1);
+        ext.revert_to(outer);
+
+        assert_eq!(ext.balance(&a).await?, Word::from(100u64));
+        assert_eq!(ext.code(&a).await?, (vec![], Word::zero()));
+        assert!(ext.state[&a].state.is_empty());
+        assert!(!ext.is_address_warm(&b));
+        assert!(ext.logs.is_empty());
+
+        // Changes outside of any checkpoint are final
+        ext.set_balance(&a, Word::one());
+        let checkpoint = ext.checkpoint();
+        ext.revert_to(checkpoint);
+        assert_eq!(ext.balance(&a).await?, Word::one());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_journal_out_of_order() -> eyre::Result<()> {
+        let a = addr("00000000000000000000000000000000000000aa");
+        let mut ext = Ext::local();
+        ext.state.insert(a, Account::default());
+
+        // Reverting the outer checkpoint undoes the inner one left open
+        let outer = ext.checkpoint();
+        ext.set_balance(&a, Word::from(1u64));
+        let _inner = ext.checkpoint();
+        ext.set_balance(&a, Word::from(2u64));
+        ext.revert_to(outer);
+        assert!(ext.balance(&a).await?.is_zero());
+        assert!(ext.journal.is_empty());
+
+        // Committing it keeps both, the outer checkpoint is still open
+        let outer = ext.checkpoint();
+        let middle = ext.checkpoint();
+        ext.set_balance(&a, Word::from(3u64));
+        let _inner = ext.checkpoint();
+        ext.set_balance(&a, Word::from(4u64));
+        ext.commit(middle);
+        assert_eq!(ext.checkpoints.len(), 1);
+        assert_eq!(ext.journal.len(), 2);
+        ext.revert_to(outer);
+        assert!(ext.balance(&a).await?.is_zero());
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "closed twice")]
+    fn test_journal_closed_twice() {
+        let mut ext = Ext::local();
+        let checkpoint = ext.checkpoint();
+        ext.commit(checkpoint);
+        ext.commit(checkpoint);
+    }
+
+    #[tokio::test]
+    async fn test_deleted_storage() -> eyre::Result<()> {
+        let a = addr("00000000000000000000000000000000000000aa");
+        // Any remote fetch fails
+        let mut ext = Ext::at_hash("0x00".to_string(), EthClient::new("http://127.0.0.1:1"));
+        let account = Account {
+            value: Word::one(),
+            ..Account::default()
+        };
+        ext.state.insert(a, account);
+        assert!(ext.get(&a, &Word::one()).await.is_err());
+
+        ext.destroy(&a);
+        ext.reset(TxContext::default());
+        assert!(ext.balance(&a).await?.is_zero());
+        assert!(ext.get(&a, &Word::one()).await?.is_zero());
+
+        // The parent of a fork that deleted the account forgets its storage
+        let mut parent = Ext::local();
+        parent.state.insert(a, Account::default());
+        parent.put(&a, Word::one(), Word::one()).await?;
+        let mut fork = parent.fork();
+        fork.destroy(&a);
+        fork.reset(TxContext::default());
+        parent.merge(fork);
+        assert!(parent.get(&a, &Word::one()).await?.is_zero());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_snapshot() -> eyre::Result<()> {
+        let a = addr("00000000000000000000000000000000000000aa");
+        let b = addr("00000000000000000000000000000000000000bb");
+        let key = Word::one();
+        let mut ext = Ext::local();
+        ext.state.insert(a, Account::default());
+        ext.set_balance(&a, Word::from(100u64));
+        ext.put(&a, key, Word::from(7u64)).await?;
+
+        let snapshot = ext.snapshot();
+        assert!(ext.state.is_empty());
+        let mut x = snapshot.ext();
+        let mut y = snapshot.ext();
+        x.set_balance(&a, Word::from(90u64));
+        x.put(&a, key, Word::from(8u64)).await?;
+        x.state.insert(b, Account::default());
+        assert_eq!(y.balance(&a).await?, Word::from(100u64));
+        assert_eq!(y.get(&a, &key).await?, Word::from(7u64));
+        assert!(y.pull(&b).await.is_err());
+        // Reads do not copy the account into the fork
+        assert!(y.state.is_empty());
+
+        // A fork of a fork sees its state, but not its later changes
+        let mut z = x.fork();
+        x.set_nonce(&a, Word::one());
+        assert_eq!(z.balance(&a).await?, Word::from(90u64));
+        assert_eq!(z.nonce(&a).await?, Word::zero());
+        z.put(&a, key, Word::from(9u64)).await?;
+        z.destroy(&b);
+
+        x.merge(z);
+        assert_eq!(x.get(&a, &key).await?, Word::from(9u64));
+        // Not written by the fork
+        assert_eq!(x.nonce(&a).await?, Word::one());
+        assert_eq!(x.destroyed_accounts, vec![b]);
+        assert_eq!(ext.get(&a, &key).await?, Word::from(7u64));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_flatten() -> eyre::Result<()> {
+        let a = addr("00000000000000000000000000000000000000aa");
+        let mut ext = Ext::local();
+        ext.state.insert(a, Account::default());
+        for index in 1..=2 * MAX_LAYERS as u64 {
+            ext.set_balance(&a, Word::from(index));
+            ext.state
+                .insert(Address::from(&Word::from(index)), Account::default());
+            let _ = ext.snapshot();
+            assert!(ext.base.as_ref().unwrap().depth < MAX_LAYERS);
+        }
+        assert_eq!(ext.balance(&a).await?, Word::from(2 * MAX_LAYERS as u64));
+        for index in 1..=2 * MAX_LAYERS as u64 {
+            assert!(ext.cached(&Address::from(&Word::from(index))).is_some());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,359 @@
+use serde::{Deserialize, Serialize};
+
+use evm_common::{address::Address, block::Header, word::Word};
+use evm_event::{AccountEvent, Event, EventData};
+
+use crate::{
+    executor::{AccountTouch, Evm},
+    ext::{Ext, TxContext},
+    spec::Spec,
+    tracer::EventTracer,
+};
+
+/// Fees paid by a settled transaction
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Fees {
+    /// Gas charged to the sender, after refunds and the calldata floor
+    pub gas: i64,
+    /// Effective gas price
+    pub price: Word,
+    /// Total gas fee: gas * price
+    pub total: Word,
+    /// EIP-1559: base fee part of the gas fee, removed from circulation
+    pub burned: Word,
+    /// Priority fee part of the gas fee, paid to the coinbase
+    pub tipped: Word,
+    /// EIP-4844: blob fee, removed from circulation
+    pub blob_burned: Word,
+}
+
+/// Upfront charge of the sender of a transaction
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Charge {
+    /// Balance before the charge
+    pub balance: Word,
+    /// Amount taken from the balance: less than the upfront cost if the
+    /// balance falls short of it
+    pub debited: Word,
+}
+
+/// Prices a transaction pays in its block
+#[derive(Clone, Debug, Default)]
+pub struct FeeMarket {
+    pub price: Word,
+    pub base_fee: Word,
+    pub blob_gas: u64,
+    pub blob_price: Word,
+}
+
+impl FeeMarket {
+    pub fn new(tx: &TxContext, header: &Header, spec: Spec) -> Self {
+        // EIP-1559: min(max_fee_per_gas, base_fee + max_priority_fee_per_gas),
+        // legacy transactions pay their gas price
+        let price = if tx.gas_max_priority_fee.is_zero() {
+            tx.gas_price
+        } else {
+            Word::min(tx.gas_max_fee, header.base_fee + tx.gas_max_priority_fee)
+        };
+        let blob_price = if tx.blob_gas_used > 0 {
+            header
+                .blob_gas_price_with_fraction(spec.blob_base_fee_update_fraction())
+                .min(tx.blob_max_fee)
+        } else {
+            Word::zero()
+        };
+        Self {
+            price,
+            base_fee: header.base_fee,
+            blob_gas: tx.blob_gas_used,
+            blob_price,
+        }
+    }
+
+    /// Priority fee per gas paid to the coinbase, the rest of the price is burned
+    pub fn tip(&self) -> Word {
+        self.price.saturating_sub(self.base_fee)
+    }
+
+    pub fn blob_fee(&self) -> Word {
+        Word::from(self.blob_gas) * self.blob_price
+    }
+
+    /// Buy the whole gas limit and the blob gas before execution
+    pub async fn charge(
+        &self,
+        from: &Address,
+        gas_limit: i64,
+        ext: &mut Ext,
+    ) -> eyre::Result<Charge> {
+        let balance = ext.balance(from).await?;
+        let upfront = Word::from(gas_limit) * self.price + self.blob_fee();
+        // Balance checks are left to strict validation: a sender short of
+        // the upfront cost pays what it has
+        let debited = upfront.min(balance);
+        if !debited.is_zero() {
+            ext.account_mut(from).value = balance - debited;
+        }
+        Ok(Charge { balance, debited })
+    }
+
+    /// Refund the unused gas to the sender and pay the priority fee to the
+    /// coinbase. Fee payments are final: they are recorded as touches that
+    /// survive a revert.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn settle<T: EventTracer>(
+        &self,
+        from: &Address,
+        coinbase: &Address,
+        gas_used: i64,
+        charge: Charge,
+        evm: &mut Evm,
+        ext: &mut Ext,
+        tracer: &mut T,
+    ) -> eyre::Result<Fees> {
+        let total = Word::from(gas_used) * self.price;
+        // Only what was debited is refunded
+        let refund = charge.debited.saturating_sub(total + self.blob_fee());
+        if !refund.is_zero() {
+            ext.account_mut(from).value += refund;
+        }
+        let balance_after = ext.balance(from).await?;
+        evm.touches
+            .push(AccountTouch::FeePay(*from, charge.balance, balance_after));
+
+        // Burned and tipped add up to what the sender paid, the blob fee
+        // coming out of it first
+        let paid = charge.debited - refund;
+        let blob_burned = self.blob_fee().min(paid);
+        let gas_paid = paid - blob_burned;
+        let tipped = (Word::from(gas_used) * self.tip()).min(gas_paid);
+        if !coinbase.is_zero() {
+            // The coinbase is touched even if no priority fee is paid. Its
+            // balance is not read: parallel execution defers the credit.
+            let current = ext.credit(coinbase, tipped).await?;
+            let updated = current + tipped;
+            if !tipped.is_zero() && T::TRACE.events {
+                tracer.push(Event {
+                    data: EventData::Account(AccountEvent::SetValue {
+                        address: *coinbase,
+                        val: current,
+                        new: updated,
+                    }),
+                    depth: 1,
+                    reverted: false,
+                });
+            }
+            evm.touches
+                .push(AccountTouch::FeePay(*coinbase, current, updated));
+        }
+
+        Ok(Fees {
+            gas: gas_used,
+            price: self.price,
+            total,
+            burned: gas_paid - tipped,
+            tipped,
+            blob_burned,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ext::Account;
+
+    use super::*;
+
+    #[test]
+    fn test_fee_market() {
+        let header = Header {
+            base_fee: Word::from(10u64),
+            ..Header::default()
+        };
+        let tx = TxContext {
+            gas_price: Word::from(12u64),
+            gas_max_fee: Word::from(20u64),
+            gas_max_priority_fee: Word::from(2u64),
+            blob_max_fee: Word::from(5u64),
+            blob_gas_used: 131072,
+            ..TxContext::default()
+        };
+        let market = FeeMarket::new(&tx, &header, Spec::Cancun);
+        assert_eq!(market.price, Word::from(12u64));
+        assert_eq!(market.tip(), Word::from(2u64));
+        // Zero excess blob gas: minimal blob gas price
+        assert_eq!(market.blob_fee(), Word::from(131072u64));
+
+        // Capped by the max fee
+        let tx = TxContext {
+            gas_max_fee: Word::from(11u64),
+            ..tx
+        };
+        let market = FeeMarket::new(&tx, &header, Spec::Cancun);
+        assert_eq!(market.price, Word::from(11u64));
+        assert_eq!(market.tip(), Word::one());
+    }
+
+    #[tokio::test]
+    async fn test_settle() {
+        let from = Address::from([1u8; 20]);
+        let coinbase = Address::from([2u8; 20]);
+        let mut ext = Ext::local();
+        ext.state.insert(
+            from,
+            Account {
+                value: Word::from(1_000_000u64),
+                ..Account::default()
+            },
+        );
+        ext.state.insert(coinbase, Account::default());
+        let market = FeeMarket {
+            price: Word::from(3u64),
+            base_fee: Word::from(2u64),
+            blob_gas: 10,
+            blob_price: Word::from(4u64),
+        };
+
+        let charge = market.charge(&from, 100_000, &mut ext).await.unwrap();
+        assert_eq!(charge.balance, Word::from(1_000_000u64));
+        assert_eq!(charge.debited, Word::from(300_040u64));
+        assert_eq!(ext.balance(&from).await.unwrap(), Word::from(699_960u64));
+
+        let mut evm = Evm::default();
+        let mut tracer = crate::tracer::NoopTracer;
+        let fees = market
+            .settle(
+                &from,
+                &coinbase,
+                21_000,
+                charge,
+                &mut evm,
+                &mut ext,
+                &mut tracer,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            fees,
+            Fees {
+                gas: 21_000,
+                price: Word::from(3u64),
+                total: Word::from(63_000u64),
+                burned: Word::from(42_000u64),
+                tipped: Word::from(21_000u64),
+                blob_burned: Word::from(40u64),
+            }
+        );
+        assert_eq!(ext.balance(&from).await.unwrap(), Word::from(936_960u64));
+        assert_eq!(ext.balance(&coinbase).await.unwrap(), Word::from(21_000u64));
+        assert!(evm.touches.iter().all(|touch| touch.survives_revert()));
+    }
+
+    #[tokio::test]
+    async fn test_settle_short_balance() {
+        let from = Address::from([1u8; 20]);
+        let coinbase = Address::from([2u8; 20]);
+        let mut ext = Ext::local();
+        ext.state.insert(
+            from,
+            Account {
+                value: Word::from(70_000u64),
+                ..Account::default()
+            },
+        );
+        ext.state.insert(coinbase, Account::default());
+        let market = FeeMarket {
+            price: Word::from(3u64),
+            base_fee: Word::from(2u64),
+            ..FeeMarket::default()
+        };
+
+        // 300_000 upfront, 70_000 available
+        let charge = market.charge(&from, 100_000, &mut ext).await.unwrap();
+        assert_eq!(charge.debited, Word::from(70_000u64));
+        assert!(ext.balance(&from).await.unwrap().is_zero());
+
+        let mut evm = Evm::default();
+        let mut tracer = crate::tracer::NoopTracer;
+        market
+            .settle(
+                &from,
+                &coinbase,
+                21_000,
+                charge,
+                &mut evm,
+                &mut ext,
+                &mut tracer,
+            )
+            .await
+            .unwrap();
+        // Pays 63_000 for the gas used, out of the 70_000 it had
+        assert_eq!(ext.balance(&from).await.unwrap(), Word::from(7_000u64));
+        assert_eq!(ext.balance(&coinbase).await.unwrap(), Word::from(21_000u64));
+
+        // Short of the gas used as well: no more than the balance is paid out
+        ext.account_mut(&from).value = Word::from(10_000u64);
+        let charge = market.charge(&from, 100_000, &mut ext).await.unwrap();
+        let fees = market
+            .settle(
+                &from,
+                &coinbase,
+                21_000,
+                charge,
+                &mut evm,
+                &mut ext,
+                &mut tracer,
+            )
+            .await
+            .unwrap();
+        assert!(ext.balance(&from).await.unwrap().is_zero());
+        assert_eq!(ext.balance(&coinbase).await.unwrap(), Word::from(31_000u64));
+        assert_eq!(fees.tipped, Word::from(10_000u64));
+        assert!(fees.burned.is_zero());
+    }
+
+    #[tokio::test]
+    async fn test_settle_short_of_blob_fee() {
+        let from = Address::from([1u8; 20]);
+        let coinbase = Address::from([2u8; 20]);
+        let mut ext = Ext::local();
+        ext.state.insert(
+            from,
+            Account {
+                value: Word::from(30_000u64),
+                ..Account::default()
+            },
+        );
+        ext.state.insert(coinbase, Account::default());
+        let market = FeeMarket {
+            price: Word::from(3u64),
+            base_fee: Word::from(2u64),
+            blob_gas: 10_000,
+            blob_price: Word::from(2u64),
+        };
+
+        let charge = market.charge(&from, 100_000, &mut ext).await.unwrap();
+        let mut evm = Evm::default();
+        let mut tracer = crate::tracer::NoopTracer;
+        let fees = market
+            .settle(
+                &from,
+                &coinbase,
+                21_000,
+                charge,
+                &mut evm,
+                &mut ext,
+                &mut tracer,
+            )
+            .await
+            .unwrap();
+        // 20_000 for the blobs, the remaining 10_000 pays for the gas
+        assert_eq!(fees.blob_burned, Word::from(20_000u64));
+        assert_eq!(fees.tipped, Word::from(10_000u64));
+        assert!(fees.burned.is_zero());
+        assert_eq!(
+            fees.burned + fees.tipped + fees.blob_burned,
+            charge.debited - ext.balance(&from).await.unwrap()
+        );
+    }
+}
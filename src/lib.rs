@@ -1,12 +1,29 @@
+pub mod access_list;
 pub mod allocator;
+pub mod annotate;
+pub mod artifact;
+pub mod coverage;
 pub mod decoder;
+pub mod delegation;
+pub mod eof;
+pub mod estimate;
 pub mod eth;
 pub mod executor;
 pub mod ext;
+pub mod fees;
+pub mod limits;
 pub mod opcodes;
+pub mod parallel;
 pub mod precompiles;
+pub mod prestate;
+// Wall-clock timings: `Instant::now` panics on wasm32
+#[cfg(not(target_arch = "wasm32"))]
+pub mod profiler;
+pub mod session;
 pub mod solenoid;
+pub mod spec;
 pub mod tracer;
+pub mod validation;
 
 pub mod common {
     pub use evm_common::*;
@@ -0,0 +1,62 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Resource limits of the execution. Defaults are the mainnet consensus
+/// values: L2s and devnets may allow larger code, simulations of untrusted
+/// input may need a tighter memory and step budget.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionLimits {
+    /// Maximal stack height. EOF code is validated against the 1024 of
+    /// mainnet regardless.
+    pub stack: usize,
+    /// Maximal depth of nested calls and creates
+    pub call_depth: usize,
+    /// EIP-170: maximal size of the deployed code
+    pub code_size: usize,
+    /// EIP-3860: maximal size of the initcode
+    pub initcode_size: usize,
+    /// Maximal memory of a call frame in bytes. Not a consensus rule: the
+    /// memory expansion cost makes large allocations impractical anyway.
+    pub memory: usize,
+    /// Maximal number of instructions executed by the transaction across all
+    /// its frames. Every frame halts once the budget is exhausted.
+    pub steps: Option<u64>,
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        Self {
+            stack: 1024,
+            call_depth: 1024,
+            code_size: 0x6000,
+            initcode_size: 2 * 0x6000,
+            // 2 MB: opinionated allocation sanity check limit
+            memory: 2 * 1024 * 1024,
+            steps: None,
+        }
+    }
+}
+
+/// Stops a running execution from another task or thread: every frame halts
+/// with [`evm_event::HaltReason::Cancelled`] on its next step. Clones share
+/// the same flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
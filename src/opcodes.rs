@@ -23,10 +23,93 @@ impl Opcode {
             self.n as usize
         }
     }
+
+    /// Constant gas cost charged once per basic block (see `decoder::Block`).
+    /// Opcodes with dynamic costs (memory expansion, account access etc.) and
+    /// the ones observing the remaining gas charge everything on execution.
+    pub fn static_gas(&self) -> i64 {
+        match self.code {
+            0x5b => 1,
+            0x30 | 0x32..=0x34 | 0x36 | 0x38 | 0x3a | 0x3d | 0x41..=0x46 | 0x48 | 0x4a => 2,
+            0x50 | 0x58 | 0x59 | 0x5f => 2,
+            0x01 | 0x03 | 0x10..=0x1d | 0x35 | 0x49 | 0x60..=0x9f => 3,
+            0x02 | 0x04..=0x07 | 0x0b | 0x1e | 0x47 => 5,
+            0x08 | 0x09 | 0x56 => 8,
+            0x57 => 10,
+            0x40 => 20,
+            0x5c | 0x5d => 100,
+            // EOF
+            0xd2 | 0xe0 => 2,
+            0xd1 | 0xe4 | 0xe6..=0xe8 | 0xf7 => 3,
+            0xd0 | 0xe1 | 0xe2 => 4,
+            0xe3 | 0xe5 => 5,
+            _ => 0,
+        }
+    }
+
+    /// Number of stack items (taken, put back)
+    pub fn stack_io(&self) -> (usize, usize) {
+        let n = self.n as usize;
+        match self.code {
+            0x60..=0x7f => (0, 1),
+            0x80..=0x8f => (n, n + 1),
+            0x90..=0x9f => (n + 1, n + 1),
+            0xa0..=0xa4 => (n + 2, 0),
+            0x00 | 0x5b | 0xfe => (0, 0),
+            0x30 | 0x32..=0x34 | 0x36 | 0x38 | 0x3a | 0x3d | 0x41..=0x48 | 0x4a => (0, 1),
+            0x58..=0x5a | 0x5f => (0, 1),
+            0x15 | 0x19 | 0x1e | 0x31 | 0x35 | 0x3b | 0x3f | 0x40 | 0x49 => (1, 1),
+            0x51 | 0x54 | 0x5c => (1, 1),
+            0x50 | 0x56 | 0xff => (1, 0),
+            0x01..=0x07 | 0x0a | 0x0b | 0x10..=0x14 | 0x16..=0x18 | 0x1a..=0x1d | 0x20 => (2, 1),
+            0x52 | 0x53 | 0x55 | 0x57 | 0x5d | 0xf3 | 0xfd => (2, 0),
+            0x08 | 0x09 => (3, 1),
+            0x37 | 0x39 | 0x3e | 0x5e => (3, 0),
+            0xf0 => (3, 1),
+            0x3c => (4, 0),
+            0xf5 => (4, 1),
+            0xf4 | 0xfa => (6, 1),
+            0xf1 | 0xf2 => (7, 1),
+            // EOF: stack effects of CALLF, RETF, JUMPF, DUPN, SWAPN and
+            // EXCHANGE depend on the immediates and are checked at deploy time
+            0xd1 | 0xd2 => (0, 1),
+            0xd0 | 0xf7 => (1, 1),
+            0xe1 | 0xe2 => (1, 0),
+            0xee => (2, 0),
+            0xd3 => (3, 0),
+            0xf9 | 0xfb => (3, 1),
+            0xec | 0xf8 => (4, 1),
+            _ => (0, 0),
+        }
+    }
+
+    /// Instruction ends a basic block: it changes the control flow, or its
+    /// semantics depend on the remaining gas (that must not include static
+    /// gas charged in advance for the following instructions).
+    pub fn is_block_end(&self) -> bool {
+        matches!(
+            self.code,
+            0x00 | 0x55 | 0x56 | 0x57 | 0x5a | 0xf0..=0xf5 | 0xfa | 0xfd | 0xfe | 0xff
+        ) || matches!(self.code, 0xe0..=0xe5 | 0xec | 0xee | 0xf8 | 0xf9 | 0xfb)
+    }
+
+    /// Opcode is only defined in EOF code (legacy code treats it as undefined)
+    pub fn is_eof_only(&self) -> bool {
+        matches!(
+            self.code,
+            0xd0..=0xd3 | 0xe0..=0xe8 | 0xec | 0xee | 0xf7 | 0xf8 | 0xf9 | 0xfb
+        )
+    }
 }
 
+const UNDEFINED: Opcode = Opcode {
+    code: 0xfe,
+    name: "undefined",
+    n: 0,
+};
+
 static OPCODES: Lazy<[Opcode; 256]> = Lazy::new(|| {
-    let mut table = [Opcode::new(0xfe, "undefined", 0); 256];
+    let mut table = [UNDEFINED; 256];
 
     // 0s: Stop and Arithmetic Operations
     table[0x00] = Opcode::new(0x00, "STOP", 0);
@@ -131,6 +214,25 @@ static OPCODES: Lazy<[Opcode; 256]> = Lazy::new(|| {
         table[0xa0 + i] = Opcode::new(0xa0 + i as u8, "LOG_", i as u8);
     }
 
+    // EOF: data section access
+    table[0xd0] = Opcode::new(0xd0, "DATALOAD", 0);
+    table[0xd1] = Opcode::new(0xd1, "DATALOADN", 0);
+    table[0xd2] = Opcode::new(0xd2, "DATASIZE", 0);
+    table[0xd3] = Opcode::new(0xd3, "DATACOPY", 0);
+
+    // EOF: control flow and stack operations
+    table[0xe0] = Opcode::new(0xe0, "RJUMP", 0);
+    table[0xe1] = Opcode::new(0xe1, "RJUMPI", 0);
+    table[0xe2] = Opcode::new(0xe2, "RJUMPV", 0);
+    table[0xe3] = Opcode::new(0xe3, "CALLF", 0);
+    table[0xe4] = Opcode::new(0xe4, "RETF", 0);
+    table[0xe5] = Opcode::new(0xe5, "JUMPF", 0);
+    table[0xe6] = Opcode::new(0xe6, "DUPN", 0);
+    table[0xe7] = Opcode::new(0xe7, "SWAPN", 0);
+    table[0xe8] = Opcode::new(0xe8, "EXCHANGE", 0);
+    table[0xec] = Opcode::new(0xec, "EOFCREATE", 0);
+    table[0xee] = Opcode::new(0xee, "RETURNCONTRACT", 0);
+
     // System operations
     table[0xf0] = Opcode::new(0xf0, "CREATE", 0);
     table[0xf1] = Opcode::new(0xf1, "CALL", 0);
@@ -138,7 +240,11 @@ static OPCODES: Lazy<[Opcode; 256]> = Lazy::new(|| {
     table[0xf3] = Opcode::new(0xf3, "RETURN", 0);
     table[0xf4] = Opcode::new(0xf4, "DELEGATECALL", 0);
     table[0xf5] = Opcode::new(0xf5, "CREATE2", 0);
+    table[0xf7] = Opcode::new(0xf7, "RETURNDATALOAD", 0);
+    table[0xf8] = Opcode::new(0xf8, "EXTCALL", 0);
+    table[0xf9] = Opcode::new(0xf9, "EXTDELEGATECALL", 0);
     table[0xfa] = Opcode::new(0xfa, "STATICCALL", 0);
+    table[0xfb] = Opcode::new(0xfb, "EXTSTATICCALL", 0);
     table[0xfd] = Opcode::new(0xfd, "REVERT", 0);
     table[0xfe] = Opcode::new(0xfe, "INVALID", 0);
     table[0xff] = Opcode::new(0xff, "SELFDESTRUCT", 0);
@@ -150,6 +256,16 @@ pub fn get_opcode(value: u8) -> Opcode {
     OPCODES[value as usize]
 }
 
+/// Opcode as seen by legacy code, where EOF-only opcodes are undefined
+pub fn get_legacy_opcode(value: u8) -> Opcode {
+    let opcode = OPCODES[value as usize];
+    if opcode.is_eof_only() {
+        UNDEFINED
+    } else {
+        opcode
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,268 @@
+use std::{collections::HashSet, future::Future, sync::Arc};
+
+use evm_common::{address::Address, word::Word};
+use evm_event::{AccountEvent, EventData};
+
+use crate::{
+    executor::AccountTouch,
+    ext::{Accesses, Account, Ext, Locations, Snapshot},
+    solenoid::CallResult,
+    tracer::EventTracer,
+};
+
+/// Transaction executed as part of a block
+pub struct Executed<T: EventTracer> {
+    pub result: CallResult<T>,
+    /// State right after the transaction
+    pub state: Snapshot,
+}
+
+// Shared state changed by the transactions committed in a round
+#[derive(Default)]
+struct Written {
+    accounts: HashSet<Address>,
+    storage: HashSet<(Address, Word)>,
+    // Accounts deleted with their storage
+    cleared: HashSet<Address>,
+}
+
+impl Written {
+    fn conflicts(&self, reads: &Locations) -> bool {
+        reads
+            .accounts
+            .iter()
+            .any(|addr| self.accounts.contains(addr) || self.cleared.contains(addr))
+            || reads
+                .storage
+                .iter()
+                .any(|slot| self.storage.contains(slot) || self.cleared.contains(&slot.0))
+    }
+}
+
+/// Execute the transactions of a block in parallel, with results identical
+/// to sequential execution (Block-STM style).
+///
+/// Every transaction is first executed speculatively and concurrently on
+/// the state before the block, with its reads recorded. They are then
+/// committed in order, as long as none of them read state written by the
+/// ones committed since the snapshot it ran on. A conflicting transaction is
+/// executed again on the committed state, which it cannot conflict with; the
+/// results of the others are kept.
+///
+/// `run` executes the transaction at the index on the `Ext` and gives it
+/// back. It is called like sequential execution would: with
+/// [`Ext::reset`] first. The fee credit of the coinbase is deferred: it does
+/// not conflict unless the transaction reads the coinbase balance, the
+/// `FeePay` touch and `SetValue` event of the coinbase are rebased on the
+/// committed balance.
+pub async fn execute<T, F, Fut>(
+    snapshot: Snapshot,
+    coinbase: Address,
+    count: usize,
+    run: F,
+) -> eyre::Result<Vec<Executed<T>>>
+where
+    T: EventTracer + Send + 'static,
+    F: Fn(usize, Ext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = eyre::Result<(CallResult<T>, Ext)>> + Send + 'static,
+{
+    let run = Arc::new(run);
+    let spawn = |index: usize, snapshot: &Snapshot| {
+        let run = run.clone();
+        let mut ext = snapshot.ext();
+        ext.track_accesses();
+        tokio::spawn(async move { run(index, ext).await })
+    };
+
+    // Speculative executions with the number of transactions committed
+    // before the snapshot they run on
+    let mut tasks = (0..count)
+        .map(|index| Some((0, spawn(index, &snapshot))))
+        .collect::<Vec<_>>();
+    let mut committed = snapshot.ext();
+    // State written by every committed transaction
+    let mut written: Vec<Written> = Vec::with_capacity(count);
+    let mut executed: Vec<Executed<T>> = Vec::with_capacity(count);
+    for index in 0..count {
+        loop {
+            let (base, task) = tasks[index].take().expect("one task per transaction");
+            // An error of a speculative execution can come from an
+            // inconsistent state: it only counts on the committed one
+            let done = match task.await? {
+                Ok(done) => Some(done),
+                Err(e) if base == index => {
+                    tasks.iter().flatten().for_each(|(_, task)| task.abort());
+                    return Err(e);
+                }
+                Err(_) => None,
+            };
+            let valid = done.and_then(|(result, mut ext)| {
+                let accesses = ext.take_accesses().unwrap_or_default();
+                let conflicts = written[base..]
+                    .iter()
+                    .any(|written| written.conflicts(&accesses.reads));
+                (!conflicts).then_some((result, ext, accesses))
+            });
+            let Some((result, ext, accesses)) = valid else {
+                tasks[index] = Some((index, spawn(index, &committed.snapshot())));
+                continue;
+            };
+            let mut writes = Written::default();
+            let result =
+                commit(&mut committed, ext, accesses, result, coinbase, &mut writes).await?;
+            written.push(writes);
+            executed.push(Executed {
+                result,
+                state: committed.snapshot(),
+            });
+            break;
+        }
+    }
+    Ok(executed)
+}
+
+// Apply the changes of the transaction to the committed state
+async fn commit<T: EventTracer>(
+    committed: &mut Ext,
+    ext: Ext,
+    accesses: Accesses,
+    mut result: CallResult<T>,
+    coinbase: Address,
+    written: &mut Written,
+) -> eyre::Result<CallResult<T>> {
+    // SELFDESTRUCTs of the previous transaction take effect, see `Ext::reset`
+    for addr in std::mem::take(&mut committed.destroyed_accounts) {
+        committed.state.insert(addr, Account::deleted());
+    }
+
+    let Accesses {
+        reads,
+        writes,
+        credits,
+    } = accesses;
+    // State fetched by the transaction is the same for all of them
+    for (addr, account) in &ext.state {
+        if !committed.state.contains_key(addr) && committed.base(addr).is_none() {
+            committed.state.insert(*addr, account.clone());
+            continue;
+        }
+        let base = ext.base(addr);
+        let mut fetched = account
+            .state
+            .iter()
+            .filter(|(key, _)| base.is_none_or(|base| !base.state.contains_key(key)))
+            .peekable();
+        if fetched.peek().is_none() {
+            continue;
+        }
+        let target = committed.owned(addr);
+        for (key, val) in fetched {
+            target.state.entry(*key).or_insert(*val);
+        }
+    }
+    for addr in &writes.accounts {
+        let Some(account) = ext.state.get(addr) else {
+            continue;
+        };
+        committed.pull(addr).await?;
+        let target = committed.account_mut(addr);
+        target.value = account.value;
+        target.nonce = account.nonce;
+        target.code = account.code.clone();
+    }
+    for (addr, key) in &writes.storage {
+        if let Some(val) = ext
+            .state
+            .get(addr)
+            .and_then(|account| account.state.get(key))
+        {
+            committed.pull(addr).await?;
+            committed.put(addr, *key, *val).await?;
+        }
+    }
+
+    // The coinbase is credited the fee by every transaction: the credit
+    // only conflicts with transactions reading its balance
+    let mut accounts = writes.accounts;
+    for (addr, credit) in credits {
+        accounts.insert(addr);
+        if addr == coinbase && !reads.accounts.contains(&coinbase) {
+            let current = committed.credit(&coinbase, credit).await?;
+            rebase(&mut result, &coinbase, current);
+        } else if let Some(account) = ext.state.get(&addr) {
+            committed.pull(&addr).await?;
+            committed.account_mut(&addr).value = account.value;
+        }
+    }
+
+    written.accounts.extend(accounts);
+    written.storage.extend(writes.storage);
+    written
+        .cleared
+        .extend(ext.destroyed_accounts.iter().copied());
+    committed.destroyed_accounts = ext.destroyed_accounts;
+    Ok(result)
+}
+
+// Rebase the fee payment of the coinbase on its committed balance
+fn rebase<T: EventTracer>(result: &mut CallResult<T>, coinbase: &Address, current: Word) {
+    for touch in result.evm.touches.iter_mut() {
+        if let AccountTouch::FeePay(addr, val, new) = touch
+            && addr == coinbase
+        {
+            *new = current + (*new - *val);
+            *val = current;
+        }
+    }
+    if T::TRACE.events {
+        for mut event in result.tracer.take() {
+            if let EventData::Account(AccountEvent::SetValue { address, val, new }) =
+                &mut event.data
+                && address == coinbase
+            {
+                *new = current + (*new - *val);
+                *val = current;
+            }
+            result.tracer.push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{executor::Evm, solenoid::GasResult, tracer::NoopTracer};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_commit_fetched_storage() -> eyre::Result<()> {
+        let a = Address::from([0xaa; 20]);
+        let key = Word::one();
+        let mut committed = Ext::local();
+        committed.state.insert(a, Account::default());
+        let snapshot = committed.snapshot();
+
+        // Fetched by the transaction, with the account in the base layer
+        let mut ext = snapshot.ext();
+        ext.owned(&a).state.insert(key, Word::from(7u64));
+        let result = CallResult {
+            evm: Evm::default(),
+            ret: vec![],
+            tracer: NoopTracer,
+            gas: GasResult::default(),
+        };
+        let mut written = Written::default();
+        commit(
+            &mut committed,
+            ext,
+            Accesses::default(),
+            result,
+            Address::zero(),
+            &mut written,
+        )
+        .await?;
+        assert_eq!(committed.state[&a].state[&key], Word::from(7u64));
+        assert!(written.storage.is_empty());
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
+use std::{collections::HashMap, sync::Arc};
+
 use eyre::{Result, eyre};
+use futures::future::{self, BoxFuture};
 
 use ark_bn254::{Bn254, Fq, Fq2, G1Affine, G1Projective, G2Affine};
 use ark_ec::{AffineRepr, CurveGroup, pairing::Pairing};
@@ -12,23 +15,32 @@ use tiny_keccak::{Hasher, Keccak};
 
 use evm_common::address::Address;
 
+use crate::{ext::Ext, spec::Spec};
+
 // EIP-7823: max allowed byte length for each MODEXP input field
 const MODEXP_MAX_INPUT_LEN: usize = 1024;
 
-pub fn is_precompile(address: &Address) -> bool {
+pub fn is_precompile(address: &Address, spec: Spec) -> bool {
     let b = address.0;
-    // 0x01–0x0A: standard precompiles (bytes 0..19 all zero, last byte 1–10)
-    if b[0..19] == [0u8; 19] && (1..=10).contains(&b[19]) {
+    // 0x01–0x09: standard precompiles (bytes 0..19 all zero, last byte 1–9)
+    if b[0..19] == [0u8; 19] && (1..=9).contains(&b[19]) {
         return true;
     }
-    // 0x100: P256VERIFY (EIP-7951) — bytes 0..18 zero, byte 18 = 0x01, byte 19 = 0x00
+    // 0x0A: KZG point evaluation (EIP-4844, Cancun)
+    if b[0..19] == [0u8; 19] && b[19] == 10 {
+        return spec.is_enabled(Spec::Cancun);
+    }
+    // 0x100: P256VERIFY (EIP-7951, Osaka) — bytes 0..18 zero, byte 18 = 0x01, byte 19 = 0x00
     if b[0..18] == [0u8; 18] && b[18] == 0x01 && b[19] == 0x00 {
-        return true;
+        return spec.is_enabled(Spec::Osaka);
     }
     false
 }
 
-pub fn execute(address: &Address, input: &[u8]) -> eyre::Result<Vec<u8>> {
+pub fn execute(address: &Address, input: &[u8], spec: Spec) -> eyre::Result<Vec<u8>> {
+    if !is_precompile(address, spec) {
+        eyre::bail!("Invalid precompile address");
+    }
     let b = address.0;
     if b[0..18] == [0u8; 18] && b[18] == 0x01 && b[19] == 0x00 {
         return p256verify(input);
@@ -38,7 +50,7 @@ pub fn execute(address: &Address, input: &[u8]) -> eyre::Result<Vec<u8>> {
         2 => sha256(input),
         3 => ripemd160(input),
         4 => identity(input),
-        5 => modexp(input),
+        5 => modexp(input, spec),
         6 => bn128_add(input),
         7 => bn128_mul(input),
         8 => bn128_pairing(input),
@@ -48,7 +60,7 @@ pub fn execute(address: &Address, input: &[u8]) -> eyre::Result<Vec<u8>> {
     }
 }
 
-pub fn gas_cost(address: &Address, input: &[u8]) -> i64 {
+pub fn gas_cost(address: &Address, input: &[u8], spec: Spec) -> i64 {
     let b = address.0;
     if b[0..18] == [0u8; 18] && b[18] == 0x01 && b[19] == 0x00 {
         return 6900; // P256VERIFY (EIP-7951)
@@ -58,7 +70,7 @@ pub fn gas_cost(address: &Address, input: &[u8]) -> i64 {
         2 => 60 + 12 * input.len().div_ceil(32) as u64,   // sha256
         3 => 600 + 120 * input.len().div_ceil(32) as u64, // ripemd160
         4 => 15 + 3 * input.len().div_ceil(32) as u64,    // identity
-        5 => modexp_gas_cost(input),                      // modexp
+        5 => modexp_gas_cost(input, spec),                // modexp
         6 => 150,                                         // bn128_add
         7 => 6000,                                        // bn128_mul
         8 => 45000 + 34000 * (input.len() / 192) as u64,  // bn128_pairing
@@ -68,8 +80,179 @@ pub fn gas_cost(address: &Address, input: &[u8]) -> i64 {
     }) as i64
 }
 
+/// A contract implemented natively by the client. Stateful precompiles get
+/// access to the state through `ext`: like storage written by contracts,
+/// their writes through the `Ext` setters (`set_balance`, `put`, ...) are
+/// reverted with the calling frame.
+pub trait Precompile: Send + Sync {
+    /// Gas charged for the call with the given input
+    fn gas_cost(&self, input: &[u8], spec: Spec) -> i64;
+
+    /// Output of the call, an error makes the call fail
+    fn execute<'a>(
+        &'a self,
+        input: &'a [u8],
+        spec: Spec,
+        ext: &'a mut Ext,
+    ) -> BoxFuture<'a, Result<Vec<u8>>>;
+}
+
+/// One of the precompiles defined by the spec, see [`execute`]
+pub struct Standard(pub Address);
+
+impl Precompile for Standard {
+    fn gas_cost(&self, input: &[u8], spec: Spec) -> i64 {
+        gas_cost(&self.0, input, spec)
+    }
+
+    fn execute<'a>(
+        &'a self,
+        input: &'a [u8],
+        spec: Spec,
+        _ext: &'a mut Ext,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(future::ready(execute(&self.0, input, spec)))
+    }
+}
+
+/// A precompile with the gas cost replaced, see [`Precompiles::reprice`]
+struct Repriced<F> {
+    inner: Arc<dyn Precompile>,
+    gas_cost: F,
+}
+
+impl<F: Fn(&[u8], Spec) -> i64 + Send + Sync> Precompile for Repriced<F> {
+    fn gas_cost(&self, input: &[u8], spec: Spec) -> i64 {
+        (self.gas_cost)(input, spec)
+    }
+
+    fn execute<'a>(
+        &'a self,
+        input: &'a [u8],
+        spec: Spec,
+        ext: &'a mut Ext,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        self.inner.execute(input, spec, ext)
+    }
+}
+
+#[derive(Clone)]
+struct Entry {
+    // First spec the precompile is active in, `None` if always active
+    since: Option<Spec>,
+    precompile: Arc<dyn Precompile>,
+}
+
+/// Precompiles available to the executor, by address. Defaults to the
+/// standard precompiles, each active from the spec that introduced it.
+#[derive(Clone)]
+pub struct Precompiles {
+    entries: HashMap<Address, Entry>,
+}
+
+impl Default for Precompiles {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl Precompiles {
+    /// No precompiles at all
+    pub fn empty() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Precompiles defined by the spec: 0x01-0x09, KZG point evaluation
+    /// (0x0a) since Cancun and P256VERIFY (0x100) since Osaka
+    pub fn standard() -> Self {
+        let mut entries = HashMap::new();
+        let mut add = |address: Address, since: Option<Spec>| {
+            let precompile = Arc::new(Standard(address));
+            entries.insert(address, Entry { since, precompile });
+        };
+        for i in 1..=9u8 {
+            add(short_address(i as u16), None);
+        }
+        add(short_address(0x0a), Some(Spec::Cancun));
+        add(short_address(0x100), Some(Spec::Osaka));
+        Self { entries }
+    }
+
+    /// Add a precompile active in every spec, replacing the existing one
+    pub fn insert(&mut self, address: Address, precompile: impl Precompile + 'static) {
+        let entry = Entry {
+            since: None,
+            precompile: Arc::new(precompile),
+        };
+        self.entries.insert(address, entry);
+    }
+
+    /// Disable the precompile: the address becomes a regular account
+    pub fn remove(&mut self, address: &Address) -> bool {
+        self.entries.remove(address).is_some()
+    }
+
+    /// Override the gas cost of the precompile, keeping its behavior
+    pub fn reprice(
+        &mut self,
+        address: &Address,
+        gas_cost: impl Fn(&[u8], Spec) -> i64 + Send + Sync + 'static,
+    ) -> bool {
+        let Some(entry) = self.entries.get_mut(address) else {
+            return false;
+        };
+        entry.precompile = Arc::new(Repriced {
+            inner: entry.precompile.clone(),
+            gas_cost,
+        });
+        true
+    }
+
+    pub fn with(mut self, address: Address, precompile: impl Precompile + 'static) -> Self {
+        self.insert(address, precompile);
+        self
+    }
+
+    pub fn without(mut self, address: &Address) -> Self {
+        self.remove(address);
+        self
+    }
+
+    pub fn contains(&self, address: &Address, spec: Spec) -> bool {
+        self.get(address, spec).is_some()
+    }
+
+    /// Precompile at the address, if it is active in the spec
+    pub fn get(&self, address: &Address, spec: Spec) -> Option<Arc<dyn Precompile>> {
+        self.entries
+            .get(address)
+            .filter(|entry| entry.since.is_none_or(|since| spec.is_enabled(since)))
+            .map(|entry| entry.precompile.clone())
+    }
+
+    /// Addresses of the precompiles active in the spec
+    pub fn addresses(&self, spec: Spec) -> Vec<Address> {
+        let mut addresses: Vec<_> = self
+            .entries
+            .keys()
+            .filter(|address| self.contains(address, spec))
+            .copied()
+            .collect();
+        addresses.sort();
+        addresses
+    }
+}
+
+fn short_address(n: u16) -> Address {
+    let mut bytes = [0u8; 20];
+    bytes[18..].copy_from_slice(&n.to_be_bytes());
+    Address(bytes)
+}
+
 // 0x01: ECRecover - ECDSA signature recovery
-fn ecrecover(input: &[u8]) -> eyre::Result<Vec<u8>> {
+pub(crate) fn ecrecover(input: &[u8]) -> eyre::Result<Vec<u8>> {
     if input.len() != 128 {
         return Ok(vec![0u8; 32]); // Return zero address on invalid input
     }
@@ -224,7 +407,7 @@ fn p256verify(input: &[u8]) -> eyre::Result<Vec<u8>> {
 }
 
 // 0x05: Modular exponentiation
-fn modexp(input: &[u8]) -> eyre::Result<Vec<u8>> {
+fn modexp(input: &[u8], spec: Spec) -> eyre::Result<Vec<u8>> {
     if input.len() < 96 {
         return Ok(vec![]);
     }
@@ -239,10 +422,11 @@ fn modexp(input: &[u8]) -> eyre::Result<Vec<u8>> {
         .try_into()
         .unwrap_or(0usize);
 
-    // EIP-7823: each field must not exceed MODEXP_MAX_INPUT_LEN bytes
-    if base_len > MODEXP_MAX_INPUT_LEN
-        || exp_len > MODEXP_MAX_INPUT_LEN
-        || mod_len > MODEXP_MAX_INPUT_LEN
+    // EIP-7823 (Osaka): each field must not exceed MODEXP_MAX_INPUT_LEN bytes
+    if spec.is_enabled(Spec::Osaka)
+        && (base_len > MODEXP_MAX_INPUT_LEN
+            || exp_len > MODEXP_MAX_INPUT_LEN
+            || mod_len > MODEXP_MAX_INPUT_LEN)
     {
         eyre::bail!("MODEXP input exceeds EIP-7823 size limit");
     }
@@ -273,12 +457,14 @@ fn modexp(input: &[u8]) -> eyre::Result<Vec<u8>> {
     Ok(result_bytes)
 }
 
-fn modexp_gas_cost(input: &[u8]) -> u64 {
-    // EIP-7883: minimum cost raised to 500
-    const MIN_GAS: u64 = 500;
+fn modexp_gas_cost(input: &[u8], spec: Spec) -> u64 {
+    // EIP-7883 (Osaka): minimum cost raised to 500 (was 200 in EIP-2565),
+    // large-exponent multiplier raised to 16 (was 8), no division by 3
+    let is_osaka = spec.is_enabled(Spec::Osaka);
+    let (min_gas, multiplier, divisor) = if is_osaka { (500, 16, 1) } else { (200, 8, 3) };
 
     if input.len() < 96 {
-        return MIN_GAS;
+        return min_gas;
     }
 
     let base_len = BigUint::from_bytes_be(&input[0..32])
@@ -292,9 +478,10 @@ fn modexp_gas_cost(input: &[u8]) -> u64 {
         .unwrap_or(0u64);
 
     // EIP-7823: reject oversized inputs (consume all gas — caller handles that)
-    if base_len > MODEXP_MAX_INPUT_LEN as u64
-        || exp_len > MODEXP_MAX_INPUT_LEN as u64
-        || mod_len > MODEXP_MAX_INPUT_LEN as u64
+    if is_osaka
+        && (base_len > MODEXP_MAX_INPUT_LEN as u64
+            || exp_len > MODEXP_MAX_INPUT_LEN as u64
+            || mod_len > MODEXP_MAX_INPUT_LEN as u64)
     {
         return u64::MAX;
     }
@@ -337,18 +524,16 @@ fn modexp_gas_cost(input: &[u8]) -> u64 {
                         break;
                     }
                 }
-                // EIP-7883: multiplier is 16 (was 8 in EIP-2565)
-                16 * (exp_len - 32) + bit_len.saturating_sub(1)
+                multiplier * (exp_len - 32) + bit_len.saturating_sub(1)
             } else {
-                16 * (exp_len - 32)
+                multiplier * (exp_len - 32)
             }
         } else {
             1
         };
 
-    // EIP-7883: no division by 3; minimum raised to 500
-    let gas = multiplication_complexity * iteration_count.max(1);
-    gas.max(MIN_GAS)
+    let gas = multiplication_complexity * iteration_count.max(1) / divisor;
+    gas.max(min_gas)
 }
 
 // 0x06: BN128 elliptic curve point addition
@@ -795,6 +980,37 @@ mod tests {
         hex::decode(hex_str.replace("0x", "").replace(" ", "")).unwrap()
     }
 
+    #[test]
+    fn test_registry() {
+        let kzg = short_address(0x0a);
+        let p256 = short_address(0x100);
+        let standard = Precompiles::standard();
+        for spec in [Spec::Shanghai, Spec::Cancun, Spec::Prague, Spec::Osaka] {
+            for i in 1..=0x200 {
+                let address = short_address(i);
+                assert_eq!(
+                    standard.contains(&address, spec),
+                    is_precompile(&address, spec),
+                    "{address} in {spec:?}"
+                );
+            }
+        }
+        assert_eq!(standard.addresses(Spec::Shanghai).len(), 9);
+        assert_eq!(standard.addresses(Spec::Osaka).last(), Some(&p256));
+
+        let custom = short_address(0xff01);
+        let precompiles = standard.without(&kzg).with(custom, Standard(custom));
+        assert!(!precompiles.contains(&kzg, Spec::Osaka));
+        assert!(precompiles.contains(&custom, Spec::Shanghai));
+
+        let mut precompiles = precompiles;
+        assert!(precompiles.reprice(&p256, |_, _| 3450));
+        assert!(!precompiles.reprice(&kzg, |_, _| 0));
+        let p256verify = precompiles.get(&p256, Spec::Osaka).unwrap();
+        assert_eq!(p256verify.gas_cost(&[], Spec::Osaka), 3450);
+        assert!(precompiles.get(&p256, Spec::Prague).is_none());
+    }
+
     #[test]
     fn test_is_precompile() {
         // Test valid precompile addresses
@@ -803,7 +1019,7 @@ mod tests {
             addr_bytes[19] = i;
             let address = Address(addr_bytes);
             assert!(
-                is_precompile(&address),
+                is_precompile(&address, Spec::Osaka),
                 "Address 0x{:02x} should be a precompile",
                 i
             );
@@ -814,7 +1030,7 @@ mod tests {
         addr_bytes[18] = 0x01;
         addr_bytes[19] = 0x00;
         assert!(
-            is_precompile(&Address(addr_bytes)),
+            is_precompile(&Address(addr_bytes), Spec::Osaka),
             "Address 0x100 should be a precompile (P256VERIFY)"
         );
 
@@ -822,13 +1038,13 @@ mod tests {
         let mut addr_bytes = [0u8; 20];
         addr_bytes[19] = 0;
         assert!(
-            !is_precompile(&Address(addr_bytes)),
+            !is_precompile(&Address(addr_bytes), Spec::Osaka),
             "Address 0x00 should not be a precompile"
         );
 
         addr_bytes[19] = 11;
         assert!(
-            !is_precompile(&Address(addr_bytes)),
+            !is_precompile(&Address(addr_bytes), Spec::Osaka),
             "Address 0x0B should not be a precompile"
         );
 
@@ -836,11 +1052,25 @@ mod tests {
         addr_bytes[0] = 1;
         addr_bytes[19] = 1;
         assert!(
-            !is_precompile(&Address(addr_bytes)),
+            !is_precompile(&Address(addr_bytes), Spec::Osaka),
             "Address with non-zero prefix should not be a precompile"
         );
     }
 
+    #[test]
+    fn test_is_precompile_by_spec() {
+        let mut addr_bytes = [0u8; 20];
+        addr_bytes[19] = 10;
+        assert!(!is_precompile(&Address(addr_bytes), Spec::Shanghai));
+        assert!(is_precompile(&Address(addr_bytes), Spec::Cancun));
+
+        let mut addr_bytes = [0u8; 20];
+        addr_bytes[18] = 0x01;
+        assert!(!is_precompile(&Address(addr_bytes), Spec::Prague));
+        assert!(is_precompile(&Address(addr_bytes), Spec::Osaka));
+        assert!(execute(&Address(addr_bytes), &[], Spec::Prague).is_err());
+    }
+
     #[test]
     fn test_gas_cost() {
         let mut addr_bytes = [0u8; 20];
@@ -848,38 +1078,38 @@ mod tests {
         // Test ecrecover gas cost
         addr_bytes[19] = 1;
         let address = Address(addr_bytes);
-        assert_eq!(gas_cost(&address, &[]), 3000);
+        assert_eq!(gas_cost(&address, &[], Spec::Osaka), 3000);
 
         // Test sha256 gas cost
         addr_bytes[19] = 2;
         let address = Address(addr_bytes);
         let input = vec![0u8; 64]; // 64 bytes = 2 words
-        assert_eq!(gas_cost(&address, &input), 60 + 12 * 2);
+        assert_eq!(gas_cost(&address, &input, Spec::Osaka), 60 + 12 * 2);
 
         // Test ripemd160 gas cost
         addr_bytes[19] = 3;
         let address = Address(addr_bytes);
-        assert_eq!(gas_cost(&address, &input), 600 + 120 * 2);
+        assert_eq!(gas_cost(&address, &input, Spec::Osaka), 600 + 120 * 2);
 
         // Test identity gas cost
         addr_bytes[19] = 4;
         let address = Address(addr_bytes);
-        assert_eq!(gas_cost(&address, &input), 15 + 3 * 2);
+        assert_eq!(gas_cost(&address, &input, Spec::Osaka), 15 + 3 * 2);
 
         // Test bn128_add gas cost
         addr_bytes[19] = 6;
         let address = Address(addr_bytes);
-        assert_eq!(gas_cost(&address, &[]), 150);
+        assert_eq!(gas_cost(&address, &[], Spec::Osaka), 150);
 
         // Test bn128_mul gas cost
         addr_bytes[19] = 7;
         let address = Address(addr_bytes);
-        assert_eq!(gas_cost(&address, &[]), 6000);
+        assert_eq!(gas_cost(&address, &[], Spec::Osaka), 6000);
 
         // Test kzg_point_evaluation gas cost
         addr_bytes[19] = 10;
         let address = Address(addr_bytes);
-        assert_eq!(gas_cost(&address, &[]), 50000);
+        assert_eq!(gas_cost(&address, &[], Spec::Osaka), 50000);
     }
 
     // 0x01: ECRecover tests
@@ -1053,7 +1283,7 @@ mod tests {
     #[test]
     fn test_modexp_invalid_length() {
         let input = vec![0u8; 50]; // Too short
-        let result = modexp(&input).unwrap();
+        let result = modexp(&input, Spec::Osaka).unwrap();
         assert_eq!(result, Vec::<u8>::new());
     }
 
@@ -1073,7 +1303,7 @@ mod tests {
         input.push(2); // exp = 2
         input.push(5); // mod = 5
 
-        let result = modexp(&input).unwrap();
+        let result = modexp(&input, Spec::Osaka).unwrap();
         assert_eq!(result, vec![4]);
     }
 
@@ -1090,7 +1320,7 @@ mod tests {
         input.push(2); // exp = 2
         input.push(0); // mod = 0
 
-        let result = modexp(&input).unwrap();
+        let result = modexp(&input, Spec::Osaka).unwrap();
         assert_eq!(result, vec![0]); // Should return zero
     }
 
@@ -1098,15 +1328,18 @@ mod tests {
     fn test_modexp_gas_cost() {
         // EIP-7883: minimum cost is now 500
         let input = vec![0u8; 50]; // Too short
-        assert_eq!(modexp_gas_cost(&input), 500);
+        assert_eq!(modexp_gas_cost(&input, Spec::Osaka), 500);
 
         let mut input = vec![0u8; 96];
         input[31] = 1; // base_len = 1
         input[63] = 1; // exp_len = 1
         input[95] = 1; // mod_len = 1
 
-        let cost = modexp_gas_cost(&input);
+        let cost = modexp_gas_cost(&input, Spec::Osaka);
         assert!(cost >= 500);
+
+        // EIP-2565: minimum cost before Osaka is 200
+        assert_eq!(modexp_gas_cost(&input, Spec::Prague), 200);
     }
 
     // 0x06: BN128 Add tests
@@ -1543,14 +1776,14 @@ mod tests {
         addr_bytes[19] = 1;
         let address = Address(addr_bytes);
         let input = vec![0u8; 128];
-        let result = execute(&address, &input);
+        let result = execute(&address, &input, Spec::Osaka);
         assert!(result.is_ok());
 
         // Test sha256 execution
         addr_bytes[19] = 2;
         let address = Address(addr_bytes);
         let input = b"test".to_vec();
-        let result = execute(&address, &input);
+        let result = execute(&address, &input, Spec::Osaka);
         assert!(result.is_ok());
         assert_eq!(result.unwrap().len(), 32);
 
@@ -1558,14 +1791,14 @@ mod tests {
         addr_bytes[19] = 4;
         let address = Address(addr_bytes);
         let input = vec![1, 2, 3, 4, 5];
-        let result = execute(&address, &input);
+        let result = execute(&address, &input, Spec::Osaka);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), input);
 
         // Test invalid precompile
         addr_bytes[19] = 99;
         let address = Address(addr_bytes);
-        let result = execute(&address, &[]);
+        let result = execute(&address, &[], Spec::Osaka);
         assert!(result.is_err());
     }
 
@@ -0,0 +1,214 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use evm_common::{Hex, address::Address, word::Word};
+
+use crate::{executor::AccountTouch, ext::Ext};
+
+/// Options of the geth `prestateTracer` (`tracerConfig`)
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct PrestateConfig {
+    /// Output the changes made by the transaction instead of the state it read
+    #[serde(rename = "diffMode", default)]
+    pub diff_mode: bool,
+}
+
+/// Account in the output of the geth `prestateTracer`
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct AccountState {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<Word>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<Hex>,
+    #[serde(
+        skip_serializing_if = "BTreeMap::is_empty",
+        serialize_with = "serialize_storage"
+    )]
+    pub storage: BTreeMap<Word, Word>,
+}
+
+impl AccountState {
+    fn is_empty(&self) -> bool {
+        self.balance.is_none_or(|balance| balance.is_zero())
+            && self.nonce.is_none()
+            && self.code.is_none()
+    }
+}
+
+pub type State = BTreeMap<Address, AccountState>;
+
+/// Output of the geth `prestateTracer`
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum PrestateTrace {
+    /// Accounts and storage touched by the transaction, as they were before
+    Prestate(State),
+    /// Accounts changed by the transaction: all of their fields before, the
+    /// changed ones after. Deleted accounts are only in `pre`, created ones
+    /// only in `post`.
+    Diff { pre: State, post: State },
+}
+
+// Storage keys and values are 32-byte hashes, not quantities
+fn serialize_storage<S: Serializer>(
+    storage: &BTreeMap<Word, Word>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let hash = |word: &Word| format!("0x{}", hex::encode(word.into_bytes()));
+    serializer.collect_map(storage.iter().map(|(key, val)| (hash(key), hash(val))))
+}
+
+/// Accounts and storage slots touched by a transaction. Reads in reverted
+/// frames count, writes are only kept by the frames that succeeded.
+pub fn touched(touches: &[AccountTouch]) -> BTreeMap<Address, BTreeSet<Word>> {
+    let mut touched: BTreeMap<Address, BTreeSet<Word>> = BTreeMap::new();
+    for touch in touches {
+        match touch {
+            AccountTouch::GetState(address, key, _, _)
+            | AccountTouch::SetState(address, key, _, _, _) => {
+                touched.entry(*address).or_default().insert(*key);
+            }
+            AccountTouch::FeePay(address, _, _)
+            | AccountTouch::GetNonce(address, _)
+            | AccountTouch::GetValue(address, _)
+            | AccountTouch::GetCode(address, _, _)
+            | AccountTouch::SetNonce(address, _, _)
+            | AccountTouch::SetValue(address, _, _)
+            | AccountTouch::Create(address, _, _, _, _)
+            | AccountTouch::SetCode(address, _, _)
+            | AccountTouch::Destroy(address) => {
+                touched.entry(*address).or_default();
+            }
+            AccountTouch::Noop
+            | AccountTouch::WarmUp(_)
+            | AccountTouch::SetTransientState(_, _, _, _) => (),
+        }
+    }
+    touched
+}
+
+/// Trace the state touched by a transaction given the state before (`pre`)
+/// and after it (`post`, with the `created_accounts` and
+/// `destroyed_accounts` of the transaction).
+pub async fn trace(
+    config: PrestateConfig,
+    touches: &[AccountTouch],
+    pre: &mut Ext,
+    post: &mut Ext,
+) -> eyre::Result<PrestateTrace> {
+    let touched = touched(touches);
+    let mut state = State::new();
+    for (addr, keys) in &touched {
+        state.insert(*addr, read(pre, addr, keys).await?);
+    }
+
+    let mut trace = if config.diff_mode {
+        let mut changed = State::new();
+        let mut after = State::new();
+        for (addr, mut account) in state {
+            if post.destroyed_accounts.contains(&addr) {
+                changed.insert(addr, account);
+                continue;
+            }
+            let current = read(post, &addr, &touched[&addr]).await?;
+            let mut diff = AccountState::default();
+            if current.balance != account.balance {
+                diff.balance = current.balance;
+            }
+            if current.nonce != account.nonce {
+                diff.nonce = Some(current.nonce.unwrap_or_default());
+            }
+            if current.code != account.code {
+                diff.code = Some(current.code.unwrap_or_else(|| Hex::from(vec![])));
+            }
+            let mut modified = false;
+            account.storage.retain(|key, val| {
+                let new = current.storage[key];
+                if *val != new {
+                    modified = true;
+                    // Cleared slots are left out
+                    if !new.is_zero() {
+                        diff.storage.insert(*key, new);
+                    }
+                }
+                *val != new && !val.is_zero()
+            });
+            if modified || diff != AccountState::default() {
+                changed.insert(addr, account);
+                after.insert(addr, diff);
+            }
+        }
+        PrestateTrace::Diff {
+            pre: changed,
+            post: after,
+        }
+    } else {
+        PrestateTrace::Prestate(state)
+    };
+
+    // Accounts created by the transaction did not exist before it
+    let (PrestateTrace::Prestate(pre) | PrestateTrace::Diff { pre, .. }) = &mut trace;
+    pre.retain(|addr, account| !post.created_accounts.contains(addr) || !account.is_empty());
+    Ok(trace)
+}
+
+async fn read(ext: &mut Ext, addr: &Address, keys: &BTreeSet<Word>) -> eyre::Result<AccountState> {
+    let Some(account) = ext.try_pull(addr).await? else {
+        return Ok(AccountState {
+            balance: Some(Word::zero()),
+            storage: keys.iter().map(|key| (*key, Word::zero())).collect(),
+            ..AccountState::default()
+        });
+    };
+    let mut state = AccountState {
+        balance: Some(account.value),
+        nonce: (!account.nonce.is_zero()).then(|| account.nonce.as_u64()),
+        code: (!account.code.0.is_empty()).then(|| Hex::from(account.code.0.clone())),
+        storage: BTreeMap::new(),
+    };
+    for key in keys {
+        state.storage.insert(*key, ext.get(addr, key).await?);
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        let account = AccountState {
+            balance: Some(Word::from(255u64)),
+            nonce: Some(1),
+            code: None,
+            storage: BTreeMap::from([(Word::one(), Word::from(2u64))]),
+        };
+        assert_eq!(
+            serde_json::to_value(&account).unwrap(),
+            serde_json::json!({
+                "balance": "0xff",
+                "nonce": 1,
+                "storage": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000001":
+                        "0x0000000000000000000000000000000000000000000000000000000000000002",
+                },
+            })
+        );
+
+        let trace = PrestateTrace::Diff {
+            pre: State::new(),
+            post: State::from([(Address::default(), AccountState::default())]),
+        };
+        assert_eq!(
+            serde_json::to_value(&trace).unwrap(),
+            serde_json::json!({
+                "pre": {},
+                "post": {"0x0000000000000000000000000000000000000000": {}},
+            })
+        );
+    }
+}
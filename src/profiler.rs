@@ -0,0 +1,543 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use serde_json::json;
+
+use evm_common::{address::Address, word::Word};
+use evm_event::{CallType, Event, EventData};
+
+use crate::tracer::{EventTracer, Trace};
+
+// Instructions that enter a child frame: the gas they report includes the gas
+// forwarded to the child, and the time includes the time spent in the child
+const CALL_OPS: [u8; 10] = [
+    0xec, // EOFCREATE
+    0xf0, // CREATE
+    0xf1, // CALL
+    0xf2, // CALLCODE
+    0xf4, // DELEGATECALL
+    0xf5, // CREATE2
+    0xf8, // EXTCALL
+    0xf9, // EXTDELEGATECALL
+    0xfa, // STATICCALL
+    0xfb, // EXTSTATICCALL
+];
+
+/// Gas and wall time spent, exclusive of nested calls
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    /// Number of executed instructions, or of calls for contracts and functions
+    pub count: u64,
+    pub gas: i64,
+    pub time: Duration,
+}
+
+impl Stats {
+    fn add(&mut self, other: &Stats) {
+        self.count += other.count;
+        self.gas += other.gas;
+        self.time += other.time;
+    }
+}
+
+/// Quantity used as the weight of the folded stacks
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Weight {
+    Gas,
+    /// Wall time in nanoseconds
+    Time,
+}
+
+/// Call frame of the profile. Frames are keyed by the `to` address of the
+/// call: code run by DELEGATECALL is attributed to the calling contract.
+#[derive(Clone, Debug)]
+pub struct CallNode {
+    pub address: Address,
+    /// Function selector: first 4 bytes of the calldata
+    pub selector: Option<[u8; 4]>,
+    pub call_type: CallType,
+    pub ok: bool,
+    /// Gas used by the frame, including nested calls
+    pub gas: i64,
+    /// Start of the frame, relative to the start of the profile
+    pub start: Duration,
+    /// Wall time of the frame, including nested calls
+    pub duration: Duration,
+    /// Gas and time of the frame itself, by opcode name
+    pub opcodes: BTreeMap<String, Stats>,
+    pub children: Vec<CallNode>,
+}
+
+impl CallNode {
+    /// Gas and time of the frame itself, exclusive of nested calls
+    pub fn own(&self) -> Stats {
+        let mut stats = Stats {
+            count: 1,
+            ..Stats::default()
+        };
+        for op in self.opcodes.values() {
+            stats.gas += op.gas;
+            stats.time += op.time;
+        }
+        stats
+    }
+
+    /// Frame name in the exported profiles: address and function selector
+    pub fn label(&self) -> String {
+        match self.selector {
+            Some(selector) => format!("{}:0x{}", self.address, hex::encode(selector)),
+            None => self.address.to_string(),
+        }
+    }
+}
+
+/// Gas and wall time aggregated per call frame, contract, function, pc and
+/// opcode. See [`ProfilingTracer`].
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    /// Outermost call frames, usually one per transaction
+    pub calls: Vec<CallNode>,
+    pub contracts: BTreeMap<Address, Stats>,
+    pub functions: BTreeMap<(Address, Option<[u8; 4]>), Stats>,
+    pub pcs: BTreeMap<(Address, usize), Stats>,
+    pub opcodes: BTreeMap<String, Stats>,
+}
+
+impl Profile {
+    /// Gas and time of the instructions of the contract in the pc range
+    pub fn pc_range(&self, address: &Address, range: Range<usize>) -> Stats {
+        let mut stats = Stats::default();
+        for (_, pc_stats) in self
+            .pcs
+            .range((*address, range.start)..(*address, range.end))
+        {
+            stats.add(pc_stats);
+        }
+        stats
+    }
+
+    /// Folded stacks, one line per call path and opcode, as consumed by
+    /// flamegraph tools (e.g. `inferno-flamegraph`, `flamegraph.pl`)
+    pub fn folded(&self, weight: Weight) -> String {
+        fn walk(node: &CallNode, path: &str, weight: Weight, out: &mut String) {
+            let path = if path.is_empty() {
+                node.label()
+            } else {
+                format!("{path};{}", node.label())
+            };
+            let value = |stats: &Stats| match weight {
+                Weight::Gas => stats.gas.max(0) as u128,
+                Weight::Time => stats.time.as_nanos(),
+            };
+            for (name, stats) in &node.opcodes {
+                let value = value(stats);
+                if value > 0 {
+                    let _ = writeln!(out, "{path};{name} {value}");
+                }
+            }
+            // Not spent by instructions, e.g. by a precompile or on code deposit
+            let own = node.own();
+            let rest = Stats {
+                count: 0,
+                gas: node.gas - own.gas - node.children.iter().map(|c| c.gas).sum::<i64>(),
+                time: node.duration.saturating_sub(
+                    own.time + node.children.iter().map(|c| c.duration).sum::<Duration>(),
+                ),
+            };
+            let rest = value(&rest);
+            if rest > 0 {
+                let _ = writeln!(out, "{path} {rest}");
+            }
+            for child in &node.children {
+                walk(child, &path, weight, out);
+            }
+        }
+
+        let mut out = String::new();
+        for node in &self.calls {
+            walk(node, "", weight, &mut out);
+        }
+        out
+    }
+
+    /// Call frames as Chrome trace-event JSON (`chrome://tracing`, Perfetto)
+    pub fn chrome_trace(&self) -> serde_json::Value {
+        fn walk(node: &CallNode, events: &mut Vec<serde_json::Value>) {
+            events.push(json!({
+                "name": node.label(),
+                "cat": format!("{:?}", node.call_type),
+                "ph": "X",
+                "ts": node.start.as_secs_f64() * 1e6,
+                "dur": node.duration.as_secs_f64() * 1e6,
+                "pid": 1,
+                "tid": 1,
+                "args": {
+                    "address": node.address,
+                    "gas": node.gas,
+                    "ok": node.ok,
+                },
+            }));
+            for child in &node.children {
+                walk(child, events);
+            }
+        }
+
+        let mut events = Vec::new();
+        for node in &self.calls {
+            walk(node, &mut events);
+        }
+        json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ns",
+        })
+    }
+}
+
+enum Record {
+    Call {
+        depth: usize,
+        address: Address,
+        selector: Option<[u8; 4]>,
+        call_type: CallType,
+        gas: i64,
+    },
+    Return {
+        depth: usize,
+        ok: bool,
+        gas_used: i64,
+    },
+    Halt {
+        depth: usize,
+    },
+    Op {
+        depth: usize,
+        pc: usize,
+        op: u8,
+        name: String,
+        gas_cost: i64,
+    },
+}
+
+/// Records calls and executed instructions with the time they completed at.
+/// Stack and memory snapshots are not taken, see [`Trace`].
+#[derive(Default)]
+pub struct ProfilingTracer {
+    records: Vec<(Record, Instant)>,
+}
+
+impl EventTracer for ProfilingTracer {
+    const TRACE: Trace = Trace {
+        events: true,
+        opcodes: true,
+        ..Trace::NONE
+    };
+
+    fn push(&mut self, event: Event) {
+        let depth = event.depth;
+        let record = match event.data {
+            EventData::Call {
+                data,
+                to,
+                gas,
+                r#type,
+                ..
+            } => Record::Call {
+                depth,
+                address: to,
+                selector: data
+                    .as_ref()
+                    .get(..4)
+                    .map(|s| s.try_into().expect("4 bytes")),
+                call_type: r#type,
+                gas: gas.min(Word::from(i64::MAX as u64)).as_i64(),
+            },
+            EventData::Return { ok, gas_used, .. } => Record::Return {
+                depth,
+                ok,
+                gas_used,
+            },
+            EventData::Halt(_) => Record::Halt { depth },
+            EventData::OpCode(op) => Record::Op {
+                depth,
+                pc: op.pc,
+                op: op.op,
+                name: op.name,
+                gas_cost: op.gas_cost,
+            },
+            _ => return,
+        };
+        self.records.push((record, Instant::now()));
+    }
+
+    fn join(&mut self, other: Self, _reverted: bool) {
+        // Keep the time the child events were recorded at
+        self.records.extend(other.records);
+    }
+}
+
+// Call frame being built from the records
+struct Open {
+    depth: usize,
+    node: CallNode,
+    // Gas the frame was called with, all used on exceptional halt
+    call_gas: i64,
+    returned: Option<i64>,
+    started: Instant,
+    // Time of the last record of the frame itself
+    last: Instant,
+    // Time of the last record of the frame or of its children
+    end: Instant,
+    // Instruction of the parent (name and pc) that entered the frame
+    entered_by: Option<(String, usize)>,
+    // Last executed instruction (name and pc) if it enters a child frame
+    pending: Option<(String, usize)>,
+}
+
+impl ProfilingTracer {
+    pub fn profile(&self) -> Profile {
+        let mut profile = Profile::default();
+        let Some((_, start)) = self.records.first() else {
+            return profile;
+        };
+        let mut frames: Vec<Open> = Vec::new();
+        for (record, at) in &self.records {
+            let depth = match record {
+                Record::Call { depth, .. }
+                | Record::Return { depth, .. }
+                | Record::Halt { depth }
+                | Record::Op { depth, .. } => *depth,
+            };
+            // A record of the parent (or of a sibling) completes the frame
+            while frames.last().is_some_and(|frame| {
+                frame.depth > depth
+                    || (frame.depth == depth && matches!(record, Record::Call { .. }))
+            }) {
+                let frame = frames.pop().expect("frame");
+                close(frame, &mut frames, &mut profile);
+            }
+
+            match record {
+                Record::Call {
+                    depth,
+                    address,
+                    selector,
+                    call_type,
+                    gas,
+                } => {
+                    let node = CallNode {
+                        address: *address,
+                        selector: *selector,
+                        call_type: *call_type,
+                        ok: true,
+                        gas: 0,
+                        start: at.duration_since(*start),
+                        duration: Duration::ZERO,
+                        opcodes: BTreeMap::new(),
+                        children: Vec::new(),
+                    };
+                    // The gas forwarded to the child is not spent by the call instruction
+                    let entered_by = frames.last_mut().and_then(|parent| {
+                        let (name, pc) = parent.pending.take()?;
+                        let forwarded = (*gas).min(parent.node.opcodes[&name].gas);
+                        let adjust = |stats: &mut Stats| stats.gas -= forwarded;
+                        parent.node.opcodes.entry(name.clone()).and_modify(adjust);
+                        profile.opcodes.entry(name.clone()).and_modify(adjust);
+                        profile
+                            .pcs
+                            .entry((parent.node.address, pc))
+                            .and_modify(adjust);
+                        Some((name, pc))
+                    });
+                    frames.push(Open {
+                        depth: *depth,
+                        node,
+                        call_gas: *gas,
+                        returned: None,
+                        started: *at,
+                        last: *at,
+                        end: *at,
+                        entered_by,
+                        pending: None,
+                    });
+                }
+                Record::Return { ok, gas_used, .. } => {
+                    if let Some(frame) = frames.last_mut() {
+                        frame.node.ok = *ok;
+                        frame.returned = Some(*gas_used);
+                        frame.last = *at;
+                    }
+                }
+                Record::Halt { .. } => {
+                    if let Some(frame) = frames.last_mut() {
+                        frame.node.ok = false;
+                        frame.returned = Some(frame.call_gas);
+                        frame.last = *at;
+                    }
+                }
+                Record::Op {
+                    pc,
+                    op,
+                    name,
+                    gas_cost,
+                    ..
+                } => {
+                    // Instructions outside of any call frame are not profiled
+                    let Some(frame) = frames.last_mut() else {
+                        continue;
+                    };
+                    let stats = Stats {
+                        count: 1,
+                        gas: *gas_cost,
+                        time: at.saturating_duration_since(frame.last),
+                    };
+                    frame.last = *at;
+                    frame.pending = CALL_OPS.contains(op).then(|| (name.clone(), *pc));
+                    frame
+                        .node
+                        .opcodes
+                        .entry(name.clone())
+                        .or_default()
+                        .add(&stats);
+                    profile.opcodes.entry(name.clone()).or_default().add(&stats);
+                    profile
+                        .pcs
+                        .entry((frame.node.address, *pc))
+                        .or_default()
+                        .add(&stats);
+                }
+            }
+        }
+        while let Some(frame) = frames.pop() {
+            close(frame, &mut frames, &mut profile);
+        }
+        profile
+    }
+}
+
+fn close(mut frame: Open, frames: &mut [Open], profile: &mut Profile) {
+    let end = frame.end.max(frame.last);
+    let node = &mut frame.node;
+    let own = node.own();
+    node.gas = frame
+        .returned
+        .unwrap_or_else(|| own.gas + node.children.iter().map(|c| c.gas).sum::<i64>());
+    node.duration = end.duration_since(frame.started);
+    profile.contracts.entry(node.address).or_default().add(&own);
+    profile
+        .functions
+        .entry((node.address, node.selector))
+        .or_default()
+        .add(&own);
+
+    let Some(parent) = frames.last_mut() else {
+        profile.calls.push(frame.node);
+        return;
+    };
+    // The time spent in the child is not spent by the call instruction
+    if let Some((name, pc)) = frame.entered_by {
+        let duration = frame.node.duration;
+        let adjust = |stats: &mut Stats| stats.time = stats.time.saturating_sub(duration);
+        parent.node.opcodes.entry(name.clone()).and_modify(adjust);
+        profile.opcodes.entry(name).and_modify(adjust);
+        profile
+            .pcs
+            .entry((parent.node.address, pc))
+            .and_modify(adjust);
+    }
+    parent.end = parent.end.max(end);
+    parent.node.children.push(frame.node);
+}
+
+#[cfg(test)]
+mod tests {
+    use evm_common::{call::Call, hash::keccak256};
+
+    use super::*;
+    use crate::{
+        decoder::Decoder,
+        executor::{Context, Evm, Executor, Gas},
+        ext::{Account, Ext},
+        spec::Spec,
+    };
+
+    #[tokio::test]
+    async fn test_profile() {
+        let a = Address::from([0xaa; 20]);
+        let b = Address::from([0xbb; 20]);
+        // CALL(GAS, b, 0, 0, 4, 0, 0); POP; STOP
+        let caller = hex::decode(format!(
+            "60006000600460006000{}{}5af15000",
+            "73",
+            hex::encode(b.0)
+        ))
+        .unwrap();
+        // SSTORE(0, 1 + 2); STOP
+        let callee = hex::decode("600160020160005500").unwrap();
+        let mut ext = Ext::local();
+        for (address, code) in [(a, &caller), (b, &callee)] {
+            let account = Account {
+                code: (code.clone(), Word::from_bytes(&keccak256(code))),
+                ..Account::default()
+            };
+            ext.state.insert(address, account);
+        }
+        let call = Call {
+            data: vec![],
+            value: Word::zero(),
+            from: Address::from([1u8; 20]),
+            to: a,
+            gas: Word::from(100_000u64),
+        };
+        let mut evm = Evm {
+            gas: Gas::new(100_000),
+            ..Evm::default()
+        };
+        let (tracer, _) = Executor::<ProfilingTracer>::new()
+            .execute_with_context(
+                &Decoder::decode(caller, Spec::default()),
+                &call,
+                &mut evm,
+                &mut ext,
+                Context::default(),
+            )
+            .await;
+        let profile = tracer.profile();
+
+        assert_eq!(profile.calls.len(), 1);
+        let root = &profile.calls[0];
+        assert_eq!(root.address, a);
+        assert_eq!(root.selector, None);
+        assert_eq!(root.children.len(), 1);
+        let child = &root.children[0];
+        assert_eq!(child.address, b);
+        assert_eq!(child.selector, Some([0u8; 4]));
+        assert_eq!(child.call_type, CallType::Call);
+        assert!(child.ok);
+        assert!(child.duration <= root.duration);
+
+        // 3 * PUSH1 + ADD + SSTORE (cold, zero to non-zero) + STOP
+        assert_eq!(child.gas, 22_112);
+        assert_eq!(child.own().gas, child.gas);
+        // CALL is charged for the cold access and the memory expansion only
+        assert_eq!(root.opcodes["CALL"].gas, 2_603);
+        assert_eq!(root.gas, evm.gas.used);
+        assert_eq!(root.gas, root.own().gas + child.gas);
+
+        assert_eq!(profile.contracts[&b].gas, 22_112);
+        assert_eq!(profile.functions[&(b, Some([0u8; 4]))].count, 1);
+        assert_eq!(profile.opcodes["SSTORE"].count, 1);
+        assert_eq!(profile.opcodes["PUSH1"].count, 8);
+        let stats = profile.pc_range(&b, 0..5);
+        assert_eq!((stats.count, stats.gas), (3, 9));
+
+        let folded = profile.folded(Weight::Gas);
+        assert!(folded.contains(&format!("{a};{b}:0x00000000;SSTORE 22100\n")));
+        assert!(folded.contains(&format!("{a};CALL 2603\n")));
+        let trace = profile.chrome_trace();
+        assert_eq!(trace["traceEvents"].as_array().unwrap().len(), 2);
+    }
+}
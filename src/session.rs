@@ -0,0 +1,237 @@
+use std::borrow::Cow;
+
+use evm_common::{address::Address, call::Call};
+
+use crate::{
+    decoder::{Bytecode, Instruction},
+    executor::{Context, Evm, Executor, ExecutorError, Frame, Resume, Step},
+    ext::Ext,
+    tracer::EventTracer,
+};
+
+/// Step-wise execution of a call: run one instruction at a time (or until
+/// a predicate holds), inspect or modify the state of the current frame,
+/// then resume. Nested calls push child frames, so stepping follows the
+/// execution into (and out of) callees.
+pub struct Session<'a, T: EventTracer> {
+    frame: Frame<'a, T>,
+    stack: Vec<(Frame<'a, T>, Resume)>,
+    ret: Option<Vec<u8>>,
+    // Instructions executed so far, in all frames
+    steps: u64,
+    // Set once the execution is interrupted: every frame halts in turn
+    interrupt: Option<ExecutorError>,
+}
+
+/// Read-only view of an active call frame
+pub struct FrameView<'s> {
+    pub code: &'s Bytecode,
+    pub call: &'s Call,
+    pub evm: &'s Evm,
+    pub ctx: &'s Context,
+}
+
+impl FrameView<'_> {
+    /// Account the frame runs as, whose storage it uses: the created one for
+    /// creates, the caller for DELEGATECALL and CALLCODE
+    pub fn address(&self) -> Address {
+        if self.call.to.is_zero() {
+            self.ctx.created
+        } else {
+            self.call.to
+        }
+    }
+}
+
+impl<T: EventTracer> Session<'static, T> {
+    /// Session that owns the code and the call, e.g. to be kept around
+    /// between the requests of a debugger client
+    pub fn owned(
+        executor: Executor<T>,
+        code: Bytecode,
+        call: Call,
+        evm: Evm,
+        ext: &mut Ext,
+        ctx: Context,
+    ) -> Self {
+        Self::with_frame(executor, Cow::Owned(code), Cow::Owned(call), evm, ext, ctx)
+    }
+}
+
+impl<'a, T: EventTracer> Session<'a, T> {
+    pub fn new(
+        executor: Executor<T>,
+        code: &'a Bytecode,
+        call: &'a Call,
+        evm: Evm,
+        ext: &mut Ext,
+        ctx: Context,
+    ) -> Self {
+        Self::with_frame(
+            executor,
+            Cow::Borrowed(code),
+            Cow::Borrowed(call),
+            evm,
+            ext,
+            ctx,
+        )
+    }
+
+    fn with_frame(
+        executor: Executor<T>,
+        code: Cow<'a, Bytecode>,
+        call: Cow<'a, Call>,
+        evm: Evm,
+        ext: &mut Ext,
+        ctx: Context,
+    ) -> Self {
+        let mut frame = Frame {
+            executor,
+            code,
+            call,
+            evm,
+            ctx,
+        };
+        let ret = frame.enter(ext);
+        Self {
+            frame,
+            stack: Vec::new(),
+            ret,
+            steps: 0,
+            interrupt: None,
+        }
+    }
+
+    /// Execute the next instruction (in whatever frame is current).
+    /// Returns false if the execution is already complete.
+    pub async fn step(&mut self, ext: &mut Ext) -> bool {
+        if self.ret.is_some() {
+            return false;
+        }
+        // Once the step budget is exhausted, the execution is cancelled or
+        // the deadline has passed, every frame halts on its next step
+        self.steps += 1;
+        if self.interrupt.is_none() {
+            self.interrupt = self.frame.executor.interrupt(self.steps);
+        }
+        if let Some(e) = self.interrupt.clone() {
+            let ret = self.frame.halt(e);
+            self.unwind(ret, ext).await;
+            return true;
+        }
+        match self.frame.step(ext).await {
+            Step::Next => (),
+            Step::Suspend => {
+                let (child, resume) = *self.frame.executor.pending.take().expect("child frame");
+                let parent = std::mem::replace(&mut self.frame, child);
+                self.stack.push((parent, resume));
+                if let Some(ret) = self.frame.enter(ext) {
+                    self.unwind(ret, ext).await;
+                }
+            }
+            Step::Return(ret) => self.unwind(ret, ext).await,
+        }
+        true
+    }
+
+    /// Step until the predicate holds (checked before every instruction) or
+    /// the execution completes. Returns true if the predicate was hit.
+    pub async fn run_until<F>(&mut self, ext: &mut Ext, mut predicate: F) -> bool
+    where
+        F: FnMut(&Self) -> bool,
+    {
+        while !self.is_done() {
+            if predicate(self) {
+                return true;
+            }
+            self.step(ext).await;
+        }
+        false
+    }
+
+    /// Run to completion
+    pub async fn run(&mut self, ext: &mut Ext) {
+        while self.step(ext).await {}
+    }
+
+    /// Number of instructions executed so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Check if the outermost frame is complete
+    pub fn is_done(&self) -> bool {
+        self.ret.is_some()
+    }
+
+    /// Depth of the current frame (of the outermost frame if complete)
+    pub fn depth(&self) -> usize {
+        self.frame.ctx.depth
+    }
+
+    pub fn code(&self) -> &Bytecode {
+        &self.frame.code
+    }
+
+    pub fn call(&self) -> &Call {
+        &self.frame.call
+    }
+
+    pub fn ctx(&self) -> &Context {
+        &self.frame.ctx
+    }
+
+    pub fn evm(&self) -> &Evm {
+        &self.frame.evm
+    }
+
+    /// State of the current frame: stack, memory, pc, gas etc. Changes take
+    /// effect on the next step. The static gas prepaid for the rest of the
+    /// basic block is given back: the instructions up to the next block are
+    /// charged and checked one by one, wherever the pc is moved to.
+    pub fn evm_mut(&mut self) -> &mut Evm {
+        self.frame.evm.leave_block();
+        &mut self.frame.evm
+    }
+
+    /// Instruction to be executed by the next step
+    pub fn instruction(&self) -> Option<&Instruction> {
+        if self.is_done() || self.frame.evm.stopped {
+            return None;
+        }
+        self.frame.code.instructions.get(self.frame.evm.pc)
+    }
+
+    /// All active frames, from the outermost to the current one
+    pub fn frames(&self) -> impl Iterator<Item = FrameView<'_>> {
+        self.stack
+            .iter()
+            .map(|(frame, _)| frame)
+            .chain(std::iter::once(&self.frame))
+            .map(|frame| FrameView {
+                code: &frame.code,
+                call: &frame.call,
+                evm: &frame.evm,
+                ctx: &frame.ctx,
+            })
+    }
+
+    /// Tracer, return data and final state of the outermost frame,
+    /// or None if the execution is not complete yet.
+    pub fn finish(self) -> Option<(T, Vec<u8>, Evm)> {
+        let ret = self.ret?;
+        let (tracer, evm) = self.frame.into_parts();
+        Some((tracer, ret, evm))
+    }
+
+    async fn unwind(&mut self, mut ret: Vec<u8>, ext: &mut Ext) {
+        while let Some((parent, resume)) = self.stack.pop() {
+            let child = std::mem::replace(&mut self.frame, parent);
+            match self.frame.resume(resume, child, ret, ext).await {
+                Some(parent_ret) => ret = parent_ret,
+                None => return,
+            }
+        }
+        self.ret = Some(ret);
+    }
+}
@@ -1,13 +1,27 @@
+use std::{collections::HashSet, time::Instant};
+
 use evm_event::CallType;
+use eyre::OptionExt as _;
 use serde::{Deserialize, Serialize};
 
 use evm_common::{address::Address, block::Header, call::Call, hash::keccak256, word::Word};
 
 use crate::{
+    access_list::{self, AccessListResult},
     decoder::Decoder,
-    executor::{AccountTouch, Context, Evm, Executor, Gas},
-    ext::Ext,
-    tracer::{EventTracer, LoggingTracer},
+    delegation,
+    eof::{self, Container},
+    estimate::{self, GasEstimate},
+    executor::{AccountTouch, Context, Evm, Executor, Gas, Prelude},
+    ext::{Checkpoint, Ext},
+    fees::{Charge, FeeMarket, Fees},
+    limits::{CancellationToken, ExecutionLimits},
+    precompiles::Precompiles,
+    prestate::{self, PrestateConfig, PrestateTrace},
+    session::Session,
+    spec::{Chain, Spec},
+    tracer::{EventTracer, LoggingTracer, NoopTracer},
+    validation::{self, InvalidTx},
 };
 
 #[derive(Default)]
@@ -50,19 +64,37 @@ impl Solenoid {
 
 pub trait Builder {
     fn with_header(self, header: Header) -> Self;
+    fn with_chain(self, chain: Chain) -> Self;
+    fn with_spec(self, spec: Spec) -> Self;
     fn with_sender(self, sender: Address) -> Self;
     fn with_value(self, amount: Word) -> Self;
     fn with_gas(self, gas: Word) -> Self;
+    /// Reject the transaction if it is invalid instead of executing it
+    fn with_validation(self) -> Self;
+    fn with_limits(self, limits: ExecutionLimits) -> Self;
+    /// Replace the standard precompiles available to the transaction
+    fn with_precompiles(self, precompiles: Precompiles) -> Self;
+    /// Halt the execution once the token is cancelled
+    fn with_cancellation(self, token: CancellationToken) -> Self;
+    /// Halt the execution once the deadline has passed, except on wasm32
+    fn with_deadline(self, deadline: Instant) -> Self;
     fn ready(self) -> Runner;
 }
 
 #[derive(Default)]
 pub struct CreateBuilder {
     header: Header,
+    chain: Chain,
+    spec: Option<Spec>,
     from: Address,
     value: Word,
     gas: Word,
     code: Vec<u8>,
+    validate: bool,
+    limits: ExecutionLimits,
+    precompiles: Precompiles,
+    cancellation: Option<CancellationToken>,
+    deadline: Option<Instant>,
 }
 
 impl Builder for CreateBuilder {
@@ -71,6 +103,16 @@ impl Builder for CreateBuilder {
         self
     }
 
+    fn with_chain(mut self, chain: Chain) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    fn with_spec(mut self, spec: Spec) -> Self {
+        self.spec = Some(spec);
+        self
+    }
+
     fn with_sender(mut self, sender: Address) -> Self {
         self.from = sender;
         self
@@ -86,9 +128,36 @@ impl Builder for CreateBuilder {
         self
     }
 
+    fn with_validation(mut self) -> Self {
+        self.validate = true;
+        self
+    }
+
+    fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    fn with_precompiles(mut self, precompiles: Precompiles) -> Self {
+        self.precompiles = precompiles;
+        self
+    }
+
+    fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
     fn ready(self) -> Runner {
         Runner {
             header: self.header,
+            chain: self.chain,
+            spec: self.spec,
             call: Call {
                 from: self.from,
                 to: Address::zero(),
@@ -97,6 +166,11 @@ impl Builder for CreateBuilder {
                 ..Default::default()
             },
             code: self.code,
+            validate: self.validate,
+            limits: self.limits,
+            precompiles: self.precompiles,
+            cancellation: self.cancellation,
+            deadline: self.deadline,
         }
     }
 }
@@ -104,11 +178,18 @@ impl Builder for CreateBuilder {
 #[derive(Default)]
 pub struct ExecuteBuilder {
     header: Header,
+    chain: Chain,
+    spec: Option<Spec>,
     from: Address,
     to: Address,
     value: Word,
     gas: Word,
     data: Vec<u8>,
+    validate: bool,
+    limits: ExecutionLimits,
+    precompiles: Precompiles,
+    cancellation: Option<CancellationToken>,
+    deadline: Option<Instant>,
 }
 
 impl Builder for ExecuteBuilder {
@@ -117,6 +198,16 @@ impl Builder for ExecuteBuilder {
         self
     }
 
+    fn with_chain(mut self, chain: Chain) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    fn with_spec(mut self, spec: Spec) -> Self {
+        self.spec = Some(spec);
+        self
+    }
+
     fn with_sender(mut self, sender: Address) -> Self {
         self.from = sender;
         self
@@ -132,9 +223,36 @@ impl Builder for ExecuteBuilder {
         self
     }
 
+    fn with_validation(mut self) -> Self {
+        self.validate = true;
+        self
+    }
+
+    fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    fn with_precompiles(mut self, precompiles: Precompiles) -> Self {
+        self.precompiles = precompiles;
+        self
+    }
+
+    fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
     fn ready(self) -> Runner {
         Runner {
             header: self.header,
+            chain: self.chain,
+            spec: self.spec,
             call: Call {
                 from: self.from,
                 to: self.to,
@@ -143,6 +261,11 @@ impl Builder for ExecuteBuilder {
                 data: self.data,
             },
             code: vec![],
+            validate: self.validate,
+            limits: self.limits,
+            precompiles: self.precompiles,
+            cancellation: self.cancellation,
+            deadline: self.deadline,
         }
     }
 }
@@ -150,10 +273,17 @@ impl Builder for ExecuteBuilder {
 #[derive(Default)]
 pub struct TransferBuilder {
     header: Header,
+    chain: Chain,
+    spec: Option<Spec>,
     from: Address,
     to: Address,
     value: Word,
     gas: Word,
+    validate: bool,
+    limits: ExecutionLimits,
+    precompiles: Precompiles,
+    cancellation: Option<CancellationToken>,
+    deadline: Option<Instant>,
 }
 
 impl Builder for TransferBuilder {
@@ -162,6 +292,16 @@ impl Builder for TransferBuilder {
         self
     }
 
+    fn with_chain(mut self, chain: Chain) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    fn with_spec(mut self, spec: Spec) -> Self {
+        self.spec = Some(spec);
+        self
+    }
+
     fn with_sender(mut self, sender: Address) -> Self {
         self.from = sender;
         self
@@ -177,9 +317,36 @@ impl Builder for TransferBuilder {
         self
     }
 
+    fn with_validation(mut self) -> Self {
+        self.validate = true;
+        self
+    }
+
+    fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    fn with_precompiles(mut self, precompiles: Precompiles) -> Self {
+        self.precompiles = precompiles;
+        self
+    }
+
+    fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
     fn ready(self) -> Runner {
         Runner {
             header: self.header,
+            chain: self.chain,
+            spec: self.spec,
             call: Call {
                 from: self.from,
                 to: self.to,
@@ -188,23 +355,162 @@ impl Builder for TransferBuilder {
                 data: vec![],
             },
             code: vec![],
+            validate: self.validate,
+            limits: self.limits,
+            precompiles: self.precompiles,
+            cancellation: self.cancellation,
+            deadline: self.deadline,
         }
     }
 }
 
+// Gas limit of `Runner::estimate_gas` without a transaction or block one
+const DEFAULT_GAS_CAP: i64 = 30_000_000;
+
+#[derive(Clone)]
 pub struct Runner {
     header: Header,
+    chain: Chain,
+    spec: Option<Spec>,
     call: Call,
     code: Vec<u8>,
+    validate: bool,
+    limits: ExecutionLimits,
+    precompiles: Precompiles,
+    cancellation: Option<CancellationToken>,
+    deadline: Option<Instant>,
 }
 
 impl Runner {
     pub async fn apply(self, ext: &mut Ext) -> eyre::Result<CallResult<LoggingTracer>> {
-        let coinbase = self.header.miner;
-        let base_fee = self.header.base_fee;
+        self.apply_with_tracer(LoggingTracer::default(), ext).await
+    }
+
+    /// EIP-2930: execute the transaction (`eth_createAccessList`) and build
+    /// the access list that saves the most gas, starting from the one of
+    /// `ext.tx_ctx`. The state of `ext` is left as is.
+    pub async fn create_access_list(self, ext: &mut Ext) -> eyre::Result<AccessListResult> {
+        let spec = self
+            .spec
+            .unwrap_or_else(|| self.chain.spec_for(&self.header));
+        // Warm before the execution: sender, recipient, coinbase, precompiles
+        // and EIP-7702 authorities
+        let mut excluded: HashSet<Address> = self.precompiles.addresses(spec).into_iter().collect();
+        excluded.extend([self.call.from, self.header.miner]);
+        if self.call.to.is_zero() {
+            let nonce = ext.nonce(&self.call.from).await?;
+            excluded.insert(self.call.from.create(nonce));
+        } else {
+            excluded.insert(self.call.to);
+        }
+        excluded.extend(
+            ext.tx_ctx
+                .authorization_list
+                .iter()
+                .filter_map(delegation::authority),
+        );
+        access_list::create(self, excluded, ext).await
+    }
 
-        let exe = Executor::<LoggingTracer>::with_tracer(LoggingTracer::default());
-        let exe = exe.with_header(self.header);
+    /// Minimal gas limit at which the transaction succeeds
+    /// (`eth_estimateGas`), up to the gas of the transaction or else the
+    /// block gas limit. The state of `ext` is left as is. Returns an error
+    /// that downcasts to [`EstimateError`] if it fails at any gas limit.
+    ///
+    /// [`EstimateError`]: crate::estimate::EstimateError
+    pub async fn estimate_gas(self, ext: &mut Ext) -> eyre::Result<GasEstimate> {
+        let spec = self
+            .spec
+            .unwrap_or_else(|| self.chain.spec_for(&self.header));
+        // The initcode of a create is its data
+        let data = if self.call.to.is_zero() {
+            &self.code
+        } else {
+            &self.call.data
+        };
+        let min = intrinsic_gas(&self.call, data, ext, spec).max(spec.gas_floor(data));
+        let max = if !self.call.gas.is_zero() {
+            self.call.gas.as_i64()
+        } else if !self.header.gas_limit.is_zero() {
+            self.header.gas_limit.as_i64()
+        } else {
+            DEFAULT_GAS_CAP
+        };
+        estimate::estimate(min, max, ext, |gas, mut ext| {
+            let mut runner = self.clone();
+            runner.call.gas = Word::from(gas);
+            async move { runner.apply_with_tracer(NoopTracer, &mut ext).await }
+        })
+        .await
+    }
+
+    /// Execute the transaction with the given tracer and trace the state it
+    /// touched like the geth `prestateTracer`, see [`PrestateConfig`]
+    pub async fn apply_with_prestate<T: EventTracer>(
+        self,
+        tracer: T,
+        config: PrestateConfig,
+        ext: &mut Ext,
+    ) -> eyre::Result<(CallResult<T>, PrestateTrace)> {
+        let mut pre = ext.fork();
+        let result = self.apply_with_tracer(tracer, ext).await?;
+        let trace = prestate::trace(config, &result.evm.touches, &mut pre, ext).await?;
+        Ok((result, trace))
+    }
+
+    /// Execute the transaction with the given tracer, e.g. a [`ProfilingTracer`]
+    ///
+    /// [`ProfilingTracer`]: crate::profiler::ProfilingTracer
+    pub async fn apply_with_tracer<T: EventTracer>(
+        self,
+        tracer: T,
+        ext: &mut Ext,
+    ) -> eyre::Result<CallResult<T>> {
+        let mut prepared = self.prepare(tracer, ext).await?;
+        prepared.session.run(ext).await;
+        prepared.finish(ext).await
+    }
+
+    /// Apply the prelude of the transaction: fees, intrinsic gas, nonce,
+    /// delegations and value. Its top-level frame is left to run in the
+    /// session of the returned [`Prepared`].
+    pub async fn prepare<T: EventTracer>(
+        mut self,
+        tracer: T,
+        ext: &mut Ext,
+    ) -> eyre::Result<Prepared<T>> {
+        if self.validate {
+            validation::validate(&self.call, &self.header, ext).await?;
+        }
+        if self.call.to.is_zero() && self.code.len() > self.limits.initcode_size {
+            return Err(InvalidTx::InitcodeSizeExceeded {
+                size: self.code.len(),
+                limit: self.limits.initcode_size,
+            }
+            .into());
+        }
+
+        let coinbase = self.header.miner;
+        let spec = self
+            .spec
+            .unwrap_or_else(|| self.chain.spec_for(&self.header));
+        let market = FeeMarket::new(&ext.tx_ctx, &self.header, spec);
+
+        let exe = Executor::<T>::with_tracer(tracer);
+        let exe = exe
+            .with_header(self.header)
+            .with_chain(self.chain)
+            .with_spec(spec)
+            .with_limits(self.limits.clone())
+            .with_precompiles(self.precompiles);
+        let exe = match self.cancellation {
+            Some(token) => exe.with_cancellation(token),
+            None => exe,
+        };
+        let exe = match self.deadline {
+            Some(deadline) => exe.with_deadline(deadline),
+            None => exe,
+        };
 
         // EIP-3651 (Shanghai): Pre-warm coinbase address
         if !coinbase.is_zero() {
@@ -223,8 +529,7 @@ impl Runner {
         };
 
         // Check and resolve delegation: CODE = <0xef0100> + <20 bytes address>
-        let code = if code.len() == 23 && code.starts_with(&[0xef, 0x01, 0x00]) {
-            let target = Address::try_from(&code[3..]).expect("address");
+        let code = if let Some(target) = delegation::target(&code) {
             // eprintln!("DEBUG: delegation {} -> {}", self.call.to, target);
             let (code, codehash) = ext.code(&target).await?;
             evm.touches
@@ -234,47 +539,90 @@ impl Runner {
             code
         };
 
-        let code = Decoder::decode(code);
-
-        let call_cost = 21000i64;
-        let data_cost = {
-            let total_calldata_len = self.call.data.len();
-            let nonzero_bytes_count = self.call.data.iter().filter(|byte| *byte != &0).count();
-            nonzero_bytes_count * 16 + (total_calldata_len - nonzero_bytes_count) * 4
-        } as i64;
-        let upfront_gas_reduction = if self.call.to.is_zero() {
-            let create_cost = 32000i64;
-            let init_code_cost = 2 * self.call.data.len().div_ceil(32) as i64;
-            data_cost + create_cost + call_cost + init_code_cost
+        // EIP-7698: EOF initcode of a creation transaction is followed by the
+        // calldata, invalid initcode fails the transaction before execution
+        let mut is_invalid_initcode = false;
+        let code = if !self.call.to.is_zero() {
+            Decoder::decode(code, spec)
+        } else if spec.is_enabled(Spec::Eof) && eof::is_eof(&code) {
+            match Container::split_initcode(&code) {
+                Ok((container, data)) => {
+                    self.call.data = data;
+                    Decoder::decode_eof(container)
+                }
+                Err(_) => {
+                    is_invalid_initcode = true;
+                    Decoder::decode_legacy(vec![])
+                }
+            }
         } else {
-            call_cost + data_cost
+            Decoder::decode_legacy(code)
         };
 
-        let upfront_gas_reduction = upfront_gas_reduction + ext.tx_ctx.access_list_cost();
+        let data = if self.call.to.is_zero() {
+            &self.code
+        } else {
+            &self.call.data
+        };
+        let upfront_gas_reduction = intrinsic_gas(&self.call, data, ext, spec);
+        let gas_floor = spec.gas_floor(data);
         ext.apply_access_list();
 
         evm.gas = Gas::new(self.call.gas.as_i64() - upfront_gas_reduction);
+        if is_invalid_initcode {
+            evm.stopped = true;
+            evm.reverted = true;
+        }
+
+        if !self.call.to.is_zero() {
+            // Calls buy their gas in Executor::prepare, the nonce bump is not
+            // reverted by the executor even if the call reverts
+            let mut executor = exe;
+            let (prelude, reloaded) = executor.prepare(&code, &self.call, &mut evm, ext).await?;
+            let code = reloaded.unwrap_or(code);
+            let session = Session::owned(
+                executor.inner(),
+                code,
+                self.call.clone(),
+                evm,
+                ext,
+                prelude.ctx,
+            );
+            return Ok(Prepared {
+                session,
+                pending: Pending::Call {
+                    executor: Box::new(executor),
+                    prelude,
+                    call: self.call,
+                },
+            });
+        }
 
         ext.pull(&self.call.from).await?;
         let nonce = ext.account_mut(&self.call.from).nonce;
         let created = self.call.from.create(nonce);
-        ext.created_accounts.push(created);
+        // Everything from here on is undone if the creation fails, except fees
+        let checkpoint = ext.checkpoint();
+        ext.create_account(&created);
         if ext.warm_address(&created) {
             evm.touches.push(AccountTouch::WarmUp(created));
         }
         // Initialize the created account in state with nonce=0 before constructor runs.
         ext.state.entry(created).or_default();
 
-        // Transfer CREATE value from tx sender to created address (before init code runs).
-        // Only for CREATE (to.is_zero()); for CALL/transfer the executor handles value transfer.
-        if self.call.to.is_zero() && !self.call.value.is_zero() {
+        let charge = market
+            .charge(&self.call.from, self.call.gas.as_i64(), ext)
+            .await?;
+
+        // Transfer CREATE value from tx sender to created address (before init code runs)
+        if !self.call.value.is_zero() {
             let sender_balance = ext.balance(&self.call.from).await?;
             let created_balance = ext.balance(&created).await?;
             if sender_balance >= self.call.value {
                 let new_sender = sender_balance - self.call.value;
                 let new_created = created_balance + self.call.value;
-                ext.account_mut(&self.call.from).value = new_sender;
-                ext.account_mut(&created).value = new_created;
+                ext.set_balance(&self.call.from, new_sender);
+                ext.set_balance(&created, new_created);
                 evm.touches.push(AccountTouch::SetValue(
                     self.call.from,
                     sender_balance,
@@ -288,108 +636,155 @@ impl Runner {
             }
         }
 
-        if !self.call.to.is_zero() {
-            let (tracer, ret) = exe.execute(&code, &self.call, &mut evm, ext).await?;
-            if evm.reverted {
-                // evm.revert(ext).await?; // do not re-revert
-                // Re-increment nonce (nonce is never reverted for valid transactions)
-                ext.account_mut(&self.call.from).nonce += Word::one();
-            }
-
-            let gas_final = evm.gas.finalized(upfront_gas_reduction, evm.reverted);
-
-            return Ok(CallResult {
-                evm,
-                ret,
-                tracer,
-                gas: GasResult {
-                    gas_max: self.call.gas.as_i64(),
-                    gas_use: gas_final,
-                    gas_fee: Word::from(gas_final) * ext.tx_ctx.gas_price,
-                },
-            });
-        };
-
         let ctx = Context {
             created,
             call_type: CallType::Create,
             depth: 1,
             ..Default::default()
         };
-        let (tracer, mut ret) = exe
-            .execute_with_context(&code, &self.call, &mut evm, ext, ctx)
-            .await;
+        let session = Session::owned(exe, code, self.call.clone(), evm, ext, ctx);
+        Ok(Prepared {
+            session,
+            pending: Pending::Create(Creation {
+                call: self.call,
+                created,
+                coinbase,
+                market,
+                charge,
+                checkpoint,
+                upfront_gas_reduction,
+                gas_floor,
+                code_size: self.limits.code_size,
+            }),
+        })
+    }
+}
 
-        let deployed_code_cost = 200 * ret.len() as i64;
-        let gas_final = if !evm.reverted && evm.gas.remaining() < deployed_code_cost {
-            // Not enough gas to cover deployed code cost
-            ret.clear();
-            evm.reverted = true;
-            let gas_limit = self.call.gas.as_i64();
-            evm.gas(gas_limit).ok();
-            gas_limit
-        } else {
-            evm.gas
-                .finalized(upfront_gas_reduction + deployed_code_cost, evm.reverted)
-        };
+/// Transaction with its prelude applied, see [`Runner::prepare`]. Once its
+/// session is complete, [`Prepared::finish`] settles it like
+/// [`Runner::apply_with_tracer`] does.
+pub struct Prepared<T: EventTracer> {
+    pub session: Session<'static, T>,
+    pending: Pending<T>,
+}
 
-        // Transfer priority fee to coinbase (same logic as in executor.rs)
-        // TODO: Avoid duplication of fee calculation code (see executor.rs)
-        if !coinbase.is_zero() {
-            let coinbase_gas_price = if ext.tx_ctx.gas_max_priority_fee.is_zero() {
-                // Legacy transaction
-                ext.tx_ctx.gas_price.saturating_sub(base_fee)
-            } else {
-                // EIP-1559 transaction
-                let effective_gas_price = {
-                    let base_plus_priority = base_fee + ext.tx_ctx.gas_max_priority_fee;
-                    Word::min(ext.tx_ctx.gas_max_fee, base_plus_priority)
-                };
-                effective_gas_price.saturating_sub(base_fee)
-            };
+// What is left to do once the top-level frame is complete
+enum Pending<T: EventTracer> {
+    Call {
+        executor: Box<Executor<T>>,
+        prelude: Prelude,
+        call: Call,
+    },
+    Create(Creation),
+}
 
-            let priority_fee_total = Word::from(gas_final) * coinbase_gas_price;
-
-            if !priority_fee_total.is_zero() {
-                // TODO charge the fee from tx.sender account
-                ext.pull(&coinbase).await?;
-                let current_coinbase_balance = ext.account_mut(&coinbase).value;
-                let new_coinbase_balance = current_coinbase_balance + priority_fee_total;
-                ext.account_mut(&coinbase).value = new_coinbase_balance;
-                evm.touches.push(AccountTouch::FeePay(
-                    coinbase,
-                    current_coinbase_balance,
-                    new_coinbase_balance,
-                ));
-                // println!("[SOLE] COINBASE (GAS-C): {new_coinbase_balance:#x} *{priority_fee_total:#x}");
+// Creation transaction, deployed once its initcode is complete
+struct Creation {
+    call: Call,
+    created: Address,
+    coinbase: Address,
+    market: FeeMarket,
+    charge: Charge,
+    checkpoint: Checkpoint,
+    upfront_gas_reduction: i64,
+    gas_floor: i64,
+    code_size: usize,
+}
+
+impl<T: EventTracer> Prepared<T> {
+    /// Close the checkpoint of the transaction, charge the gas and settle
+    /// the fees. Fails if the session is not complete.
+    pub async fn finish(self, ext: &mut Ext) -> eyre::Result<CallResult<T>> {
+        let (tracer, ret, mut evm) = self
+            .session
+            .finish()
+            .ok_or_eyre("transaction is not complete")?;
+        match self.pending {
+            Pending::Call {
+                executor,
+                prelude,
+                call,
+            } => {
+                let tracer = executor
+                    .settle(prelude, &call, tracer, &ret, &mut evm, ext)
+                    .await?;
+                let gas = GasResult::new(call.gas.as_i64(), evm.fees.clone());
+                Ok(CallResult {
+                    evm,
+                    ret,
+                    tracer,
+                    gas,
+                })
             }
+            Pending::Create(creation) => creation.finish(tracer, ret, evm, ext).await,
         }
+    }
+}
+
+impl Creation {
+    // Deploy the code returned by the initcode, unless it failed
+    async fn finish<T: EventTracer>(
+        self,
+        mut tracer: T,
+        mut ret: Vec<u8>,
+        mut evm: Evm,
+        ext: &mut Ext,
+    ) -> eyre::Result<CallResult<T>> {
+        let Self {
+            call,
+            created,
+            coinbase,
+            market,
+            charge,
+            checkpoint,
+            upfront_gas_reduction,
+            gas_floor,
+            code_size,
+        } = self;
+
+        let deployed_code_cost = 200 * ret.len() as i64;
+        // EIP-170: too large code fails the deployment like out of gas
+        let is_too_large = ret.len() > code_size;
+        let gas_final =
+            if !evm.reverted && (evm.gas.remaining() < deployed_code_cost || is_too_large) {
+                // Not enough gas to cover deployed code cost
+                ret.clear();
+                evm.reverted = true;
+                let gas_limit = call.gas.as_i64();
+                evm.gas(gas_limit).ok();
+                gas_limit
+            } else {
+                evm.gas
+                    .finalized(upfront_gas_reduction + deployed_code_cost, evm.reverted)
+                    .max(gas_floor)
+            };
 
         if evm.reverted {
-            evm.revert(ext).await?;
+            ext.revert_to(checkpoint);
             // Re-increment nonce (nonce is never reverted even for failed tx)
-            let nonce = ext.account_mut(&self.call.from).nonce;
-            ext.account_mut(&self.call.from).nonce = nonce + Word::one();
+            let nonce = ext.account_mut(&call.from).nonce;
+            ext.set_nonce(&call.from, nonce + Word::one());
             evm.touches.push(AccountTouch::SetNonce(
-                self.call.from,
+                call.from,
                 nonce.as_u64(),
                 nonce.as_u64() + 1,
             ));
         } else {
             ext.pull(&created).await?;
-            ext.pull(&self.call.from).await?;
+            ext.pull(&call.from).await?;
+            ext.commit(checkpoint);
 
-            let nonce = ext.account_mut(&self.call.from).nonce;
-            ext.account_mut(&self.call.from).nonce = nonce + Word::one();
+            let nonce = ext.account_mut(&call.from).nonce;
+            ext.set_nonce(&call.from, nonce + Word::one());
             evm.touches.push(AccountTouch::SetNonce(
-                self.call.from,
+                call.from,
                 nonce.as_u64(),
                 nonce.as_u64() + 1,
             ));
 
             let hash = Word::from_bytes(&keccak256(&ret));
-            *ext.code_mut(&created) = (ret.clone(), hash);
-            ext.account_mut(&created).nonce = Word::one();
+            ext.set_code(&created, (ret.clone(), hash));
+            ext.set_nonce(&created, Word::one());
             // TODO: check for transferred balance into newly created contract
             evm.touches.push(AccountTouch::Create(
                 created,
@@ -400,30 +795,53 @@ impl Runner {
             ));
         }
 
-        // Deduct gas fee from sender
-        let gas_fee = Word::from(gas_final) * ext.tx_ctx.gas_price;
-        let sender_balance = ext.balance(&self.call.from).await?;
-        let new_sender_balance = sender_balance.saturating_sub(gas_fee);
-        ext.account_mut(&self.call.from).value = new_sender_balance;
-        evm.touches.push(AccountTouch::FeePay(
-            self.call.from,
-            sender_balance,
-            new_sender_balance,
-        ));
+        let fees = market
+            .settle(
+                &call.from,
+                &coinbase,
+                gas_final,
+                charge,
+                &mut evm,
+                ext,
+                &mut tracer,
+            )
+            .await?;
+        evm.fees = fees.clone();
 
         Ok(CallResult {
             evm,
             ret,
             tracer,
-            gas: GasResult {
-                gas_max: self.call.gas.as_i64(),
-                gas_use: gas_final,
-                gas_fee: Word::from(gas_final) * ext.tx_ctx.gas_price,
-            },
+            gas: GasResult::new(call.gas.as_i64(), fees),
         })
     }
 }
 
+// Charged before execution: base cost, calldata, initcode and lists
+fn intrinsic_gas(call: &Call, data: &[u8], ext: &Ext, spec: Spec) -> i64 {
+    let call_cost = 21000i64;
+    let data_cost = {
+        let total_calldata_len = data.len();
+        let nonzero_bytes_count = data.iter().filter(|byte| *byte != &0).count();
+        nonzero_bytes_count * 16 + (total_calldata_len - nonzero_bytes_count) * 4
+    } as i64;
+    let upfront_gas_reduction = if call.to.is_zero() {
+        let create_cost = 32000i64;
+        let init_code_cost = 2 * data.len().div_ceil(32) as i64;
+        data_cost + create_cost + call_cost + init_code_cost
+    } else {
+        call_cost + data_cost
+    };
+
+    // EIP-7702: the authorization list is ignored before Prague
+    let authorization_list_cost = if spec.is_enabled(Spec::Prague) {
+        ext.tx_ctx.authorization_list_cost()
+    } else {
+        0
+    };
+    upfront_gas_reduction + ext.tx_ctx.access_list_cost() + authorization_list_cost
+}
+
 pub struct CallResult<T: EventTracer> {
     pub evm: Evm,
     pub ret: Vec<u8>,
@@ -436,4 +854,16 @@ pub struct GasResult {
     pub gas_max: i64,
     pub gas_use: i64,
     pub gas_fee: Word,
+    pub fees: Fees,
+}
+
+impl GasResult {
+    fn new(gas_max: i64, fees: Fees) -> Self {
+        Self {
+            gas_max,
+            gas_use: fees.gas,
+            gas_fee: fees.total,
+            fees,
+        }
+    }
 }
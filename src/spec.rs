@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+
+use evm_common::block::Header;
+
+/// Hardfork rule set the executor runs with. Forks are ordered, so
+/// `spec >= Spec::Cancun` reads as "Cancun rules are active".
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize,
+)]
+pub enum Spec {
+    Shanghai,
+    Cancun,
+    Prague,
+    #[default]
+    Osaka,
+    /// EIP-7892: blob parameter only forks, Osaka rules with more blobs
+    Bpo1,
+    Bpo2,
+    /// EIP-7692: EVM Object Format (EOF v1). Not scheduled on any public
+    /// network, enabled by a custom fork schedule for testing EOF contracts.
+    Eof,
+}
+
+impl Spec {
+    pub fn is_enabled(&self, fork: Spec) -> bool {
+        *self >= fork
+    }
+
+    /// Check if the opcode is defined under this rule set. Opcodes that are
+    /// not yet activated behave as undefined ones (exceptional halt).
+    pub fn has_opcode(&self, opcode: u8) -> bool {
+        match opcode {
+            // EIP-1153 (TLOAD, TSTORE), EIP-5656 (MCOPY),
+            // EIP-4844 (BLOBHASH), EIP-7516 (BLOBBASEFEE)
+            0x5c | 0x5d | 0x5e | 0x49 | 0x4a => self.is_enabled(Spec::Cancun),
+            // EIP-7939 (CLZ)
+            0x1e => self.is_enabled(Spec::Osaka),
+            // EIP-7692 (EOF)
+            op if crate::opcodes::get_opcode(op).is_eof_only() => self.is_enabled(Spec::Eof),
+            _ => true,
+        }
+    }
+
+    /// EIP-4844 / EIP-7691 / EIP-7892: denominator of the blob base fee
+    /// exponent, raised with the blob target
+    pub fn blob_base_fee_update_fraction(&self) -> u128 {
+        match self {
+            Spec::Shanghai | Spec::Cancun => 3338477,
+            Spec::Prague | Spec::Osaka => 5007716,
+            Spec::Bpo1 => 8346193,
+            Spec::Bpo2 | Spec::Eof => 11684671,
+        }
+    }
+
+    /// EIP-7623 (Prague): minimal gas charged for a transaction based on its calldata
+    pub fn gas_floor(&self, data: &[u8]) -> i64 {
+        if !self.is_enabled(Spec::Prague) {
+            return 0;
+        }
+        let zero_bytes = data.iter().filter(|b| **b == 0).count() as i64;
+        let nonzero_bytes = data.len() as i64 - zero_bytes;
+        let calldata_tokens = zero_bytes + nonzero_bytes * 4;
+        21000 + 10 * calldata_tokens
+    }
+}
+
+/// Activation timestamps of the supported hardforks. Shanghai is the oldest
+/// supported rule set: anything before `cancun` runs with Shanghai rules.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ForkSchedule {
+    pub cancun: u64,
+    pub prague: u64,
+    pub osaka: u64,
+    #[serde(default)]
+    pub bpo1: Option<u64>,
+    #[serde(default)]
+    pub bpo2: Option<u64>,
+    #[serde(default)]
+    pub eof: Option<u64>,
+}
+
+impl Default for ForkSchedule {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+impl ForkSchedule {
+    pub fn mainnet() -> Self {
+        Self {
+            cancun: 1710338135,
+            prague: 1746612311,
+            osaka: 1764798551,
+            bpo1: Some(1765290071),
+            bpo2: Some(1767747671),
+            eof: None,
+        }
+    }
+
+    pub fn spec_at(&self, timestamp: u64) -> Spec {
+        if self.eof.is_some_and(|eof| timestamp >= eof) {
+            Spec::Eof
+        } else if self.bpo2.is_some_and(|bpo2| timestamp >= bpo2) {
+            Spec::Bpo2
+        } else if self.bpo1.is_some_and(|bpo1| timestamp >= bpo1) {
+            Spec::Bpo1
+        } else if timestamp >= self.osaka {
+            Spec::Osaka
+        } else if timestamp >= self.prague {
+            Spec::Prague
+        } else if timestamp >= self.cancun {
+            Spec::Cancun
+        } else {
+            Spec::Shanghai
+        }
+    }
+
+    /// Rule set for the given block. A default (zero-timestamp) header carries
+    /// no block context at all, so it runs with the latest rules.
+    pub fn spec_for(&self, header: &Header) -> Spec {
+        if header.timestamp.is_zero() {
+            return Spec::default();
+        }
+        self.spec_at(header.timestamp.as_u64())
+    }
+}
+
+/// Chain configuration: chain id (EIP-155) and hardfork activation schedule
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Chain {
+    pub id: u64,
+    pub forks: ForkSchedule,
+}
+
+impl Default for Chain {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+impl Chain {
+    pub fn new(id: u64, forks: ForkSchedule) -> Self {
+        Self { id, forks }
+    }
+
+    pub fn mainnet() -> Self {
+        Self::new(1, ForkSchedule::mainnet())
+    }
+
+    pub fn sepolia() -> Self {
+        let forks = ForkSchedule {
+            cancun: 1706655072,
+            prague: 1741159776,
+            osaka: 1760427360,
+            bpo1: Some(1761017184),
+            bpo2: Some(1761607008),
+            eof: None,
+        };
+        Self::new(11155111, forks)
+    }
+
+    pub fn holesky() -> Self {
+        let forks = ForkSchedule {
+            cancun: 1707305664,
+            prague: 1740434112,
+            osaka: 1759308480,
+            bpo1: Some(1759800000),
+            bpo2: Some(1760389824),
+            eof: None,
+        };
+        Self::new(17000, forks)
+    }
+
+    /// Known chain configuration for the given chain id
+    pub fn by_id(id: u64) -> Option<Self> {
+        [Self::mainnet(), Self::sepolia(), Self::holesky()]
+            .into_iter()
+            .find(|chain| chain.id == id)
+    }
+
+    pub fn spec_for(&self, header: &Header) -> Spec {
+        self.forks.spec_for(header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mainnet_schedule() {
+        let forks = ForkSchedule::mainnet();
+        assert_eq!(forks.spec_at(1681338455), Spec::Shanghai);
+        assert_eq!(forks.spec_at(1710338134), Spec::Shanghai);
+        assert_eq!(forks.spec_at(1710338135), Spec::Cancun);
+        assert_eq!(forks.spec_at(1746612311), Spec::Prague);
+        assert_eq!(forks.spec_at(1764798551), Spec::Osaka);
+        assert_eq!(forks.spec_at(1765290071), Spec::Bpo1);
+        assert_eq!(forks.spec_at(1767747671), Spec::Bpo2);
+        assert_eq!(forks.spec_for(&Header::default()), Spec::Osaka);
+    }
+
+    #[test]
+    fn test_chain_by_id() {
+        assert_eq!(Chain::by_id(1), Some(Chain::mainnet()));
+        assert_eq!(Chain::by_id(11155111).map(|chain| chain.id), Some(11155111));
+        assert_eq!(Chain::by_id(42), None);
+        assert_eq!(Chain::default().spec_for(&Header::default()), Spec::Osaka);
+    }
+
+    #[test]
+    fn test_opcodes() {
+        assert!(!Spec::Shanghai.has_opcode(0x5c));
+        assert!(Spec::Cancun.has_opcode(0x5c));
+        assert!(!Spec::Prague.has_opcode(0x1e));
+        assert!(Spec::Osaka.has_opcode(0x1e));
+        assert!(Spec::Shanghai.has_opcode(0x5f));
+        assert!(!Spec::Osaka.has_opcode(0xe0));
+        assert!(Spec::Eof.has_opcode(0xe0));
+    }
+
+    #[test]
+    fn test_eof_schedule() {
+        let forks = ForkSchedule {
+            eof: Some(1800000000),
+            ..ForkSchedule::mainnet()
+        };
+        assert_eq!(forks.spec_at(1799999999), Spec::Bpo2);
+        assert_eq!(forks.spec_at(1800000000), Spec::Eof);
+        assert_eq!(ForkSchedule::mainnet().spec_at(u64::MAX), Spec::Bpo2);
+    }
+
+    #[test]
+    fn test_blob_base_fee_update_fraction() {
+        assert_eq!(Spec::Shanghai.blob_base_fee_update_fraction(), 3338477);
+        assert_eq!(Spec::Cancun.blob_base_fee_update_fraction(), 3338477);
+        assert_eq!(Spec::Prague.blob_base_fee_update_fraction(), 5007716);
+        assert_eq!(Spec::Osaka.blob_base_fee_update_fraction(), 5007716);
+        assert_eq!(Spec::Bpo1.blob_base_fee_update_fraction(), 8346193);
+        assert_eq!(Spec::Bpo2.blob_base_fee_update_fraction(), 11684671);
+        assert_eq!(Spec::Eof.blob_base_fee_update_fraction(), 11684671);
+    }
+
+    #[test]
+    fn test_gas_floor() {
+        assert_eq!(Spec::Cancun.gas_floor(&[1, 0]), 0);
+        assert_eq!(Spec::Prague.gas_floor(&[1, 0]), 21000 + 10 * 5);
+    }
+}
@@ -1,6 +1,48 @@
 use evm_event::Event;
 
+/// Parts of the trace consumed by a tracer. The executor does not build what
+/// is not consumed: the checks are resolved at compile time per tracer type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Trace {
+    /// Call, return, account and state events
+    pub events: bool,
+    /// OpCode event for every executed instruction
+    pub opcodes: bool,
+    /// Stack snapshot in OpCode events
+    pub stack: bool,
+    /// Memory snapshot in OpCode events
+    pub memory: bool,
+    /// Debug info (JSON) in OpCode events
+    pub debug: bool,
+}
+
+impl Trace {
+    pub const ALL: Self = Self {
+        events: true,
+        opcodes: true,
+        stack: true,
+        memory: true,
+        debug: true,
+    };
+
+    pub const NONE: Self = Self {
+        events: false,
+        opcodes: false,
+        stack: false,
+        memory: false,
+        debug: false,
+    };
+
+    /// No per-instruction events, only calls, returns and state changes
+    pub const STATE: Self = Self {
+        events: true,
+        ..Self::NONE
+    };
+}
+
 pub trait EventTracer: Default {
+    const TRACE: Trace = Trace::ALL;
+
     fn push(&mut self, _event: Event) {}
 
     fn peek(&self) -> &[Event] {
@@ -26,7 +68,9 @@ pub trait EventTracer: Default {
 #[derive(Default)]
 pub struct NoopTracer;
 
-impl EventTracer for NoopTracer {}
+impl EventTracer for NoopTracer {
+    const TRACE: Trace = Trace::NONE;
+}
 
 #[derive(Default)]
 pub struct LoggingTracer(Vec<Event>);
@@ -44,3 +88,84 @@ impl EventTracer for LoggingTracer {
         std::mem::take(&mut self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use evm_common::{address::Address, call::Call, word::Word};
+    use evm_event::EventData;
+
+    use super::*;
+    use crate::{
+        decoder::Decoder,
+        executor::{Context, Evm, Executor, Gas},
+        ext::Ext,
+        spec::Spec,
+    };
+
+    #[derive(Default)]
+    struct StateTracer(LoggingTracer);
+
+    impl EventTracer for StateTracer {
+        const TRACE: Trace = Trace::STATE;
+
+        fn push(&mut self, event: Event) {
+            self.0.push(event);
+        }
+
+        fn take(&mut self) -> Vec<Event> {
+            self.0.take()
+        }
+    }
+
+    async fn trace<T: EventTracer>() -> Vec<Event> {
+        // MSTORE(0, 42); RETURN(0, 32)
+        let code = Decoder::decode(
+            hex::decode("602a60005260206000f3").unwrap(),
+            Spec::default(),
+        );
+        let call = Call {
+            data: vec![],
+            value: Word::zero(),
+            from: Address::from([1u8; 20]),
+            to: Address::from([2u8; 20]),
+            gas: Word::from(100_000u64),
+        };
+        let mut evm = Evm {
+            gas: Gas::new(100_000),
+            ..Evm::default()
+        };
+        let (mut tracer, ret) = Executor::<T>::new()
+            .execute_with_context(
+                &code,
+                &call,
+                &mut evm,
+                &mut Ext::local(),
+                Context::default(),
+            )
+            .await;
+        assert_eq!(Word::from_bytes(&ret), Word::from(42u64));
+        tracer.take()
+    }
+
+    #[tokio::test]
+    async fn test_trace_levels() {
+        let events = trace::<LoggingTracer>().await;
+        let opcodes = events
+            .iter()
+            .filter_map(|event| match &event.data {
+                EventData::OpCode(opcode) => Some(opcode),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(opcodes.len(), 6);
+        assert_eq!(opcodes[0].stack, vec![Word::from(42u64)]);
+
+        let events = trace::<StateTracer>().await;
+        assert!(!events.is_empty());
+        assert!(
+            events
+                .iter()
+                .all(|event| !matches!(event.data, EventData::OpCode(_)))
+        );
+    }
+}
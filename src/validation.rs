@@ -0,0 +1,199 @@
+use thiserror::Error;
+
+use evm_common::{address::Address, block::Header, call::Call, word::Word};
+
+use crate::{delegation, ext::Ext};
+
+/// Reasons for a transaction to be rejected before execution. Unlike a
+/// revert, an invalid transaction cannot be included in a block at all.
+#[derive(Debug, Error, Eq, PartialEq)]
+pub enum InvalidTx {
+    #[error("Insufficient funds for gas * price + value: have {have:?}, need {need:?}")]
+    InsufficientFunds { have: Word, need: Word },
+    #[error("Nonce too low: account has {state}, transaction has {tx}")]
+    NonceTooLow { state: Word, tx: Word },
+    #[error("Nonce too high: account has {state}, transaction has {tx}")]
+    NonceTooHigh { state: Word, tx: Word },
+    #[error("Max fee per gas {max_fee} is less than block base fee {base_fee}")]
+    FeeCapTooLow { max_fee: Word, base_fee: Word },
+    #[error("Max priority fee per gas {max_priority_fee} is higher than max fee {max_fee}")]
+    PriorityFeeTooHigh {
+        max_priority_fee: Word,
+        max_fee: Word,
+    },
+    #[error("Transaction gas {gas} exceeds block gas limit {limit}")]
+    GasLimitExceeded { gas: Word, limit: Word },
+    #[error("Sender is not an EOA: {0}")]
+    SenderNotEoa(Address),
+    #[error("Initcode size {size} exceeds the limit {limit}")]
+    InitcodeSizeExceeded { size: usize, limit: usize },
+}
+
+/// Check that the transaction is valid against the block and the current
+/// state of the sender. Returns an error that downcasts to [`InvalidTx`]
+/// when the transaction is rejected.
+pub async fn validate(call: &Call, header: &Header, ext: &mut Ext) -> eyre::Result<()> {
+    let tx = &ext.tx_ctx;
+    // Legacy transactions carry a single gas price for both fee caps
+    let max_fee = if tx.gas_max_fee.is_zero() {
+        tx.gas_price
+    } else {
+        tx.gas_max_fee
+    };
+    if !tx.gas_max_fee.is_zero() && tx.gas_max_priority_fee > tx.gas_max_fee {
+        return Err(InvalidTx::PriorityFeeTooHigh {
+            max_priority_fee: tx.gas_max_priority_fee,
+            max_fee: tx.gas_max_fee,
+        }
+        .into());
+    }
+    if max_fee < header.base_fee {
+        return Err(InvalidTx::FeeCapTooLow {
+            max_fee,
+            base_fee: header.base_fee,
+        }
+        .into());
+    }
+    // A default header has no gas limit to check against
+    if !header.gas_limit.is_zero() && call.gas > header.gas_limit {
+        return Err(InvalidTx::GasLimitExceeded {
+            gas: call.gas,
+            limit: header.gas_limit,
+        }
+        .into());
+    }
+
+    // Overflowing upfront cost can never be covered by any balance
+    let (gas_fee, o1) = call.gas.overflowing_mul(max_fee);
+    let (blob_fee, o2) = Word::from(tx.blob_gas_used).overflowing_mul(tx.blob_max_fee);
+    let (fee, o3) = gas_fee.overflowing_add(blob_fee);
+    let (need, o4) = fee.overflowing_add(call.value);
+    let need = if o1 || o2 || o3 || o4 {
+        Word::max()
+    } else {
+        need
+    };
+    let tx_nonce = tx.nonce;
+
+    // EIP-3607: Reject transactions from senders with deployed code
+    let (code, _) = ext.code(&call.from).await?;
+    if !code.is_empty() && delegation::target(&code).is_none() {
+        return Err(InvalidTx::SenderNotEoa(call.from).into());
+    }
+
+    if let Some(tx) = tx_nonce {
+        let state = ext.nonce(&call.from).await?;
+        if tx < state {
+            return Err(InvalidTx::NonceTooLow { state, tx }.into());
+        }
+        if tx > state {
+            return Err(InvalidTx::NonceTooHigh { state, tx }.into());
+        }
+    }
+
+    let have = ext.balance(&call.from).await?;
+    if have < need {
+        return Err(InvalidTx::InsufficientFunds { have, need }.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ext::{Account, TxContext};
+
+    use super::*;
+
+    async fn check(call: &Call, header: &Header, ext: &mut Ext) -> Option<InvalidTx> {
+        validate(call, header, ext)
+            .await
+            .err()
+            .map(|e| e.downcast::<InvalidTx>().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let from = Address::from([1u8; 20]);
+        let mut ext = Ext::local();
+        ext.state.insert(
+            from,
+            Account {
+                value: Word::from(1_000_000u64),
+                nonce: Word::from(3u64),
+                ..Account::default()
+            },
+        );
+        ext.reset(TxContext {
+            gas_price: Word::from(10u64),
+            gas_max_fee: Word::from(10u64),
+            gas_max_priority_fee: Word::from(2u64),
+            nonce: Some(Word::from(3u64)),
+            ..TxContext::default()
+        });
+        let header = Header {
+            base_fee: Word::from(8u64),
+            gas_limit: Word::from(30_000_000u64),
+            ..Header::default()
+        };
+        let mut call = Call {
+            from,
+            to: Address::from([2u8; 20]),
+            gas: Word::from(50_000u64),
+            value: Word::from(500_000u64),
+            ..Call::default()
+        };
+        assert_eq!(check(&call, &header, &mut ext).await, None);
+
+        call.value = Word::from(500_001u64);
+        assert_eq!(
+            check(&call, &header, &mut ext).await,
+            Some(InvalidTx::InsufficientFunds {
+                have: Word::from(1_000_000u64),
+                need: Word::from(1_000_001u64),
+            })
+        );
+        call.value = Word::zero();
+
+        call.gas = Word::from(30_000_001u64);
+        assert!(matches!(
+            check(&call, &header, &mut ext).await,
+            Some(InvalidTx::GasLimitExceeded { .. })
+        ));
+        call.gas = Word::from(50_000u64);
+
+        ext.tx_ctx.nonce = Some(Word::from(2u64));
+        assert!(matches!(
+            check(&call, &header, &mut ext).await,
+            Some(InvalidTx::NonceTooLow { .. })
+        ));
+        ext.tx_ctx.nonce = Some(Word::from(4u64));
+        assert!(matches!(
+            check(&call, &header, &mut ext).await,
+            Some(InvalidTx::NonceTooHigh { .. })
+        ));
+        ext.tx_ctx.nonce = None;
+
+        ext.tx_ctx.gas_max_priority_fee = Word::from(11u64);
+        assert!(matches!(
+            check(&call, &header, &mut ext).await,
+            Some(InvalidTx::PriorityFeeTooHigh { .. })
+        ));
+        ext.tx_ctx.gas_max_priority_fee = Word::zero();
+
+        ext.tx_ctx.gas_max_fee = Word::from(7u64);
+        assert!(matches!(
+            check(&call, &header, &mut ext).await,
+            Some(InvalidTx::FeeCapTooLow { .. })
+        ));
+        ext.tx_ctx.gas_max_fee = Word::from(10u64);
+
+        // EIP-7702 delegated senders are still EOAs
+        ext.account_mut(&from).code = (delegation::designator(&call.to), Word::zero());
+        assert_eq!(check(&call, &header, &mut ext).await, None);
+        ext.account_mut(&from).code = (vec![0x00], Word::zero());
+        assert_eq!(
+            check(&call, &header, &mut ext).await,
+            Some(InvalidTx::SenderNotEoa(from))
+        );
+    }
+}
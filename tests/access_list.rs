@@ -0,0 +1,107 @@
+use evm_common::{
+    address::{Address, addr},
+    block::{AccessListItem, Header},
+    word::Word,
+};
+
+use solenoid::{
+    ext::{Account, Ext, TxContext},
+    solenoid::{Builder, Solenoid},
+};
+
+mod common;
+
+// SLOAD(5); CALL(GAS, 0xbb, 0, 0, 0, 0, 0); BALANCE(COINBASE); BALANCE(0x01)
+static CALLER: &str = "600554505f5f5f5f5f7300000000000000000000000000000000000000bb5af150413150600131500\
+0";
+
+// SLOAD(0); SLOAD(1)
+static CALLEE: &str = "5f54506001545000";
+
+const FROM: &str = "f39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+const CALLER_ADDRESS: &str = "00000000000000000000000000000000000000aa";
+const CALLEE_ADDRESS: &str = "00000000000000000000000000000000000000bb";
+const COINBASE: &str = "00000000000000000000000000000000c0ffee00";
+
+async fn create_access_list(
+    access_list: Vec<AccessListItem>,
+) -> eyre::Result<(solenoid::access_list::AccessListResult, Ext)> {
+    let mut ext = common::local([
+        (addr(FROM), Account::default()),
+        (
+            addr(CALLER_ADDRESS),
+            common::contract(&hex::decode(CALLER)?),
+        ),
+        (
+            addr(CALLEE_ADDRESS),
+            common::contract(&hex::decode(CALLEE)?),
+        ),
+        (addr(COINBASE), Account::default()),
+        (
+            addr("0000000000000000000000000000000000000001"),
+            Account::default(),
+        ),
+    ]);
+    ext.reset(TxContext {
+        access_list,
+        ..TxContext::default()
+    });
+    let result = Solenoid::new()
+        .execute(addr(CALLER_ADDRESS), "", &[])
+        .with_header(Header {
+            miner: addr(COINBASE),
+            ..Header::default()
+        })
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .create_access_list(&mut ext)
+        .await?;
+    Ok((result, ext))
+}
+
+fn entries(list: &[AccessListItem]) -> Vec<(Address, Vec<Word>)> {
+    list.iter()
+        .map(|item| (item.address, item.storage_keys.clone()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_create_access_list() -> eyre::Result<()> {
+    let (result, mut ext) = create_access_list(vec![]).await?;
+    // The recipient, coinbase and precompiles are warm anyway
+    assert_eq!(
+        entries(&result.access_list),
+        vec![(addr(CALLEE_ADDRESS), vec![Word::zero(), Word::one()])]
+    );
+    // Warm account access and two warm SLOADs, minus the cost of the list
+    assert_eq!(
+        result.gas_used_without - result.gas_used,
+        2500 + 2 * 2000 - 2400 - 2 * 1900
+    );
+    // Executed on forks of the state
+    assert_eq!(ext.nonce(&addr(FROM)).await?, Word::zero());
+    assert!(ext.accessed_addresses.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unused_entries_are_dropped() -> eyre::Result<()> {
+    let unused = addr("00000000000000000000000000000000000000cc");
+    let (result, _) = create_access_list(vec![
+        AccessListItem {
+            address: unused,
+            storage_keys: vec![Word::one()],
+        },
+        AccessListItem {
+            address: addr(CALLEE_ADDRESS),
+            storage_keys: vec![Word::from(7u64)],
+        },
+    ])
+    .await?;
+    assert_eq!(
+        entries(&result.access_list),
+        vec![(addr(CALLEE_ADDRESS), vec![Word::zero(), Word::one()])]
+    );
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use std::{ops::Range, sync::Arc};
+
+use evm_common::{
+    address::{Address, addr},
+    word::Word,
+};
+use evm_event::EventData;
+
+use solenoid::{
+    annotate::{AnnotatingTracer, Annotator},
+    artifact::{Artifacts, Contract, Source, SourceMap, SourceMapEntry},
+    ext::Account,
+    solenoid::{Builder, Solenoid},
+    tracer::{EventTracer, LoggingTracer},
+};
+
+mod common;
+
+static CALL: &str = include_str!("../etc/call/Call.bin-runtime");
+static CALL_SOL: &str = include_str!("../etc/call/Call.sol");
+
+static FAIL: &str = include_str!("../etc/fail/Fail.bin-runtime");
+static FAIL_SOL: &str = include_str!("../etc/fail/Fail.sol");
+
+const CALL_ADDRESS: &str = "00000000000000000000000000000000000ca11e";
+const FAIL_ADDRESS: &str = "000000000000000000000000000000000000fa11";
+
+// The artifacts come without source maps: map pc ranges of the bytecode to
+// the source of the statements they were compiled from
+fn contract(code: &str, file: usize, source: &str, spans: &[(Range<usize>, &str)]) -> Contract {
+    let bytecode = hex::decode(code.trim()).unwrap();
+    let mut entries = Vec::new();
+    let mut pc = 0;
+    while pc < bytecode.len() {
+        let entry = spans
+            .iter()
+            .find(|(pcs, _)| pcs.contains(&pc))
+            .map(|(_, snippet)| SourceMapEntry {
+                offset: source.find(snippet).unwrap(),
+                length: snippet.len(),
+                file: Some(file),
+                jump: '-',
+            })
+            .unwrap_or(SourceMapEntry {
+                jump: '-',
+                ..SourceMapEntry::default()
+            });
+        entries.push(entry);
+        let op = bytecode[pc];
+        pc += 1 + if (0x60..=0x7f).contains(&op) {
+            (op - 0x5f) as usize
+        } else {
+            0
+        };
+    }
+    Contract {
+        bytecode,
+        source_map: SourceMap(entries),
+    }
+}
+
+fn annotator() -> Arc<Annotator> {
+    let mut artifacts = Artifacts::default();
+    artifacts.contracts.insert(
+        "Call.sol:Call".to_string(),
+        contract(
+            CALL,
+            0,
+            CALL_SOL,
+            &[
+                (0x00..0x4b, "contract Call"),
+                (0x9a..0x11c, "Cell(target).set(value_)"),
+            ],
+        ),
+    );
+    artifacts.contracts.insert(
+        "Fail.sol:Fail".to_string(),
+        contract(
+            FAIL,
+            1,
+            FAIL_SOL,
+            &[
+                (0x00..0x38, "contract Fail"),
+                (
+                    0x7a..0x106,
+                    "require(msg.sender == owner, \"not an owner\")",
+                ),
+            ],
+        ),
+    );
+    artifacts.sources = vec![
+        Source {
+            path: "Call.sol".to_string(),
+            content: CALL_SOL.to_string(),
+        },
+        Source {
+            path: "Fail.sol".to_string(),
+            content: FAIL_SOL.to_string(),
+        },
+    ];
+    Arc::new(Annotator::new(&artifacts))
+}
+
+async fn run(
+    to: Address,
+    method: &str,
+    args: &[u8],
+) -> eyre::Result<AnnotatingTracer<LoggingTracer>> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let call = addr(CALL_ADDRESS);
+    let fail = addr(FAIL_ADDRESS);
+
+    let mut ext = common::local([
+        (from, Account::default()),
+        (call, common::contract(&hex::decode(CALL.trim())?)),
+        (fail, common::contract(&hex::decode(FAIL.trim())?)),
+    ]);
+    // Call.target (slot 1) is the Fail contract, that has no `set(uint256)`
+    ext.account_mut(&call)
+        .state
+        .insert(Word::one(), fail.as_word());
+
+    let code = ext.state[&to].code.0.clone();
+    let tracer = AnnotatingTracer::new(LoggingTracer::default(), annotator(), &code);
+    let result = Solenoid::new()
+        .execute(to, method, args)
+        .with_sender(from)
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .apply_with_tracer(tracer, &mut ext)
+        .await?;
+    assert!(result.evm.reverted);
+    Ok(result.tracer)
+}
+
+#[tokio::test]
+async fn test_annotated_opcodes() -> eyre::Result<()> {
+    let tracer = run(addr(FAIL_ADDRESS), "is_owner()", &[]).await?;
+
+    let sources: Vec<_> = tracer
+        .peek()
+        .iter()
+        .filter_map(|event| match &event.data {
+            EventData::OpCode(op) => Some((op.pc, op.debug.get("source").cloned())),
+            _ => None,
+        })
+        .collect();
+    let source = |pc| {
+        sources
+            .iter()
+            .find(|(at, _)| *at == pc)
+            .and_then(|(_, source)| source.clone())
+    };
+    assert_eq!(
+        source(0x7a),
+        Some(serde_json::json!({
+            "file": "Fail.sol",
+            "line": 19,
+            "column": 9,
+            "function": "Fail.is_owner",
+        }))
+    );
+    assert_eq!(
+        source(0x00),
+        Some(serde_json::json!({
+            "file": "Fail.sol",
+            "line": 6,
+            "column": 1,
+            "function": "Fail",
+        }))
+    );
+    // Not in the source map: ABI decoding helpers
+    assert!(sources.iter().any(|(_, source)| source.is_none()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_revert_stack_trace() -> eyre::Result<()> {
+    let tracer = run(addr(FAIL_ADDRESS), "is_owner()", &[]).await?;
+    let trace = tracer.revert().expect("stack trace");
+    assert_eq!(trace.reason.as_deref(), Some("not an owner"));
+    assert_eq!(
+        trace.to_string(),
+        "Error: not an owner\n    at Fail.is_owner (Fail.sol:19:9)\n"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_nested_revert_stack_trace() -> eyre::Result<()> {
+    let tracer = run(
+        addr(CALL_ADDRESS),
+        "set(uint256)",
+        &Word::one().into_bytes(),
+    )
+    .await?;
+    let trace = tracer.revert().expect("stack trace");
+    assert_eq!(trace.reason, None);
+    assert_eq!(
+        trace.frames.iter().map(|f| f.address).collect::<Vec<_>>(),
+        vec![addr(FAIL_ADDRESS), addr(CALL_ADDRESS)]
+    );
+    assert_eq!(
+        trace.to_string(),
+        "Error: reverted\n    at Fail (Fail.sol:6:1)\n    at Call.set (Call.sol:37:9)\n"
+    );
+    Ok(())
+}
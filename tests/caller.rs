@@ -10,6 +10,7 @@ use solenoid::{
     eth::EthClient,
     executor::{AccountTouch, Evm, Executor},
     ext::Ext,
+    spec::Spec,
     tracer::NoopTracer,
 };
 
@@ -22,7 +23,7 @@ async fn test_deploy() -> eyre::Result<()> {
     dotenv::dotenv()?;
     let code = include_str!("../etc/call/Call.bin");
     let code = hex::decode(code)?;
-    let code = Decoder::decode(code);
+    let code = Decoder::decode(code, Spec::default());
     let to = Address::zero();
 
     // TODO: extract EthClient trait and provide mock impl here?
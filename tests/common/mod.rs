@@ -0,0 +1,20 @@
+//! Fixtures shared by the integration tests
+
+use evm_common::{address::Address, hash::keccak256, word::Word};
+
+use solenoid::ext::{Account, Ext};
+
+/// Account holding the given bytecode
+pub fn contract(code: &[u8]) -> Account {
+    Account {
+        code: (code.to_vec(), Word::from_bytes(&keccak256(code))),
+        ..Account::default()
+    }
+}
+
+/// Local state made of the given accounts only
+pub fn local(accounts: impl IntoIterator<Item = (Address, Account)>) -> Ext {
+    let mut ext = Ext::local();
+    ext.state.extend(accounts);
+    ext
+}
@@ -0,0 +1,108 @@
+use evm_common::{address::addr, block::Header, word::Word};
+
+use solenoid::{
+    ext::{Account, TxContext},
+    solenoid::{Builder, ExecuteBuilder, Solenoid},
+    spec::Chain,
+};
+
+mod common;
+
+const FROM: &str = "f39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+const TO: &str = "00000000000000000000000000000000000000aa";
+
+// <op>; PUSH0; MSTORE; RETURN(0, 32)
+fn returning(op: &str) -> String {
+    format!("{op}5f5260205ff3")
+}
+
+// Word returned by the code
+async fn run<F>(code: &str, tx_ctx: TxContext, configure: F) -> eyre::Result<Word>
+where
+    F: FnOnce(ExecuteBuilder) -> ExecuteBuilder,
+{
+    let mut ext = common::local([
+        (addr(FROM), Account::default()),
+        (addr(TO), common::contract(&hex::decode(returning(code))?)),
+    ]);
+    ext.reset(tx_ctx);
+    let builder = Solenoid::new()
+        .execute(addr(TO), "", &[])
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(100_000u64));
+    let result = configure(builder).ready().apply(&mut ext).await?;
+    assert!(!result.evm.reverted);
+    Ok(Word::from_bytes(&result.ret))
+}
+
+#[tokio::test]
+async fn test_blobhash() -> eyre::Result<()> {
+    let hashes = vec![Word::from(0x0101u64), Word::from(0x0102u64)];
+    for (index, expected) in [(0, hashes[0]), (1, hashes[1]), (2, Word::zero())] {
+        let tx_ctx = TxContext {
+            blob_hashes: hashes.clone(),
+            ..TxContext::default()
+        };
+        // BLOBHASH(index)
+        let hash = run(&format!("60{index:02x}49"), tx_ctx, |builder| builder).await?;
+        assert_eq!(hash, expected);
+    }
+
+    // Out of range by far: the index is not truncated
+    let tx_ctx = TxContext {
+        blob_hashes: hashes.clone(),
+        ..TxContext::default()
+    };
+    // BLOBHASH(2^64)
+    let hash = run("6801000000000000000049", tx_ctx, |builder| builder).await?;
+    assert_eq!(hash, Word::zero());
+
+    // No blobs
+    let hash = run("5f49", TxContext::default(), |builder| builder).await?;
+    assert_eq!(hash, Word::zero());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_prevrandao() -> eyre::Result<()> {
+    let randao = Word::from(0xabcdu64);
+    // After the Merge: the randomness of the beacon chain
+    let header = Header {
+        mix_hash: randao,
+        ..Header::default()
+    };
+    let value = run("44", TxContext::default(), |builder| {
+        builder.with_header(header)
+    })
+    .await?;
+    assert_eq!(value, randao);
+
+    // Before the Merge: DIFFICULTY, the mix hash is the proof-of-work one
+    let header = Header {
+        mix_hash: randao,
+        difficulty: Word::from(0x0bad_f00du64),
+        ..Header::default()
+    };
+    let value = run("44", TxContext::default(), |builder| {
+        builder.with_header(header)
+    })
+    .await?;
+    assert_eq!(value, Word::from(0x0bad_f00du64));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_chainid() -> eyre::Result<()> {
+    for chain in [Chain::mainnet(), Chain::sepolia(), Chain::holesky()] {
+        let id = Word::from(chain.id);
+        let value = run("46", TxContext::default(), |builder| {
+            builder.with_chain(chain)
+        })
+        .await?;
+        assert_eq!(value, id);
+    }
+    // Default chain: mainnet
+    let value = run("46", TxContext::default(), |builder| builder).await?;
+    assert_eq!(value, Word::one());
+    Ok(())
+}
@@ -9,6 +9,7 @@ use solenoid::{
     eth::EthClient,
     executor::{AccountTouch, Evm, Executor},
     ext::Ext,
+    spec::Spec,
     tracer::NoopTracer,
 };
 
@@ -16,7 +17,7 @@ static CODE: &str = include_str!("../etc/counter/Counter.bin-runtime");
 
 fn code() -> eyre::Result<Bytecode> {
     let code = hex::decode(CODE.trim_start_matches("0x"))?;
-    Ok(Decoder::decode(code))
+    Ok(Decoder::decode(code, Spec::default()))
 }
 
 async fn call(
@@ -56,7 +57,7 @@ async fn test_deploy() -> eyre::Result<()> {
     dotenv::dotenv()?;
     let code = include_str!("../etc/counter/Counter.bin");
     let code = hex::decode(code)?;
-    let code = Decoder::decode(code);
+    let code = Decoder::decode(code, Spec::default());
     let to = Address::zero();
 
     // TODO: extract EthClient trait and provide mock impl here?
@@ -0,0 +1,98 @@
+use evm_common::{
+    address::{Address, addr},
+    word::Word,
+};
+
+use solenoid::{
+    artifact::{Contract, Source, SourceMap},
+    coverage::{Coverage, CoverageTracer},
+    ext::Account,
+    solenoid::{Builder, Solenoid},
+};
+
+mod common;
+
+static SOURCE: &str = "contract Flag {
+    fallback() external {
+        if (msg.data.length > 0) {
+            flag = 1;
+        }
+    }
+}
+";
+
+// CALLDATASIZE; PUSH1 5; JUMPI; STOP; JUMPDEST; SSTORE(0, 1); STOP
+static FLAG: &str = "36600557005b600160005500";
+
+// CALL(GAS, FLAG, 0, 0, 1, 0, 0); STOP
+static CALLER: &str = "6000600060016000600073000000000000000000000000000000000000f1a65af100";
+
+fn contract() -> Contract {
+    let offset = |s: &str| SOURCE.find(s).unwrap();
+    let map = format!(
+        "{}:24:0:-;;;{}:8;{}:8;;;;{}:8",
+        offset("if"),
+        offset("fallback"),
+        offset("flag"),
+        offset("fallback"),
+    );
+    Contract {
+        bytecode: hex::decode(FLAG).unwrap(),
+        source_map: SourceMap::parse(&map).unwrap(),
+    }
+}
+
+async fn run(to: Address, data: &[u8], code: &str) -> eyre::Result<Coverage> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let mut ext = common::local([
+        (from, Account::default()),
+        (
+            addr("000000000000000000000000000000000000f1a6"),
+            common::contract(&hex::decode(FLAG)?),
+        ),
+        (
+            addr("00000000000000000000000000000000000ca11e"),
+            common::contract(&hex::decode(CALLER)?),
+        ),
+    ]);
+    let result = Solenoid::new()
+        .execute(to, "", data)
+        .with_sender(from)
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .apply_with_tracer(CoverageTracer::new(&hex::decode(code)?), &mut ext)
+        .await?;
+    Ok(result.tracer.into_coverage())
+}
+
+#[tokio::test]
+async fn test_coverage() -> eyre::Result<()> {
+    let flag = addr("000000000000000000000000000000000000f1a6");
+    let contract = contract();
+    let sources = [Source {
+        path: "Flag.sol".to_string(),
+        content: SOURCE.to_string(),
+    }];
+
+    let mut coverage = run(flag, &[], FLAG).await?;
+    assert_eq!(
+        coverage.lcov(std::slice::from_ref(&contract), &sources),
+        "TN:\nSF:Flag.sol\nBRDA:3,0,0,0\nBRDA:3,0,1,1\nBRF:2\nBRH:1\n\
+         DA:2,1\nDA:3,1\nDA:4,0\nLF:3\nLH:2\nend_of_record\n"
+    );
+
+    coverage.merge(run(flag, &[1], FLAG).await?);
+    // Nested call, the code is known from the caller
+    let caller = addr("00000000000000000000000000000000000ca11e");
+    coverage.merge(run(caller, &[], CALLER).await?);
+
+    let flag_coverage = coverage.get(&contract.bytecode).unwrap();
+    assert_eq!(flag_coverage.branches[&3], (2, 1));
+    assert_eq!(flag_coverage.hits[&10], 2);
+    assert_eq!(
+        coverage.lcov(&[contract], &sources),
+        "TN:\nSF:Flag.sol\nBRDA:3,0,0,2\nBRDA:3,0,1,1\nBRF:2\nBRH:2\n\
+         DA:2,2\nDA:3,3\nDA:4,2\nLF:3\nLH:3\nend_of_record\n"
+    );
+    Ok(())
+}
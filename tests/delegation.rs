@@ -0,0 +1,116 @@
+use k256::ecdsa::SigningKey;
+
+use evm_common::{
+    address::{Address, addr},
+    block::Authorization,
+    hash::keccak256,
+    word::Word,
+};
+
+use solenoid::{
+    delegation::{self, signing_hash},
+    ext::{Account, Ext, TxContext},
+    solenoid::{Builder, CallResult, Solenoid},
+    spec::Spec,
+    tracer::NoopTracer,
+};
+
+mod common;
+
+// SSTORE(0, 42)
+static STORE: &str = "602a5f5500";
+
+const FROM: &str = "f39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+const TARGET: &str = "00000000000000000000000000000000000000aa";
+
+fn key() -> SigningKey {
+    SigningKey::from_slice(&[0x11u8; 32]).unwrap()
+}
+
+fn authority() -> Address {
+    let pubkey = key().verifying_key().to_encoded_point(false);
+    let hash = keccak256(&pubkey.as_bytes()[1..]);
+    Address::try_from(&hash[12..]).unwrap()
+}
+
+// Delegation of the authority to TARGET, valid on any chain
+fn authorization() -> Authorization {
+    let mut auth = Authorization {
+        chain_id: Word::zero(),
+        address: addr(TARGET),
+        nonce: Word::zero(),
+        y_parity: Word::zero(),
+        r: Word::zero(),
+        s: Word::zero(),
+    };
+    let (signature, recovery_id) = key()
+        .sign_prehash_recoverable(&signing_hash(&auth))
+        .unwrap();
+    let bytes = signature.to_bytes();
+    auth.r = Word::from_bytes(&bytes[..32]);
+    auth.s = Word::from_bytes(&bytes[32..]);
+    auth.y_parity = Word::from(recovery_id.to_byte() as u64);
+    auth
+}
+
+// Type-4 transaction calling the authority
+async fn call(spec: Spec) -> eyre::Result<(CallResult<NoopTracer>, Ext)> {
+    // The authority already exists: the authorization is partly refunded
+    let existing = Account {
+        value: Word::one(),
+        ..Account::default()
+    };
+    let mut ext = common::local([
+        (addr(FROM), Account::default()),
+        (addr(TARGET), common::contract(&hex::decode(STORE)?)),
+        (authority(), existing),
+    ]);
+    ext.reset(TxContext {
+        authorization_list: vec![authorization()],
+        ..TxContext::default()
+    });
+    let result = Solenoid::new()
+        .execute(authority(), "", &[])
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(100_000u64))
+        .with_spec(spec)
+        .ready()
+        .apply_with_tracer(NoopTracer, &mut ext)
+        .await?;
+    Ok((result, ext))
+}
+
+#[tokio::test]
+async fn test_delegated_call() -> eyre::Result<()> {
+    let (result, mut ext) = call(Spec::Prague).await?;
+    assert!(!result.evm.reverted);
+
+    let (code, _) = ext.code(&authority()).await?;
+    assert_eq!(code, delegation::designator(&addr(TARGET)));
+    assert_eq!(ext.nonce(&authority()).await?, Word::one());
+    // The code of the target runs on the storage of the authority
+    assert_eq!(
+        ext.get(&authority(), &Word::zero()).await?,
+        Word::from(42u64)
+    );
+    assert_eq!(ext.get(&addr(TARGET), &Word::zero()).await?, Word::zero());
+
+    // Intrinsic 21000 + 25000, SSTORE 22100 + PUSH1 3 + PUSH0 2, then the
+    // refund of the existing authority
+    let refund = delegation::PER_EMPTY_ACCOUNT_COST - delegation::PER_AUTH_BASE_COST;
+    assert_eq!(result.gas.gas_use, 21000 + 25000 + 22105 - refund);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_authorizations_before_prague() -> eyre::Result<()> {
+    let (result, mut ext) = call(Spec::Cancun).await?;
+    assert!(!result.evm.reverted);
+
+    // Neither applied nor charged
+    let (code, _) = ext.code(&authority()).await?;
+    assert!(code.is_empty());
+    assert_eq!(ext.nonce(&authority()).await?, Word::zero());
+    assert_eq!(result.gas.gas_use, 21000);
+    Ok(())
+}
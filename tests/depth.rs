@@ -0,0 +1,66 @@
+use evm_common::{address::addr, call::Call, word::Word};
+use evm_event::EventData;
+
+use solenoid::{
+    decoder::Decoder,
+    executor::{Evm, Executor},
+    ext::Account,
+    spec::Spec,
+    tracer::{EventTracer, LoggingTracer},
+};
+
+mod common;
+
+// CALL(gas(), address(), 0, 0, 0, 0, 0); STOP
+static RECURSIVE: &str = "60006000600060006000305af100";
+
+#[test]
+fn test_max_call_depth_on_small_stack() -> eyre::Result<()> {
+    // Way below the stack size required by a recursive executor at depth 1024
+    let handle = std::thread::Builder::new()
+        .stack_size(512 * 1024)
+        .spawn(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .build()?
+                .block_on(max_depth())
+        })?;
+    let depth = handle.join().expect("no stack overflow")?;
+    assert_eq!(depth, 1025);
+    Ok(())
+}
+
+async fn max_depth() -> eyre::Result<usize> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let to = addr("5fbdb2315678afecb367f032d93f642f64180aa3");
+
+    let code = hex::decode(RECURSIVE)?;
+    let mut ext = common::local([(from, Account::default()), (to, common::contract(&code))]);
+
+    let call = Call {
+        data: vec![],
+        value: Word::zero(),
+        from,
+        to,
+        gas: Word::from(1_000_000_000_000u64),
+    };
+    let executor = Executor::<LoggingTracer>::with_tracer(LoggingTracer::default());
+    let mut evm = Evm::default();
+    let (mut tracer, _) = executor
+        .execute(
+            &Decoder::decode(code, Spec::default()),
+            &call,
+            &mut evm,
+            &mut ext,
+        )
+        .await?;
+    assert!(!evm.reverted);
+
+    let depth = tracer
+        .take()
+        .into_iter()
+        .filter(|event| matches!(event.data, EventData::Call { .. }))
+        .map(|event| event.depth)
+        .max()
+        .unwrap_or_default();
+    Ok(depth)
+}
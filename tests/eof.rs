@@ -0,0 +1,217 @@
+use evm_common::{address::addr, call::Call, word::Word};
+
+use solenoid::{
+    decoder::Decoder,
+    eof,
+    executor::{Evm, Executor},
+    ext::{Account, Ext},
+    solenoid::{Builder, Solenoid},
+    spec::Spec,
+    tracer::NoopTracer,
+};
+
+mod common;
+
+type Section<'a> = ((u8, u8, u16), &'a str);
+
+fn container(sections: &[Section], containers: &[Vec<u8>], data: &str, data_size: u16) -> Vec<u8> {
+    let code = sections
+        .iter()
+        .map(|(_, code)| hex::decode(code).unwrap())
+        .collect::<Vec<_>>();
+    let mut bytes = vec![0xef, 0x00, 0x01, 0x01];
+    bytes.extend_from_slice(&(4 * sections.len() as u16).to_be_bytes());
+    bytes.push(0x02);
+    bytes.extend_from_slice(&(code.len() as u16).to_be_bytes());
+    for code in &code {
+        bytes.extend_from_slice(&(code.len() as u16).to_be_bytes());
+    }
+    if !containers.is_empty() {
+        bytes.push(0x03);
+        bytes.extend_from_slice(&(containers.len() as u16).to_be_bytes());
+        for container in containers {
+            bytes.extend_from_slice(&(container.len() as u32).to_be_bytes());
+        }
+    }
+    bytes.push(0xff);
+    bytes.extend_from_slice(&data_size.to_be_bytes());
+    bytes.push(0x00);
+    for ((inputs, outputs, max), _) in sections {
+        bytes.extend_from_slice(&[*inputs, *outputs]);
+        bytes.extend_from_slice(&max.to_be_bytes());
+    }
+    code.iter().for_each(|code| bytes.extend_from_slice(code));
+    containers
+        .iter()
+        .for_each(|container| bytes.extend_from_slice(container));
+    bytes.extend_from_slice(&hex::decode(data).unwrap());
+    bytes
+}
+
+async fn call(spec: Spec, code: Vec<u8>, ext: &mut Ext) -> eyre::Result<(Evm, Vec<u8>)> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let to = addr("5fbdb2315678afecb367f032d93f642f64180aa3");
+    ext.state.insert(from, Account::default());
+    ext.state.insert(to, common::contract(&code));
+
+    let call = Call {
+        data: vec![],
+        value: Word::zero(),
+        from,
+        to,
+        gas: Word::from(1_000_000u64),
+    };
+    let executor = Executor::<NoopTracer>::new().with_spec(spec);
+    let mut evm = Evm::default();
+    let (_, ret) = executor
+        .execute(&Decoder::decode(code, spec), &call, &mut evm, ext)
+        .await?;
+    Ok((evm, ret))
+}
+
+// Section 0: CALLF 1; DUP1; RJUMPI +1; INVALID; PUSH0; MSTORE; PUSH1 32; PUSH0; RETURN
+// Section 1: DATALOADN 0; DUP1; ADD; RETF
+fn doubler() -> Vec<u8> {
+    let data = format!("{:064x}", 21);
+    container(
+        &[
+            ((0, 0x80, 2), "e3000180e10001fe5f5260205ff3"),
+            ((0, 1, 2), "d100008001e4"),
+        ],
+        &[],
+        &data,
+        32,
+    )
+}
+
+#[tokio::test]
+async fn test_eof_sections() -> eyre::Result<()> {
+    let mut ext = Ext::local();
+    let (evm, ret) = call(Spec::Eof, doubler(), &mut ext).await?;
+    assert!(!evm.reverted);
+    assert_eq!(Word::from_bytes(&ret), Word::from(42u64));
+
+    // EOF is not enabled: 0xEF is an undefined opcode
+    let mut ext = Ext::local();
+    let (evm, ret) = call(Spec::Osaka, doubler(), &mut ext).await?;
+    assert!(evm.reverted);
+    assert!(ret.is_empty());
+    Ok(())
+}
+
+// Deployed code, data is appended on deploy: DATALOADN 0; PUSH0; MSTORE; PUSH1 32; PUSH0; RETURN
+fn runtime() -> Vec<u8> {
+    container(&[((0, 0x80, 2), "d100005f5260205ff3")], &[], "", 32)
+}
+
+// Append the calldata to the deployed code:
+// PUSH1 32; PUSH0; PUSH0; CALLDATACOPY; PUSH1 32; PUSH0; RETURNCONTRACT 0
+fn initcode() -> Vec<u8> {
+    container(
+        &[((0, 0x80, 3), "60205f5f3760205fee00")],
+        &[runtime()],
+        "",
+        0,
+    )
+}
+
+#[tokio::test]
+async fn test_eof_create() -> eyre::Result<()> {
+    // MSTORE(0, 42); EOFCREATE 0 (value 0, salt 0, input 0..32); PUSH0; MSTORE; RETURN 0..32
+    let factory = container(
+        &[((0, 0x80, 4), "602a5f5260205f5f5fec005f5260205ff3")],
+        &[initcode()],
+        "",
+        0,
+    );
+    let mut ext = Ext::local();
+    let (evm, ret) = call(Spec::Eof, factory, &mut ext).await?;
+    assert!(!evm.reverted);
+
+    let to = addr("5fbdb2315678afecb367f032d93f642f64180aa3");
+    let created = eof::create_address(&to, &Word::zero());
+    assert_eq!(Word::from_bytes(&ret), (&created).into());
+
+    let (code, _) = ext.code(&created).await?;
+    let mut expected = runtime();
+    expected.extend_from_slice(&Word::from(42u64).into_bytes());
+    assert_eq!(code, expected);
+    assert_eq!(ext.nonce(&to).await?, Word::one());
+
+    // Deployed code returns its data
+    let (evm, ret) = call(Spec::Eof, code, &mut Ext::local()).await?;
+    assert!(!evm.reverted);
+    assert_eq!(Word::from_bytes(&ret), Word::from(42u64));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eof_create_tx() -> eyre::Result<()> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let mut ext = common::local([(from, Account::default())]);
+
+    // EIP-7698: calldata follows the initcode container
+    let mut code = initcode();
+    code.extend_from_slice(&Word::from(7u64).into_bytes());
+    let result = Solenoid::new()
+        .create(code)
+        .with_sender(from)
+        .with_gas(Word::from(1_000_000u64))
+        .with_spec(Spec::Eof)
+        .ready()
+        .apply(&mut ext)
+        .await?;
+    assert!(!result.evm.reverted);
+
+    let created = from.create(Word::zero());
+    let (code, _) = ext.code(&created).await?;
+    let mut expected = runtime();
+    expected.extend_from_slice(&Word::from(7u64).into_bytes());
+    assert_eq!(code, expected);
+
+    // Invalid initcode: only the intrinsic gas is charged
+    let mut code = initcode();
+    let len = code.len();
+    code[len - 1] = 0x01;
+    let result = Solenoid::new()
+        .create(code)
+        .with_sender(from)
+        .with_gas(Word::from(1_000_000u64))
+        .with_spec(Spec::Eof)
+        .ready()
+        .apply(&mut ext)
+        .await?;
+    assert!(result.evm.reverted);
+    assert_eq!(result.evm.gas.used, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eof_ext_call() -> eyre::Result<()> {
+    let callee = addr("00000000000000000000000000000000000000ca");
+    // EXTCALL(callee, 0, 0, 0); PUSH0; RETURNDATALOAD; PUSH0; MSTORE;
+    // PUSH1 32; MSTORE; PUSH1 64; PUSH0; RETURN
+    let caller = container(
+        &[(
+            (0, 0x80, 4),
+            "5f5f5f7300000000000000000000000000000000000000caf85ff75f5260205260405ff3",
+        )],
+        &[],
+        "",
+        0,
+    );
+
+    // Status: 0 on success, 1 on revert, 2 on exceptional halt
+    for (code, data, status) in [
+        (doubler(), 42u64, 0u64),
+        (hex::decode("5f5ffd")?, 0, 1),
+        (hex::decode("fe")?, 0, 2),
+    ] {
+        let mut ext = common::local([(callee, common::contract(&code))]);
+        let (evm, ret) = call(Spec::Eof, caller.clone(), &mut ext).await?;
+        assert!(!evm.reverted);
+        assert_eq!(Word::from_bytes(&ret[..32]), Word::from(data));
+        assert_eq!(Word::from_bytes(&ret[32..]), Word::from(status));
+    }
+    Ok(())
+}
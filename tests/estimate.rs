@@ -0,0 +1,190 @@
+use evm_common::{
+    address::{Address, addr},
+    word::Word,
+};
+
+use solenoid::{
+    estimate::{EstimateError, GasEstimate},
+    ext::{Account, Ext},
+    solenoid::{Builder, Solenoid},
+    spec::Spec,
+};
+
+mod common;
+
+static FAIL: &str = include_str!("../etc/fail/Fail.bin-runtime");
+
+// CALL(GAS, 0xbb, 0, 0, 0, 0, 0) or REVERT
+static CALLER: &str = "5f5f5f5f5f7300000000000000000000000000000000000000bb5af115602157005b5f5ffd";
+
+// SSTORE(1, 1)
+static STORE: &str = "600160015500";
+
+// SSTORE(0, 0) of a non-zero slot: refunded
+static CLEAR: &str = "5f5f5500";
+
+// JUMPDEST; JUMP(0)
+static LOOP: &str = "5b5f56";
+
+const FROM: &str = "f39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+const TO: &str = "00000000000000000000000000000000000000aa";
+const CALLEE: &str = "00000000000000000000000000000000000000bb";
+
+fn ext(code: &str) -> Ext {
+    let mut ext = common::local([
+        (addr(FROM), Account::default()),
+        (
+            addr(TO),
+            common::contract(&hex::decode(code.trim()).unwrap()),
+        ),
+        (addr(CALLEE), common::contract(&hex::decode(STORE).unwrap())),
+    ]);
+    ext.state_mut(&addr(TO)).insert(Word::zero(), Word::one());
+    ext
+}
+
+async fn run(code: &str, to: Address, data: &[u8], gas: u64) -> eyre::Result<(bool, i64)> {
+    let result = Solenoid::new()
+        .execute(to, "", data)
+        .with_spec(Spec::Prague)
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(gas))
+        .ready()
+        .apply(&mut ext(code))
+        .await?;
+    Ok((!result.evm.reverted, result.gas.gas_use))
+}
+
+async fn estimate(code: &str, to: Address, data: &[u8]) -> eyre::Result<GasEstimate> {
+    let mut ext = ext(code);
+    let estimate = Solenoid::new()
+        .execute(to, "", data)
+        .with_spec(Spec::Prague)
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .estimate_gas(&mut ext)
+        .await?;
+    // Executed on forks of the state
+    assert!(ext.nonce(&addr(FROM)).await?.is_zero());
+
+    // Minimal
+    let gas = estimate.gas_limit as u64;
+    assert_eq!(run(code, to, data, gas).await?, (true, estimate.gas_used));
+    assert!(!run(code, to, data, gas - 1).await?.0);
+    Ok(estimate)
+}
+
+#[tokio::test]
+async fn test_estimate_nested_call() -> eyre::Result<()> {
+    let estimate = estimate(CALLER, addr(TO), &[]).await?;
+    // 1/64 of the gas is withheld from the callee
+    assert!(estimate.gas_limit > estimate.gas_used);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_estimate_refund() -> eyre::Result<()> {
+    let estimate = estimate(CLEAR, addr(TO), &[]).await?;
+    // The refund is only paid back after the execution
+    assert!(estimate.gas_limit >= estimate.gas_used + 4800);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_estimate_floor() -> eyre::Result<()> {
+    // EIP-7623: calldata costs 40 per non-zero byte without execution
+    let mut ext = ext(STORE);
+    let estimate = Solenoid::new()
+        .execute(addr(FROM), "", &[1u8; 100])
+        .with_spec(Spec::Prague)
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .estimate_gas(&mut ext)
+        .await?;
+    assert_eq!(estimate.gas_limit, 21_000 + 100 * 40);
+    assert_eq!(estimate.gas_used, 21_000 + 100 * 40);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_estimate_create() -> eyre::Result<()> {
+    // STOP followed by 99 non-zero bytes: only the intrinsic gas is used
+    let mut code = vec![0x00];
+    code.extend([1u8; 99]);
+    let mut ext = ext(STORE);
+    let estimate = Solenoid::new()
+        .create(code)
+        .with_spec(Spec::Prague)
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .estimate_gas(&mut ext)
+        .await?;
+    // Base, creation, calldata and EIP-3860 initcode words
+    let intrinsic = 21_000 + 32_000 + 4 + 99 * 16 + 4 * 2;
+    assert_eq!(estimate.gas_limit, intrinsic);
+    assert_eq!(estimate.gas_used, intrinsic);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_floor() -> eyre::Result<()> {
+    // EIP-7623: large initcode costs more as calldata tokens than as
+    // intrinsic gas
+    let mut code = vec![0x00];
+    code.extend([1u8; 1999]);
+    let result = Solenoid::new()
+        .create(code)
+        .with_spec(Spec::Prague)
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .apply(&mut ext(STORE))
+        .await?;
+    assert!(!result.evm.reverted);
+    assert_eq!(result.gas.gas_use, 21_000 + 10 * (1 + 1999 * 4));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_estimate_revert_reason() -> eyre::Result<()> {
+    let mut ext = ext(FAIL);
+    let error = Solenoid::new()
+        .execute(addr(TO), "is_owner()", &[])
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .estimate_gas(&mut ext)
+        .await
+        .unwrap_err();
+    let error = error.downcast::<EstimateError>()?;
+    assert_eq!(error.to_string(), "Execution reverted: not an owner");
+    assert!(matches!(
+        error,
+        EstimateError::Reverted {
+            reason: Some(_),
+            ..
+        }
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_estimate_out_of_gas() -> eyre::Result<()> {
+    let mut ext = ext(LOOP);
+    let error = Solenoid::new()
+        .execute(addr(TO), "", &[])
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(100_000u64))
+        .ready()
+        .estimate_gas(&mut ext)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.downcast::<EstimateError>()?,
+        EstimateError::GasLimitExceeded(100_000)
+    );
+    Ok(())
+}
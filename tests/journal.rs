@@ -0,0 +1,117 @@
+use evm_common::{
+    address::{Address, addr},
+    word::Word,
+};
+
+use solenoid::{
+    ext::{Account, Ext},
+    solenoid::{Builder, Solenoid},
+};
+
+mod common;
+
+// SSTORE(0, 1); CALL(GAS, 0xbb, 1, 0, 0, 0, 0); SSTORE(1, success)
+static CALLER: &str = "60015f555f5f5f5f60017300000000000000000000000000000000000000bb5af1600155";
+
+// SSTORE(0, 42); LOG0(0, 0); REVERT(0, 0)
+static REVERTING: &str = "602a5f555f5fa05f5ffd";
+
+// SSTORE(0, 42); LOG0(0, 0); STOP
+static STORING: &str = "602a5f555f5fa000";
+
+const CALLER_ADDRESS: Address = Address([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xaa,
+]);
+const CALLEE_ADDRESS: Address = Address([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xbb,
+]);
+
+fn account(code: &str, value: u64) -> Account {
+    Account {
+        value: Word::from(value),
+        ..common::contract(&hex::decode(code).unwrap())
+    }
+}
+
+async fn run(caller: String, callee: &str) -> eyre::Result<(Ext, bool)> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let mut ext = common::local([
+        (from, Account::default()),
+        (CALLER_ADDRESS, account(&caller, 10)),
+        (CALLEE_ADDRESS, account(callee, 0)),
+    ]);
+    let result = Solenoid::new()
+        .execute(CALLER_ADDRESS, "", &[])
+        .with_sender(from)
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .apply(&mut ext)
+        .await?;
+    Ok((ext, result.evm.reverted))
+}
+
+#[tokio::test]
+async fn test_reverted_call() -> eyre::Result<()> {
+    let (mut ext, reverted) = run(format!("{CALLER}00"), REVERTING).await?;
+    assert!(!reverted);
+
+    // The caller continues with the failure of the call
+    assert_eq!(ext.get(&CALLER_ADDRESS, &Word::zero()).await?, Word::one());
+    assert_eq!(ext.get(&CALLER_ADDRESS, &Word::one()).await?, Word::zero());
+    // Everything done by the callee is undone, including the value transfer
+    assert_eq!(ext.get(&CALLEE_ADDRESS, &Word::zero()).await?, Word::zero());
+    assert_eq!(ext.balance(&CALLEE_ADDRESS).await?, Word::zero());
+    assert_eq!(ext.balance(&CALLER_ADDRESS).await?, Word::from(10u64));
+    assert!(ext.logs.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reverted_caller() -> eyre::Result<()> {
+    let (mut ext, reverted) = run(format!("{CALLER}5f5ffd"), STORING).await?;
+    assert!(reverted);
+
+    // The call succeeded, but is undone with its caller
+    assert_eq!(ext.get(&CALLER_ADDRESS, &Word::zero()).await?, Word::zero());
+    assert_eq!(ext.get(&CALLEE_ADDRESS, &Word::zero()).await?, Word::zero());
+    assert_eq!(ext.balance(&CALLEE_ADDRESS).await?, Word::zero());
+    assert_eq!(ext.balance(&CALLER_ADDRESS).await?, Word::from(10u64));
+    assert!(ext.logs.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_successful_call() -> eyre::Result<()> {
+    let (mut ext, reverted) = run(format!("{CALLER}00"), STORING).await?;
+    assert!(!reverted);
+
+    assert_eq!(ext.get(&CALLER_ADDRESS, &Word::one()).await?, Word::one());
+    assert_eq!(
+        ext.get(&CALLEE_ADDRESS, &Word::zero()).await?,
+        Word::from(42u64)
+    );
+    assert_eq!(ext.balance(&CALLEE_ADDRESS).await?, Word::one());
+    assert_eq!(ext.logs.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reverted_create() -> eyre::Result<()> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let mut ext = common::local([(from, Account::default())]);
+    // REVERT(0, 0)
+    let result = Solenoid::new()
+        .create(hex::decode("5f5ffd")?)
+        .with_sender(from)
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .apply(&mut ext)
+        .await?;
+    assert!(result.evm.reverted);
+    assert!(ext.created_accounts.is_empty());
+
+    // A call creates nothing
+    let (ext, _) = run(format!("{CALLER}00"), STORING).await?;
+    assert!(ext.created_accounts.is_empty());
+    Ok(())
+}
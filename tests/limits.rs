@@ -0,0 +1,199 @@
+use std::time::{Duration, Instant};
+
+use evm_common::{
+    address::{Address, addr},
+    call::Call,
+    word::Word,
+};
+use evm_event::{EventData, HaltReason};
+
+use solenoid::{
+    decoder::Decoder,
+    executor::{Evm, Executor},
+    ext::{Account, Ext},
+    limits::{CancellationToken, ExecutionLimits},
+    solenoid::{Builder, ExecuteBuilder, Solenoid},
+    spec::Spec,
+    tracer::{EventTracer, LoggingTracer},
+    validation::InvalidTx,
+};
+
+mod common;
+
+// MSTORE(0, 0); RETURN(0, 32): deploys 32 zero bytes
+static INITCODE: &str = "600060005260206000f3";
+
+// JUMPDEST; PUSH1 0; JUMP: loops until out of gas
+static LOOP: &str = "5b600056";
+
+// MSTORE(0x10000, 1)
+static MSTORE: &str = "600162010000525f5ff3";
+
+// PUSH0 17 times; STOP
+static PUSH17: &str = "5f5f5f5f5f5f5f5f5f5f5f5f5f5f5f5f5f00";
+
+// JUMPDEST; SSTORE(0, 1); PUSH1 0; JUMP: writes storage until out of gas
+static SSTORE_LOOP: &str = "5b6001600055600056";
+
+async fn create(code: &str, limits: ExecutionLimits) -> eyre::Result<bool> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let mut ext = common::local([(from, Account::default())]);
+    let result = Solenoid::new()
+        .create(hex::decode(code)?)
+        .with_sender(from)
+        .with_gas(Word::from(1_000_000u64))
+        .with_limits(limits)
+        .ready()
+        .apply(&mut ext)
+        .await?;
+    Ok(result.evm.reverted)
+}
+
+async fn call(code: &str, limits: ExecutionLimits) -> eyre::Result<(Evm, LoggingTracer)> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let to = addr("5fbdb2315678afecb367f032d93f642f64180aa3");
+    let code = hex::decode(code)?;
+    let mut ext = common::local([(from, Account::default()), (to, common::contract(&code))]);
+    let call = Call {
+        data: vec![],
+        value: Word::zero(),
+        from,
+        to,
+        gas: Word::from(1_000_000u64),
+    };
+    let executor =
+        Executor::<LoggingTracer>::with_tracer(LoggingTracer::default()).with_limits(limits);
+    let mut evm = Evm::default();
+    let (tracer, _) = executor
+        .execute(
+            &Decoder::decode(code, Spec::default()),
+            &call,
+            &mut evm,
+            &mut ext,
+        )
+        .await?;
+    Ok((evm, tracer))
+}
+
+/// Run the storage writing loop with practically unlimited gas
+async fn run_until_halted<F>(configure: F) -> eyre::Result<(Evm, Ext, Address)>
+where
+    F: FnOnce(ExecuteBuilder) -> ExecuteBuilder,
+{
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let to = addr("5fbdb2315678afecb367f032d93f642f64180aa3");
+    let code = hex::decode(SSTORE_LOOP)?;
+    let mut ext = common::local([(from, Account::default()), (to, common::contract(&code))]);
+    let builder = Solenoid::new()
+        .execute(to, "", &[])
+        .with_sender(from)
+        .with_gas(Word::from(u64::MAX / 2));
+    let result = configure(builder).ready().apply(&mut ext).await?;
+    Ok((result.evm, ext, to))
+}
+
+#[tokio::test]
+async fn test_code_size_limit() -> eyre::Result<()> {
+    let reverted = create(INITCODE, ExecutionLimits::default()).await?;
+    assert!(!reverted);
+
+    let limits = ExecutionLimits {
+        code_size: 31,
+        ..ExecutionLimits::default()
+    };
+    let reverted = create(INITCODE, limits).await?;
+    assert!(reverted);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_initcode_size_limit() -> eyre::Result<()> {
+    let limits = ExecutionLimits {
+        initcode_size: 9,
+        ..ExecutionLimits::default()
+    };
+    let Err(err) = create(INITCODE, limits).await else {
+        panic!("invalid transaction");
+    };
+    assert_eq!(
+        err.downcast::<InvalidTx>()?,
+        InvalidTx::InitcodeSizeExceeded { size: 10, limit: 9 }
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stack_limit() -> eyre::Result<()> {
+    let (evm, _) = call(PUSH17, ExecutionLimits::default()).await?;
+    assert!(!evm.reverted);
+
+    let limits = ExecutionLimits {
+        stack: 16,
+        ..ExecutionLimits::default()
+    };
+    let (evm, _) = call(PUSH17, limits).await?;
+    assert!(evm.reverted);
+    assert_eq!(evm.halt, Some(HaltReason::StackOverflow));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_step_limit() -> eyre::Result<()> {
+    let limits = ExecutionLimits {
+        steps: Some(100),
+        ..ExecutionLimits::default()
+    };
+    let (evm, mut tracer) = call(LOOP, limits).await?;
+    assert!(evm.reverted);
+    // Halted long before running out of gas
+    let halt = tracer
+        .take()
+        .into_iter()
+        .find_map(|event| match event.data {
+            EventData::Halt(reason) => Some(reason),
+            _ => None,
+        });
+    assert!(matches!(halt, Some(HaltReason::StepLimit)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_memory_limit() -> eyre::Result<()> {
+    let (evm, _) = call(MSTORE, ExecutionLimits::default()).await?;
+    assert!(!evm.reverted);
+
+    let limits = ExecutionLimits {
+        memory: 0x10000,
+        ..ExecutionLimits::default()
+    };
+    let (evm, _) = call(MSTORE, limits).await?;
+    assert!(evm.reverted);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancellation() -> eyre::Result<()> {
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        cancel.cancel();
+    });
+    let (evm, mut ext, to) = run_until_halted(|builder| builder.with_cancellation(token)).await?;
+    assert!(evm.reverted);
+    assert_eq!(evm.halt, Some(HaltReason::Cancelled));
+    // Storage written before the cancellation is reverted
+    assert_eq!(ext.get(&to, &Word::zero()).await?, Word::zero());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_deadline() -> eyre::Result<()> {
+    let deadline = Instant::now() + Duration::from_millis(20);
+    let (evm, mut ext, to) = run_until_halted(|builder| builder.with_deadline(deadline)).await?;
+    assert!(Instant::now() >= deadline);
+    assert!(evm.reverted);
+    assert_eq!(evm.halt, Some(HaltReason::Timeout));
+    assert_eq!(ext.get(&to, &Word::zero()).await?, Word::zero());
+    Ok(())
+}
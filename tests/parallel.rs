@@ -0,0 +1,205 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use evm_common::{
+    address::{Address, addr},
+    block::Header,
+    word::Word,
+};
+use evm_event::{AccountEvent, EventData};
+
+use solenoid::{
+    ext::{Account, Ext, TxContext},
+    parallel,
+    solenoid::{Builder, CallResult, Runner, Solenoid},
+    tracer::{EventTracer, LoggingTracer},
+};
+
+mod common;
+
+// SSTORE(0, SLOAD(0) + 1)
+static COUNTER: &str = "5f546001015f5500";
+
+// SSTORE(CALLER, CALLDATALOAD(0))
+static STORE: &str = "5f35335500";
+
+// SSTORE(0, BALANCE(COINBASE))
+static TIPS: &str = "41315f5500";
+
+const COINBASE: &str = "00000000000000000000000000000000c0ffee00";
+const COUNTER_ADDRESS: &str = "000000000000000000000000000000000000c0c0";
+const STORE_ADDRESS: &str = "0000000000000000000000000000000000005707";
+const TIPS_ADDRESS: &str = "000000000000000000000000000000000000713f";
+
+fn sender(index: u8) -> Address {
+    Address::from([index + 1; 20])
+}
+
+// (sender, contract, value to store)
+const TXS: &[(u8, &str, u64)] = &[
+    (0, STORE_ADDRESS, 1),
+    (1, STORE_ADDRESS, 2),
+    (2, COUNTER_ADDRESS, 0),
+    (3, COUNTER_ADDRESS, 0),
+    // Same sender as the first one
+    (0, STORE_ADDRESS, 3),
+    // Reads the fees paid by the previous transactions
+    (1, TIPS_ADDRESS, 0),
+    (2, STORE_ADDRESS, 4),
+    (3, COUNTER_ADDRESS, 0),
+];
+
+fn header() -> Header {
+    Header {
+        base_fee: Word::from(10u64),
+        miner: addr(COINBASE),
+        ..Header::default()
+    }
+}
+
+fn tx(index: usize) -> (TxContext, Runner) {
+    let (from, to, value) = TXS[index];
+    let ctx = TxContext {
+        gas_price: Word::from(12u64),
+        gas_max_fee: Word::from(20u64),
+        gas_max_priority_fee: Word::from(2u64),
+        ..TxContext::default()
+    };
+    let runner = Solenoid::new()
+        .execute(addr(to), "", &Word::from(value).into_bytes())
+        .with_header(header())
+        .with_sender(sender(from))
+        .with_gas(Word::from(100_000u64))
+        .ready();
+    (ctx, runner)
+}
+
+fn genesis() -> Ext {
+    let mut ext = common::local([
+        (addr(COINBASE), Account::default()),
+        (
+            addr(COUNTER_ADDRESS),
+            common::contract(&hex::decode(COUNTER).unwrap()),
+        ),
+        (
+            addr(STORE_ADDRESS),
+            common::contract(&hex::decode(STORE).unwrap()),
+        ),
+        (
+            addr(TIPS_ADDRESS),
+            common::contract(&hex::decode(TIPS).unwrap()),
+        ),
+    ]);
+    for index in 0..4 {
+        let account = Account {
+            value: Word::from(1_000_000_000u64),
+            ..Account::default()
+        };
+        ext.state.insert(sender(index), account);
+    }
+    ext
+}
+
+// Everything observable of a transaction and of the state after it
+async fn outcome(
+    result: &mut CallResult<LoggingTracer>,
+    ext: &mut Ext,
+) -> eyre::Result<(String, Vec<Word>)> {
+    let values = result
+        .tracer
+        .take()
+        .into_iter()
+        .filter_map(|event| match event.data {
+            EventData::Account(event @ AccountEvent::SetValue { .. }) => Some(event),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut state = Vec::new();
+    for address in [COINBASE, COUNTER_ADDRESS, STORE_ADDRESS, TIPS_ADDRESS] {
+        state.push(ext.balance(&addr(address)).await?);
+    }
+    for index in 0..4 {
+        state.push(ext.balance(&sender(index)).await?);
+        state.push(ext.nonce(&sender(index)).await?);
+        state.push(
+            ext.get(&addr(STORE_ADDRESS), &sender(index).as_word())
+                .await?,
+        );
+    }
+    state.push(ext.get(&addr(COUNTER_ADDRESS), &Word::zero()).await?);
+    state.push(ext.get(&addr(TIPS_ADDRESS), &Word::zero()).await?);
+    let result = format!(
+        "{:?} {} {:?} {:?} {}",
+        result.evm.touches,
+        hex::encode(&result.ret),
+        result.evm.reverted,
+        result.gas,
+        serde_json::to_string(&values)?,
+    );
+    Ok((result, state))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_parallel_matches_sequential() -> eyre::Result<()> {
+    let mut sequential = Vec::new();
+    let mut ext = genesis();
+    for index in 0..TXS.len() {
+        let (ctx, runner) = tx(index);
+        ext.reset(ctx);
+        let mut result = runner.apply(&mut ext).await?;
+        sequential.push(outcome(&mut result, &mut ext).await?);
+    }
+
+    let executed = parallel::execute(
+        genesis().snapshot(),
+        addr(COINBASE),
+        TXS.len(),
+        |index, mut ext| async move {
+            let (ctx, runner) = tx(index);
+            ext.reset(ctx);
+            let result = runner.apply(&mut ext).await?;
+            Ok((result, ext))
+        },
+    )
+    .await?;
+    let mut parallel = Vec::new();
+    for mut executed in executed {
+        let mut ext = executed.state.ext();
+        parallel.push(outcome(&mut executed.result, &mut ext).await?);
+    }
+
+    assert_eq!(parallel, sequential);
+    let (_, state) = sequential.last().unwrap();
+    // The counter is incremented by every transaction calling it
+    assert_eq!(state[state.len() - 2], Word::from(3u64));
+    // The fees paid by the transactions before it
+    assert!(!state[state.len() - 1].is_zero());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_only_conflicts_run_again() -> eyre::Result<()> {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let counter = runs.clone();
+    parallel::execute(
+        genesis().snapshot(),
+        addr(COINBASE),
+        TXS.len(),
+        move |index, mut ext| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            async move {
+                let (ctx, runner) = tx(index);
+                ext.reset(ctx);
+                let result = runner.apply(&mut ext).await?;
+                Ok((result, ext))
+            }
+        },
+    )
+    .await?;
+    // Read state written before them: the second COUNTER call, TIPS and the
+    // second transactions of senders 0, 2 and 3
+    assert_eq!(runs.load(Ordering::Relaxed), TXS.len() + 5);
+    Ok(())
+}
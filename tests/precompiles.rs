@@ -0,0 +1,139 @@
+use futures::future::BoxFuture;
+
+use evm_common::{
+    address::{Address, addr},
+    word::Word,
+};
+use evm_event::{CallType, EventData};
+
+use solenoid::{
+    ext::{Account, Ext},
+    precompiles::{Precompile, Precompiles},
+    solenoid::{Builder, CallResult, Solenoid},
+    spec::Spec,
+    tracer::{EventTracer, LoggingTracer},
+};
+
+mod common;
+
+// STATICCALL(GAS, 0xff01, 0, 0, 0, 32); MSTORE(32, success); RETURN(0, 64)
+static CALL_ORACLE: &str = "602060006000600061ff015afa60205260406000f3";
+
+// Same as above, calling SHA256 (0x02) with empty input
+static CALL_SHA256: &str = "60206000600060006100025afa60205260406000f3";
+
+/// Test oracle: returns the word stored at slot 0 of its own account
+struct Oracle(Address);
+
+impl Precompile for Oracle {
+    fn gas_cost(&self, _input: &[u8], _spec: Spec) -> i64 {
+        1000
+    }
+
+    fn execute<'a>(
+        &'a self,
+        _input: &'a [u8],
+        _spec: Spec,
+        ext: &'a mut Ext,
+    ) -> BoxFuture<'a, eyre::Result<Vec<u8>>> {
+        Box::pin(async move {
+            let value = ext.get(&self.0, &Word::zero()).await?;
+            Ok(value.into_bytes().to_vec())
+        })
+    }
+}
+
+async fn call(code: &str, precompiles: Precompiles) -> eyre::Result<CallResult<LoggingTracer>> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let to = addr("5fbdb2315678afecb367f032d93f642f64180aa3");
+    let oracle = addr("000000000000000000000000000000000000ff01");
+    let sha256 = addr("0000000000000000000000000000000000000002");
+    let code = hex::decode(code)?;
+    let mut oracle_account = Account::default();
+    oracle_account.state.insert(Word::zero(), Word::from(42u64));
+    let mut ext = common::local([
+        (from, Account::default()),
+        (to, common::contract(&code)),
+        (oracle, oracle_account),
+        (sha256, Account::default()),
+    ]);
+
+    Solenoid::new()
+        .execute(to, "", &[])
+        .with_sender(from)
+        .with_gas(Word::from(1_000_000u64))
+        .with_precompiles(precompiles)
+        .ready()
+        .apply(&mut ext)
+        .await
+}
+
+fn precompile_calls(tracer: &LoggingTracer) -> Vec<(CallType, Address)> {
+    tracer
+        .peek()
+        .iter()
+        .filter_map(|event| match &event.data {
+            EventData::Call { r#type, to, .. } => Some((*r#type, *to)),
+            _ => None,
+        })
+        .filter(|(r#type, _)| matches!(r#type, CallType::Precompile(_)))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_custom_precompile() -> eyre::Result<()> {
+    let oracle = addr("000000000000000000000000000000000000ff01");
+
+    // Without the precompile the oracle is a regular account without code
+    let result = call(CALL_ORACLE, Precompiles::default()).await?;
+    assert_eq!(result.ret[..32], [0u8; 32]);
+    assert!(precompile_calls(&result.tracer).is_empty());
+
+    let precompiles = Precompiles::default().with(oracle, Oracle(oracle));
+    let result = call(CALL_ORACLE, precompiles).await?;
+    assert!(!result.evm.reverted);
+    assert_eq!(Word::from_bytes(&result.ret[..32]), Word::from(42u64));
+    assert_eq!(Word::from_bytes(&result.ret[32..]), Word::one());
+    assert_eq!(
+        precompile_calls(&result.tracer),
+        vec![(CallType::Precompile(oracle), oracle)]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_disabled_precompile() -> eyre::Result<()> {
+    let sha256 = addr("0000000000000000000000000000000000000002");
+
+    let result = call(CALL_SHA256, Precompiles::default()).await?;
+    assert_eq!(
+        hex::encode(&result.ret[..32]),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(precompile_calls(&result.tracer).len(), 1);
+
+    let result = call(CALL_SHA256, Precompiles::default().without(&sha256)).await?;
+    assert!(!result.evm.reverted);
+    assert_eq!(result.ret[..32], [0u8; 32]);
+    assert!(precompile_calls(&result.tracer).is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_repriced_precompile() -> eyre::Result<()> {
+    let sha256 = addr("0000000000000000000000000000000000000002");
+
+    let result = call(CALL_SHA256, Precompiles::default()).await?;
+    let gas_used = result.evm.gas.used;
+
+    let mut precompiles = Precompiles::default();
+    assert!(precompiles.reprice(&sha256, |_, _| 1060));
+    let result = call(CALL_SHA256, precompiles).await?;
+    assert!(!result.evm.reverted);
+    assert_eq!(result.evm.gas.used, gas_used + 1000);
+    assert_eq!(
+        hex::encode(&result.ret[..32]),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use evm_common::{
+    address::{Address, addr},
+    word::Word,
+};
+use serde_json::json;
+
+use solenoid::{
+    ext::{Account, Ext},
+    prestate::{PrestateConfig, PrestateTrace},
+    solenoid::{Builder, Solenoid},
+    tracer::NoopTracer,
+};
+
+mod common;
+
+// SSTORE(1, SLOAD(0) + 1); SSTORE(2, 0); POP(BALANCE(0xcc))
+static CODE: &str = "5f546001016001555f6002557300000000000000000000000000000000000000cc315000";
+
+const FROM: &str = "f39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+const TO: &str = "00000000000000000000000000000000000000aa";
+const OTHER: &str = "00000000000000000000000000000000000000cc";
+
+fn slot(n: u64) -> String {
+    format!("0x{}", hex::encode(Word::from(n).into_bytes()))
+}
+
+fn ext() -> Ext {
+    let other = Account {
+        value: Word::from(9u64),
+        ..Account::default()
+    };
+    let mut ext = common::local([
+        (addr(FROM), Account::default()),
+        (addr(TO), common::contract(&hex::decode(CODE).unwrap())),
+        (addr(OTHER), other),
+    ]);
+    ext.state_mut(&addr(TO)).extend([
+        (Word::zero(), Word::from(5u64)),
+        (Word::from(2u64), Word::from(7u64)),
+    ]);
+    ext
+}
+
+async fn call(diff_mode: bool) -> eyre::Result<PrestateTrace> {
+    let (result, trace) = Solenoid::new()
+        .execute(addr(TO), "", &[])
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(100_000u64))
+        .ready()
+        .apply_with_prestate(NoopTracer, PrestateConfig { diff_mode }, &mut ext())
+        .await?;
+    assert!(!result.evm.reverted);
+    Ok(trace)
+}
+
+async fn create(diff_mode: bool) -> eyre::Result<PrestateTrace> {
+    let mut ext = common::local([(addr(FROM), Account::default())]);
+    // STOP
+    let (result, trace) = Solenoid::new()
+        .create(vec![0x00])
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(100_000u64))
+        .ready()
+        .apply_with_prestate(NoopTracer, PrestateConfig { diff_mode }, &mut ext)
+        .await?;
+    assert!(!result.evm.reverted);
+    Ok(trace)
+}
+
+fn created() -> Address {
+    addr(FROM).create(Word::zero())
+}
+
+#[tokio::test]
+async fn test_prestate() -> eyre::Result<()> {
+    let trace = call(false).await?;
+    assert_eq!(
+        serde_json::to_value(&trace)?,
+        json!({
+            format!("0x{FROM}"): {"balance": "0x0"},
+            format!("0x{TO}"): {
+                "balance": "0x0",
+                "code": format!("0x{CODE}"),
+                "storage": {
+                    slot(0): slot(5),
+                    slot(1): slot(0),
+                    slot(2): slot(7),
+                },
+            },
+            format!("0x{OTHER}"): {"balance": "0x9"},
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_prestate_diff() -> eyre::Result<()> {
+    let trace = call(true).await?;
+    // Unchanged accounts and slots are left out, as are empty slots
+    assert_eq!(
+        serde_json::to_value(&trace)?,
+        json!({
+            "pre": {
+                format!("0x{FROM}"): {"balance": "0x0"},
+                format!("0x{TO}"): {
+                    "balance": "0x0",
+                    "code": format!("0x{CODE}"),
+                    "storage": {slot(2): slot(7)},
+                },
+            },
+            "post": {
+                format!("0x{FROM}"): {"nonce": 1},
+                format!("0x{TO}"): {"storage": {slot(1): slot(6)}},
+            },
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_prestate_created() -> eyre::Result<()> {
+    let PrestateTrace::Prestate(pre) = create(false).await? else {
+        panic!("expected prestate");
+    };
+    assert!(!pre.contains_key(&created()));
+
+    let PrestateTrace::Diff { pre, post } = create(true).await? else {
+        panic!("expected diff");
+    };
+    assert!(!pre.contains_key(&created()));
+    assert_eq!(post[&created()].nonce, Some(1));
+    assert_eq!(post[&addr(FROM)].nonce, Some(1));
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use evm_common::{
+    address::{Address, addr},
+    word::Word,
+};
+
+use solenoid::{
+    ext::{Account, Ext, TxContext},
+    solenoid::{Builder, Solenoid},
+};
+
+mod common;
+
+// SSTORE(0, 1); SELFDESTRUCT(0xbb)
+static INIT: &str = "60015f557300000000000000000000000000000000000000bbff";
+
+// SELFDESTRUCT(0xbb)
+static DESTRUCT: &str = "7300000000000000000000000000000000000000bbff";
+
+const FROM: &str = "f39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+const CONTRACT: &str = "00000000000000000000000000000000000000aa";
+const BENEFICIARY: &str = "00000000000000000000000000000000000000bb";
+
+fn ext() -> Ext {
+    let from = Account {
+        value: Word::from(5u64),
+        ..Account::default()
+    };
+    let contract = Account {
+        value: Word::from(7u64),
+        ..common::contract(&hex::decode(DESTRUCT).unwrap())
+    };
+    common::local([
+        (addr(FROM), from),
+        (addr(CONTRACT), contract),
+        (addr(BENEFICIARY), Account::default()),
+    ])
+}
+
+#[tokio::test]
+async fn test_destroy_created() -> eyre::Result<()> {
+    let mut ext = ext();
+    let created: Address = addr(FROM).create(Word::zero());
+    let result = Solenoid::new()
+        .create(hex::decode(INIT)?)
+        .with_sender(addr(FROM))
+        .with_value(Word::from(5u64))
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .apply(&mut ext)
+        .await?;
+    assert!(!result.evm.reverted);
+    assert_eq!(ext.destroyed_accounts, vec![created]);
+    assert_eq!(ext.balance(&addr(BENEFICIARY)).await?, Word::from(5u64));
+
+    // Deleted with its storage at the end of the transaction
+    ext.reset(TxContext::default());
+    assert!(ext.destroyed_accounts.is_empty());
+    assert!(ext.balance(&created).await?.is_zero());
+    assert!(ext.nonce(&created).await?.is_zero());
+    assert!(ext.code(&created).await?.0.is_empty());
+    assert!(ext.get(&created, &Word::zero()).await?.is_zero());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_destroy_existing() -> eyre::Result<()> {
+    let mut ext = ext();
+    let result = Solenoid::new()
+        .execute(addr(CONTRACT), "", &[])
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .apply(&mut ext)
+        .await?;
+    assert!(!result.evm.reverted);
+
+    // EIP-6780: only the balance is sent, the account stays
+    assert!(ext.destroyed_accounts.is_empty());
+    ext.reset(TxContext::default());
+    assert_eq!(ext.balance(&addr(BENEFICIARY)).await?, Word::from(7u64));
+    assert!(ext.balance(&addr(CONTRACT)).await?.is_zero());
+    assert_eq!(ext.code(&addr(CONTRACT)).await?.0, hex::decode(DESTRUCT)?);
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use evm_common::{address::addr, call::Call, word::Word};
+use evm_event::HaltReason;
+
+use solenoid::{
+    decoder::Decoder,
+    executor::{Context, Evm, Executor, Gas},
+    ext::{Account, Ext},
+    session::Session,
+    solenoid::{Builder, Solenoid},
+    spec::Spec,
+    tracer::NoopTracer,
+};
+
+mod common;
+
+// ADD(1, 2); MSTORE(0, ...); RETURN(0, 32)
+static CALLEE: &str = "600160020160005260206000f3";
+
+// CALL(gas(), <callee>, 0, 0, 0, 0, 32); POP; RETURN(0, 32)
+fn caller(callee: &str) -> String {
+    format!("6020600060006000600073{callee}5af15060206000f3")
+}
+
+#[tokio::test]
+async fn test_step_into_nested_call() -> eyre::Result<()> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let to = addr("5fbdb2315678afecb367f032d93f642f64180aa3");
+    let callee = "e7f1725e7734ce288f8367e1bb143e90bb3f0512";
+
+    let mut ext = common::local([
+        (from, Account::default()),
+        (to, common::contract(&hex::decode(caller(callee))?)),
+        (addr(callee), common::contract(&hex::decode(CALLEE)?)),
+    ]);
+
+    let code = Decoder::decode(ext.code(&to).await?.0, Spec::default());
+    let call = Call {
+        data: vec![],
+        value: Word::zero(),
+        from,
+        to,
+        gas: Word::from(1_000_000u64),
+    };
+    let evm = Evm {
+        gas: Gas::new(1_000_000),
+        ..Evm::default()
+    };
+    let ctx = Context {
+        origin: from,
+        depth: 1,
+        ..Context::default()
+    };
+    let executor = Executor::<NoopTracer>::with_tracer(NoopTracer);
+    let mut session = Session::new(executor, &code, &call, evm, &mut ext, ctx);
+    assert_eq!(session.depth(), 1);
+    assert_eq!(session.instruction().map(|i| i.opcode.code), Some(0x60));
+
+    assert!(session.step(&mut ext).await);
+    assert_eq!(session.evm().pc, 1);
+    assert_eq!(session.evm().stack, vec![Word::from(32u64)]);
+
+    assert!(session.run_until(&mut ext, |s| s.depth() == 2).await);
+    assert_eq!(session.evm().pc, 0);
+    assert_eq!(session.call().to, addr(callee));
+    assert_eq!(session.frames().count(), 2);
+
+    let is_add = |s: &Session<NoopTracer>| s.instruction().map(|i| i.opcode.code) == Some(0x01);
+    assert!(session.run_until(&mut ext, is_add).await);
+    assert_eq!(
+        session.evm().stack,
+        vec![Word::from(1u64), Word::from(2u64)]
+    );
+    // Patch the stack of the callee: ADD(1, 41)
+    *session.evm_mut().stack.last_mut().unwrap() = Word::from(41u64);
+
+    assert!(session.run_until(&mut ext, |s| s.depth() == 1).await);
+    assert_eq!(session.frames().count(), 1);
+    assert_eq!(session.instruction().map(|i| i.opcode.code), Some(0x50));
+
+    session.run(&mut ext).await;
+    assert!(session.is_done());
+    assert!(!session.step(&mut ext).await);
+
+    let (_, ret, evm) = session.finish().expect("complete");
+    assert!(!evm.reverted);
+    assert_eq!(Word::from_bytes(&ret), Word::from(42u64));
+    Ok(())
+}
+
+fn start(code: &str, gas: i64, ext: &mut Ext) -> eyre::Result<Session<'static, NoopTracer>> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    let to = addr("5fbdb2315678afecb367f032d93f642f64180aa3");
+    let code = hex::decode(code)?;
+    ext.state.insert(from, Account::default());
+    ext.state.insert(to, common::contract(&code));
+    let call = Call {
+        data: vec![],
+        value: Word::zero(),
+        from,
+        to,
+        gas: Word::from(gas as u64),
+    };
+    let evm = Evm {
+        gas: Gas::new(gas),
+        ..Evm::default()
+    };
+    let ctx = Context {
+        origin: from,
+        depth: 1,
+        ..Context::default()
+    };
+    let code = Decoder::decode(code, Spec::default());
+    let executor = Executor::<NoopTracer>::with_tracer(NoopTracer);
+    Ok(Session::owned(executor, code, call, evm, ext, ctx))
+}
+
+#[tokio::test]
+async fn test_move_into_block() -> eyre::Result<()> {
+    // STOP; PUSH1 2; ADD; PUSH1 0; MSTORE: the second block starts at PUSH1
+    let code = "00600201600052";
+    let mut ext = Ext::local();
+    let mut session = start(code, 100, &mut ext)?;
+
+    // Skip to ADD in the middle of the block, with the operands in place
+    let evm = session.evm_mut();
+    evm.pc = 2;
+    evm.stack = vec![Word::from(40u64), Word::from(2u64)];
+    session.step(&mut ext).await;
+    assert_eq!(session.evm().stack, vec![Word::from(42u64)]);
+    // Only ADD is charged
+    assert_eq!(session.evm().gas.used, 3);
+
+    session.run(&mut ext).await;
+    let (_, _, evm) = session.finish().expect("complete");
+    assert!(!evm.reverted);
+    // PUSH1 0 + MSTORE + memory expansion
+    assert_eq!(evm.gas.used, 3 + 3 + 3 + 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_halt_within_block() -> eyre::Result<()> {
+    // PUSH1 1; ADD; STOP: ADD underflows
+    let mut ext = Ext::local();
+    let mut session = start("60010100", 100, &mut ext)?;
+    session.step(&mut ext).await;
+    assert_eq!(session.evm().stack, vec![Word::one()]);
+    assert_eq!(session.instruction().map(|i| i.opcode.code), Some(0x01));
+    session.step(&mut ext).await;
+    assert!(session.is_done());
+    assert_eq!(session.evm().halt, Some(HaltReason::StackUnderflow));
+
+    // PUSH1 1; PUSH1 1; ADD; STOP: out of gas at ADD
+    let mut ext = Ext::local();
+    let mut session = start("600160010100", 8, &mut ext)?;
+    session.step(&mut ext).await;
+    session.step(&mut ext).await;
+    assert_eq!(session.evm().gas.used, 6);
+    assert!(!session.is_done());
+    session.step(&mut ext).await;
+    assert!(session.is_done());
+    assert_eq!(session.evm().halt, Some(HaltReason::OutOfGas));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_prepared_create() -> eyre::Result<()> {
+    let from = addr("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+    // SSTORE(1, 1); STOP
+    let initcode = hex::decode("600160015500")?;
+    let runner = || {
+        Solenoid::new()
+            .create(initcode.clone())
+            .with_spec(Spec::Prague)
+            .with_sender(from)
+            .with_gas(Word::from(100_000u64))
+            .ready()
+    };
+    let mut ext = common::local([(from, Account::default())]);
+    let applied = runner().apply(&mut ext).await?;
+
+    let mut ext = common::local([(from, Account::default())]);
+    let mut prepared = runner().prepare(NoopTracer, &mut ext).await?;
+    let session = &mut prepared.session;
+    let is_sstore = |s: &Session<NoopTracer>| s.instruction().map(|i| i.opcode.code) == Some(0x55);
+    assert!(session.run_until(&mut ext, is_sstore).await);
+    // Runs as the created account
+    let created = from.create(Word::zero());
+    assert_eq!(session.frames().last().unwrap().address(), created);
+
+    session.run(&mut ext).await;
+    let result = prepared.finish(&mut ext).await?;
+    assert!(!result.evm.reverted);
+    assert_eq!(result.gas.gas_use, applied.gas.gas_use);
+    assert_eq!(ext.get(&created, &Word::one()).await?, Word::one());
+    assert_eq!(ext.nonce(&from).await?, Word::one());
+    Ok(())
+}
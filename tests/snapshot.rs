@@ -0,0 +1,103 @@
+use evm_common::{
+    address::{Address, addr},
+    word::Word,
+};
+
+use solenoid::{
+    ext::{Account, Ext, Snapshot},
+    solenoid::{Builder, Solenoid},
+};
+
+mod common;
+
+// SSTORE(0, CALLDATALOAD(0))
+static STORE: &str = "5f355f5500";
+
+const FROM: &str = "f39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+const TO: &str = "000000000000000000000000000000000000c0de";
+
+fn warmed_up() -> Snapshot {
+    let mut ext = common::local([
+        (addr(FROM), Account::default()),
+        (addr(TO), common::contract(&hex::decode(STORE).unwrap())),
+    ]);
+    ext.snapshot()
+}
+
+async fn store(ext: &mut Ext, to: Address, value: u64) -> eyre::Result<()> {
+    let result = Solenoid::new()
+        .execute(to, "", &Word::from(value).into_bytes())
+        .with_sender(addr(FROM))
+        .with_gas(Word::from(1_000_000u64))
+        .ready()
+        .apply(ext)
+        .await?;
+    assert!(!result.evm.reverted);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rewind() -> eyre::Result<()> {
+    let to = addr(TO);
+    let mut ext = warmed_up().ext();
+
+    let mut a = ext.fork();
+    store(&mut a, to, 1).await?;
+    assert_eq!(a.get(&to, &Word::zero()).await?, Word::one());
+
+    // Discard A, try B from the same state
+    let mut b = ext.fork();
+    store(&mut b, to, 2).await?;
+    assert_eq!(b.get(&to, &Word::zero()).await?, Word::from(2u64));
+    assert_eq!(ext.get(&to, &Word::zero()).await?, Word::zero());
+
+    ext.merge(b);
+    assert_eq!(ext.get(&to, &Word::zero()).await?, Word::from(2u64));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_merge_keeps_later_writes() -> eyre::Result<()> {
+    let to = addr(TO);
+    let mut ext = warmed_up().ext();
+    store(&mut ext, to, 1).await?;
+
+    // The fork only reads the nonce and slot 0, but has its own copy of
+    // the account once it writes slot 1
+    let mut fork = ext.fork();
+    assert_eq!(fork.get(&to, &Word::zero()).await?, Word::one());
+    assert!(fork.nonce(&to).await?.is_zero());
+    fork.put(&to, Word::one(), Word::from(3u64)).await?;
+
+    store(&mut ext, to, 2).await?;
+    ext.set_nonce(&to, Word::one());
+    ext.merge(fork);
+    assert_eq!(ext.get(&to, &Word::zero()).await?, Word::from(2u64));
+    assert_eq!(ext.nonce(&to).await?, Word::one());
+    assert_eq!(ext.get(&to, &Word::one()).await?, Word::from(3u64));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_concurrent_simulations() -> eyre::Result<()> {
+    let snapshot = warmed_up();
+    let tasks = (1..=4u64)
+        .map(|value| {
+            let snapshot = snapshot.clone();
+            tokio::spawn(async move {
+                let to = addr(TO);
+                let mut ext = snapshot.ext();
+                store(&mut ext, to, value).await?;
+                ext.get(&to, &Word::zero()).await
+            })
+        })
+        .collect::<Vec<_>>();
+    for (value, task) in (1..=4u64).zip(tasks) {
+        assert_eq!(task.await??, Word::from(value));
+    }
+    assert_eq!(
+        snapshot.ext().get(&addr(TO), &Word::zero()).await?,
+        Word::zero()
+    );
+    Ok(())
+}
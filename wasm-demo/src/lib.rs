@@ -431,7 +431,10 @@ pub async fn trace_transaction(
         gas_max_priority_fee: tx.gas_info.max_priority_fee.unwrap_or_default(),
         blob_max_fee: tx.gas_info.max_fee_per_blob.unwrap_or_default(),
         blob_gas_used: (tx.blob_count() * 131072) as u64,
+        blob_hashes: tx.blob_versioned_hashes.clone().unwrap_or_default(),
         access_list: tx.access_list.clone(),
+        authorization_list: tx.authorization_list.clone(),
+        nonce: Some(tx.nonce),
     };
     ext.reset(tx_ctx);
 